@@ -8,13 +8,24 @@
 //! ```
 //!
 //! Both this example and the above command needs to be run as root.
+//!
+//! If a file name is passed as the first argument, the packets are also written to it in the
+//! pcapng format, so they can be inspected with Wireshark:
+//!
+//! ```sh
+//! cargo run --example nflog -- nflog.pcapng
+//! ```
 extern crate neli;
 
-use neli::consts::netfilter::{LogCmd, LogCopyMode, NetfilterMsg, NfLogCfg};
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+
+use neli::consts::netfilter::{LogCmd, LogCopyMode, NetfilterMsg};
 use neli::consts::{NlFamily, NlmF};
-use neli::netfilter::{LogConfigMode, LogConfigReq, LogPacket};
+use neli::netfilter::pcapng::PcapngWriter;
+use neli::netfilter::{LogConfigReq, LogPacket};
 use neli::nl::Nlmsghdr;
-use neli::nlattr::Nlattr;
 use neli::socket::NlSocket;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -25,22 +36,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // of the functionality is kind of sparse, so there are some unknowns, like why do we have to
     // do the PfUnbind first (every other example out there on the Internet does that and doesn't
     // explain it).
-    let cmds = vec![
-        Nlattr::new(None, NfLogCfg::Cmd, LogCmd::PfUnbind)?,
+    let req = LogConfigReq::new(libc::AF_INET, 10, Vec::new())
+        .cmd(LogCmd::PfUnbind)?
         // This one says we are interested in the first 50 bytes of each packet. If not included,
         // it'll send us the whole packets.
-        Nlattr::new(
-            None,
-            NfLogCfg::Mode,
-            LogConfigMode {
-                copy_mode: LogCopyMode::Packet,
-                copy_range: 50,
-            },
-        )?,
-        Nlattr::new(None, NfLogCfg::Cmd, LogCmd::PfBind)?,
-        Nlattr::new(None, NfLogCfg::Cmd, LogCmd::Bind)?,
-    ];
-    let req = LogConfigReq::new(libc::AF_INET, 10, cmds);
+        .mode(LogCopyMode::Packet, 50)?
+        .cmd(LogCmd::PfBind)?
+        .cmd(LogCmd::Bind)?;
     let flags = vec![NlmF::Request, NlmF::Ack];
     let msg = Nlmsghdr::new(None, NetfilterMsg::LogConfig, flags, None, None, req);
     // Send the request to the kernel
@@ -48,12 +50,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // And check it succeeds.
     socket.recv_ack()?;
 
+    let mut pcapng = match env::args().nth(1) {
+        Some(path) => Some(PcapngWriter::new(BufWriter::new(File::create(path)?))?),
+        None => None,
+    };
+
     // Now, let's start getting the packets. A real world application would do something more
     // useful with them then just print them, but hey, this is an example.
     for pkt in socket.iter::<NetfilterMsg, LogPacket>() {
         let pkt = pkt?;
         match pkt.nl_type {
-            NetfilterMsg::LogPacket => println!("{:?}", pkt.nl_payload),
+            NetfilterMsg::LogPacket => {
+                println!("{:?}", pkt.nl_payload);
+                if let Some(ref mut pcapng) = pcapng {
+                    pcapng.write_packet(&pkt.nl_payload)?;
+                    // Flush after every packet so the file is usable when interrupted
+                    pcapng.flush()?;
+                }
+            }
             // TODO: Does anyone have any idea what these messages are and why we get them?
             _ => println!("Some other message received"),
         }
@@ -0,0 +1,759 @@
+//! Rust code generation from a parsed spec.
+//!
+//! The output is meant to be `include!`d into its own module of a crate depending on `neli`.
+//! It has no inner attributes or module docs so that `include!` accepts it.
+
+use std::fmt::Write;
+
+use spec::{AttrKind, AttrSet, DefinitionKind, Operation, Protocol, Spec};
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn words(name: &str) -> Vec<&str> {
+    name.split(&['-', '_', '.', ' '][..])
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// `CamelCase` type or variant name
+fn camel(name: &str) -> String {
+    let mut ident = String::new();
+    for word in words(name) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            ident.extend(first.to_uppercase());
+            ident.push_str(chars.as_str());
+        }
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, 'V');
+    }
+    if ident == "Self" {
+        ident.push_str("Value");
+    }
+    ident
+}
+
+/// `snake_case` field or method name
+fn snake(name: &str) -> String {
+    let mut ident = words(name).join("_").to_lowercase();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// `SCREAMING_SNAKE_CASE` constant name
+fn upper(name: &str) -> String {
+    words(name).join("_").to_uppercase()
+}
+
+fn doc(out: &mut String, indent: &str, doc: Option<&String>, default: &str) {
+    let text = match doc {
+        Some(doc) if !doc.is_empty() => doc.as_str(),
+        _ => default,
+    };
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            let _ = writeln!(out, "{}///", indent);
+        } else {
+            let _ = writeln!(out, "{}/// {}", indent, line);
+        }
+    }
+}
+
+struct Generator<'a> {
+    spec: &'a Spec,
+    family: String,
+    needs_uint: bool,
+}
+
+impl<'a> Generator<'a> {
+    fn cmd_enum(&self) -> String {
+        format!("{}Cmd", self.family)
+    }
+
+    fn set_struct(&self, set: &str) -> String {
+        camel(set)
+    }
+
+    fn set_enum(&self, set: &str) -> String {
+        format!("{}Attr", camel(set))
+    }
+
+    fn def_enum(&self, name: &str) -> String {
+        let ident = camel(name);
+        if self.spec.attr_set(name).is_some() || ident == self.cmd_enum() {
+            format!("{}Enum", ident)
+        } else {
+            ident
+        }
+    }
+
+    fn const_name(&self, name: &str) -> String {
+        format!("{}_{}", upper(&self.spec.name), upper(name))
+    }
+
+    /// Whether a nested set of `from` contains `target` through single nested attributes,
+    /// which requires boxing to give the struct a size
+    fn reaches(&self, from: &str, target: &str, seen: &mut Vec<String>) -> bool {
+        if from == target {
+            return true;
+        }
+        if seen.iter().any(|s| s == from) {
+            return false;
+        }
+        seen.push(from.to_string());
+        let set = match self.spec.attr_set(from) {
+            Some(set) => set,
+            None => return false,
+        };
+        set.attrs.iter().any(|attr| match attr.kind {
+            AttrKind::Nest(ref nested) if !attr.multi => self.reaches(nested, target, seen),
+            _ => false,
+        })
+    }
+
+    fn rust_type(&self, kind: &AttrKind) -> String {
+        match *kind {
+            AttrKind::Int { ref ty, .. } => ty.replace('s', "i"),
+            AttrKind::Uint => "u64".to_string(),
+            AttrKind::Sint => "i64".to_string(),
+            AttrKind::String => "String".to_string(),
+            AttrKind::Nest(ref set) => self.set_struct(set),
+            AttrKind::IndexedArray(ref sub) => format!("Vec<{}>", self.rust_type(sub)),
+            AttrKind::Flag => "bool".to_string(),
+            _ => "Vec<u8>".to_string(),
+        }
+    }
+
+    /// Expression of the payload of an attribute from `v`, a reference to the value
+    fn payload_expr(&self, kind: &AttrKind, v: &str) -> String {
+        match *kind {
+            AttrKind::Int { ref ty, big_endian } => {
+                let wire = ty.replace('s', "u");
+                match (ty.starts_with('s'), big_endian) {
+                    (false, false) => format!("*{}", v),
+                    (false, true) => format!("{}.to_be()", v),
+                    (true, false) => format!("*{} as {}", v, wire),
+                    (true, true) => format!("(*{} as {}).to_be()", v, wire),
+                }
+            }
+            AttrKind::Uint => format!("*{}", v),
+            AttrKind::Sint => format!("*{} as u64", v),
+            AttrKind::String => format!("{}.as_str()", v),
+            _ => format!("{}.as_slice()", v),
+        }
+    }
+
+    /// Expression creating an attribute of type `ty` from `v`, a reference to the value
+    fn attr_expr(&mut self, kind: &AttrKind, ty: &str, v: &str) -> String {
+        match *kind {
+            AttrKind::Nest(_) => format!(
+                "Nlattr::new_nested({}, &{}.to_attrs()?).map(Nlattr::with_nested_flag)",
+                ty, v
+            ),
+            _ => format!("Nlattr::new(None, {}, {})", ty, self.payload_expr(kind, v)),
+        }
+    }
+
+    /// Expression parsing the value of attribute `a`
+    fn parse_expr(&mut self, kind: &AttrKind, a: &str) -> String {
+        match *kind {
+            AttrKind::Int { ref ty, big_endian } => {
+                let wire = ty.replace('s', "u");
+                let mut expr = format!("{}.get_payload_as::<{}>()?", a, wire);
+                if big_endian {
+                    expr = format!("{}::from_be({})", wire, expr);
+                }
+                if ty.starts_with('s') {
+                    expr = format!("{} as {}", expr, ty.replace('s', "i"));
+                }
+                expr
+            }
+            AttrKind::Uint => {
+                self.needs_uint = true;
+                format!("parse_uint({})?", a)
+            }
+            AttrKind::Sint => {
+                self.needs_uint = true;
+                format!("parse_uint({})? as i64", a)
+            }
+            AttrKind::String => format!("{}.get_payload_as::<String>()?", a),
+            AttrKind::Nest(ref set) => format!(
+                "{}::from_attrs(&{}.get_nested_attributes::<{}>()?)?",
+                self.set_struct(set),
+                a,
+                self.set_enum(set)
+            ),
+            _ => format!("{}.payload.clone()", a),
+        }
+    }
+
+    fn gen_header(&self, out: &mut String) {
+        let spec = self.spec;
+        let _ = writeln!(
+            out,
+            "// Generated by neli-codegen from the `{}` YNL spec. Do not edit.\n",
+            spec.name
+        );
+        let has_sets = spec
+            .attribute_sets
+            .iter()
+            .any(|set| set.subset_of.is_none());
+        let has_ops = self.ops().next().is_some();
+        let mut consts = Vec::new();
+        if has_ops || !spec.operations.is_empty() {
+            consts.push("Cmd");
+        }
+        if has_sets {
+            consts.push("NlAttrType");
+        }
+        if has_ops {
+            consts.push("NlmF");
+        }
+        let mut errs = Vec::new();
+        if has_sets {
+            errs.push("DeError");
+        }
+        if has_ops {
+            errs.push("NlError");
+        }
+        if has_sets {
+            errs.push("SerError");
+        }
+        if !consts.is_empty() {
+            let _ = writeln!(out, "use neli::consts::{{{}}};", consts.join(", "));
+        }
+        if !errs.is_empty() {
+            let _ = writeln!(out, "use neli::err::{{{}}};", errs.join(", "));
+        }
+        if has_ops {
+            let _ = writeln!(out, "use neli::genl::Genlmsghdr;");
+            let _ = writeln!(out, "use neli::nl::Nlmsghdr;");
+        }
+        if has_sets {
+            let _ = writeln!(out, "use neli::nlattr::{{AttrHandle, Nlattr}};");
+        }
+        if has_ops {
+            let _ = writeln!(out, "use neli::socket::NlSocket;");
+        }
+        let mut root = vec!["impl_var", "impl_var_base"];
+        if !consts.is_empty() {
+            root.push("impl_var_trait");
+        }
+        root.push("Nl");
+        if has_sets {
+            root.push("StreamReadBuffer");
+            root.push("StreamWriteBuffer");
+        }
+        let _ = writeln!(out, "use neli::{{{}}};\n", root.join(", "));
+    }
+
+    fn gen_family(&self, out: &mut String) {
+        let spec = self.spec;
+        if spec.protocol != Protocol::Genetlink {
+            return;
+        }
+        let _ = writeln!(
+            out,
+            "/// Name of the `{}` generic netlink family\npub const {}: &str = \"{}\";",
+            spec.name,
+            self.const_name("genl-name"),
+            spec.name
+        );
+        let _ = writeln!(
+            out,
+            "/// Version of the `{}` generic netlink family\npub const {}: u8 = {};",
+            spec.name,
+            self.const_name("genl-version"),
+            spec.version
+        );
+        for group in spec.mcast_groups.iter() {
+            let _ = writeln!(
+                out,
+                "/// Multicast group `{}`\npub const {}: &str = \"{}\";",
+                group,
+                self.const_name(&format!("mcgrp-{}", group)),
+                group
+            );
+        }
+        out.push('\n');
+    }
+
+    fn gen_definitions(&self, out: &mut String) {
+        for def in self.spec.definitions.iter() {
+            match def.kind {
+                DefinitionKind::Const(value) => {
+                    doc(
+                        out,
+                        "",
+                        def.doc.as_ref(),
+                        &format!("Constant `{}`", def.name),
+                    );
+                    let _ = writeln!(
+                        out,
+                        "pub const {}: u64 = {};\n",
+                        self.const_name(&def.name),
+                        value
+                    );
+                }
+                DefinitionKind::Enum(ref entries) | DefinitionKind::Flags(ref entries) => {
+                    let _ = writeln!(out, "impl_var!(");
+                    doc(
+                        out,
+                        "    ",
+                        def.doc.as_ref(),
+                        &format!("Values of `{}`", def.name),
+                    );
+                    let _ = writeln!(out, "    {}, u32,", self.def_enum(&def.name));
+                    let variants = entries
+                        .iter()
+                        .map(|e| format!("    {} => {}", camel(&e.name), e.value))
+                        .collect::<Vec<_>>();
+                    let _ = writeln!(out, "{}\n);\n", variants.join(",\n"));
+                }
+                DefinitionKind::Other => (),
+            }
+        }
+    }
+
+    fn gen_cmds(&self, out: &mut String) {
+        let spec = self.spec;
+        if spec.operations.is_empty() {
+            return;
+        }
+        let _ = writeln!(out, "impl_var_trait!(");
+        let _ = writeln!(out, "    /// Commands of the `{}` family", spec.name);
+        let _ = writeln!(out, "    {}, u8, Cmd,", self.cmd_enum());
+        let variants = spec
+            .operations
+            .iter()
+            .map(|op| format!("    {} => {}", camel(&op.name), op.value))
+            .collect::<Vec<_>>();
+        let _ = writeln!(out, "{}\n);\n", variants.join(",\n"));
+    }
+
+    fn gen_set(&mut self, set: &AttrSet, out: &mut String) {
+        let name = self.set_struct(&set.name);
+        let attr_enum = self.set_enum(&set.name);
+        if let Some(ref parent) = set.subset_of {
+            let _ = writeln!(
+                out,
+                "/// Subset of `{}`\npub type {} = {};",
+                parent,
+                attr_enum,
+                self.set_enum(parent)
+            );
+            let _ = writeln!(
+                out,
+                "/// Subset of `{}`\npub type {} = {};\n",
+                parent,
+                name,
+                self.set_struct(parent)
+            );
+            return;
+        }
+
+        // Attribute type enum
+        let _ = writeln!(out, "impl_var_trait!(");
+        let _ = writeln!(
+            out,
+            "    /// Attributes of the `{}` attribute set",
+            set.name
+        );
+        let _ = writeln!(out, "    {}, u16, NlAttrType,", attr_enum);
+        let variants = set
+            .attrs
+            .iter()
+            .map(|a| format!("    {} => {}", camel(&a.name), a.value))
+            .collect::<Vec<_>>();
+        let _ = writeln!(out, "{}\n);\n", variants.join(",\n"));
+
+        let fields = set
+            .attrs
+            .iter()
+            .filter(|a| a.kind != AttrKind::Unused && a.kind != AttrKind::Pad)
+            .collect::<Vec<_>>();
+
+        // Struct
+        doc(
+            out,
+            "",
+            set.doc.as_ref(),
+            &format!("Attributes of the `{}` attribute set", set.name),
+        );
+        let _ = writeln!(out, "#[derive(Clone, Debug, Default, PartialEq)]");
+        if fields.is_empty() {
+            let _ = writeln!(out, "pub struct {};\n", name);
+        } else {
+            let _ = writeln!(out, "pub struct {} {{", name);
+            for attr in fields.iter() {
+                doc(out, "    ", attr.doc.as_ref(), &format!("`{}`", attr.name));
+                let mut ty = self.rust_type(&attr.kind);
+                if let AttrKind::Nest(ref nested) = attr.kind {
+                    if !attr.multi && self.reaches(nested, &set.name, &mut Vec::new()) {
+                        ty = format!("Box<{}>", ty);
+                    }
+                }
+                let ty = match attr.kind {
+                    AttrKind::Flag | AttrKind::IndexedArray(_) => ty,
+                    _ if attr.multi => format!("Vec<{}>", ty),
+                    _ => format!("Option<{}>", ty),
+                };
+                let _ = writeln!(out, "    pub {}: {},", snake(&attr.name), ty);
+            }
+            let _ = writeln!(out, "}}\n");
+        }
+
+        // Conversion from and to attributes
+        let _ = writeln!(out, "impl {} {{", name);
+        let _ = writeln!(out, "    /// Serialize the set into attributes");
+        let _ = writeln!(
+            out,
+            "    pub fn to_attrs(&self) -> Result<Vec<Nlattr<{}, Vec<u8>>>, SerError> {{",
+            attr_enum
+        );
+        if fields.is_empty() {
+            let _ = writeln!(out, "        Ok(Vec::new())\n    }}\n");
+        } else {
+            let _ = writeln!(out, "        let mut attrs = Vec::new();");
+            for attr in fields.iter() {
+                let field = snake(&attr.name);
+                let ty = format!("{}::{}", attr_enum, camel(&attr.name));
+                match attr.kind {
+                    AttrKind::Flag => {
+                        let _ = writeln!(
+                            out,
+                            "        if self.{} {{\n            \
+                             attrs.push(Nlattr::new(None, {}, Vec::<u8>::new())?);\n        }}",
+                            field, ty
+                        );
+                    }
+                    AttrKind::IndexedArray(ref sub) => {
+                        let expr = self.attr_expr(sub, "i as u16", "v");
+                        let _ = writeln!(
+                            out,
+                            "        if !self.{field}.is_empty() {{
+            let mut items = Vec::new();
+            for (i, v) in self.{field}.iter().enumerate() {{
+                items.push({expr}?);
+            }}
+            attrs.push(Nlattr::new_nested({ty}, &items)?.with_nested_flag());
+        }}",
+                            field = field,
+                            expr = expr,
+                            ty = ty
+                        );
+                    }
+                    _ if attr.multi => {
+                        let expr = self.attr_expr(&attr.kind, &ty, "v");
+                        let _ = writeln!(
+                            out,
+                            "        for v in self.{}.iter() {{\n            \
+                             attrs.push({}?);\n        }}",
+                            field, expr
+                        );
+                    }
+                    _ => {
+                        let expr = self.attr_expr(&attr.kind, &ty, "v");
+                        let _ = writeln!(
+                            out,
+                            "        if let Some(ref v) = self.{} {{\n            \
+                             attrs.push({}?);\n        }}",
+                            field, expr
+                        );
+                    }
+                }
+            }
+            let _ = writeln!(out, "        Ok(attrs)\n    }}\n");
+        }
+
+        let _ = writeln!(out, "    /// Parse the set from attributes");
+        if fields.is_empty() {
+            let _ = writeln!(
+                out,
+                "    pub fn from_attrs(_handle: &AttrHandle<{}>) -> Result<Self, DeError> {{\n        \
+                 Ok({})\n    }}\n}}\n",
+                attr_enum, name
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "    pub fn from_attrs(handle: &AttrHandle<{}>) -> Result<Self, DeError> {{",
+                attr_enum
+            );
+            let _ = writeln!(out, "        let mut set = {}::default();", name);
+            let _ = writeln!(out, "        for attr in handle.iter() {{");
+            let mut arms = Vec::new();
+            for attr in fields.iter() {
+                let field = snake(&attr.name);
+                let mut parse = match attr.kind {
+                    AttrKind::IndexedArray(ref sub) => self.parse_expr(sub, "item"),
+                    ref kind => self.parse_expr(kind, "attr"),
+                };
+                if let AttrKind::Nest(ref nested) = attr.kind {
+                    if !attr.multi && self.reaches(nested, &set.name, &mut Vec::new()) {
+                        parse = format!("Box::new({})", parse);
+                    }
+                }
+                let stmt = match attr.kind {
+                    AttrKind::Flag => format!("set.{} = true", field),
+                    AttrKind::IndexedArray(_) => format!(
+                        "{{\n                    \
+                         for item in attr.get_nested_attributes::<u16>()?.iter() {{\n                        \
+                         set.{}.push({});\n                    }}\n                }}",
+                        field, parse
+                    ),
+                    _ if attr.multi => format!("set.{}.push({})", field, parse),
+                    _ => format!("set.{} = Some({})", field, parse),
+                };
+                arms.push((format!("{}::{}", attr_enum, camel(&attr.name)), stmt));
+            }
+            if arms.len() == 1 {
+                let _ = writeln!(
+                    out,
+                    "            if attr.nla_type == {} {{\n                {};\n            }}",
+                    arms[0].0, arms[0].1
+                );
+            } else {
+                let _ = writeln!(out, "            match attr.nla_type {{");
+                for (variant, stmt) in arms.iter() {
+                    let _ = writeln!(out, "                {} => {},", variant, stmt);
+                }
+                let _ = writeln!(out, "                _ => (),\n            }}");
+            }
+            let _ = writeln!(out, "        }}\n        Ok(set)\n    }}\n}}\n");
+        }
+
+        // Nl implementation
+        let _ = writeln!(
+            out,
+            "impl Nl for {name} {{
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {{
+        self.to_attrs()?.serialize(buf)
+    }}
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {{
+        let attrs = Vec::<Nlattr<{attr_enum}, Vec<u8>>>::deserialize(buf)?;
+        {name}::from_attrs(&AttrHandle::new(attrs))
+    }}
+
+    fn size(&self) -> usize {{
+        self.to_attrs().map(|attrs| attrs.asize()).unwrap_or(0)
+    }}
+}}
+",
+            name = name,
+            attr_enum = attr_enum
+        );
+    }
+
+    /// Operations that requests can be generated for
+    fn ops(&self) -> impl Iterator<Item = &'a Operation> {
+        let genetlink = self.spec.protocol == Protocol::Genetlink;
+        self.spec.operations.iter().filter(move |op| {
+            genetlink
+                && !op.event
+                && op.attribute_set.is_some()
+                && (op.do_op.is_some() || op.dump.is_some())
+        })
+    }
+
+    fn gen_ops(&self, out: &mut String) {
+        let spec = self.spec;
+        let ops = self.ops().collect::<Vec<_>>();
+        if ops.is_empty() {
+            return;
+        }
+        let family = snake(&spec.name);
+        let trait_name = format!("{}Ops", self.family);
+        let mut decls = Vec::new();
+        let mut impls = Vec::new();
+
+        decls.push(format!(
+            "    /// Resolve the ID of the `{}` family\n    \
+             fn {}_family_id(&mut self) -> Result<u16, NlError>;",
+            spec.name, family
+        ));
+        impls.push(format!(
+            "    fn {}_family_id(&mut self) -> Result<u16, NlError> {{\n        \
+             self.resolve_genl_family({})\n    }}",
+            family,
+            self.const_name("genl-name")
+        ));
+
+        for op in ops {
+            let set = op.attribute_set.as_ref().expect("Filtered by ops()");
+            let set_struct = self.set_struct(set);
+            let set_enum = self.set_enum(set);
+            let modes = [(&op.do_op, false), (&op.dump, true)];
+            for &(mode, dump) in modes.iter() {
+                let mode = match *mode {
+                    Some(ref mode) => mode,
+                    None => continue,
+                };
+                let method = if dump {
+                    format!("{}_{}_dump", family, snake(&op.name))
+                } else {
+                    format!("{}_{}", family, snake(&op.name))
+                };
+                let ret = if dump {
+                    format!("Vec<{}>", set_struct)
+                } else if mode.reply.is_some() {
+                    set_struct.clone()
+                } else {
+                    "()".to_string()
+                };
+                let sig = format!(
+                    "fn {}(\n        &mut self,\n        family_id: u16,\n        \
+                     request: &{},\n    ) -> Result<{}, NlError>",
+                    method, set_struct, ret
+                );
+
+                let mut decl = String::new();
+                let default = format!("`{}` {}", op.name, if dump { "dump" } else { "request" });
+                doc(&mut decl, "    ", op.doc.as_ref(), &default);
+                if !mode.request.is_empty() {
+                    let _ = writeln!(
+                        decl,
+                        "    ///\n    /// Request attributes: {}",
+                        mode.request.join(", ")
+                    );
+                }
+                let _ = write!(decl, "    {};", sig);
+                decls.push(decl);
+
+                let flags = if dump {
+                    "NlmF::Request, NlmF::Dump"
+                } else if mode.reply.is_some() {
+                    "NlmF::Request"
+                } else {
+                    "NlmF::Request, NlmF::Ack"
+                };
+                let mut body = format!(
+                    "    {} {{\n        let genlhdr = Genlmsghdr::new(\n            {}::{},\n            \
+                     {},\n            request.to_attrs()?,\n        )?;\n        \
+                     self.send_nl(Nlmsghdr::new(\n            None,\n            family_id,\n            \
+                     vec![{}],\n            None,\n            None,\n            genlhdr,\n        ))?;\n",
+                    sig,
+                    self.cmd_enum(),
+                    camel(&op.name),
+                    self.const_name("genl-version"),
+                    flags
+                );
+                let recv = format!(
+                    "self.recv_all::<u16, Genlmsghdr<{}, {}>>()?",
+                    self.cmd_enum(),
+                    set_enum
+                );
+                if dump {
+                    let _ = write!(
+                        body,
+                        "        {}\n            .iter()\n            \
+                         .map(|msg| Ok({}::from_attrs(&msg.nl_payload.get_attr_handle())?))\n            \
+                         .collect()\n    }}",
+                        recv, set_struct
+                    );
+                } else if mode.reply.is_some() {
+                    let _ = write!(
+                        body,
+                        "        let msg = {}\n            .pop()\n            \
+                         .ok_or_else(|| NlError::new(\"No reply returned\"))?;\n        \
+                         Ok({}::from_attrs(&msg.nl_payload.get_attr_handle())?)\n    }}",
+                        recv, set_struct
+                    );
+                } else {
+                    let _ = write!(body, "        self.recv_ack()\n    }}");
+                }
+                impls.push(body);
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "/// Operations of the `{}` family\npub trait {} {{\n{}\n}}\n",
+            spec.name,
+            trait_name,
+            decls.join("\n\n")
+        );
+        let _ = writeln!(
+            out,
+            "impl {} for NlSocket {{\n{}\n}}\n",
+            trait_name,
+            impls.join("\n\n")
+        );
+    }
+
+    fn gen_helpers(&self, out: &mut String) {
+        if self.needs_uint {
+            let _ = writeln!(
+                out,
+                "/// Parse a variable length integer, sent as 4 or 8 bytes
+fn parse_uint<T>(attr: &Nlattr<T, Vec<u8>>) -> Result<u64, DeError>
+where
+    T: NlAttrType,
+{{
+    if attr.payload.len() == 4 {{
+        Ok(u64::from(attr.get_payload_as::<u32>()?))
+    }} else {{
+        attr.get_payload_as::<u64>()
+    }}
+}}
+"
+            );
+        }
+    }
+}
+
+/// Generate the Rust code for a spec
+pub fn generate_spec(spec: &Spec) -> String {
+    let mut gen = Generator {
+        spec,
+        family: camel(&spec.name),
+        needs_uint: false,
+    };
+    let mut body = String::new();
+    gen.gen_family(&mut body);
+    gen.gen_definitions(&mut body);
+    gen.gen_cmds(&mut body);
+    for set in spec.attribute_sets.iter() {
+        gen.gen_set(set, &mut body);
+    }
+    gen.gen_ops(&mut body);
+    gen.gen_helpers(&mut body);
+
+    let mut out = String::new();
+    gen.gen_header(&mut out);
+    out.push_str(body.trim_end());
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(camel("get-device"), "GetDevice");
+        assert_eq!(camel("1000baseT-full"), "V1000baseTFull");
+        assert_eq!(camel("self"), "SelfValue");
+        assert_eq!(snake("rx-bytes"), "rx_bytes");
+        assert_eq!(snake("type"), "type_");
+        assert_eq!(upper("key-len"), "KEY_LEN");
+    }
+}
@@ -0,0 +1,110 @@
+//! # neli-codegen
+//!
+//! Generates `neli` bindings from the YNL specs the kernel ships in
+//! `Documentation/netlink/specs/`, so that a new family can be supported by vendoring its spec
+//! instead of writing its `consts` module by hand.
+//!
+//! For each spec, the generated code contains:
+//! * `impl_var!` enums for the enum and flags definitions and constants for the other
+//!   definitions.
+//! * An `impl_var_trait!` command enum and one attribute enum per attribute set.
+//! * One struct per attribute set with `to_attrs()`, `from_attrs()` and an `Nl` implementation
+//!   that serializes the struct as its attributes.
+//! * For generic netlink families, a `<Family>Ops` trait implemented for `NlSocket` with one
+//!   method per `do` and `dump` request.
+//!
+//! ## Usage
+//!
+//! Generate the bindings from a build script:
+//!
+//! ```no_run
+//! extern crate neli_codegen;
+//!
+//! use std::env;
+//! use std::path::Path;
+//!
+//! let out_dir = env::var("OUT_DIR").unwrap();
+//! neli_codegen::generate_file("specs/wireguard.yaml", Path::new(&out_dir).join("wireguard.rs"))
+//!     .unwrap();
+//! ```
+//!
+//! and include them in a module of their own:
+//!
+//! ```ignore
+//! mod wireguard {
+//!     include!(concat!(env!("OUT_DIR"), "/wireguard.rs"));
+//! }
+//! ```
+//!
+//! The `neli-codegen` binary does the same from the command line:
+//! `neli-codegen specs/wireguard.yaml src/wireguard.rs`.
+//!
+//! ## Design decisions
+//!
+//! Only the unified message ID model is supported, which is what all generic netlink specs but
+//! a few classic netlink ones use. Attribute types without a typed representation, such as
+//! `bitfield32` and `sub-message`, are kept as raw bytes.
+
+#![deny(missing_docs)]
+
+extern crate yaml_rust;
+
+mod gen;
+pub mod spec;
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use yaml_rust::ScanError;
+
+use spec::Spec;
+
+/// Error while reading a spec or writing the generated code
+#[derive(Debug)]
+pub struct CodegenError(String);
+
+impl CodegenError {
+    /// Create new error from `&str`
+    pub fn new(s: &str) -> Self {
+        CodegenError(s.to_string())
+    }
+}
+
+impl From<io::Error> for CodegenError {
+    fn from(v: io::Error) -> Self {
+        CodegenError::new(&v.to_string())
+    }
+}
+
+impl From<ScanError> for CodegenError {
+    fn from(v: ScanError) -> Self {
+        CodegenError::new(&v.to_string())
+    }
+}
+
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for CodegenError {}
+
+/// Generate the Rust code for the YNL spec `source`
+pub fn generate(source: &str) -> Result<String, CodegenError> {
+    Ok(gen::generate_spec(&Spec::parse(source)?))
+}
+
+/// Generate the Rust code for the YNL spec at `spec` and write it to `out`
+pub fn generate_file<P, Q>(spec: P, out: Q) -> Result<(), CodegenError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let code = generate(&fs::read_to_string(spec)?)?;
+    fs::write(out, code)?;
+    Ok(())
+}
@@ -0,0 +1,24 @@
+extern crate neli_codegen;
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.len() {
+        1 => std::fs::read_to_string(&args[0])
+            .map_err(neli_codegen::CodegenError::from)
+            .and_then(|spec| neli_codegen::generate(&spec))
+            .and_then(|code| Ok(io::stdout().write_all(code.as_bytes())?)),
+        2 => neli_codegen::generate_file(&args[0], &args[1]),
+        _ => {
+            eprintln!("Usage: neli-codegen SPEC [OUTPUT]");
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,448 @@
+//! Model of the parts of a YNL spec that code is generated from.
+//!
+//! Only what the generator needs is kept. Keys the generator does not use, such as `checks`
+//! and `name-prefix`, are ignored, so a spec that the kernel tooling accepts is accepted here.
+
+use yaml_rust::{Yaml, YamlLoader};
+
+use CodegenError;
+
+/// Protocol of a family
+#[derive(Clone, Debug, PartialEq)]
+pub enum Protocol {
+    /// Generic netlink family (`genetlink`, `genetlink-c` and `genetlink-legacy`)
+    Genetlink,
+    /// Classic netlink family such as rtnetlink (`netlink-raw`)
+    NetlinkRaw,
+}
+
+/// Type of an attribute or of the entries of an indexed array
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttrKind {
+    /// Reserved attribute that is never sent
+    Unused,
+    /// Padding for 64 bit alignment
+    Pad,
+    /// Attribute without payload
+    Flag,
+    /// Integer of the given YNL type, eg. `u32` or `s16`
+    Int {
+        /// YNL type name
+        ty: String,
+        /// The value is big endian
+        big_endian: bool,
+    },
+    /// Variable length unsigned integer (`uint`)
+    Uint,
+    /// Variable length signed integer (`sint`)
+    Sint,
+    /// NUL terminated string
+    String,
+    /// Raw bytes, also used for types without a typed representation
+    Binary,
+    /// Nested attribute set
+    Nest(String),
+    /// Array of entries nested under index attributes
+    IndexedArray(Box<AttrKind>),
+}
+
+/// Attribute of an attribute set
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attr {
+    /// Name of the attribute
+    pub name: String,
+    /// Attribute type number
+    pub value: i64,
+    /// Type of the attribute
+    pub kind: AttrKind,
+    /// The attribute may occur more than once
+    pub multi: bool,
+    /// Documentation
+    pub doc: Option<String>,
+}
+
+/// Attribute set
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttrSet {
+    /// Name of the set
+    pub name: String,
+    /// Set this set is a subset of
+    pub subset_of: Option<String>,
+    /// Attributes of the set
+    pub attrs: Vec<Attr>,
+    /// Documentation
+    pub doc: Option<String>,
+}
+
+/// Entry of an enum or flags definition
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Name of the entry
+    pub name: String,
+    /// Value of the entry, the bit value for flags
+    pub value: i64,
+}
+
+/// Kind of a definition
+#[derive(Clone, Debug, PartialEq)]
+pub enum DefinitionKind {
+    /// Constant
+    Const(i64),
+    /// Enumeration
+    Enum(Vec<Entry>),
+    /// Flags
+    Flags(Vec<Entry>),
+    /// Definition without generated code, eg. a `struct`
+    Other,
+}
+
+/// Definition of a constant, enum or flags
+#[derive(Clone, Debug, PartialEq)]
+pub struct Definition {
+    /// Name of the definition
+    pub name: String,
+    /// Kind and values of the definition
+    pub kind: DefinitionKind,
+    /// Documentation
+    pub doc: Option<String>,
+}
+
+/// Request or reply of an operation
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpMode {
+    /// Attributes of the request
+    pub request: Vec<String>,
+    /// Attributes of the reply, `None` if the operation only returns an ACK
+    pub reply: Option<Vec<String>>,
+}
+
+/// Operation of a family
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+    /// Name of the operation
+    pub name: String,
+    /// Command number
+    pub value: i64,
+    /// Attribute set of the requests and replies
+    pub attribute_set: Option<String>,
+    /// `do` request
+    pub do_op: Option<OpMode>,
+    /// `dump` request
+    pub dump: Option<OpMode>,
+    /// The operation is a notification or an event
+    pub event: bool,
+    /// Documentation
+    pub doc: Option<String>,
+}
+
+/// Parsed YNL spec
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spec {
+    /// Name of the family
+    pub name: String,
+    /// Protocol of the family
+    pub protocol: Protocol,
+    /// Generic netlink version
+    pub version: i64,
+    /// Documentation
+    pub doc: Option<String>,
+    /// Constants, enums and flags
+    pub definitions: Vec<Definition>,
+    /// Attribute sets
+    pub attribute_sets: Vec<AttrSet>,
+    /// Operations
+    pub operations: Vec<Operation>,
+    /// Names of the multicast groups
+    pub mcast_groups: Vec<String>,
+}
+
+fn err(msg: &str) -> CodegenError {
+    CodegenError::new(msg)
+}
+
+fn string(yaml: &Yaml, key: &str, what: &str) -> Result<String, CodegenError> {
+    yaml[key]
+        .as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| err(&format!("{} is missing `{}`", what, key)))
+}
+
+fn opt_string(yaml: &Yaml, key: &str) -> Option<String> {
+    yaml[key].as_str().map(|s| s.trim().to_string())
+}
+
+fn list(yaml: &Yaml) -> &[Yaml] {
+    yaml.as_vec().map(|v| v.as_slice()).unwrap_or(&[])
+}
+
+/// Names in an `attributes` list of a request or reply
+fn attr_names(yaml: &Yaml) -> Vec<String> {
+    list(&yaml["attributes"])
+        .iter()
+        .filter_map(|a| a.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+fn parse_entries(def: &Yaml, flags: bool) -> Result<Vec<Entry>, CodegenError> {
+    let mut value = def["value-start"].as_i64().unwrap_or(0);
+    let mut entries = Vec::new();
+    for entry in list(&def["entries"]) {
+        let name = match entry.as_str() {
+            Some(name) => name.to_string(),
+            None => {
+                if let Some(v) = entry["value"].as_i64() {
+                    value = v;
+                }
+                string(entry, "name", "Enum entry")?
+            }
+        };
+        let entry_value = if flags { 1i64 << value } else { value };
+        entries.push(Entry {
+            name,
+            value: entry_value,
+        });
+        value += 1;
+    }
+    Ok(entries)
+}
+
+fn parse_definition(def: &Yaml) -> Result<Definition, CodegenError> {
+    let name = string(def, "name", "Definition")?;
+    let kind = match def["type"].as_str() {
+        Some("const") => DefinitionKind::Const(
+            def["value"]
+                .as_i64()
+                .ok_or_else(|| err(&format!("Constant {} has no integer value", name)))?,
+        ),
+        Some("enum") => DefinitionKind::Enum(parse_entries(def, false)?),
+        Some("flags") => DefinitionKind::Flags(parse_entries(def, true)?),
+        _ => DefinitionKind::Other,
+    };
+    Ok(Definition {
+        name,
+        kind,
+        doc: opt_string(def, "doc"),
+    })
+}
+
+fn parse_kind(attr: &Yaml, ty: &str, name: &str) -> Result<AttrKind, CodegenError> {
+    let nested = || {
+        attr["nested-attributes"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| {
+                err(&format!(
+                    "Nested attribute {} has no `nested-attributes`",
+                    name
+                ))
+            })
+    };
+    Ok(match ty {
+        "unused" => AttrKind::Unused,
+        "pad" => AttrKind::Pad,
+        "flag" => AttrKind::Flag,
+        "u8" | "u16" | "u32" | "u64" | "s8" | "s16" | "s32" | "s64" => AttrKind::Int {
+            ty: ty.to_string(),
+            big_endian: attr["byte-order"].as_str() == Some("big-endian"),
+        },
+        "uint" => AttrKind::Uint,
+        "sint" => AttrKind::Sint,
+        "string" => AttrKind::String,
+        "nest" => AttrKind::Nest(nested()?),
+        "indexed-array" => {
+            let sub_type = attr["sub-type"].as_str().unwrap_or("binary");
+            AttrKind::IndexedArray(Box::new(parse_kind(attr, sub_type, name)?))
+        }
+        // Older name of an indexed array of nests
+        "array-nest" => AttrKind::IndexedArray(Box::new(AttrKind::Nest(nested()?))),
+        _ => AttrKind::Binary,
+    })
+}
+
+fn parse_attr_set(set: &Yaml) -> Result<AttrSet, CodegenError> {
+    let name = string(set, "name", "Attribute set")?;
+    let subset_of = opt_string(set, "subset-of");
+    let mut value = 1;
+    let mut attrs = Vec::new();
+    for attr in list(&set["attributes"]) {
+        let attr_name = string(attr, "name", &format!("Attribute of {}", name))?;
+        if let Some(v) = attr["value"].as_i64() {
+            value = v;
+        }
+        let kind = match attr["type"].as_str() {
+            Some(ty) => parse_kind(attr, ty, &attr_name)?,
+            // Attributes of subsets may leave the type to the parent set
+            None if subset_of.is_some() => AttrKind::Binary,
+            None => return Err(err(&format!("Attribute {} has no type", attr_name))),
+        };
+        attrs.push(Attr {
+            name: attr_name,
+            value,
+            kind,
+            multi: attr["multi-attr"].as_bool().unwrap_or(false),
+            doc: opt_string(attr, "doc"),
+        });
+        value += 1;
+    }
+    Ok(AttrSet {
+        name,
+        subset_of,
+        attrs,
+        doc: opt_string(set, "doc"),
+    })
+}
+
+fn parse_op_mode(mode: &Yaml) -> Option<OpMode> {
+    if mode.is_badvalue() {
+        return None;
+    }
+    let reply = &mode["reply"];
+    Some(OpMode {
+        request: attr_names(&mode["request"]),
+        reply: if reply.is_badvalue() {
+            None
+        } else {
+            Some(attr_names(reply))
+        },
+    })
+}
+
+fn parse_operations(ops: &Yaml) -> Result<Vec<Operation>, CodegenError> {
+    if ops["enum-model"].as_str() == Some("directional") {
+        return Err(err("Directional message IDs are not supported"));
+    }
+    let mut value = 1;
+    let mut operations = Vec::new();
+    for op in list(&ops["list"]) {
+        let name = string(op, "name", "Operation")?;
+        if let Some(v) = op["value"].as_i64() {
+            value = v;
+        }
+        operations.push(Operation {
+            name,
+            value,
+            attribute_set: opt_string(op, "attribute-set"),
+            do_op: parse_op_mode(&op["do"]),
+            dump: parse_op_mode(&op["dump"]),
+            event: !op["event"].is_badvalue() || !op["notify"].is_badvalue(),
+            doc: opt_string(op, "doc"),
+        });
+        value += 1;
+    }
+    Ok(operations)
+}
+
+impl Spec {
+    /// Parse a YNL spec
+    pub fn parse(source: &str) -> Result<Self, CodegenError> {
+        let docs = YamlLoader::load_from_str(source)?;
+        let yaml = docs.first().ok_or_else(|| err("Spec is empty"))?;
+        let protocol = match yaml["protocol"].as_str().unwrap_or("genetlink") {
+            "genetlink" | "genetlink-c" | "genetlink-legacy" => Protocol::Genetlink,
+            "netlink-raw" => Protocol::NetlinkRaw,
+            protocol => return Err(err(&format!("Unknown protocol {}", protocol))),
+        };
+        Ok(Spec {
+            name: string(yaml, "name", "Spec")?,
+            protocol,
+            version: yaml["version"].as_i64().unwrap_or(1),
+            doc: opt_string(yaml, "doc"),
+            definitions: list(&yaml["definitions"])
+                .iter()
+                .map(parse_definition)
+                .collect::<Result<_, _>>()?,
+            attribute_sets: list(&yaml["attribute-sets"])
+                .iter()
+                .map(parse_attr_set)
+                .collect::<Result<_, _>>()?,
+            operations: parse_operations(&yaml["operations"])?,
+            mcast_groups: list(&yaml["mcast-groups"]["list"])
+                .iter()
+                .filter_map(|g| g["name"].as_str().map(|s| s.to_string()))
+                .collect(),
+        })
+    }
+
+    /// Find an attribute set by name
+    pub fn attr_set(&self, name: &str) -> Option<&AttrSet> {
+        self.attribute_sets.iter().find(|set| set.name == name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let spec = Spec::parse(
+            "
+name: test
+protocol: genetlink
+definitions:
+  - name: mode
+    type: enum
+    value-start: 2
+    entries: [ a, b, { name: c, value: 7 }, d ]
+  - name: opts
+    type: flags
+    entries: [ x, y ]
+attribute-sets:
+  - name: main
+    attributes:
+      - name: id
+        type: u32
+      - name: port
+        type: u16
+        byte-order: big-endian
+        value: 5
+      - name: items
+        type: indexed-array
+        sub-type: u32
+operations:
+  list:
+    - name: get
+      attribute-set: main
+      do:
+        request: &req
+          attributes: [ id ]
+        reply: *req
+    - name: set
+      value: 4
+      attribute-set: main
+      do:
+        request:
+          attributes: [ id, port ]
+",
+        )
+        .unwrap();
+        match spec.definitions[0].kind {
+            DefinitionKind::Enum(ref entries) => {
+                let values = entries.iter().map(|e| e.value).collect::<Vec<_>>();
+                assert_eq!(values, vec![2, 3, 7, 8]);
+            }
+            ref kind => panic!("Unexpected kind {:?}", kind),
+        }
+        match spec.definitions[1].kind {
+            DefinitionKind::Flags(ref entries) => assert_eq!(entries[1].value, 2),
+            ref kind => panic!("Unexpected kind {:?}", kind),
+        }
+        let set = spec.attr_set("main").unwrap();
+        assert_eq!(
+            set.attrs.iter().map(|a| a.value).collect::<Vec<_>>(),
+            vec![1, 5, 6]
+        );
+        assert_eq!(
+            set.attrs[2].kind,
+            AttrKind::IndexedArray(Box::new(AttrKind::Int {
+                ty: "u32".to_string(),
+                big_endian: false
+            }))
+        );
+        assert_eq!(spec.operations[0].value, 1);
+        assert_eq!(spec.operations[1].value, 4);
+        let get = spec.operations[0].do_op.as_ref().unwrap();
+        assert_eq!(get.reply, Some(vec!["id".to_string()]));
+        assert_eq!(spec.operations[1].do_op.as_ref().unwrap().reply, None);
+    }
+}
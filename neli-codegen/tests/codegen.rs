@@ -0,0 +1,123 @@
+extern crate buffering;
+extern crate neli;
+extern crate neli_codegen;
+
+#[allow(dead_code)]
+#[rustfmt::skip]
+mod sample {
+    include!("generated/sample.rs");
+}
+
+#[allow(dead_code)]
+#[rustfmt::skip]
+mod wireguard {
+    include!("generated/wireguard.rs");
+}
+
+use neli::{Nl, StreamReadBuffer, StreamWriteBuffer};
+
+const AF_INET: u16 = 2;
+
+fn assert_generated(spec: &str, generated: &str, name: &str) {
+    let output = neli_codegen::generate(spec).unwrap();
+    assert!(
+        output == generated,
+        "tests/generated/{name}.rs is out of date; regenerate it with \
+         `cargo run -p neli-codegen -- tests/specs/{name}.yaml tests/generated/{name}.rs`",
+        name = name
+    );
+}
+
+fn round_trip<T>(value: &T) -> T
+where
+    T: Nl,
+{
+    let mut mem = StreamWriteBuffer::new_growable(Some(value.asize()));
+    value.serialize(&mut mem).unwrap();
+    let mut buf = StreamReadBuffer::new(mem.as_ref());
+    buf.set_size_hint(mem.as_ref().len());
+    T::deserialize(&mut buf).unwrap()
+}
+
+#[test]
+fn test_generated_up_to_date() {
+    assert_generated(
+        include_str!("specs/sample.yaml"),
+        include_str!("generated/sample.rs"),
+        "sample",
+    );
+    assert_generated(
+        include_str!("specs/wireguard.yaml"),
+        include_str!("generated/wireguard.rs"),
+        "wireguard",
+    );
+}
+
+#[test]
+fn test_sample_consts() {
+    assert_eq!(sample::NELI_SAMPLE_GENL_NAME, "neli-sample");
+    assert_eq!(sample::NELI_SAMPLE_MAX_NAME_LEN, 16);
+    assert_eq!(u32::from(sample::Mode::On), 4);
+    assert_eq!(sample::Mode::from(5), sample::Mode::SelfValue);
+    assert_eq!(u8::from(sample::NeliSampleCmd::Changed), 2);
+    assert_eq!(u16::from(sample::ConfigAttr::Name), 1);
+}
+
+#[test]
+fn test_sample_round_trip() {
+    let config = sample::Config {
+        name: Some("eth0".to_string()),
+        enabled: true,
+        port: Some(8080),
+        offset: Some(-3),
+        count: Some(1 << 40),
+        delta: Some(-7),
+        mode: Some(u32::from(sample::Mode::On)),
+        tags: vec!["a".to_string(), "b".to_string()],
+        ids: vec![10, 20, 30],
+        stats: Some(sample::Stats { rx: Some(42) }),
+        parent: Some(Box::new(sample::Config {
+            name: Some("bond0".to_string()),
+            ..Default::default()
+        })),
+    };
+    assert_eq!(round_trip(&config), config);
+
+    let attrs = config.to_attrs().unwrap();
+    let port = attrs
+        .iter()
+        .find(|attr| attr.nla_type == sample::ConfigAttr::Port)
+        .unwrap();
+    assert_eq!(port.payload, 8080u16.to_be_bytes());
+    let count = attrs
+        .iter()
+        .find(|attr| attr.nla_type == sample::ConfigAttr::Count)
+        .unwrap();
+    assert_eq!(count.payload.len(), 8);
+
+    let empty = sample::Config::default();
+    assert!(empty.to_attrs().unwrap().is_empty());
+    assert_eq!(round_trip(&empty), empty);
+}
+
+#[test]
+fn test_wireguard_round_trip() {
+    let device = wireguard::Wgdevice {
+        ifname: Some("wg0".to_string()),
+        private_key: Some(vec![1; 32]),
+        listen_port: Some(51820),
+        peers: vec![wireguard::Wgpeer {
+            public_key: Some(vec![2; 32]),
+            persistent_keepalive_interval: Some(25),
+            allowedips: vec![wireguard::Wgallowedip {
+                family: Some(AF_INET),
+                ipaddr: Some(vec![10, 0, 0, 1]),
+                cidr_mask: Some(32),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    assert_eq!(round_trip(&device), device);
+}
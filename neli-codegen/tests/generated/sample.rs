@@ -0,0 +1,313 @@
+// Generated by neli-codegen from the `neli-sample` YNL spec. Do not edit.
+
+use neli::consts::{Cmd, NlAttrType, NlmF};
+use neli::err::{DeError, NlError, SerError};
+use neli::genl::Genlmsghdr;
+use neli::nl::Nlmsghdr;
+use neli::nlattr::{AttrHandle, Nlattr};
+use neli::socket::NlSocket;
+use neli::{impl_var, impl_var_base, impl_var_trait, Nl, StreamReadBuffer, StreamWriteBuffer};
+
+/// Name of the `neli-sample` generic netlink family
+pub const NELI_SAMPLE_GENL_NAME: &str = "neli-sample";
+/// Version of the `neli-sample` generic netlink family
+pub const NELI_SAMPLE_GENL_VERSION: u8 = 1;
+/// Multicast group `monitor`
+pub const NELI_SAMPLE_MCGRP_MONITOR: &str = "monitor";
+
+impl_var!(
+    /// Values of `mode`
+    Mode, u32,
+    Off => 0,
+    On => 4,
+    SelfValue => 5
+);
+
+/// Constant `max-name-len`
+pub const NELI_SAMPLE_MAX_NAME_LEN: u64 = 16;
+
+impl_var_trait!(
+    /// Commands of the `neli-sample` family
+    NeliSampleCmd, u8, Cmd,
+    Get => 1,
+    Changed => 2
+);
+
+impl_var_trait!(
+    /// Attributes of the `config` attribute set
+    ConfigAttr, u16, NlAttrType,
+    Name => 1,
+    Enabled => 2,
+    Port => 3,
+    Offset => 4,
+    Count => 5,
+    Delta => 6,
+    Mode => 7,
+    Tags => 8,
+    Ids => 9,
+    Stats => 10,
+    Pad => 11,
+    Parent => 12
+);
+
+/// Attributes of the `config` attribute set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// `name`
+    pub name: Option<String>,
+    /// `enabled`
+    pub enabled: bool,
+    /// `port`
+    pub port: Option<u16>,
+    /// `offset`
+    pub offset: Option<i32>,
+    /// `count`
+    pub count: Option<u64>,
+    /// `delta`
+    pub delta: Option<i64>,
+    /// `mode`
+    pub mode: Option<u32>,
+    /// `tags`
+    pub tags: Vec<String>,
+    /// `ids`
+    pub ids: Vec<u32>,
+    /// `stats`
+    pub stats: Option<Stats>,
+    /// `parent`
+    pub parent: Option<Box<Config>>,
+}
+
+impl Config {
+    /// Serialize the set into attributes
+    pub fn to_attrs(&self) -> Result<Vec<Nlattr<ConfigAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ref v) = self.name {
+            attrs.push(Nlattr::new(None, ConfigAttr::Name, v.as_str())?);
+        }
+        if self.enabled {
+            attrs.push(Nlattr::new(None, ConfigAttr::Enabled, Vec::<u8>::new())?);
+        }
+        if let Some(ref v) = self.port {
+            attrs.push(Nlattr::new(None, ConfigAttr::Port, v.to_be())?);
+        }
+        if let Some(ref v) = self.offset {
+            attrs.push(Nlattr::new(None, ConfigAttr::Offset, *v as u32)?);
+        }
+        if let Some(ref v) = self.count {
+            attrs.push(Nlattr::new(None, ConfigAttr::Count, *v)?);
+        }
+        if let Some(ref v) = self.delta {
+            attrs.push(Nlattr::new(None, ConfigAttr::Delta, *v as u64)?);
+        }
+        if let Some(ref v) = self.mode {
+            attrs.push(Nlattr::new(None, ConfigAttr::Mode, *v)?);
+        }
+        for v in self.tags.iter() {
+            attrs.push(Nlattr::new(None, ConfigAttr::Tags, v.as_str())?);
+        }
+        if !self.ids.is_empty() {
+            let mut items = Vec::new();
+            for (i, v) in self.ids.iter().enumerate() {
+                items.push(Nlattr::new(None, i as u16, *v)?);
+            }
+            attrs.push(Nlattr::new_nested(ConfigAttr::Ids, &items)?.with_nested_flag());
+        }
+        if let Some(ref v) = self.stats {
+            attrs.push(Nlattr::new_nested(ConfigAttr::Stats, &v.to_attrs()?).map(Nlattr::with_nested_flag)?);
+        }
+        if let Some(ref v) = self.parent {
+            attrs.push(Nlattr::new_nested(ConfigAttr::Parent, &v.to_attrs()?).map(Nlattr::with_nested_flag)?);
+        }
+        Ok(attrs)
+    }
+
+    /// Parse the set from attributes
+    pub fn from_attrs(handle: &AttrHandle<ConfigAttr>) -> Result<Self, DeError> {
+        let mut set = Config::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                ConfigAttr::Name => set.name = Some(attr.get_payload_as::<String>()?),
+                ConfigAttr::Enabled => set.enabled = true,
+                ConfigAttr::Port => set.port = Some(u16::from_be(attr.get_payload_as::<u16>()?)),
+                ConfigAttr::Offset => set.offset = Some(attr.get_payload_as::<u32>()? as i32),
+                ConfigAttr::Count => set.count = Some(parse_uint(attr)?),
+                ConfigAttr::Delta => set.delta = Some(parse_uint(attr)? as i64),
+                ConfigAttr::Mode => set.mode = Some(attr.get_payload_as::<u32>()?),
+                ConfigAttr::Tags => set.tags.push(attr.get_payload_as::<String>()?),
+                ConfigAttr::Ids => {
+                    for item in attr.get_nested_attributes::<u16>()?.iter() {
+                        set.ids.push(item.get_payload_as::<u32>()?);
+                    }
+                },
+                ConfigAttr::Stats => set.stats = Some(Stats::from_attrs(&attr.get_nested_attributes::<StatsAttr>()?)?),
+                ConfigAttr::Parent => set.parent = Some(Box::new(Config::from_attrs(&attr.get_nested_attributes::<ConfigAttr>()?)?)),
+                _ => (),
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl Nl for Config {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.to_attrs()?.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let attrs = Vec::<Nlattr<ConfigAttr, Vec<u8>>>::deserialize(buf)?;
+        Config::from_attrs(&AttrHandle::new(attrs))
+    }
+
+    fn size(&self) -> usize {
+        self.to_attrs().map(|attrs| attrs.asize()).unwrap_or(0)
+    }
+}
+
+impl_var_trait!(
+    /// Attributes of the `stats` attribute set
+    StatsAttr, u16, NlAttrType,
+    Rx => 1
+);
+
+/// Attributes of the `stats` attribute set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// `rx`
+    pub rx: Option<u64>,
+}
+
+impl Stats {
+    /// Serialize the set into attributes
+    pub fn to_attrs(&self) -> Result<Vec<Nlattr<StatsAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ref v) = self.rx {
+            attrs.push(Nlattr::new(None, StatsAttr::Rx, *v)?);
+        }
+        Ok(attrs)
+    }
+
+    /// Parse the set from attributes
+    pub fn from_attrs(handle: &AttrHandle<StatsAttr>) -> Result<Self, DeError> {
+        let mut set = Stats::default();
+        for attr in handle.iter() {
+            if attr.nla_type == StatsAttr::Rx {
+                set.rx = Some(attr.get_payload_as::<u64>()?);
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl Nl for Stats {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.to_attrs()?.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let attrs = Vec::<Nlattr<StatsAttr, Vec<u8>>>::deserialize(buf)?;
+        Stats::from_attrs(&AttrHandle::new(attrs))
+    }
+
+    fn size(&self) -> usize {
+        self.to_attrs().map(|attrs| attrs.asize()).unwrap_or(0)
+    }
+}
+
+/// Subset of `config`
+pub type ConfigIdAttr = ConfigAttr;
+/// Subset of `config`
+pub type ConfigId = Config;
+
+/// Operations of the `neli-sample` family
+pub trait NeliSampleOps {
+    /// Resolve the ID of the `neli-sample` family
+    fn neli_sample_family_id(&mut self) -> Result<u16, NlError>;
+
+    /// Get a configuration.
+    ///
+    /// Request attributes: name
+    fn neli_sample_get(
+        &mut self,
+        family_id: u16,
+        request: &Config,
+    ) -> Result<Config, NlError>;
+
+    /// Get a configuration.
+    fn neli_sample_get_dump(
+        &mut self,
+        family_id: u16,
+        request: &Config,
+    ) -> Result<Vec<Config>, NlError>;
+}
+
+impl NeliSampleOps for NlSocket {
+    fn neli_sample_family_id(&mut self) -> Result<u16, NlError> {
+        self.resolve_genl_family(NELI_SAMPLE_GENL_NAME)
+    }
+
+    fn neli_sample_get(
+        &mut self,
+        family_id: u16,
+        request: &Config,
+    ) -> Result<Config, NlError> {
+        let genlhdr = Genlmsghdr::new(
+            NeliSampleCmd::Get,
+            NELI_SAMPLE_GENL_VERSION,
+            request.to_attrs()?,
+        )?;
+        self.send_nl(Nlmsghdr::new(
+            None,
+            family_id,
+            vec![NlmF::Request],
+            None,
+            None,
+            genlhdr,
+        ))?;
+        let msg = self.recv_all::<u16, Genlmsghdr<NeliSampleCmd, ConfigAttr>>()?
+            .pop()
+            .ok_or_else(|| NlError::new("No reply returned"))?;
+        Ok(Config::from_attrs(&msg.nl_payload.get_attr_handle())?)
+    }
+
+    fn neli_sample_get_dump(
+        &mut self,
+        family_id: u16,
+        request: &Config,
+    ) -> Result<Vec<Config>, NlError> {
+        let genlhdr = Genlmsghdr::new(
+            NeliSampleCmd::Get,
+            NELI_SAMPLE_GENL_VERSION,
+            request.to_attrs()?,
+        )?;
+        self.send_nl(Nlmsghdr::new(
+            None,
+            family_id,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            genlhdr,
+        ))?;
+        self.recv_all::<u16, Genlmsghdr<NeliSampleCmd, ConfigAttr>>()?
+            .iter()
+            .map(|msg| Ok(Config::from_attrs(&msg.nl_payload.get_attr_handle())?))
+            .collect()
+    }
+}
+
+/// Parse a variable length integer, sent as 4 or 8 bytes
+fn parse_uint<T>(attr: &Nlattr<T, Vec<u8>>) -> Result<u64, DeError>
+where
+    T: NlAttrType,
+{
+    if attr.payload.len() == 4 {
+        Ok(u64::from(attr.get_payload_as::<u32>()?))
+    } else {
+        attr.get_payload_as::<u64>()
+    }
+}
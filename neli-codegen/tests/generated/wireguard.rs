@@ -0,0 +1,463 @@
+// Generated by neli-codegen from the `wireguard` YNL spec. Do not edit.
+
+use neli::consts::{Cmd, NlAttrType, NlmF};
+use neli::err::{DeError, NlError, SerError};
+use neli::genl::Genlmsghdr;
+use neli::nl::Nlmsghdr;
+use neli::nlattr::{AttrHandle, Nlattr};
+use neli::socket::NlSocket;
+use neli::{impl_var, impl_var_base, impl_var_trait, Nl, StreamReadBuffer, StreamWriteBuffer};
+
+/// Name of the `wireguard` generic netlink family
+pub const WIREGUARD_GENL_NAME: &str = "wireguard";
+/// Version of the `wireguard` generic netlink family
+pub const WIREGUARD_GENL_VERSION: u8 = 1;
+
+/// Constant `key-len`
+pub const WIREGUARD_KEY_LEN: u64 = 32;
+
+impl_var!(
+    /// Values of `wgdevice-flag`
+    WgdeviceFlag, u32,
+    ReplacePeers => 1
+);
+
+impl_var!(
+    /// Values of `wgpeer-flag`
+    WgpeerFlag, u32,
+    RemoveMe => 1,
+    ReplaceAllowedips => 2,
+    UpdateOnly => 4
+);
+
+impl_var!(
+    /// Values of `wgallowedip-flag`
+    WgallowedipFlag, u32,
+    RemoveMe => 1
+);
+
+impl_var_trait!(
+    /// Commands of the `wireguard` family
+    WireguardCmd, u8, Cmd,
+    GetDevice => 0,
+    SetDevice => 1
+);
+
+impl_var_trait!(
+    /// Attributes of the `wgdevice` attribute set
+    WgdeviceAttr, u16, NlAttrType,
+    Unspec => 0,
+    Ifindex => 1,
+    Ifname => 2,
+    PrivateKey => 3,
+    PublicKey => 4,
+    Flags => 5,
+    ListenPort => 6,
+    Fwmark => 7,
+    Peers => 8
+);
+
+/// Attributes of the `wgdevice` attribute set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wgdevice {
+    /// `ifindex`
+    pub ifindex: Option<u32>,
+    /// `ifname`
+    pub ifname: Option<String>,
+    /// Set to all zeros to remove.
+    pub private_key: Option<Vec<u8>>,
+    /// `public-key`
+    pub public_key: Option<Vec<u8>>,
+    /// 0 or WGDEVICE_F_REPLACE_PEERS if all current peers should be
+    /// removed prior to adding the list below.
+    pub flags: Option<u32>,
+    /// Set as 0 to choose randomly.
+    pub listen_port: Option<u16>,
+    /// Set as 0 to disable.
+    pub fwmark: Option<u32>,
+    /// The index/type parameter is unused on SET_DEVICE operations and is
+    /// zero on GET_DEVICE operations.
+    pub peers: Vec<Wgpeer>,
+}
+
+impl Wgdevice {
+    /// Serialize the set into attributes
+    pub fn to_attrs(&self) -> Result<Vec<Nlattr<WgdeviceAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ref v) = self.ifindex {
+            attrs.push(Nlattr::new(None, WgdeviceAttr::Ifindex, *v)?);
+        }
+        if let Some(ref v) = self.ifname {
+            attrs.push(Nlattr::new(None, WgdeviceAttr::Ifname, v.as_str())?);
+        }
+        if let Some(ref v) = self.private_key {
+            attrs.push(Nlattr::new(None, WgdeviceAttr::PrivateKey, v.as_slice())?);
+        }
+        if let Some(ref v) = self.public_key {
+            attrs.push(Nlattr::new(None, WgdeviceAttr::PublicKey, v.as_slice())?);
+        }
+        if let Some(ref v) = self.flags {
+            attrs.push(Nlattr::new(None, WgdeviceAttr::Flags, *v)?);
+        }
+        if let Some(ref v) = self.listen_port {
+            attrs.push(Nlattr::new(None, WgdeviceAttr::ListenPort, *v)?);
+        }
+        if let Some(ref v) = self.fwmark {
+            attrs.push(Nlattr::new(None, WgdeviceAttr::Fwmark, *v)?);
+        }
+        if !self.peers.is_empty() {
+            let mut items = Vec::new();
+            for (i, v) in self.peers.iter().enumerate() {
+                items.push(Nlattr::new_nested(i as u16, &v.to_attrs()?).map(Nlattr::with_nested_flag)?);
+            }
+            attrs.push(Nlattr::new_nested(WgdeviceAttr::Peers, &items)?.with_nested_flag());
+        }
+        Ok(attrs)
+    }
+
+    /// Parse the set from attributes
+    pub fn from_attrs(handle: &AttrHandle<WgdeviceAttr>) -> Result<Self, DeError> {
+        let mut set = Wgdevice::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                WgdeviceAttr::Ifindex => set.ifindex = Some(attr.get_payload_as::<u32>()?),
+                WgdeviceAttr::Ifname => set.ifname = Some(attr.get_payload_as::<String>()?),
+                WgdeviceAttr::PrivateKey => set.private_key = Some(attr.payload.clone()),
+                WgdeviceAttr::PublicKey => set.public_key = Some(attr.payload.clone()),
+                WgdeviceAttr::Flags => set.flags = Some(attr.get_payload_as::<u32>()?),
+                WgdeviceAttr::ListenPort => set.listen_port = Some(attr.get_payload_as::<u16>()?),
+                WgdeviceAttr::Fwmark => set.fwmark = Some(attr.get_payload_as::<u32>()?),
+                WgdeviceAttr::Peers => {
+                    for item in attr.get_nested_attributes::<u16>()?.iter() {
+                        set.peers.push(Wgpeer::from_attrs(&item.get_nested_attributes::<WgpeerAttr>()?)?);
+                    }
+                },
+                _ => (),
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl Nl for Wgdevice {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.to_attrs()?.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let attrs = Vec::<Nlattr<WgdeviceAttr, Vec<u8>>>::deserialize(buf)?;
+        Wgdevice::from_attrs(&AttrHandle::new(attrs))
+    }
+
+    fn size(&self) -> usize {
+        self.to_attrs().map(|attrs| attrs.asize()).unwrap_or(0)
+    }
+}
+
+impl_var_trait!(
+    /// Attributes of the `wgpeer` attribute set
+    WgpeerAttr, u16, NlAttrType,
+    Unspec => 0,
+    PublicKey => 1,
+    PresharedKey => 2,
+    Flags => 3,
+    Endpoint => 4,
+    PersistentKeepaliveInterval => 5,
+    LastHandshakeTime => 6,
+    RxBytes => 7,
+    TxBytes => 8,
+    Allowedips => 9,
+    ProtocolVersion => 10
+);
+
+/// Attributes of the `wgpeer` attribute set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wgpeer {
+    /// `public-key`
+    pub public_key: Option<Vec<u8>>,
+    /// Set as all zeros to remove.
+    pub preshared_key: Option<Vec<u8>>,
+    /// 0 and/or WGPEER_F_REMOVE_ME if the specified peer should not exist
+    /// at the end of the operation, rather than added/updated and/or
+    /// WGPEER_F_REPLACE_ALLOWEDIPS if all current allowed IPs of this
+    /// peer should be removed prior to adding the list below and/or
+    /// WGPEER_F_UPDATE_ONLY if the peer should only be set if it already
+    /// exists.
+    pub flags: Option<u32>,
+    /// struct sockaddr_in or struct sockaddr_in6
+    pub endpoint: Option<Vec<u8>>,
+    /// Set as 0 to disable.
+    pub persistent_keepalive_interval: Option<u16>,
+    /// `last-handshake-time`
+    pub last_handshake_time: Option<Vec<u8>>,
+    /// `rx-bytes`
+    pub rx_bytes: Option<u64>,
+    /// `tx-bytes`
+    pub tx_bytes: Option<u64>,
+    /// The index/type parameter is unused on SET_DEVICE operations and is
+    /// zero on GET_DEVICE operations.
+    pub allowedips: Vec<Wgallowedip>,
+    /// Should not be set or used at all by most users of this API, as the
+    /// most recent protocol will be used when this is unset. Otherwise,
+    /// must be set to 1.
+    pub protocol_version: Option<u32>,
+}
+
+impl Wgpeer {
+    /// Serialize the set into attributes
+    pub fn to_attrs(&self) -> Result<Vec<Nlattr<WgpeerAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ref v) = self.public_key {
+            attrs.push(Nlattr::new(None, WgpeerAttr::PublicKey, v.as_slice())?);
+        }
+        if let Some(ref v) = self.preshared_key {
+            attrs.push(Nlattr::new(None, WgpeerAttr::PresharedKey, v.as_slice())?);
+        }
+        if let Some(ref v) = self.flags {
+            attrs.push(Nlattr::new(None, WgpeerAttr::Flags, *v)?);
+        }
+        if let Some(ref v) = self.endpoint {
+            attrs.push(Nlattr::new(None, WgpeerAttr::Endpoint, v.as_slice())?);
+        }
+        if let Some(ref v) = self.persistent_keepalive_interval {
+            attrs.push(Nlattr::new(None, WgpeerAttr::PersistentKeepaliveInterval, *v)?);
+        }
+        if let Some(ref v) = self.last_handshake_time {
+            attrs.push(Nlattr::new(None, WgpeerAttr::LastHandshakeTime, v.as_slice())?);
+        }
+        if let Some(ref v) = self.rx_bytes {
+            attrs.push(Nlattr::new(None, WgpeerAttr::RxBytes, *v)?);
+        }
+        if let Some(ref v) = self.tx_bytes {
+            attrs.push(Nlattr::new(None, WgpeerAttr::TxBytes, *v)?);
+        }
+        if !self.allowedips.is_empty() {
+            let mut items = Vec::new();
+            for (i, v) in self.allowedips.iter().enumerate() {
+                items.push(Nlattr::new_nested(i as u16, &v.to_attrs()?).map(Nlattr::with_nested_flag)?);
+            }
+            attrs.push(Nlattr::new_nested(WgpeerAttr::Allowedips, &items)?.with_nested_flag());
+        }
+        if let Some(ref v) = self.protocol_version {
+            attrs.push(Nlattr::new(None, WgpeerAttr::ProtocolVersion, *v)?);
+        }
+        Ok(attrs)
+    }
+
+    /// Parse the set from attributes
+    pub fn from_attrs(handle: &AttrHandle<WgpeerAttr>) -> Result<Self, DeError> {
+        let mut set = Wgpeer::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                WgpeerAttr::PublicKey => set.public_key = Some(attr.payload.clone()),
+                WgpeerAttr::PresharedKey => set.preshared_key = Some(attr.payload.clone()),
+                WgpeerAttr::Flags => set.flags = Some(attr.get_payload_as::<u32>()?),
+                WgpeerAttr::Endpoint => set.endpoint = Some(attr.payload.clone()),
+                WgpeerAttr::PersistentKeepaliveInterval => set.persistent_keepalive_interval = Some(attr.get_payload_as::<u16>()?),
+                WgpeerAttr::LastHandshakeTime => set.last_handshake_time = Some(attr.payload.clone()),
+                WgpeerAttr::RxBytes => set.rx_bytes = Some(attr.get_payload_as::<u64>()?),
+                WgpeerAttr::TxBytes => set.tx_bytes = Some(attr.get_payload_as::<u64>()?),
+                WgpeerAttr::Allowedips => {
+                    for item in attr.get_nested_attributes::<u16>()?.iter() {
+                        set.allowedips.push(Wgallowedip::from_attrs(&item.get_nested_attributes::<WgallowedipAttr>()?)?);
+                    }
+                },
+                WgpeerAttr::ProtocolVersion => set.protocol_version = Some(attr.get_payload_as::<u32>()?),
+                _ => (),
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl Nl for Wgpeer {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.to_attrs()?.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let attrs = Vec::<Nlattr<WgpeerAttr, Vec<u8>>>::deserialize(buf)?;
+        Wgpeer::from_attrs(&AttrHandle::new(attrs))
+    }
+
+    fn size(&self) -> usize {
+        self.to_attrs().map(|attrs| attrs.asize()).unwrap_or(0)
+    }
+}
+
+impl_var_trait!(
+    /// Attributes of the `wgallowedip` attribute set
+    WgallowedipAttr, u16, NlAttrType,
+    Unspec => 0,
+    Family => 1,
+    Ipaddr => 2,
+    CidrMask => 3,
+    Flags => 4
+);
+
+/// Attributes of the `wgallowedip` attribute set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wgallowedip {
+    /// `family`
+    pub family: Option<u16>,
+    /// struct in_addr or struct in6_add
+    pub ipaddr: Option<Vec<u8>>,
+    /// `cidr-mask`
+    pub cidr_mask: Option<u8>,
+    /// WGALLOWEDIP_F_REMOVE_ME if the specified IP should be removed;
+    /// otherwise, this IP will be added if it is not already present.
+    pub flags: Option<u32>,
+}
+
+impl Wgallowedip {
+    /// Serialize the set into attributes
+    pub fn to_attrs(&self) -> Result<Vec<Nlattr<WgallowedipAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ref v) = self.family {
+            attrs.push(Nlattr::new(None, WgallowedipAttr::Family, *v)?);
+        }
+        if let Some(ref v) = self.ipaddr {
+            attrs.push(Nlattr::new(None, WgallowedipAttr::Ipaddr, v.as_slice())?);
+        }
+        if let Some(ref v) = self.cidr_mask {
+            attrs.push(Nlattr::new(None, WgallowedipAttr::CidrMask, *v)?);
+        }
+        if let Some(ref v) = self.flags {
+            attrs.push(Nlattr::new(None, WgallowedipAttr::Flags, *v)?);
+        }
+        Ok(attrs)
+    }
+
+    /// Parse the set from attributes
+    pub fn from_attrs(handle: &AttrHandle<WgallowedipAttr>) -> Result<Self, DeError> {
+        let mut set = Wgallowedip::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                WgallowedipAttr::Family => set.family = Some(attr.get_payload_as::<u16>()?),
+                WgallowedipAttr::Ipaddr => set.ipaddr = Some(attr.payload.clone()),
+                WgallowedipAttr::CidrMask => set.cidr_mask = Some(attr.get_payload_as::<u8>()?),
+                WgallowedipAttr::Flags => set.flags = Some(attr.get_payload_as::<u32>()?),
+                _ => (),
+            }
+        }
+        Ok(set)
+    }
+}
+
+impl Nl for Wgallowedip {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.to_attrs()?.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let attrs = Vec::<Nlattr<WgallowedipAttr, Vec<u8>>>::deserialize(buf)?;
+        Wgallowedip::from_attrs(&AttrHandle::new(attrs))
+    }
+
+    fn size(&self) -> usize {
+        self.to_attrs().map(|attrs| attrs.asize()).unwrap_or(0)
+    }
+}
+
+/// Operations of the `wireguard` family
+pub trait WireguardOps {
+    /// Resolve the ID of the `wireguard` family
+    fn wireguard_family_id(&mut self) -> Result<u16, NlError>;
+
+    /// Retrieve WireGuard device
+    ///
+    /// The command should be called with one but not both of:
+    ///   - WGDEVICE_A_IFINDEX
+    ///   - WGDEVICE_A_IFNAME
+    ///
+    /// The kernel will then return several messages (NLM_F_MULTI). It is
+    /// possible that all of the allowed IPs of a single peer will not fit
+    /// within a single netlink message. In that case, the same peer will be
+    /// written in the following message, except it will only contain
+    /// WGPEER_A_PUBLIC_KEY and WGPEER_A_ALLOWEDIPS. This may occur several
+    /// times in a row for the same peer. It is then up to the receiver to
+    /// coalesce adjacent peers. Likewise, it is possible that all peers will
+    /// not fit within a single message. So, subsequent peers will be sent in
+    /// following messages, except those will only contain WGDEVICE_A_IFNAME
+    /// and WGDEVICE_A_PEERS. It is then up to the receiver to coalesce these
+    /// messages to form the complete list of peers.
+    ///
+    /// Request attributes: ifindex, ifname
+    fn wireguard_get_device_dump(
+        &mut self,
+        family_id: u16,
+        request: &Wgdevice,
+    ) -> Result<Vec<Wgdevice>, NlError>;
+
+    /// Set WireGuard device
+    ///
+    /// This command should be called with a wgdevice set, containing one but
+    /// not both of WGDEVICE_A_IFINDEX and WGDEVICE_A_IFNAME.
+    ///
+    /// Request attributes: ifindex, ifname, private-key, public-key, flags, listen-port, fwmark, peers
+    fn wireguard_set_device(
+        &mut self,
+        family_id: u16,
+        request: &Wgdevice,
+    ) -> Result<(), NlError>;
+}
+
+impl WireguardOps for NlSocket {
+    fn wireguard_family_id(&mut self) -> Result<u16, NlError> {
+        self.resolve_genl_family(WIREGUARD_GENL_NAME)
+    }
+
+    fn wireguard_get_device_dump(
+        &mut self,
+        family_id: u16,
+        request: &Wgdevice,
+    ) -> Result<Vec<Wgdevice>, NlError> {
+        let genlhdr = Genlmsghdr::new(
+            WireguardCmd::GetDevice,
+            WIREGUARD_GENL_VERSION,
+            request.to_attrs()?,
+        )?;
+        self.send_nl(Nlmsghdr::new(
+            None,
+            family_id,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            genlhdr,
+        ))?;
+        self.recv_all::<u16, Genlmsghdr<WireguardCmd, WgdeviceAttr>>()?
+            .iter()
+            .map(|msg| Ok(Wgdevice::from_attrs(&msg.nl_payload.get_attr_handle())?))
+            .collect()
+    }
+
+    fn wireguard_set_device(
+        &mut self,
+        family_id: u16,
+        request: &Wgdevice,
+    ) -> Result<(), NlError> {
+        let genlhdr = Genlmsghdr::new(
+            WireguardCmd::SetDevice,
+            WIREGUARD_GENL_VERSION,
+            request.to_attrs()?,
+        )?;
+        self.send_nl(Nlmsghdr::new(
+            None,
+            family_id,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            genlhdr,
+        ))?;
+        self.recv_ack()
+    }
+}
@@ -0,0 +1,572 @@
+//! This module provides a client for the Linux audit subsystem on the `NlFamily::Audit`
+//! protocol: reading and changing the audit status, managing audit rules and receiving audit
+//! records without running `auditd`.
+//!
+//! ```no_run
+//! use neli::audit::AuditRule;
+//! use neli::consts::audit::{AuditAction, AuditField, AuditFilter, AuditOp};
+//! use neli::consts::NlFamily;
+//! use neli::socket::NlSocket;
+//!
+//! let mut socket = NlSocket::connect(NlFamily::Audit, None, None, true).unwrap();
+//! // Log all calls to execve
+//! let mut rule = AuditRule::new(AuditFilter::Exit, AuditAction::Always);
+//! rule.syscalls.push(libc::SYS_execve as u32);
+//! rule.add_field(AuditField::Filterkey, AuditOp::Equal, "exec");
+//! socket.audit_add_rule(&rule).unwrap();
+//!
+//! // Register as the audit daemon to receive the records
+//! socket.audit_set_enabled(true).unwrap();
+//! let mut daemon = NlSocket::connect(NlFamily::Audit, None, None, true).unwrap();
+//! daemon.audit_set_pid(std::process::id()).unwrap();
+//! loop {
+//!     let record = daemon.audit_recv_record().unwrap();
+//!     println!("{:?} {}", record.msg_type, record.text);
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! The kernel sets the length in the netlink header of audit records to the length of the
+//! payload only, so records cannot be received with `NlSocket.recv_nl()`. Each record is sent in
+//! its own datagram and `NlSocket.audit_recv_record()` uses the datagram length instead.
+//!
+//! Once a socket is registered as the audit daemon, records can arrive between the replies to
+//! requests sent on it, so requests should be sent on a separate socket.
+//!
+//! Only one process can be registered as the audit daemon at a time. Processes with
+//! `CAP_AUDIT_READ` can instead subscribe to multicast group `1` (`AUDIT_NLGRP_READLOG`) and
+//! receive the records with the same method.
+
+use buffering::{StreamReadBuffer, StreamWriteBuffer};
+
+use consts::audit::{AuditAction, AuditField, AuditFilter, AuditMsg, AuditOp, AuditStatusMask};
+use consts::NlmF;
+use err::{DeError, NlError, SerError};
+use nl::Nlmsghdr;
+use socket::NlSocket;
+use {Nl, MAX_NL_LENGTH};
+
+/// Maximum number of fields of an audit rule
+const AUDIT_MAX_FIELDS: usize = 64;
+/// Number of words of the syscall mask of an audit rule
+const AUDIT_BITMASK_SIZE: usize = 64;
+/// Size of a netlink header, which precedes each audit record
+const NLMSG_HDRLEN: usize = 16;
+
+/// Audit status (`struct audit_status`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AuditStatus {
+    /// Fields to change in an `AuditMsg::Set` request
+    pub mask: Vec<AuditStatusMask>,
+    /// `0` disabled, `1` enabled, `2` enabled and locked until reboot
+    pub enabled: u32,
+    /// Action on critical errors: `0` silent, `1` printk, `2` panic
+    pub failure: u32,
+    /// PID of the audit daemon, `0` if none is registered
+    pub pid: u32,
+    /// Maximal number of records per second, `0` for no limit
+    pub rate_limit: u32,
+    /// Maximal number of queued records
+    pub backlog_limit: u32,
+    /// Number of records lost
+    pub lost: u32,
+    /// Number of queued records
+    pub backlog: u32,
+    /// Features supported by the kernel (`AUDIT_FEATURE_BITMAP_*`)
+    pub feature_bitmap: u32,
+    /// Time to wait for space in the backlog queue, in jiffies
+    pub backlog_wait_time: u32,
+    /// Time spent waiting for space in the backlog queue, in jiffies
+    pub backlog_wait_time_actual: u32,
+}
+
+impl Nl for AuditStatus {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.mask
+            .iter()
+            .fold(0, |acc, flag| acc | u32::from(flag))
+            .serialize(buf)?;
+        self.enabled.serialize(buf)?;
+        self.failure.serialize(buf)?;
+        self.pid.serialize(buf)?;
+        self.rate_limit.serialize(buf)?;
+        self.backlog_limit.serialize(buf)?;
+        self.lost.serialize(buf)?;
+        self.backlog.serialize(buf)?;
+        self.feature_bitmap.serialize(buf)?;
+        self.backlog_wait_time.serialize(buf)?;
+        self.backlog_wait_time_actual.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        // Older kernels send a shorter structure
+        let words = buf.take_size_hint().map(|hint| hint / 4).unwrap_or(11);
+        let mut values = [0u32; 11];
+        for value in values.iter_mut().take(words) {
+            *value = u32::deserialize(buf)?;
+        }
+        Ok(AuditStatus {
+            mask: (0..32)
+                .map(|bit| 1 << bit)
+                .filter(|flag| values[0] & flag != 0)
+                .map(AuditStatusMask::from)
+                .collect(),
+            enabled: values[1],
+            failure: values[2],
+            pid: values[3],
+            rate_limit: values[4],
+            backlog_limit: values[5],
+            lost: values[6],
+            backlog: values[7],
+            feature_bitmap: values[8],
+            backlog_wait_time: values[9],
+            backlog_wait_time_actual: values[10],
+        })
+    }
+
+    fn size(&self) -> usize {
+        11 * 4
+    }
+}
+
+/// Value of an audit rule field
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditValue {
+    /// Numeric value
+    Num(u32),
+    /// String value, for paths, keys and security labels
+    Str(String),
+}
+
+impl From<u32> for AuditValue {
+    fn from(v: u32) -> Self {
+        AuditValue::Num(v)
+    }
+}
+
+impl<'a> From<&'a str> for AuditValue {
+    fn from(v: &'a str) -> Self {
+        AuditValue::Str(v.to_string())
+    }
+}
+
+/// Field comparison of an audit rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditRuleField {
+    /// Compared field
+    pub field: AuditField,
+    /// Comparison operator
+    pub op: AuditOp,
+    /// Value compared against
+    pub value: AuditValue,
+}
+
+fn is_string_field(field: &AuditField) -> bool {
+    matches!(
+        *field,
+        AuditField::SubjUser
+            | AuditField::SubjRole
+            | AuditField::SubjType
+            | AuditField::SubjSen
+            | AuditField::SubjClr
+            | AuditField::ObjUser
+            | AuditField::ObjRole
+            | AuditField::ObjType
+            | AuditField::ObjLevLow
+            | AuditField::ObjLevHigh
+            | AuditField::Watch
+            | AuditField::Dir
+            | AuditField::Filterkey
+            | AuditField::Exe
+    )
+}
+
+/// Audit rule (`struct audit_rule_data`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditRule {
+    /// Filter list of the rule
+    pub filter: AuditFilter,
+    /// Action of the rule
+    pub action: AuditAction,
+    /// System call numbers the rule applies to, all system calls if empty
+    pub syscalls: Vec<u32>,
+    /// Field comparisons that all have to match
+    pub fields: Vec<AuditRuleField>,
+}
+
+impl AuditRule {
+    /// Create a rule applying to all system calls without field comparisons
+    pub fn new(filter: AuditFilter, action: AuditAction) -> Self {
+        AuditRule {
+            filter,
+            action,
+            syscalls: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Add the comparison of `field` with `value` to the rule
+    pub fn add_field<V>(&mut self, field: AuditField, op: AuditOp, value: V) -> &mut Self
+    where
+        V: Into<AuditValue>,
+    {
+        self.fields.push(AuditRuleField {
+            field,
+            op,
+            value: value.into(),
+        });
+        self
+    }
+
+    fn strings(&self) -> Vec<u8> {
+        let mut strings = Vec::new();
+        for field in self.fields.iter() {
+            if let AuditValue::Str(ref s) = field.value {
+                strings.extend_from_slice(s.as_bytes());
+            }
+        }
+        strings
+    }
+}
+
+impl Nl for AuditRule {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        if self.fields.len() > AUDIT_MAX_FIELDS {
+            return Err(SerError::new("Audit rules have at most 64 fields"));
+        }
+        let mut mask = [0u32; AUDIT_BITMASK_SIZE];
+        if self.syscalls.is_empty() {
+            mask = [!0; AUDIT_BITMASK_SIZE];
+        }
+        for &syscall in self.syscalls.iter() {
+            let word = mask
+                .get_mut(syscall as usize / 32)
+                .ok_or_else(|| SerError::new("System call number out of range"))?;
+            *word |= 1 << (syscall % 32);
+        }
+        let mut fields = [0u32; AUDIT_MAX_FIELDS];
+        let mut values = [0u32; AUDIT_MAX_FIELDS];
+        let mut fieldflags = [0u32; AUDIT_MAX_FIELDS];
+        for (i, field) in self.fields.iter().enumerate() {
+            fields[i] = u32::from(&field.field);
+            fieldflags[i] = u32::from(&field.op);
+            values[i] = match field.value {
+                AuditValue::Num(v) => v,
+                AuditValue::Str(ref s) => s.len() as u32,
+            };
+        }
+        let strings = self.strings();
+
+        self.filter.serialize(buf)?;
+        self.action.serialize(buf)?;
+        (self.fields.len() as u32).serialize(buf)?;
+        for word in mask
+            .iter()
+            .chain(fields.iter())
+            .chain(values.iter())
+            .chain(fieldflags.iter())
+        {
+            word.serialize(buf)?;
+        }
+        (strings.len() as u32).serialize(buf)?;
+        strings.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let filter = AuditFilter::deserialize(buf)?;
+        let action = AuditAction::deserialize(buf)?;
+        let field_count = u32::deserialize(buf)? as usize;
+        if field_count > AUDIT_MAX_FIELDS {
+            return Err(DeError::new("Audit rule has too many fields"));
+        }
+        let mut words = [0u32; AUDIT_BITMASK_SIZE + 3 * AUDIT_MAX_FIELDS];
+        for word in words.iter_mut() {
+            *word = u32::deserialize(buf)?;
+        }
+        let buflen = u32::deserialize(buf)? as usize;
+        buf.set_size_hint(buflen);
+        let strings = Vec::<u8>::deserialize(buf)?;
+
+        let (mask, words) = words.split_at(AUDIT_BITMASK_SIZE);
+        let syscalls = if mask.iter().all(|word| *word == !0) {
+            Vec::new()
+        } else {
+            (0..(AUDIT_BITMASK_SIZE * 32) as u32)
+                .filter(|nr| mask[*nr as usize / 32] & (1 << (nr % 32)) != 0)
+                .collect()
+        };
+        let mut offset = 0;
+        let mut fields = Vec::with_capacity(field_count);
+        for i in 0..field_count {
+            let field = AuditField::from(words[i]);
+            let value = words[AUDIT_MAX_FIELDS + i];
+            let value = if is_string_field(&field) {
+                let s = strings
+                    .get(offset..offset + value as usize)
+                    .ok_or_else(|| DeError::new("Audit rule string buffer is too short"))?;
+                offset += value as usize;
+                AuditValue::Str(String::from_utf8_lossy(s).into_owned())
+            } else {
+                AuditValue::Num(value)
+            };
+            fields.push(AuditRuleField {
+                field,
+                op: AuditOp::from(words[2 * AUDIT_MAX_FIELDS + i]),
+                value,
+            });
+        }
+        Ok(AuditRule {
+            filter,
+            action,
+            syscalls,
+            fields,
+        })
+    }
+
+    fn size(&self) -> usize {
+        4 * (3 + AUDIT_BITMASK_SIZE + 3 * AUDIT_MAX_FIELDS + 1) + self.strings().len()
+    }
+}
+
+/// Audit record
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    /// Type of the record
+    pub msg_type: AuditMsg,
+    /// Text of the record, starting with the `audit(<seconds>.<milliseconds>:<serial>): ` event
+    /// ID
+    pub text: String,
+}
+
+impl AuditRecord {
+    /// Parse a record from a datagram received from the kernel
+    pub fn parse(buf: &[u8]) -> Result<Self, DeError> {
+        if buf.len() < NLMSG_HDRLEN {
+            return Err(DeError::new(
+                "Audit record is shorter than a netlink header",
+            ));
+        }
+        let msg_type = u16::from_ne_bytes([buf[4], buf[5]]);
+        let text = String::from_utf8_lossy(&buf[NLMSG_HDRLEN..]);
+        Ok(AuditRecord {
+            msg_type: AuditMsg::from(msg_type),
+            text: text.trim_end_matches(&['\0', '\n'][..]).to_string(),
+        })
+    }
+
+    /// Event ID of the record as seconds, milliseconds and serial number. Records of the same
+    /// event share the same ID.
+    pub fn event_id(&self) -> Option<(u64, u32, u64)> {
+        let id = self.text.get(6..)?.split(')').next()?;
+        if !self.text.starts_with("audit(") {
+            return None;
+        }
+        let mut parts = id.split(&['.', ':'][..]);
+        let secs = parts.next()?.parse().ok()?;
+        let millis = parts.next()?.parse().ok()?;
+        let serial = parts.next()?.parse().ok()?;
+        Some((secs, millis, serial))
+    }
+
+    /// Body of the record without the event ID
+    pub fn body(&self) -> &str {
+        match self.text.find("): ") {
+            Some(end) if self.text.starts_with("audit(") => &self.text[end + 3..],
+            _ => &self.text,
+        }
+    }
+}
+
+impl NlSocket {
+    /// Get the audit status
+    pub fn audit_get_status(&mut self) -> Result<AuditStatus, NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            AuditMsg::Get,
+            vec![NlmF::Request],
+            None,
+            None,
+            AuditStatus::default(),
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_all::<AuditMsg, AuditStatus>()?
+            .pop()
+            .map(|msg| msg.nl_payload)
+            .ok_or_else(|| NlError::new("No audit status received"))
+    }
+
+    /// Change the fields of the audit status selected by `status.mask`
+    pub fn audit_set_status(&mut self, status: &AuditStatus) -> Result<(), NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            AuditMsg::Set,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            status.clone(),
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+
+    /// Enable or disable auditing
+    pub fn audit_set_enabled(&mut self, enabled: bool) -> Result<(), NlError> {
+        self.audit_set_status(&AuditStatus {
+            mask: vec![AuditStatusMask::Enabled],
+            enabled: enabled as u32,
+            ..Default::default()
+        })
+    }
+
+    /// Register process `pid`, which must be the calling process, as the audit daemon. Audit
+    /// records are then sent to this socket. A `pid` of `0` unregisters the daemon and can be
+    /// sent from any socket of the daemon process.
+    pub fn audit_set_pid(&mut self, pid: u32) -> Result<(), NlError> {
+        self.audit_set_status(&AuditStatus {
+            mask: vec![AuditStatusMask::Pid],
+            pid,
+            ..Default::default()
+        })
+    }
+
+    /// Add audit rule `rule`
+    pub fn audit_add_rule(&mut self, rule: &AuditRule) -> Result<(), NlError> {
+        self.audit_rule_cmd(AuditMsg::AddRule, rule)
+    }
+
+    /// Delete audit rule `rule`, which must match an existing rule exactly
+    pub fn audit_del_rule(&mut self, rule: &AuditRule) -> Result<(), NlError> {
+        self.audit_rule_cmd(AuditMsg::DelRule, rule)
+    }
+
+    /// List all audit rules
+    pub fn audit_list_rules(&mut self) -> Result<Vec<AuditRule>, NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            AuditMsg::ListRules,
+            vec![NlmF::Request],
+            None,
+            None,
+            Vec::<u8>::new(),
+        );
+        self.send_nl(nlhdr)?;
+        Ok(self
+            .recv_all::<AuditMsg, AuditRule>()?
+            .into_iter()
+            .map(|msg| msg.nl_payload)
+            .collect())
+    }
+
+    /// Log a userspace message of type `msg_type` (eg. `AuditMsg::UserCmd`), which requires
+    /// `CAP_AUDIT_WRITE`
+    pub fn audit_log_user_message(
+        &mut self,
+        msg_type: AuditMsg,
+        text: &str,
+    ) -> Result<(), NlError> {
+        let mut payload = text.as_bytes().to_vec();
+        payload.push(0);
+        let nlhdr = Nlmsghdr::new(
+            None,
+            msg_type,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            payload,
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+
+    /// Receive the next audit record
+    pub fn audit_recv_record(&mut self) -> Result<AuditRecord, NlError> {
+        let mut buf = vec![0; MAX_NL_LENGTH];
+        let len = self.recv(&mut buf[..], 0)?;
+        Ok(AuditRecord::parse(&buf[..len])?)
+    }
+
+    fn audit_rule_cmd(&mut self, msg_type: AuditMsg, rule: &AuditRule) -> Result<(), NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            msg_type,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            rule.clone(),
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use consts::NlFamily;
+
+    #[test]
+    fn test_audit_rule_round_trip() {
+        let mut rule = AuditRule::new(AuditFilter::Exit, AuditAction::Always);
+        rule.syscalls = vec![2, 59];
+        rule.add_field(AuditField::Arch, AuditOp::Equal, 0xc000_003e)
+            .add_field(AuditField::Dir, AuditOp::Equal, "/etc")
+            .add_field(AuditField::Filterkey, AuditOp::Equal, "config");
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        rule.serialize(&mut mem).unwrap();
+        assert_eq!(mem.as_ref().len(), rule.size());
+        assert_eq!(&mem.as_ref()[rule.size() - 10..], b"/etcconfig");
+
+        let mut buf = StreamReadBuffer::new(mem.as_ref());
+        assert_eq!(AuditRule::deserialize(&mut buf).unwrap(), rule);
+    }
+
+    #[test]
+    fn test_audit_record() {
+        let mut buf = vec![0u8; NLMSG_HDRLEN];
+        buf[4..6].copy_from_slice(&1300u16.to_ne_bytes());
+        buf.extend_from_slice(b"audit(1700000000.123:42): arch=c000003e syscall=59\0");
+        let record = AuditRecord::parse(&buf).unwrap();
+        assert_eq!(record.msg_type, AuditMsg::Syscall);
+        assert_eq!(record.event_id(), Some((1_700_000_000, 123, 42)));
+        assert_eq!(record.body(), "arch=c000003e syscall=59");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_audit() {
+        let mut s = NlSocket::connect(NlFamily::Audit, None, None, true).unwrap();
+        let status = s.audit_get_status().unwrap();
+
+        let mut rule = AuditRule::new(AuditFilter::Exit, AuditAction::Always);
+        rule.syscalls = vec![libc::SYS_getpid as u32];
+        rule.add_field(AuditField::Filterkey, AuditOp::Equal, "neli_test");
+        s.audit_add_rule(&rule).unwrap();
+        assert!(s.audit_list_rules().unwrap().contains(&rule));
+        s.audit_del_rule(&rule).unwrap();
+
+        s.audit_set_enabled(true).unwrap();
+        let mut daemon = NlSocket::connect(NlFamily::Audit, None, None, true).unwrap();
+        daemon.audit_set_pid(std::process::id()).unwrap();
+        s.audit_log_user_message(AuditMsg::UserCmd, "neli_test")
+            .unwrap();
+        let record = loop {
+            let record = daemon.audit_recv_record().unwrap();
+            if record.msg_type == AuditMsg::UserCmd {
+                break record;
+            }
+        };
+        assert!(record.body().contains("neli_test"), "{}", record.text);
+        s.audit_set_pid(0).unwrap();
+        s.audit_set_enabled(status.enabled != 0).unwrap();
+    }
+}
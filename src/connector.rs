@@ -0,0 +1,658 @@
+//! This module provides `cn_msg` framing for the `NlFamily::Connector` protocol and a listener
+//! for the process events connector (`CN_IDX_PROC`).
+//!
+//! ```no_run
+//! use neli::connector::ProcEvent;
+//! use neli::consts::connector::CnIdx;
+//! use neli::consts::NlFamily;
+//! use neli::socket::NlSocket;
+//!
+//! let mut socket =
+//!     NlSocket::connect(NlFamily::Connector, Some(0), Some(vec![CnIdx::Proc.into()]), false)
+//!         .unwrap();
+//! socket.proc_cn_listen().unwrap();
+//! loop {
+//!     match socket.proc_cn_recv_event().unwrap().event {
+//!         ProcEvent::Exec { pid, .. } => println!("{} executed a program", pid),
+//!         ProcEvent::Exit { pid, exit_code, .. } => println!("{} exited with {}", pid, exit_code),
+//!         _ => (),
+//!     }
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! Connector messages are carried in netlink messages of type `NLMSG_DONE`, so they are sent
+//! and received as `Nlmsghdr<Nlmsg, CnMsg<P>>`.
+//!
+//! The kernel answers subscription requests on the multicast group rather than to the
+//! requesting socket, so acknowledgements sent for other listeners are received as
+//! `ProcEvent::Ack` and can be ignored. Subscribing requires `CAP_NET_ADMIN` in the initial user
+//! namespace.
+
+use buffering::{StreamReadBuffer, StreamWriteBuffer};
+
+use consts::connector::{CnIdx, ProcCnMcastOp, ProcEventWhat};
+use consts::Nlmsg;
+use err::{DeError, NlError, SerError};
+use nl::Nlmsghdr;
+use socket::NlSocket;
+use Nl;
+
+/// Connector value of the process events connector
+pub const CN_VAL_PROC: u32 = 1;
+
+/// Size of the `event_data` union of `struct proc_event`
+const PROC_EVENT_DATA_LEN: usize = 24;
+/// Size of `comm` in process events
+const TASK_COMM_LEN: usize = 16;
+
+/// Connector ID (`struct cb_id`) identifying the kernel module a message is for
+#[derive(Clone, Debug, PartialEq)]
+pub struct CbId {
+    /// Index of the module
+    pub idx: CnIdx,
+    /// Value within the module
+    pub val: u32,
+}
+
+impl CbId {
+    /// Create a connector ID
+    pub fn new(idx: CnIdx, val: u32) -> Self {
+        CbId { idx, val }
+    }
+}
+
+impl Nl for CbId {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.idx.serialize(buf)?;
+        self.val.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        Ok(CbId {
+            idx: CnIdx::deserialize(buf)?,
+            val: u32::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.idx.size() + self.val.size()
+    }
+}
+
+/// Connector message (`struct cn_msg`). The length of the data is computed from `data`.
+#[derive(Debug, PartialEq)]
+pub struct CnMsg<P> {
+    /// Module the message is for
+    pub id: CbId,
+    /// Sequence number
+    pub seq: u32,
+    /// Acknowledgement number, replies set it to the request's number plus one
+    pub ack: u32,
+    /// Flags
+    pub flags: u16,
+    /// Data of the message
+    pub data: P,
+}
+
+impl<P> CnMsg<P>
+where
+    P: Nl,
+{
+    /// Create a connector message
+    pub fn new(id: CbId, seq: u32, ack: u32, data: P) -> Self {
+        CnMsg {
+            id,
+            seq,
+            ack,
+            flags: 0,
+            data,
+        }
+    }
+}
+
+impl<P> Nl for CnMsg<P>
+where
+    P: Nl,
+{
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.id.serialize(buf)?;
+        self.seq.serialize(buf)?;
+        self.ack.serialize(buf)?;
+        (self.data.size() as u16).serialize(buf)?;
+        self.flags.serialize(buf)?;
+        self.data.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        buf.take_size_hint();
+        let id = CbId::deserialize(buf)?;
+        let seq = u32::deserialize(buf)?;
+        let ack = u32::deserialize(buf)?;
+        let len = u16::deserialize(buf)?;
+        let flags = u16::deserialize(buf)?;
+        buf.set_size_hint(len as usize);
+        Ok(CnMsg {
+            id,
+            seq,
+            ack,
+            flags,
+            data: P::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.id.size()
+            + self.seq.size()
+            + self.ack.size()
+            + 0u16.size()
+            + self.flags.size()
+            + self.data.size()
+    }
+}
+
+/// Process event reported by the process events connector
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcEvent {
+    /// Acknowledgement of a subscription request, `err` is an errno value or `0`
+    Ack {
+        /// Error of the request
+        err: u32,
+    },
+    /// A process or thread was created
+    Fork {
+        /// PID of the parent thread
+        parent_pid: i32,
+        /// Thread group ID of the parent
+        parent_tgid: i32,
+        /// PID of the new thread
+        child_pid: i32,
+        /// Thread group ID of the new thread
+        child_tgid: i32,
+    },
+    /// A process executed a new program
+    Exec {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+    },
+    /// The user IDs of a process changed
+    Uid {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+        /// Real user ID
+        ruid: u32,
+        /// Effective user ID
+        euid: u32,
+    },
+    /// The group IDs of a process changed
+    Gid {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+        /// Real group ID
+        rgid: u32,
+        /// Effective group ID
+        egid: u32,
+    },
+    /// A process started a new session
+    Sid {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+    },
+    /// A process was attached to or detached from a tracer
+    Ptrace {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+        /// PID of the tracer, `0` on detach
+        tracer_pid: i32,
+        /// Thread group ID of the tracer, `0` on detach
+        tracer_tgid: i32,
+    },
+    /// The command name of a process changed
+    Comm {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+        /// New command name
+        comm: String,
+    },
+    /// A process dumped core
+    Coredump {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+        /// PID of the parent
+        parent_pid: i32,
+        /// Thread group ID of the parent
+        parent_tgid: i32,
+    },
+    /// A thread exited
+    ///
+    /// The kernel reports exits as `PROC_EVENT_EXIT` and only uses `PROC_EVENT_NONZERO_EXIT` to
+    /// filter out exits with a status of `0`. Both types are parsed as this variant, with
+    /// `nonzero_exit` recording which one was received so that `what()` and serialization keep it.
+    Exit {
+        /// PID of the thread
+        pid: i32,
+        /// Thread group ID
+        tgid: i32,
+        /// Exit status as returned by `wait()`
+        exit_code: u32,
+        /// Signal sent to the parent
+        exit_signal: u32,
+        /// PID of the parent
+        parent_pid: i32,
+        /// Thread group ID of the parent
+        parent_tgid: i32,
+        /// Whether the event type was `PROC_EVENT_NONZERO_EXIT`
+        nonzero_exit: bool,
+    },
+    /// Event of another type with its raw data
+    Other(ProcEventWhat, Vec<u8>),
+}
+
+impl ProcEvent {
+    /// Type of the event
+    pub fn what(&self) -> ProcEventWhat {
+        match *self {
+            ProcEvent::Ack { .. } => ProcEventWhat::None,
+            ProcEvent::Fork { .. } => ProcEventWhat::Fork,
+            ProcEvent::Exec { .. } => ProcEventWhat::Exec,
+            ProcEvent::Uid { .. } => ProcEventWhat::Uid,
+            ProcEvent::Gid { .. } => ProcEventWhat::Gid,
+            ProcEvent::Sid { .. } => ProcEventWhat::Sid,
+            ProcEvent::Ptrace { .. } => ProcEventWhat::Ptrace,
+            ProcEvent::Comm { .. } => ProcEventWhat::Comm,
+            ProcEvent::Coredump { .. } => ProcEventWhat::Coredump,
+            ProcEvent::Exit {
+                nonzero_exit: true, ..
+            } => ProcEventWhat::NonzeroExit,
+            ProcEvent::Exit { .. } => ProcEventWhat::Exit,
+            ProcEvent::Other(ref what, _) => what.clone(),
+        }
+    }
+
+    /// Parse the `event_data` union of an event of type `what`
+    fn parse(what: ProcEventWhat, data: &[u8]) -> Self {
+        if data.len() < PROC_EVENT_DATA_LEN {
+            return ProcEvent::Other(what, data.to_vec());
+        }
+        let word = |i: usize| {
+            let w = &data[i * 4..i * 4 + 4];
+            u32::from_ne_bytes([w[0], w[1], w[2], w[3]])
+        };
+        let pid = word(0) as i32;
+        let tgid = word(1) as i32;
+        match what {
+            ProcEventWhat::None => ProcEvent::Ack { err: word(0) },
+            ProcEventWhat::Fork => ProcEvent::Fork {
+                parent_pid: pid,
+                parent_tgid: tgid,
+                child_pid: word(2) as i32,
+                child_tgid: word(3) as i32,
+            },
+            ProcEventWhat::Exec => ProcEvent::Exec { pid, tgid },
+            ProcEventWhat::Uid => ProcEvent::Uid {
+                pid,
+                tgid,
+                ruid: word(2),
+                euid: word(3),
+            },
+            ProcEventWhat::Gid => ProcEvent::Gid {
+                pid,
+                tgid,
+                rgid: word(2),
+                egid: word(3),
+            },
+            ProcEventWhat::Sid => ProcEvent::Sid { pid, tgid },
+            ProcEventWhat::Ptrace => ProcEvent::Ptrace {
+                pid,
+                tgid,
+                tracer_pid: word(2) as i32,
+                tracer_tgid: word(3) as i32,
+            },
+            ProcEventWhat::Comm => {
+                let comm = &data[8..8 + TASK_COMM_LEN];
+                let end = comm.iter().position(|b| *b == 0).unwrap_or(comm.len());
+                ProcEvent::Comm {
+                    pid,
+                    tgid,
+                    comm: String::from_utf8_lossy(&comm[..end]).into_owned(),
+                }
+            }
+            ProcEventWhat::Coredump => ProcEvent::Coredump {
+                pid,
+                tgid,
+                parent_pid: word(2) as i32,
+                parent_tgid: word(3) as i32,
+            },
+            ProcEventWhat::Exit | ProcEventWhat::NonzeroExit => ProcEvent::Exit {
+                pid,
+                tgid,
+                exit_code: word(2),
+                exit_signal: word(3),
+                parent_pid: word(4) as i32,
+                parent_tgid: word(5) as i32,
+                nonzero_exit: what == ProcEventWhat::NonzeroExit,
+            },
+            what => ProcEvent::Other(what, data.to_vec()),
+        }
+    }
+
+    /// Serialize the `event_data` union
+    fn data(&self) -> Vec<u8> {
+        let words = match *self {
+            ProcEvent::Ack { err } => vec![err],
+            ProcEvent::Fork {
+                parent_pid,
+                parent_tgid,
+                child_pid,
+                child_tgid,
+            } => vec![
+                parent_pid as u32,
+                parent_tgid as u32,
+                child_pid as u32,
+                child_tgid as u32,
+            ],
+            ProcEvent::Exec { pid, tgid } | ProcEvent::Sid { pid, tgid } => {
+                vec![pid as u32, tgid as u32]
+            }
+            ProcEvent::Uid {
+                pid,
+                tgid,
+                ruid: r,
+                euid: e,
+            }
+            | ProcEvent::Gid {
+                pid,
+                tgid,
+                rgid: r,
+                egid: e,
+            } => vec![pid as u32, tgid as u32, r, e],
+            ProcEvent::Ptrace {
+                pid,
+                tgid,
+                tracer_pid: a,
+                tracer_tgid: b,
+            }
+            | ProcEvent::Coredump {
+                pid,
+                tgid,
+                parent_pid: a,
+                parent_tgid: b,
+            } => vec![pid as u32, tgid as u32, a as u32, b as u32],
+            ProcEvent::Exit {
+                pid,
+                tgid,
+                exit_code,
+                exit_signal,
+                parent_pid,
+                parent_tgid,
+                ..
+            } => vec![
+                pid as u32,
+                tgid as u32,
+                exit_code,
+                exit_signal,
+                parent_pid as u32,
+                parent_tgid as u32,
+            ],
+            ProcEvent::Comm {
+                pid,
+                tgid,
+                ref comm,
+            } => {
+                let mut data = Vec::with_capacity(PROC_EVENT_DATA_LEN);
+                data.extend_from_slice(&(pid as u32).to_ne_bytes());
+                data.extend_from_slice(&(tgid as u32).to_ne_bytes());
+                let comm = comm.as_bytes();
+                data.extend_from_slice(&comm[..comm.len().min(TASK_COMM_LEN - 1)]);
+                data.resize(PROC_EVENT_DATA_LEN, 0);
+                return data;
+            }
+            ProcEvent::Other(_, ref data) => {
+                let mut data = data.clone();
+                data.resize(PROC_EVENT_DATA_LEN, 0);
+                return data;
+            }
+        };
+        let mut data = words
+            .iter()
+            .flat_map(|w| w.to_ne_bytes().to_vec())
+            .collect::<Vec<_>>();
+        data.resize(PROC_EVENT_DATA_LEN, 0);
+        data
+    }
+}
+
+/// Process event message (`struct proc_event`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcEventMsg {
+    /// CPU the event occurred on
+    pub cpu: u32,
+    /// Time of the event in nanoseconds since boot
+    pub timestamp_ns: u64,
+    /// Event
+    pub event: ProcEvent,
+}
+
+impl Nl for ProcEventMsg {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.event.what().serialize(buf)?;
+        self.cpu.serialize(buf)?;
+        self.timestamp_ns.serialize(buf)?;
+        self.event.data().serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let hint = buf.take_size_hint().unwrap_or(16 + PROC_EVENT_DATA_LEN);
+        let what = ProcEventWhat::deserialize(buf)?;
+        let cpu = u32::deserialize(buf)?;
+        let timestamp_ns = u64::deserialize(buf)?;
+        buf.set_size_hint(
+            hint.checked_sub(16)
+                .ok_or_else(|| DeError::new("Process event is truncated"))?,
+        );
+        let data = Vec::<u8>::deserialize(buf)?;
+        Ok(ProcEventMsg {
+            cpu,
+            timestamp_ns,
+            event: ProcEvent::parse(what, &data),
+        })
+    }
+
+    fn size(&self) -> usize {
+        16 + PROC_EVENT_DATA_LEN
+    }
+}
+
+impl NlSocket {
+    /// Subscribe to process events and wait for the kernel to acknowledge the subscription.
+    /// Events received before the acknowledgement are dropped. The socket must be a member of
+    /// multicast group `CnIdx::Proc`.
+    pub fn proc_cn_listen(&mut self) -> Result<(), NlError> {
+        let ack = self.proc_cn_send(ProcCnMcastOp::Listen)?;
+        loop {
+            let msg = self.recv_nl::<Nlmsg, CnMsg<ProcEventMsg>>(None)?;
+            let cn_msg = msg.nl_payload;
+            if cn_msg.ack != ack.wrapping_add(1) {
+                continue;
+            }
+            if let ProcEvent::Ack { err } = cn_msg.data.event {
+                if err == 0 {
+                    return Ok(());
+                }
+                let err = std::io::Error::from_raw_os_error(err as i32);
+                return Err(NlError::Msg(err.to_string()));
+            }
+        }
+    }
+
+    /// Unsubscribe from process events. No acknowledgement is waited for, as the kernel does not
+    /// send one once the last listener unsubscribed.
+    pub fn proc_cn_ignore(&mut self) -> Result<(), NlError> {
+        self.proc_cn_send(ProcCnMcastOp::Ignore)?;
+        Ok(())
+    }
+
+    /// Receive the next process event, skipping messages of other connector modules
+    pub fn proc_cn_recv_event(&mut self) -> Result<ProcEventMsg, NlError> {
+        loop {
+            let msg = self.recv_nl::<Nlmsg, CnMsg<ProcEventMsg>>(None)?;
+            if msg.nl_payload.id == CbId::new(CnIdx::Proc, CN_VAL_PROC) {
+                return Ok(msg.nl_payload.data);
+            }
+        }
+    }
+
+    /// Send a subscription request, returning the acknowledgement number of the request
+    fn proc_cn_send(&mut self, op: ProcCnMcastOp) -> Result<u32, NlError> {
+        // The kernel copies the sequence number of the request into the acknowledgement and sets
+        // its acknowledgement number to the one of the request plus one. Acknowledgements are
+        // sent to all listeners, so the unique port ID is used as acknowledgement number to
+        // recognize ours.
+        let ack = self.port_id()?;
+        let cn_msg = CnMsg::new(CbId::new(CnIdx::Proc, CN_VAL_PROC), 0, ack, op);
+        let nlhdr = Nlmsghdr::new(None, Nlmsg::Done, Vec::new(), None, None, cn_msg);
+        self.send_nl(nlhdr)?;
+        Ok(ack)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::process::Command;
+
+    use consts::NlFamily;
+    use round_trip;
+
+    fn assert_round_trip(event: ProcEvent) {
+        let msg = CnMsg::new(
+            CbId::new(CnIdx::Proc, CN_VAL_PROC),
+            1,
+            0,
+            ProcEventMsg {
+                cpu: 2,
+                timestamp_ns: 12345,
+                event,
+            },
+        );
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        msg.serialize(&mut mem).unwrap();
+        assert_eq!(mem.as_ref().len(), 60);
+        assert_eq!(&mem.as_ref()[16..18], &40u16.to_ne_bytes());
+        assert_eq!(round_trip(&msg), msg);
+    }
+
+    #[test]
+    fn test_proc_event_round_trip() {
+        assert_round_trip(ProcEvent::Fork {
+            parent_pid: 1,
+            parent_tgid: 1,
+            child_pid: 100,
+            child_tgid: 100,
+        });
+        assert_round_trip(ProcEvent::Comm {
+            pid: 100,
+            tgid: 100,
+            comm: "worker".to_string(),
+        });
+        assert_round_trip(ProcEvent::Exit {
+            pid: 100,
+            tgid: 100,
+            exit_code: 256,
+            exit_signal: 17,
+            parent_pid: 1,
+            parent_tgid: 1,
+            nonzero_exit: false,
+        });
+        assert_round_trip(ProcEvent::Exit {
+            pid: 100,
+            tgid: 100,
+            exit_code: 256,
+            exit_signal: 17,
+            parent_pid: 1,
+            parent_tgid: 1,
+            nonzero_exit: true,
+        });
+        assert_round_trip(ProcEvent::Ack { err: 0 });
+    }
+
+    #[test]
+    fn test_subscribe_request() {
+        let msg = CnMsg::new(
+            CbId::new(CnIdx::Proc, CN_VAL_PROC),
+            7,
+            0,
+            ProcCnMcastOp::Listen,
+        );
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        msg.serialize(&mut mem).unwrap();
+        let mut expected = Vec::new();
+        for word in &[1u32, 1, 7, 0] {
+            expected.extend_from_slice(&word.to_ne_bytes());
+        }
+        expected.extend_from_slice(&4u16.to_ne_bytes());
+        expected.extend_from_slice(&0u16.to_ne_bytes());
+        expected.extend_from_slice(&1u32.to_ne_bytes());
+        assert_eq!(mem.as_ref(), expected.as_slice());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_proc_cn_listen() {
+        let mut s = NlSocket::connect(
+            NlFamily::Connector,
+            Some(0),
+            Some(vec![CnIdx::Proc.into()]),
+            false,
+        )
+        .unwrap();
+        s.proc_cn_listen().unwrap();
+        let child = Command::new("true").status().unwrap();
+        assert!(child.success());
+        let me = std::process::id() as i32;
+        let mut child_pid = None;
+        loop {
+            match s.proc_cn_recv_event().unwrap().event {
+                ProcEvent::Fork {
+                    parent_tgid,
+                    child_pid: pid,
+                    ..
+                } if parent_tgid == me => child_pid = Some(pid),
+                ProcEvent::Exit { pid, exit_code, .. } if Some(pid) == child_pid => {
+                    assert_eq!(exit_code, 0);
+                    break;
+                }
+                _ => (),
+            }
+        }
+        s.proc_cn_ignore().unwrap();
+    }
+}
@@ -0,0 +1,19 @@
+//! Constants for the ACPI event generic netlink family
+//!
+//! The values come from `drivers/acpi/event.c`.
+
+use super::{Cmd, NlAttrType};
+
+impl_var_trait! {
+    /// Commands of the ACPI event family.
+    AcpiGenlCmd, u8, Cmd,
+    Unspec => 0,
+    Event => 1
+}
+
+impl_var_trait! {
+    /// Attributes of the ACPI event family.
+    AcpiGenlAttr, u16, NlAttrType,
+    Unspec => 0,
+    Event => 1
+}
@@ -0,0 +1,190 @@
+//! Constants for the Linux audit protocol
+//!
+//! The values come from `linux/audit.h`.
+
+use super::NlType;
+
+impl_var_trait! {
+    /// Message types of the [NlFamily::Audit][super::NlFamily::Audit] protocol. Types from
+    /// 1000 to 1099 are control messages, higher types are audit records.
+    AuditMsg, u16, NlType,
+    Get => 1000,
+    Set => 1001,
+    List => 1002,
+    Add => 1003,
+    Del => 1004,
+    User => 1005,
+    Login => 1006,
+    SignalInfo => 1010,
+    AddRule => 1011,
+    DelRule => 1012,
+    ListRules => 1013,
+    Trim => 1014,
+    MakeEquiv => 1015,
+    TtyGet => 1016,
+    TtySet => 1017,
+    SetFeature => 1018,
+    GetFeature => 1019,
+    UserAuth => 1100,
+    UserAcct => 1101,
+    UserMgmt => 1102,
+    CredAcq => 1103,
+    CredDisp => 1104,
+    UserStart => 1105,
+    UserEnd => 1106,
+    UserAvc => 1107,
+    UserChauthtok => 1108,
+    UserErr => 1109,
+    CredRefr => 1110,
+    UsysConfig => 1111,
+    UserLogin => 1112,
+    UserLogout => 1113,
+    AddUser => 1114,
+    DelUser => 1115,
+    UserCmd => 1123,
+    DaemonStart => 1200,
+    DaemonEnd => 1201,
+    DaemonAbort => 1202,
+    DaemonConfig => 1203,
+    Syscall => 1300,
+    Path => 1302,
+    Ipc => 1303,
+    Socketcall => 1304,
+    ConfigChange => 1305,
+    Sockaddr => 1306,
+    Cwd => 1307,
+    Execve => 1309,
+    IpcSetPerm => 1311,
+    MqOpen => 1312,
+    MqSendrecv => 1313,
+    MqNotify => 1314,
+    MqGetsetattr => 1315,
+    KernelOther => 1316,
+    FdPair => 1317,
+    ObjPid => 1318,
+    Tty => 1319,
+    Eoe => 1320,
+    BprmFcaps => 1321,
+    Capset => 1322,
+    Mmap => 1323,
+    Netfilter => 1324,
+    Seccomp => 1326,
+    Proctitle => 1327,
+    FeatureChange => 1328,
+    Replace => 1329,
+    KernModule => 1330,
+    Fanotify => 1331,
+    TimeInjoffset => 1332,
+    TimeAdjntpval => 1333,
+    Bpf => 1334,
+    EventListener => 1335,
+    Avc => 1400,
+    SelinuxErr => 1401,
+    AvcPath => 1402,
+    MacPolicyLoad => 1403,
+    MacStatus => 1404,
+    MacConfigChange => 1405,
+    AnomPromiscuous => 1700,
+    AnomAbend => 1701,
+    AnomLink => 1702,
+    AnomCreat => 1703,
+    Kernel => 2000
+}
+
+impl_var! {
+    /// Fields of `audit_status` to change in an `AuditMsg::Set` request.
+    AuditStatusMask, u32,
+    Enabled => 0x0001,
+    Failure => 0x0002,
+    Pid => 0x0004,
+    RateLimit => 0x0008,
+    BacklogLimit => 0x0010,
+    BacklogWaitTime => 0x0020,
+    Lost => 0x0040,
+    BacklogWaitTimeActual => 0x0080
+}
+
+impl_var! {
+    /// Filter lists that audit rules are added to.
+    AuditFilter, u32,
+    User => 0,
+    Task => 1,
+    Entry => 2,
+    Watch => 3,
+    Exit => 4,
+    Exclude => 5,
+    Fs => 6,
+    UringExit => 7
+}
+
+impl_var! {
+    /// Actions of audit rules.
+    AuditAction, u32,
+    Never => 0,
+    Possible => 1,
+    Always => 2
+}
+
+impl_var! {
+    /// Fields compared by audit rules.
+    AuditField, u32,
+    Pid => 0,
+    Uid => 1,
+    Euid => 2,
+    Suid => 3,
+    Fsuid => 4,
+    Gid => 5,
+    Egid => 6,
+    Sgid => 7,
+    Fsgid => 8,
+    Loginuid => 9,
+    Pers => 10,
+    Arch => 11,
+    Msgtype => 12,
+    SubjUser => 13,
+    SubjRole => 14,
+    SubjType => 15,
+    SubjSen => 16,
+    SubjClr => 17,
+    Ppid => 18,
+    ObjUser => 19,
+    ObjRole => 20,
+    ObjType => 21,
+    ObjLevLow => 22,
+    ObjLevHigh => 23,
+    LoginuidSet => 24,
+    Sessionid => 25,
+    Fstype => 26,
+    Devmajor => 100,
+    Devminor => 101,
+    Inode => 102,
+    Exit => 103,
+    Success => 104,
+    Watch => 105,
+    Perm => 106,
+    Dir => 107,
+    Filetype => 108,
+    ObjUid => 109,
+    ObjGid => 110,
+    FieldCompare => 111,
+    Exe => 112,
+    SaddrFam => 113,
+    Arg0 => 200,
+    Arg1 => 201,
+    Arg2 => 202,
+    Arg3 => 203,
+    Filterkey => 210
+}
+
+impl_var! {
+    /// Comparison operators of audit rule fields.
+    AuditOp, u32,
+    BitMask => 0x0800_0000,
+    LessThan => 0x1000_0000,
+    GreaterThan => 0x2000_0000,
+    NotEqual => 0x3000_0000,
+    Equal => 0x4000_0000,
+    BitTest => 0x4800_0000,
+    LessThanOrEqual => 0x5000_0000,
+    GreaterThanOrEqual => 0x6000_0000
+}
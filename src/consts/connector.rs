@@ -0,0 +1,42 @@
+//! Constants for the kernel connector protocol
+//!
+//! The values come from `linux/connector.h` and `linux/cn_proc.h`.
+
+impl_var! {
+    /// Connector indices of kernel modules, also used as the multicast group of the module on
+    /// the [NlFamily::Connector][super::NlFamily::Connector] protocol.
+    CnIdx, u32,
+    Proc => 1,
+    Cifs => 2,
+    W1 => 3,
+    V86d => 4,
+    Bb => 5,
+    Dst => 6,
+    Dm => 7,
+    Drbd => 8,
+    Kvp => 9,
+    Vss => 10
+}
+
+impl_var! {
+    /// Operations of the process event connector (`enum proc_cn_mcast_op`).
+    ProcCnMcastOp, u32,
+    Listen => 1,
+    Ignore => 2
+}
+
+impl_var! {
+    /// Types of process events (`enum what` of `struct proc_event`).
+    ProcEventWhat, u32,
+    None => 0x0000_0000,
+    Fork => 0x0000_0001,
+    Exec => 0x0000_0002,
+    Uid => 0x0000_0004,
+    Gid => 0x0000_0040,
+    Sid => 0x0000_0080,
+    Ptrace => 0x0000_0100,
+    Comm => 0x0000_0200,
+    NonzeroExit => 0x2000_0000,
+    Coredump => 0x4000_0000,
+    Exit => 0x8000_0000
+}
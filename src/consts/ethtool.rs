@@ -0,0 +1,216 @@
+//! Constants for the ethtool generic netlink family
+//!
+//! The values come from `linux/ethtool_netlink.h`.
+
+use super::{Cmd, NlAttrType};
+
+impl_var_trait! {
+    /// Messages sent from userspace to the kernel.
+    EthtoolMsg, u8, Cmd,
+    UserNone => 0,
+    StrsetGet => 1,
+    LinkinfoGet => 2,
+    LinkinfoSet => 3,
+    LinkmodesGet => 4,
+    LinkmodesSet => 5,
+    LinkstateGet => 6,
+    DebugGet => 7,
+    DebugSet => 8,
+    WolGet => 9,
+    WolSet => 10,
+    FeaturesGet => 11,
+    FeaturesSet => 12,
+    PrivflagsGet => 13,
+    PrivflagsSet => 14,
+    RingsGet => 15,
+    RingsSet => 16,
+    ChannelsGet => 17,
+    ChannelsSet => 18,
+    CoalesceGet => 19,
+    CoalesceSet => 20,
+    PauseGet => 21,
+    PauseSet => 22,
+    EeeGet => 23,
+    EeeSet => 24,
+    TsinfoGet => 25,
+    CableTestAct => 26,
+    CableTestTdrAct => 27,
+    TunnelInfoGet => 28,
+    FecGet => 29,
+    FecSet => 30,
+    ModuleEepromGet => 31,
+    StatsGet => 32,
+    PhcVclocksGet => 33,
+    ModuleGet => 34,
+    ModuleSet => 35,
+    PseGet => 36,
+    PseSet => 37
+}
+
+impl_var_trait! {
+    /// Messages sent from the kernel to userspace.
+    EthtoolKernelMsg, u8, Cmd,
+    KernelNone => 0,
+    StrsetGetReply => 1,
+    LinkinfoGetReply => 2,
+    LinkinfoNtf => 3,
+    LinkmodesGetReply => 4,
+    LinkmodesNtf => 5,
+    LinkstateGetReply => 6,
+    DebugGetReply => 7,
+    DebugNtf => 8,
+    WolGetReply => 9,
+    WolNtf => 10,
+    FeaturesGetReply => 11,
+    FeaturesSetReply => 12,
+    FeaturesNtf => 13,
+    PrivflagsGetReply => 14,
+    PrivflagsNtf => 15,
+    RingsGetReply => 16,
+    RingsNtf => 17,
+    ChannelsGetReply => 18,
+    ChannelsNtf => 19,
+    CoalesceGetReply => 20,
+    CoalesceNtf => 21,
+    PauseGetReply => 22,
+    PauseNtf => 23,
+    EeeGetReply => 24,
+    EeeNtf => 25,
+    TsinfoGetReply => 26,
+    CableTestNtf => 27,
+    CableTestTdrNtf => 28,
+    TunnelInfoGetReply => 29,
+    FecGetReply => 30,
+    FecNtf => 31,
+    ModuleEepromGetReply => 32,
+    StatsGetReply => 33,
+    PhcVclocksGetReply => 34,
+    ModuleGetReply => 35,
+    ModuleNtf => 36,
+    PseGetReply => 37
+}
+
+impl_var_trait! {
+    /// Attributes of the request header nested in every message.
+    EthtoolHeaderAttr, u16, NlAttrType,
+    Unspec => 0,
+    DevIndex => 1,
+    DevName => 2,
+    Flags => 3
+}
+
+impl_var! {
+    /// Flags of the request header.
+    EthtoolFlag, u32,
+    CompactBitsets => 1,
+    OmitReply => 2,
+    Stats => 4
+}
+
+impl_var_trait! {
+    /// Attributes of a single bit in a verbose bitset.
+    EthtoolBitsetBitAttr, u16, NlAttrType,
+    Unspec => 0,
+    Index => 1,
+    Name => 2,
+    Value => 3
+}
+
+impl_var_trait! {
+    /// Attributes of the list of bits in a verbose bitset.
+    EthtoolBitsetBitsAttr, u16, NlAttrType,
+    Unspec => 0,
+    Bit => 1
+}
+
+impl_var_trait! {
+    /// Attributes of a bitset.
+    EthtoolBitsetAttr, u16, NlAttrType,
+    Unspec => 0,
+    Nomask => 1,
+    Size => 2,
+    Bits => 3,
+    Value => 4,
+    Mask => 5
+}
+
+impl_var_trait! {
+    /// Attributes of `LINKINFO` messages.
+    EthtoolLinkinfoAttr, u16, NlAttrType,
+    Unspec => 0,
+    Header => 1,
+    Port => 2,
+    Phyaddr => 3,
+    TpMdix => 4,
+    TpMdixCtrl => 5,
+    Transceiver => 6
+}
+
+impl_var_trait! {
+    /// Attributes of `LINKMODES` messages.
+    EthtoolLinkmodesAttr, u16, NlAttrType,
+    Unspec => 0,
+    Header => 1,
+    Autoneg => 2,
+    Ours => 3,
+    Peer => 4,
+    Speed => 5,
+    Duplex => 6,
+    MasterSlaveCfg => 7,
+    MasterSlaveState => 8,
+    Lanes => 9,
+    RateMatching => 10
+}
+
+impl_var_trait! {
+    /// Attributes of `FEATURES` messages.
+    EthtoolFeaturesAttr, u16, NlAttrType,
+    Unspec => 0,
+    Header => 1,
+    Hw => 2,
+    Wanted => 3,
+    Active => 4,
+    Nochange => 5
+}
+
+impl_var_trait! {
+    /// Attributes of `RINGS` messages.
+    EthtoolRingsAttr, u16, NlAttrType,
+    Unspec => 0,
+    Header => 1,
+    RxMax => 2,
+    RxMiniMax => 3,
+    RxJumboMax => 4,
+    TxMax => 5,
+    Rx => 6,
+    RxMini => 7,
+    RxJumbo => 8,
+    Tx => 9,
+    RxBufLen => 10,
+    TcpDataSplit => 11,
+    CqeSize => 12,
+    TxPush => 13
+}
+
+impl_var_trait! {
+    /// Attributes of `CHANNELS` messages.
+    EthtoolChannelsAttr, u16, NlAttrType,
+    Unspec => 0,
+    Header => 1,
+    RxMax => 2,
+    TxMax => 3,
+    OtherMax => 4,
+    CombinedMax => 5,
+    RxCount => 6,
+    TxCount => 7,
+    OtherCount => 8,
+    CombinedCount => 9
+}
+
+impl_var! {
+    /// Values of `EthtoolRingsAttr::TcpDataSplit`.
+    EthtoolTcpDataSplit, u8,
+    Unknown => 0,
+    Disabled => 1,
+    Enabled => 2
+}
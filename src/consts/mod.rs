@@ -34,10 +34,16 @@
 #[macro_use]
 mod macros;
 
+pub mod acpi;
+pub mod audit;
+pub mod connector;
+pub mod devlink;
+pub mod ethtool;
 /// Constants related to generic netlink
 pub mod genl;
 pub use crate::consts::genl::*;
 pub mod netfilter;
+pub mod nl80211;
 /// Constants related to generic netlink attributes
 pub mod nlattr;
 pub use crate::consts::nlattr::*;
@@ -50,6 +56,11 @@ pub use crate::consts::rtnl::*;
 /// Constants related to netlink socket operations
 pub mod socket;
 pub use crate::consts::socket::*;
+pub mod sock_diag;
+pub mod taskstats;
+pub mod thermal;
+pub mod wireguard;
+pub mod xfrm;
 
 /// Reimplementation of alignto macro in C
 pub fn alignto(len: usize) -> usize {
@@ -48,6 +48,49 @@ const fn nfnl_msg_type(subsys: u8, msg: u8) -> u16 {
     ((subsys as u16) << 8) | (msg as u16)
 }
 
+const fn nft_msg_type(msg: libc::c_int) -> u16 {
+    nfnl_msg_type(libc::NFNL_SUBSYS_NFTABLES as u8, msg as u8)
+}
+
+const fn ipset_msg_type(cmd: u8) -> u16 {
+    nfnl_msg_type(libc::NFNL_SUBSYS_IPSET as u8, cmd)
+}
+
+// Commands from `linux/netfilter/ipset/ip_set.h`, not exported by `libc`
+const IPSET_CMD_PROTOCOL: u8 = 1;
+const IPSET_CMD_CREATE: u8 = 2;
+const IPSET_CMD_DESTROY: u8 = 3;
+const IPSET_CMD_FLUSH: u8 = 4;
+const IPSET_CMD_RENAME: u8 = 5;
+const IPSET_CMD_SWAP: u8 = 6;
+const IPSET_CMD_LIST: u8 = 7;
+const IPSET_CMD_SAVE: u8 = 8;
+const IPSET_CMD_ADD: u8 = 9;
+const IPSET_CMD_DEL: u8 = 10;
+const IPSET_CMD_TEST: u8 = 11;
+const IPSET_CMD_HEADER: u8 = 12;
+const IPSET_CMD_TYPE: u8 = 13;
+
+// Message types from `linux/netfilter/nfnetlink_conntrack.h`, not exported by `libc`
+const IPCTNL_MSG_CT_NEW: u8 = 0;
+const IPCTNL_MSG_CT_GET: u8 = 1;
+const IPCTNL_MSG_CT_DELETE: u8 = 2;
+const IPCTNL_MSG_CT_GET_CTRZERO: u8 = 3;
+
+// Message types from `linux/netfilter/nfnetlink_acct.h`, not exported by `libc`
+const NFNL_MSG_ACCT_NEW: u8 = 0;
+const NFNL_MSG_ACCT_GET: u8 = 1;
+const NFNL_MSG_ACCT_GET_CTRZERO: u8 = 2;
+const NFNL_MSG_ACCT_DEL: u8 = 3;
+const NFNL_MSG_ACCT_OVERQUOTA: u8 = 4;
+
+// Message types from `linux/netfilter/nfnetlink_cttimeout.h`, not exported by `libc`
+const IPCTNL_MSG_TIMEOUT_NEW: u8 = 0;
+const IPCTNL_MSG_TIMEOUT_GET: u8 = 1;
+const IPCTNL_MSG_TIMEOUT_DELETE: u8 = 2;
+const IPCTNL_MSG_TIMEOUT_DEFAULT_SET: u8 = 3;
+const IPCTNL_MSG_TIMEOUT_DEFAULT_GET: u8 = 4;
+
 impl_var_trait! {
     /// Messages related to the netfilter netlink protocols.
     ///
@@ -56,7 +99,79 @@ impl_var_trait! {
     // TODO: Docs here /// A logged packet, going from kernel to userspace.
     LogPacket => nfnl_msg_type(libc::NFNL_SUBSYS_ULOG as u8, libc::NFULNL_MSG_PACKET as u8),
     // TODO: Docs here /// A logging configuration request, going from userspace to kernel.
-    LogConfig => nfnl_msg_type(libc::NFNL_SUBSYS_ULOG as u8, libc::NFULNL_MSG_CONFIG as u8)
+    LogConfig => nfnl_msg_type(libc::NFNL_SUBSYS_ULOG as u8, libc::NFULNL_MSG_CONFIG as u8),
+    QueuePacket => nfnl_msg_type(libc::NFNL_SUBSYS_QUEUE as u8, libc::NFQNL_MSG_PACKET as u8),
+    QueueVerdict => nfnl_msg_type(libc::NFNL_SUBSYS_QUEUE as u8, libc::NFQNL_MSG_VERDICT as u8),
+    QueueConfig => nfnl_msg_type(libc::NFNL_SUBSYS_QUEUE as u8, libc::NFQNL_MSG_CONFIG as u8),
+    QueueVerdictBatch => nfnl_msg_type(
+        libc::NFNL_SUBSYS_QUEUE as u8,
+        libc::NFQNL_MSG_VERDICT_BATCH as u8
+    ),
+    CtNew => nfnl_msg_type(libc::NFNL_SUBSYS_CTNETLINK as u8, IPCTNL_MSG_CT_NEW),
+    CtGet => nfnl_msg_type(libc::NFNL_SUBSYS_CTNETLINK as u8, IPCTNL_MSG_CT_GET),
+    CtDelete => nfnl_msg_type(libc::NFNL_SUBSYS_CTNETLINK as u8, IPCTNL_MSG_CT_DELETE),
+    CtGetCtrZero => nfnl_msg_type(libc::NFNL_SUBSYS_CTNETLINK as u8, IPCTNL_MSG_CT_GET_CTRZERO),
+    BatchBegin => libc::NFNL_MSG_BATCH_BEGIN as u16,
+    BatchEnd => libc::NFNL_MSG_BATCH_END as u16,
+    NftNewTable => nft_msg_type(libc::NFT_MSG_NEWTABLE),
+    NftGetTable => nft_msg_type(libc::NFT_MSG_GETTABLE),
+    NftDelTable => nft_msg_type(libc::NFT_MSG_DELTABLE),
+    NftNewChain => nft_msg_type(libc::NFT_MSG_NEWCHAIN),
+    NftGetChain => nft_msg_type(libc::NFT_MSG_GETCHAIN),
+    NftDelChain => nft_msg_type(libc::NFT_MSG_DELCHAIN),
+    NftNewRule => nft_msg_type(libc::NFT_MSG_NEWRULE),
+    NftGetRule => nft_msg_type(libc::NFT_MSG_GETRULE),
+    NftDelRule => nft_msg_type(libc::NFT_MSG_DELRULE),
+    NftNewSet => nft_msg_type(libc::NFT_MSG_NEWSET),
+    NftGetSet => nft_msg_type(libc::NFT_MSG_GETSET),
+    NftDelSet => nft_msg_type(libc::NFT_MSG_DELSET),
+    NftNewSetElem => nft_msg_type(libc::NFT_MSG_NEWSETELEM),
+    NftGetSetElem => nft_msg_type(libc::NFT_MSG_GETSETELEM),
+    NftDelSetElem => nft_msg_type(libc::NFT_MSG_DELSETELEM),
+    NftNewGen => nft_msg_type(libc::NFT_MSG_NEWGEN),
+    NftGetGen => nft_msg_type(libc::NFT_MSG_GETGEN),
+    NftTrace => nft_msg_type(libc::NFT_MSG_TRACE),
+    NftNewObj => nft_msg_type(libc::NFT_MSG_NEWOBJ),
+    NftGetObj => nft_msg_type(libc::NFT_MSG_GETOBJ),
+    NftDelObj => nft_msg_type(libc::NFT_MSG_DELOBJ),
+    IpsetProtocol => ipset_msg_type(IPSET_CMD_PROTOCOL),
+    IpsetCreate => ipset_msg_type(IPSET_CMD_CREATE),
+    IpsetDestroy => ipset_msg_type(IPSET_CMD_DESTROY),
+    IpsetFlush => ipset_msg_type(IPSET_CMD_FLUSH),
+    IpsetRename => ipset_msg_type(IPSET_CMD_RENAME),
+    IpsetSwap => ipset_msg_type(IPSET_CMD_SWAP),
+    IpsetList => ipset_msg_type(IPSET_CMD_LIST),
+    IpsetSave => ipset_msg_type(IPSET_CMD_SAVE),
+    IpsetAdd => ipset_msg_type(IPSET_CMD_ADD),
+    IpsetDel => ipset_msg_type(IPSET_CMD_DEL),
+    IpsetTest => ipset_msg_type(IPSET_CMD_TEST),
+    IpsetHeader => ipset_msg_type(IPSET_CMD_HEADER),
+    IpsetType => ipset_msg_type(IPSET_CMD_TYPE),
+    AcctNew => nfnl_msg_type(libc::NFNL_SUBSYS_ACCT as u8, NFNL_MSG_ACCT_NEW),
+    AcctGet => nfnl_msg_type(libc::NFNL_SUBSYS_ACCT as u8, NFNL_MSG_ACCT_GET),
+    AcctGetCtrZero => nfnl_msg_type(libc::NFNL_SUBSYS_ACCT as u8, NFNL_MSG_ACCT_GET_CTRZERO),
+    AcctDel => nfnl_msg_type(libc::NFNL_SUBSYS_ACCT as u8, NFNL_MSG_ACCT_DEL),
+    AcctOverquota => nfnl_msg_type(libc::NFNL_SUBSYS_ACCT as u8, NFNL_MSG_ACCT_OVERQUOTA),
+    CtTimeoutNew => nfnl_msg_type(
+        libc::NFNL_SUBSYS_CTNETLINK_TIMEOUT as u8,
+        IPCTNL_MSG_TIMEOUT_NEW
+    ),
+    CtTimeoutGet => nfnl_msg_type(
+        libc::NFNL_SUBSYS_CTNETLINK_TIMEOUT as u8,
+        IPCTNL_MSG_TIMEOUT_GET
+    ),
+    CtTimeoutDelete => nfnl_msg_type(
+        libc::NFNL_SUBSYS_CTNETLINK_TIMEOUT as u8,
+        IPCTNL_MSG_TIMEOUT_DELETE
+    ),
+    CtTimeoutDefaultSet => nfnl_msg_type(
+        libc::NFNL_SUBSYS_CTNETLINK_TIMEOUT as u8,
+        IPCTNL_MSG_TIMEOUT_DEFAULT_SET
+    ),
+    CtTimeoutDefaultGet => nfnl_msg_type(
+        libc::NFNL_SUBSYS_CTNETLINK_TIMEOUT as u8,
+        IPCTNL_MSG_TIMEOUT_DEFAULT_GET
+    )
 }
 
 impl_trait! {
@@ -73,6 +188,14 @@ impl_var_trait! {
     PfUnbind => libc::NFULNL_CFG_CMD_PF_UNBIND as u8
 }
 
+impl_var! {
+    /// Flags for the [NfLogCfg::Flags] attribute.
+    LogCfgF, u16,
+    Seq => libc::NFULNL_CFG_F_SEQ as u16,
+    SeqGlobal => libc::NFULNL_CFG_F_SEQ_GLOBAL as u16,
+    Conntrack => libc::NFULNL_CFG_F_CONNTRACK as u16
+}
+
 impl_var! {
     /// Copy mode of the logged packets.
     LogCopyMode, u8,
@@ -80,3 +203,692 @@ impl_var! {
     Meta => libc::NFULNL_COPY_META as u8,
     Packet => libc::NFULNL_COPY_PACKET as u8
 }
+
+impl_var_trait! {
+    /// Attributes inside a netfilter queue packet or verdict message.
+    NfQueueAttr, u16, NlAttrType,
+    PacketHdr => libc::NFQA_PACKET_HDR as u16,
+    VerdictHdr => libc::NFQA_VERDICT_HDR as u16,
+    Mark => libc::NFQA_MARK as u16,
+    Timestamp => libc::NFQA_TIMESTAMP as u16,
+    IfindexIndev => libc::NFQA_IFINDEX_INDEV as u16,
+    IfindexOutdev => libc::NFQA_IFINDEX_OUTDEV as u16,
+    IfindexPhyindev => libc::NFQA_IFINDEX_PHYSINDEV as u16,
+    IfindexPhyoutdev => libc::NFQA_IFINDEX_PHYSOUTDEV as u16,
+    Hwaddr => libc::NFQA_HWADDR as u16,
+    Payload => libc::NFQA_PAYLOAD as u16,
+    Ct => libc::NFQA_CT as u16,
+    CtInfo => libc::NFQA_CT_INFO as u16,
+    CapLen => libc::NFQA_CAP_LEN as u16,
+    SkbInfo => libc::NFQA_SKB_INFO as u16,
+    Exp => libc::NFQA_EXP as u16,
+    Uid => libc::NFQA_UID as u16,
+    Gid => libc::NFQA_GID as u16,
+    Secctx => libc::NFQA_SECCTX as u16
+}
+
+impl_var_trait! {
+    /// Configuration attributes for netfilter queues.
+    ///
+    /// See [QueueConfigReq][crate::netfilter::QueueConfigReq]
+    NfQueueCfg, u16, NlAttrType,
+    Cmd => libc::NFQA_CFG_CMD as u16,
+    Params => libc::NFQA_CFG_PARAMS as u16,
+    QueueMaxlen => libc::NFQA_CFG_QUEUE_MAXLEN as u16,
+    Mask => libc::NFQA_CFG_MASK as u16,
+    Flags => libc::NFQA_CFG_FLAGS as u16
+}
+
+impl_var! {
+    /// Command value for the [QueueConfigCmd][crate::netfilter::QueueConfigCmd].
+    QueueCmd, u8,
+    None => libc::NFQNL_CFG_CMD_NONE as u8,
+    Bind => libc::NFQNL_CFG_CMD_BIND as u8,
+    Unbind => libc::NFQNL_CFG_CMD_UNBIND as u8,
+    PfBind => libc::NFQNL_CFG_CMD_PF_BIND as u8,
+    PfUnbind => libc::NFQNL_CFG_CMD_PF_UNBIND as u8
+}
+
+impl_var! {
+    /// Copy mode of the queued packets.
+    QueueCopyMode, u8,
+    None => libc::NFQNL_COPY_NONE as u8,
+    Meta => libc::NFQNL_COPY_META as u8,
+    Packet => libc::NFQNL_COPY_PACKET as u8
+}
+
+impl_var! {
+    /// Flags for the [NfQueueCfg::Flags] and [NfQueueCfg::Mask] attributes.
+    QueueCfgF, u32,
+    FailOpen => libc::NFQA_CFG_F_FAIL_OPEN as u32,
+    Conntrack => libc::NFQA_CFG_F_CONNTRACK as u32,
+    Gso => libc::NFQA_CFG_F_GSO as u32,
+    UidGid => libc::NFQA_CFG_F_UID_GID as u32,
+    Secctx => libc::NFQA_CFG_F_SECCTX as u32
+}
+
+impl_var! {
+    /// Netfilter verdicts, used when issuing a verdict on a queued packet.
+    Verdict, u32,
+    Drop => libc::NF_DROP as u32,
+    Accept => libc::NF_ACCEPT as u32,
+    Stolen => libc::NF_STOLEN as u32,
+    Queue => libc::NF_QUEUE as u32,
+    Repeat => libc::NF_REPEAT as u32,
+    Stop => libc::NF_STOP as u32
+}
+
+impl_var! {
+    /// Netfilter multicast groups, for use with
+    /// [NlSocket::set_mcast_groups][crate::socket::NlSocket::set_mcast_groups].
+    NfnlGrp, u32,
+    ConntrackNew => libc::NFNLGRP_CONNTRACK_NEW as u32,
+    ConntrackUpdate => libc::NFNLGRP_CONNTRACK_UPDATE as u32,
+    ConntrackDestroy => libc::NFNLGRP_CONNTRACK_DESTROY as u32,
+    ConntrackExpNew => libc::NFNLGRP_CONNTRACK_EXP_NEW as u32,
+    ConntrackExpUpdate => libc::NFNLGRP_CONNTRACK_EXP_UPDATE as u32,
+    ConntrackExpDestroy => libc::NFNLGRP_CONNTRACK_EXP_DESTROY as u32,
+    Nftables => libc::NFNLGRP_NFTABLES as u32,
+    AcctQuota => libc::NFNLGRP_ACCT_QUOTA as u32,
+    Nftrace => libc::NFNLGRP_NFTRACE as u32
+}
+
+// The conntrack constants below come from `linux/netfilter/nfnetlink_conntrack.h` and
+// `linux/netfilter/nf_conntrack_common.h` as they are not exported by `libc`.
+
+impl_var_trait! {
+    /// Top level attributes of a conntrack message.
+    CtAttr, u16, NlAttrType,
+    TupleOrig => 1,
+    TupleReply => 2,
+    Status => 3,
+    Protoinfo => 4,
+    Help => 5,
+    NatSrc => 6,
+    Timeout => 7,
+    Mark => 8,
+    CountersOrig => 9,
+    CountersReply => 10,
+    Use => 11,
+    Id => 12,
+    NatDst => 13,
+    TupleMaster => 14,
+    SeqAdjOrig => 15,
+    SeqAdjReply => 16,
+    Secmark => 17,
+    Zone => 18,
+    Secctx => 19,
+    Timestamp => 20,
+    MarkMask => 21,
+    Labels => 22,
+    LabelsMask => 23,
+    Synproxy => 24,
+    Filter => 25,
+    StatusMask => 26
+}
+
+impl_var_trait! {
+    /// Attributes nested in [CtAttr::TupleOrig], [CtAttr::TupleReply] and
+    /// [CtAttr::TupleMaster].
+    CtTupleAttr, u16, NlAttrType,
+    Ip => 1,
+    Proto => 2,
+    Zone => 3
+}
+
+impl_var_trait! {
+    /// Attributes nested in [CtTupleAttr::Ip].
+    CtIpAttr, u16, NlAttrType,
+    V4Src => 1,
+    V4Dst => 2,
+    V6Src => 3,
+    V6Dst => 4
+}
+
+impl_var_trait! {
+    /// Attributes nested in [CtTupleAttr::Proto].
+    CtProtoAttr, u16, NlAttrType,
+    Num => 1,
+    SrcPort => 2,
+    DstPort => 3,
+    IcmpId => 4,
+    IcmpType => 5,
+    IcmpCode => 6,
+    Icmpv6Id => 7,
+    Icmpv6Type => 8,
+    Icmpv6Code => 9
+}
+
+impl_var_trait! {
+    /// Attributes nested in [CtAttr::Protoinfo].
+    CtProtoinfoAttr, u16, NlAttrType,
+    Tcp => 1,
+    Dccp => 2,
+    Sctp => 3
+}
+
+impl_var_trait! {
+    /// Attributes nested in [CtProtoinfoAttr::Tcp].
+    CtProtoinfoTcpAttr, u16, NlAttrType,
+    State => 1,
+    WscaleOriginal => 2,
+    WscaleReply => 3,
+    FlagsOriginal => 4,
+    FlagsReply => 5
+}
+
+impl_var_trait! {
+    /// Attributes nested in [CtAttr::CountersOrig] and [CtAttr::CountersReply].
+    CtCountersAttr, u16, NlAttrType,
+    Packets => 1,
+    Bytes => 2,
+    Packets32 => 3,
+    Bytes32 => 4,
+    Pad => 5
+}
+
+impl_var! {
+    /// Conntrack status bits, as found in [CtAttr::Status].
+    CtStatus, u32,
+    Expected => 1,
+    SeenReply => 1 << 1,
+    Assured => 1 << 2,
+    Confirmed => 1 << 3,
+    SrcNat => 1 << 4,
+    DstNat => 1 << 5,
+    SeqAdjust => 1 << 6,
+    SrcNatDone => 1 << 7,
+    DstNatDone => 1 << 8,
+    Dying => 1 << 9,
+    FixedTimeout => 1 << 10,
+    Template => 1 << 11,
+    Untracked => 1 << 12,
+    Helper => 1 << 13,
+    Offload => 1 << 14,
+    HwOffload => 1 << 15
+}
+
+impl_var! {
+    /// TCP connection tracking states, as found in [CtProtoinfoTcpAttr::State].
+    TcpConntrackState, u8,
+    None => 0,
+    SynSent => 1,
+    SynRecv => 2,
+    Established => 3,
+    FinWait => 4,
+    CloseWait => 5,
+    LastAck => 6,
+    TimeWait => 7,
+    Close => 8,
+    SynSent2 => 9
+}
+
+impl_var! {
+    /// Netfilter protocol families, used as the family of nftables messages.
+    NfProto, u8,
+    Unspec => libc::NFPROTO_UNSPEC as u8,
+    Inet => libc::NFPROTO_INET as u8,
+    Ipv4 => libc::NFPROTO_IPV4 as u8,
+    Arp => libc::NFPROTO_ARP as u8,
+    Netdev => libc::NFPROTO_NETDEV as u8,
+    Bridge => libc::NFPROTO_BRIDGE as u8,
+    Ipv6 => libc::NFPROTO_IPV6 as u8
+}
+
+impl_var! {
+    /// Netfilter hooks of the IPv4, IPv6 and inet families, as used in [NftHookAttr::Hooknum].
+    NfInetHook, u32,
+    PreRouting => libc::NF_INET_PRE_ROUTING as u32,
+    LocalIn => libc::NF_INET_LOCAL_IN as u32,
+    Forward => libc::NF_INET_FORWARD as u32,
+    LocalOut => libc::NF_INET_LOCAL_OUT as u32,
+    PostRouting => libc::NF_INET_POST_ROUTING as u32,
+    Ingress => libc::NF_INET_INGRESS as u32
+}
+
+// The nftables attributes below come from `linux/netfilter/nf_tables.h` as they are not exported
+// by `libc`.
+
+impl_var_trait! {
+    /// Attributes of an nftables table message.
+    NftTableAttr, u16, NlAttrType,
+    Name => 1,
+    Flags => 2,
+    Use => 3,
+    Handle => 4,
+    Pad => 5,
+    Userdata => 6
+}
+
+impl_var_trait! {
+    /// Attributes of an nftables chain message.
+    NftChainAttr, u16, NlAttrType,
+    Table => 1,
+    Handle => 2,
+    Name => 3,
+    Hook => 4,
+    Policy => 5,
+    Use => 6,
+    Type => 7,
+    Counters => 8,
+    Pad => 9,
+    Flags => 10,
+    Id => 11,
+    Userdata => 12
+}
+
+impl_var_trait! {
+    /// Attributes nested in [NftChainAttr::Hook].
+    NftHookAttr, u16, NlAttrType,
+    Hooknum => 1,
+    Priority => 2,
+    Dev => 3,
+    Devs => 4
+}
+
+impl_var_trait! {
+    /// Attributes of an nftables rule message.
+    NftRuleAttr, u16, NlAttrType,
+    Table => 1,
+    Chain => 2,
+    Handle => 3,
+    Expressions => 4,
+    Compat => 5,
+    Position => 6,
+    Userdata => 7,
+    Pad => 8,
+    Id => 9,
+    PositionId => 10,
+    ChainId => 11
+}
+
+impl_var_trait! {
+    /// Attributes of nested lists such as [NftRuleAttr::Expressions].
+    NftListAttr, u16, NlAttrType,
+    Elem => 1
+}
+
+impl_var_trait! {
+    /// Attributes of an expression in a rule.
+    NftExprAttr, u16, NlAttrType,
+    Name => 1,
+    Data => 2
+}
+
+impl_var_trait! {
+    /// Attributes of data values such as [NftImmediateAttr::Data].
+    NftDataAttr, u16, NlAttrType,
+    Value => 1,
+    Verdict => 2
+}
+
+impl_var_trait! {
+    /// Attributes nested in [NftDataAttr::Verdict].
+    NftVerdictAttr, u16, NlAttrType,
+    Code => 1,
+    Chain => 2,
+    ChainId => 3
+}
+
+impl_var_trait! {
+    /// Attributes of the `immediate` expression.
+    NftImmediateAttr, u16, NlAttrType,
+    Dreg => 1,
+    Data => 2
+}
+
+impl_var_trait! {
+    /// Attributes of the `cmp` expression.
+    NftCmpAttr, u16, NlAttrType,
+    Sreg => 1,
+    Op => 2,
+    Data => 3
+}
+
+impl_var_trait! {
+    /// Attributes of the `lookup` expression.
+    NftLookupAttr, u16, NlAttrType,
+    Set => 1,
+    Sreg => 2,
+    Dreg => 3,
+    SetId => 4,
+    Flags => 5
+}
+
+impl_var_trait! {
+    /// Attributes of the `payload` expression.
+    NftPayloadAttr, u16, NlAttrType,
+    Dreg => 1,
+    Base => 2,
+    Offset => 3,
+    Len => 4,
+    Sreg => 5,
+    CsumType => 6,
+    CsumOffset => 7,
+    CsumFlags => 8
+}
+
+impl_var_trait! {
+    /// Attributes of the `meta` expression.
+    NftMetaAttr, u16, NlAttrType,
+    Dreg => 1,
+    Key => 2,
+    Sreg => 3
+}
+
+impl_var_trait! {
+    /// Attributes of the `counter` expression.
+    NftCounterAttr, u16, NlAttrType,
+    Bytes => 1,
+    Packets => 2,
+    Pad => 3
+}
+
+impl_var_trait! {
+    /// Attributes of the `nat` expression.
+    NftNatAttr, u16, NlAttrType,
+    Type => 1,
+    Family => 2,
+    RegAddrMin => 3,
+    RegAddrMax => 4,
+    RegProtoMin => 5,
+    RegProtoMax => 6,
+    Flags => 7
+}
+
+impl_var_trait! {
+    /// Attributes of an nftables set message.
+    NftSetAttr, u16, NlAttrType,
+    Table => 1,
+    Name => 2,
+    Flags => 3,
+    KeyType => 4,
+    KeyLen => 5,
+    DataType => 6,
+    DataLen => 7,
+    Policy => 8,
+    Desc => 9,
+    Id => 10,
+    Timeout => 11,
+    GcInterval => 12,
+    Userdata => 13,
+    Pad => 14,
+    ObjType => 15,
+    Handle => 16
+}
+
+impl_var_trait! {
+    /// Attributes of an nftables set element list message.
+    NftSetElemListAttr, u16, NlAttrType,
+    Table => 1,
+    Set => 2,
+    Elements => 3,
+    SetId => 4
+}
+
+impl_var_trait! {
+    /// Attributes of a set element nested in [NftSetElemListAttr::Elements].
+    NftSetElemAttr, u16, NlAttrType,
+    Key => 1,
+    Data => 2,
+    Flags => 3,
+    Timeout => 4,
+    Expiration => 5,
+    Userdata => 6,
+    Expr => 7,
+    Pad => 8,
+    Objref => 9,
+    KeyEnd => 10
+}
+
+impl_var! {
+    /// nftables registers.
+    NftRegister, u32,
+    Verdict => libc::NFT_REG_VERDICT as u32,
+    Reg1 => libc::NFT_REG_1 as u32,
+    Reg2 => libc::NFT_REG_2 as u32,
+    Reg3 => libc::NFT_REG_3 as u32,
+    Reg4 => libc::NFT_REG_4 as u32
+}
+
+impl_var! {
+    /// Verdict codes, as used in [NftVerdictAttr::Code].
+    NftVerdictCode, i32,
+    Drop => libc::NF_DROP,
+    Accept => libc::NF_ACCEPT,
+    Continue => libc::NFT_CONTINUE,
+    Break => libc::NFT_BREAK,
+    Jump => libc::NFT_JUMP,
+    Goto => libc::NFT_GOTO,
+    Return => libc::NFT_RETURN
+}
+
+impl_var! {
+    /// Comparison operators of the `cmp` expression.
+    NftCmpOp, u32,
+    Eq => libc::NFT_CMP_EQ as u32,
+    Neq => libc::NFT_CMP_NEQ as u32,
+    Lt => libc::NFT_CMP_LT as u32,
+    Lte => libc::NFT_CMP_LTE as u32,
+    Gt => libc::NFT_CMP_GT as u32,
+    Gte => libc::NFT_CMP_GTE as u32
+}
+
+impl_var! {
+    /// Header a `payload` expression offset is relative to.
+    NftPayloadBase, u32,
+    LinkLayer => libc::NFT_PAYLOAD_LL_HEADER as u32,
+    Network => libc::NFT_PAYLOAD_NETWORK_HEADER as u32,
+    Transport => libc::NFT_PAYLOAD_TRANSPORT_HEADER as u32
+}
+
+impl_var! {
+    /// Keys of the `meta` expression.
+    NftMetaKey, u32,
+    Len => libc::NFT_META_LEN as u32,
+    Protocol => libc::NFT_META_PROTOCOL as u32,
+    Priority => libc::NFT_META_PRIORITY as u32,
+    Mark => libc::NFT_META_MARK as u32,
+    Iif => libc::NFT_META_IIF as u32,
+    Oif => libc::NFT_META_OIF as u32,
+    Iifname => libc::NFT_META_IIFNAME as u32,
+    Oifname => libc::NFT_META_OIFNAME as u32,
+    Iiftype => libc::NFT_META_IIFTYPE as u32,
+    Oiftype => libc::NFT_META_OIFTYPE as u32,
+    Skuid => libc::NFT_META_SKUID as u32,
+    Skgid => libc::NFT_META_SKGID as u32,
+    Nftrace => libc::NFT_META_NFTRACE as u32,
+    Rtclassid => libc::NFT_META_RTCLASSID as u32,
+    Secmark => libc::NFT_META_SECMARK as u32,
+    Nfproto => libc::NFT_META_NFPROTO as u32,
+    L4proto => libc::NFT_META_L4PROTO as u32,
+    BriIifname => libc::NFT_META_BRI_IIFNAME as u32,
+    BriOifname => libc::NFT_META_BRI_OIFNAME as u32,
+    Pkttype => libc::NFT_META_PKTTYPE as u32,
+    Cpu => libc::NFT_META_CPU as u32,
+    Iifgroup => libc::NFT_META_IIFGROUP as u32,
+    Oifgroup => libc::NFT_META_OIFGROUP as u32,
+    Cgroup => libc::NFT_META_CGROUP as u32,
+    Prandom => libc::NFT_META_PRANDOM as u32
+}
+
+impl_var! {
+    /// Type of the `nat` expression.
+    NftNatType, u32,
+    Snat => libc::NFT_NAT_SNAT as u32,
+    Dnat => libc::NFT_NAT_DNAT as u32
+}
+
+impl_var! {
+    /// Flags of an nftables set, as found in [NftSetAttr::Flags].
+    NftSetFlags, u32,
+    Anonymous => libc::NFT_SET_ANONYMOUS as u32,
+    Constant => libc::NFT_SET_CONSTANT as u32,
+    Interval => libc::NFT_SET_INTERVAL as u32,
+    Map => libc::NFT_SET_MAP as u32,
+    Timeout => libc::NFT_SET_TIMEOUT as u32,
+    Eval => libc::NFT_SET_EVAL as u32
+}
+
+// The ipset attributes below come from `linux/netfilter/ipset/ip_set.h` as they are not exported
+// by `libc`.
+
+impl_var_trait! {
+    /// Top level attributes of an ipset message.
+    IpsetAttr, u16, NlAttrType,
+    Protocol => 1,
+    Setname => 2,
+    Typename => 3,
+    Revision => 4,
+    Family => 5,
+    Flags => 6,
+    Data => 7,
+    Adt => 8,
+    Lineno => 9,
+    ProtocolMin => 10,
+    Index => 11
+}
+
+impl_var_trait! {
+    /// Attributes nested in [IpsetAttr::Data] of create, header and list messages, describing
+    /// a set.
+    IpsetCreateAttr, u16, NlAttrType,
+    Ip => 1,
+    IpTo => 2,
+    Cidr => 3,
+    Port => 4,
+    PortTo => 5,
+    Timeout => 6,
+    Proto => 7,
+    CadtFlags => 8,
+    Lineno => 9,
+    Mark => 10,
+    Markmask => 11,
+    Initval => 17,
+    Hashsize => 18,
+    Maxelem => 19,
+    Netmask => 20,
+    Bucketsize => 21,
+    Resize => 22,
+    Size => 23,
+    Elements => 24,
+    References => 25,
+    Memsize => 26
+}
+
+impl_var_trait! {
+    /// Attributes nested in [IpsetAttr::Data] of add, del and test messages and in the elements
+    /// of [IpsetAttr::Adt], describing a set entry.
+    IpsetAdtAttr, u16, NlAttrType,
+    Ip => 1,
+    IpTo => 2,
+    Cidr => 3,
+    Port => 4,
+    PortTo => 5,
+    Timeout => 6,
+    Proto => 7,
+    CadtFlags => 8,
+    Lineno => 9,
+    Mark => 10,
+    Markmask => 11,
+    Ether => 17,
+    Name => 18,
+    Nameref => 19,
+    Ip2 => 20,
+    Cidr2 => 21,
+    Ip2To => 22,
+    Iface => 23,
+    Bytes => 24,
+    Packets => 25,
+    Comment => 26,
+    Skbmark => 27,
+    Skbprio => 28,
+    Skbqueue => 29,
+    Pad => 30
+}
+
+impl_var_trait! {
+    /// Attributes nested in IP address attributes such as [IpsetAdtAttr::Ip].
+    IpsetIpAttr, u16, NlAttrType,
+    Ipv4 => 1,
+    Ipv6 => 2
+}
+
+// The nfacct constants below come from `linux/netfilter/nfnetlink_acct.h` as they are not
+// exported by `libc`.
+
+impl_var_trait! {
+    /// Attributes of an nfacct message.
+    NfAcctAttr, u16, NlAttrType,
+    Name => 1,
+    Pkts => 2,
+    Bytes => 3,
+    Use => 4,
+    Flags => 5,
+    Quota => 6,
+    Filter => 7,
+    Pad => 8
+}
+
+impl_var_trait! {
+    /// Attributes nested in [NfAcctAttr::Filter] of dump requests.
+    NfAcctFilterAttr, u16, NlAttrType,
+    Mask => 1,
+    Value => 2
+}
+
+impl_var! {
+    /// Flags of the [NfAcctAttr::Flags] attribute.
+    NfAcctF, u32,
+    QuotaPkts => 1,
+    QuotaBytes => 2,
+    Overquota => 4
+}
+
+// The conntrack timeout constants below come from `linux/netfilter/nfnetlink_cttimeout.h` as
+// they are not exported by `libc`.
+
+impl_var_trait! {
+    /// Attributes of a conntrack timeout policy message.
+    CtTimeoutAttr, u16, NlAttrType,
+    Name => 1,
+    L3Proto => 2,
+    L4Proto => 3,
+    Data => 4,
+    Use => 5
+}
+
+impl_var_trait! {
+    /// Timeouts nested in [CtTimeoutAttr::Data] for TCP.
+    CtTimeoutTcpAttr, u16, NlAttrType,
+    SynSent => 1,
+    SynRecv => 2,
+    Established => 3,
+    FinWait => 4,
+    CloseWait => 5,
+    LastAck => 6,
+    TimeWait => 7,
+    Close => 8,
+    SynSent2 => 9,
+    Retrans => 10,
+    Unack => 11
+}
+
+impl_var_trait! {
+    /// Timeouts nested in [CtTimeoutAttr::Data] for UDP and UDP-Lite.
+    CtTimeoutUdpAttr, u16, NlAttrType,
+    Unreplied => 1,
+    Replied => 2
+}
+
+impl_var_trait! {
+    /// Timeouts nested in [CtTimeoutAttr::Data] for ICMP and ICMPv6.
+    CtTimeoutIcmpAttr, u16, NlAttrType,
+    Timeout => 1
+}
+
+impl_var_trait! {
+    /// Timeouts nested in [CtTimeoutAttr::Data] for protocols without a specific tracker.
+    CtTimeoutGenericAttr, u16, NlAttrType,
+    Timeout => 1
+}
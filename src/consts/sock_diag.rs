@@ -0,0 +1,112 @@
+//! Constants for the socket diagnostics (`sock_diag`) protocol
+//!
+//! The values come from `linux/sock_diag.h`, `linux/inet_diag.h`, `linux/unix_diag.h` and
+//! `net/tcp_states.h` as they are not exported by `libc`.
+
+use super::{NlAttrType, NlType};
+
+impl_var_trait! {
+    /// Message types of the [NlFamily::SockOrInetDiag][super::NlFamily::SockOrInetDiag]
+    /// protocol.
+    SockDiagMsg, u16, NlType,
+    ByFamily => 20,
+    Destroy => 21
+}
+
+impl_var! {
+    /// TCP socket states, also used for the states of other socket types in `sock_diag` messages.
+    TcpState, u8,
+    Established => 1,
+    SynSent => 2,
+    SynRecv => 3,
+    FinWait1 => 4,
+    FinWait2 => 5,
+    TimeWait => 6,
+    Close => 7,
+    CloseWait => 8,
+    LastAck => 9,
+    Listen => 10,
+    Closing => 11,
+    NewSynRecv => 12
+}
+
+impl_var_trait! {
+    /// Attributes of an `inet_diag_req_v2` request.
+    InetDiagReqAttr, u16, NlAttrType,
+    Bytecode => 1,
+    SkBpfStorages => 2,
+    Protocol => 3
+}
+
+impl_var_trait! {
+    /// Extension attributes of an `inet_diag_msg` response. The extensions with a value up to
+    /// [InetDiagAttr::Shutdown] have to be requested, the others are sent when available.
+    InetDiagAttr, u16, NlAttrType,
+    Meminfo => 1,
+    Info => 2,
+    Vegasinfo => 3,
+    Cong => 4,
+    Tos => 5,
+    Tclass => 6,
+    Skmeminfo => 7,
+    Shutdown => 8,
+    Dctcpinfo => 9,
+    Protocol => 10,
+    Skv6only => 11,
+    Locals => 12,
+    Peers => 13,
+    Pad => 14,
+    Mark => 15,
+    Bbrinfo => 16,
+    ClassId => 17,
+    Md5sig => 18,
+    UlpInfo => 19,
+    SkBpfStorages => 20,
+    CgroupId => 21,
+    Sockopt => 22
+}
+
+impl_var! {
+    /// Operations of the `inet_diag` filter bytecode.
+    InetDiagBcOp, u8,
+    Nop => 0,
+    Jmp => 1,
+    SrcGe => 2,
+    SrcLe => 3,
+    DstGe => 4,
+    DstLe => 5,
+    Auto => 6,
+    SrcCond => 7,
+    DstCond => 8,
+    DevCond => 9,
+    MarkCond => 10,
+    SrcEq => 11,
+    DstEq => 12,
+    CgroupCond => 13
+}
+
+impl_var! {
+    /// Flags of the `udiag_show` field of a `unix_diag_req`, selecting the extensions of the
+    /// response.
+    UnixDiagShow, u32,
+    Name => 0x01,
+    Vfs => 0x02,
+    Peer => 0x04,
+    Icons => 0x08,
+    Rqlen => 0x10,
+    Meminfo => 0x20,
+    Uid => 0x40
+}
+
+impl_var_trait! {
+    /// Extension attributes of a `unix_diag_msg` response.
+    UnixDiagAttr, u16, NlAttrType,
+    Name => 0,
+    Vfs => 1,
+    Peer => 2,
+    Icons => 3,
+    Rqlen => 4,
+    Meminfo => 5,
+    Shutdown => 6,
+    Uid => 7
+}
@@ -39,3 +39,10 @@ impl_var!(
     Rdma => libc::NETLINK_RDMA,
     Crypto => libc::NETLINK_CRYPTO
 );
+
+impl_var!(
+    /// Multicast groups of the `NlFamily::KobjectUevent` protocol
+    UeventGrp, u32,
+    Kernel => 1,
+    Udev => 2
+);
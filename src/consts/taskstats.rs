@@ -0,0 +1,35 @@
+//! Constants for the taskstats generic netlink family
+//!
+//! The values come from `linux/taskstats.h`.
+
+use super::{Cmd, NlAttrType};
+
+impl_var_trait! {
+    /// Commands of the taskstats family.
+    TaskstatsCmd, u8, Cmd,
+    Unspec => 0,
+    Get => 1,
+    New => 2
+}
+
+impl_var_trait! {
+    /// Attributes of `TaskstatsCmd::Get` requests.
+    TaskstatsCmdAttr, u16, NlAttrType,
+    Unspec => 0,
+    Pid => 1,
+    Tgid => 2,
+    RegisterCpumask => 3,
+    DeregisterCpumask => 4
+}
+
+impl_var_trait! {
+    /// Attributes of `TaskstatsCmd::New` replies and exit notifications.
+    TaskstatsType, u16, NlAttrType,
+    Unspec => 0,
+    Pid => 1,
+    Tgid => 2,
+    Stats => 3,
+    AggrPid => 4,
+    AggrTgid => 5,
+    Null => 6
+}
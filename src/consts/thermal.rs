@@ -0,0 +1,64 @@
+//! Constants for the thermal generic netlink family
+//!
+//! The values come from `linux/thermal.h`.
+
+use super::{Cmd, NlAttrType};
+
+impl_var_trait! {
+    /// Commands of thermal event and sampling messages. Temperature samples use
+    /// `THERMAL_GENL_SAMPLING_TEMP`, which shares the value of `Unspec`.
+    ThermalGenlEvent, u8, Cmd,
+    Unspec => 0,
+    TzCreate => 1,
+    TzDelete => 2,
+    TzDisable => 3,
+    TzEnable => 4,
+    TzTripUp => 5,
+    TzTripDown => 6,
+    TzTripChange => 7,
+    TzTripAdd => 8,
+    TzTripDelete => 9,
+    CdevAdd => 10,
+    CdevDelete => 11,
+    CdevStateUpdate => 12,
+    TzGovChange => 13,
+    CpuCapabilityChange => 14
+}
+
+impl_var_trait! {
+    /// Attributes of the thermal family.
+    ThermalGenlAttr, u16, NlAttrType,
+    Unspec => 0,
+    Tz => 1,
+    TzId => 2,
+    TzTemp => 3,
+    TzTrip => 4,
+    TzTripId => 5,
+    TzTripType => 6,
+    TzTripTemp => 7,
+    TzTripHyst => 8,
+    TzMode => 9,
+    TzName => 10,
+    TzCdevWeight => 11,
+    TzGov => 12,
+    TzGovName => 13,
+    Cdev => 14,
+    CdevId => 15,
+    CdevCurState => 16,
+    CdevMaxState => 17,
+    CdevName => 18,
+    GovName => 19,
+    CpuCapability => 20,
+    CpuCapabilityId => 21,
+    CpuCapabilityPerformance => 22,
+    CpuCapabilityEfficiency => 23
+}
+
+impl_var! {
+    /// Type of a trip point.
+    ThermalTripType, u32,
+    Active => 0,
+    Passive => 1,
+    Hot => 2,
+    Critical => 3
+}
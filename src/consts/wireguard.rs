@@ -0,0 +1,65 @@
+//! Constants for the WireGuard generic netlink family
+//!
+//! The values come from `linux/wireguard.h`.
+
+use super::{Cmd, NlAttrType};
+
+impl_var_trait! {
+    /// Commands of the WireGuard family.
+    WgCmd, u8, Cmd,
+    GetDevice => 0,
+    SetDevice => 1
+}
+
+impl_var_trait! {
+    /// Attributes of a WireGuard device.
+    WgDeviceAttr, u16, NlAttrType,
+    Unspec => 0,
+    Ifindex => 1,
+    Ifname => 2,
+    PrivateKey => 3,
+    PublicKey => 4,
+    Flags => 5,
+    ListenPort => 6,
+    Fwmark => 7,
+    Peers => 8
+}
+
+impl_var! {
+    /// Flags of `WgDeviceAttr::Flags`.
+    WgDeviceFlag, u32,
+    ReplacePeers => 1
+}
+
+impl_var_trait! {
+    /// Attributes of a WireGuard peer.
+    WgPeerAttr, u16, NlAttrType,
+    Unspec => 0,
+    PublicKey => 1,
+    PresharedKey => 2,
+    Flags => 3,
+    Endpoint => 4,
+    PersistentKeepaliveInterval => 5,
+    LastHandshakeTime => 6,
+    RxBytes => 7,
+    TxBytes => 8,
+    Allowedips => 9,
+    ProtocolVersion => 10
+}
+
+impl_var! {
+    /// Flags of `WgPeerAttr::Flags`.
+    WgPeerFlag, u32,
+    RemoveMe => 1,
+    ReplaceAllowedips => 2,
+    UpdateOnly => 4
+}
+
+impl_var_trait! {
+    /// Attributes of an allowed IP range of a WireGuard peer.
+    WgAllowedIpAttr, u16, NlAttrType,
+    Unspec => 0,
+    Family => 1,
+    Ipaddr => 2,
+    CidrMask => 3
+}
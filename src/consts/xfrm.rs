@@ -0,0 +1,159 @@
+//! Constants for the IPsec transformation (XFRM) protocol
+//!
+//! The values come from `linux/xfrm.h`.
+
+use super::{NlAttrType, NlType};
+
+impl_var_trait! {
+    /// Message types of the [NlFamily::Xfrm][super::NlFamily::Xfrm] protocol.
+    XfrmMsg, u16, NlType,
+    NewSa => 0x10,
+    DelSa => 0x11,
+    GetSa => 0x12,
+    NewPolicy => 0x13,
+    DelPolicy => 0x14,
+    GetPolicy => 0x15,
+    AllocSpi => 0x16,
+    Acquire => 0x17,
+    Expire => 0x18,
+    UpdPolicy => 0x19,
+    UpdSa => 0x1a,
+    PolExpire => 0x1b,
+    FlushSa => 0x1c,
+    FlushPolicy => 0x1d,
+    NewAe => 0x1e,
+    GetAe => 0x1f,
+    Report => 0x20,
+    Migrate => 0x21,
+    NewSadInfo => 0x22,
+    GetSadInfo => 0x23,
+    NewSpdInfo => 0x24,
+    GetSpdInfo => 0x25,
+    Mapping => 0x26,
+    SetDefault => 0x27,
+    GetDefault => 0x28
+}
+
+impl_var_trait! {
+    /// Attributes of XFRM state and policy messages.
+    XfrmAttr, u16, NlAttrType,
+    Unspec => 0,
+    AlgAuth => 1,
+    AlgCrypt => 2,
+    AlgComp => 3,
+    Encap => 4,
+    Tmpl => 5,
+    Sa => 6,
+    Policy => 7,
+    SecCtx => 8,
+    Ltime => 9,
+    ReplayVal => 10,
+    ReplayThresh => 11,
+    EtimerThresh => 12,
+    Srcaddr => 13,
+    Coaddr => 14,
+    LastUsed => 15,
+    PolicyType => 16,
+    Migrate => 17,
+    AlgAead => 18,
+    Kmaddress => 19,
+    AlgAuthTrunc => 20,
+    Mark => 21,
+    Tfcpad => 22,
+    ReplayEsnVal => 23,
+    SaExtraFlags => 24,
+    Proto => 25,
+    AddressFilter => 26,
+    Pad => 27,
+    OffloadDev => 28,
+    SetMark => 29,
+    SetMarkMask => 30,
+    IfId => 31,
+    MtimerThresh => 32,
+    SaDir => 33,
+    NatKeepaliveInterval => 34,
+    SaPcpu => 35
+}
+
+impl_var_trait! {
+    /// Attributes of `XfrmMsg::NewSadInfo` messages.
+    XfrmSadAttr, u16, NlAttrType,
+    Unspec => 0,
+    Cnt => 1,
+    Hinfo => 2
+}
+
+impl_var_trait! {
+    /// Attributes of `XfrmMsg::NewSpdInfo` messages.
+    XfrmSpdAttr, u16, NlAttrType,
+    Unspec => 0,
+    Info => 1,
+    Hinfo => 2,
+    Ipv4Hthresh => 3,
+    Ipv6Hthresh => 4
+}
+
+impl_var! {
+    /// Multicast groups of the XFRM protocol.
+    XfrmNlGrp, u32,
+    Acquire => 1,
+    Expire => 2,
+    Sa => 3,
+    Policy => 4,
+    Aevents => 5,
+    Report => 6,
+    Migrate => 7,
+    Mapping => 8
+}
+
+impl_var! {
+    /// Encapsulation modes of SAs and templates.
+    XfrmMode, u8,
+    Transport => 0,
+    Tunnel => 1,
+    RouteOptimization => 2,
+    InTrigger => 3,
+    Beet => 4
+}
+
+impl_var! {
+    /// Directions of policies.
+    XfrmPolicyDir, u8,
+    In => 0,
+    Out => 1,
+    Fwd => 2
+}
+
+impl_var! {
+    /// Actions of policies.
+    XfrmPolicyAction, u8,
+    Allow => 0,
+    Block => 1
+}
+
+impl_var! {
+    /// Flags of `xfrm_usersa_info`.
+    XfrmStateFlag, u8,
+    Noecn => 1,
+    DecapDscp => 2,
+    Nopmtudisc => 4,
+    Wildrecv => 8,
+    Icmp => 16,
+    AfUnspec => 32,
+    Align4 => 64,
+    Esn => 128
+}
+
+impl_var! {
+    /// Flags of `xfrm_userpolicy_info`.
+    XfrmPolicyFlag, u8,
+    Localok => 1,
+    Icmp => 2
+}
+
+impl_var! {
+    /// Policy types of the `XfrmAttr::PolicyType` attribute.
+    XfrmPolicyType, u8,
+    Main => 0,
+    Sub => 1
+}
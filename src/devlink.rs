@@ -0,0 +1,840 @@
+//! This module provides typed access to the devlink generic netlink family, which manages
+//! devices as a whole rather than their network interfaces: ports, parameters, health reporters
+//! and device information such as firmware versions.
+//!
+//! ```no_run
+//! use neli::devlink::DevlinkSocket;
+//!
+//! let mut socket = DevlinkSocket::connect().unwrap();
+//! for device in socket.get_devices().unwrap() {
+//!     let info = socket.get_info(&device.handle).unwrap();
+//!     println!("{} {:?} {:?}", device.handle, info.driver_name, info.versions_running);
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! A devlink instance is addressed by its bus name and device name, eg. `pci` and
+//! `0000:01:00.0`. `DevlinkHandle` holds the pair and is included in every request and parsed
+//! from every reply.
+//!
+//! All devlink attributes, including nested ones, share the `DevlinkAttr` type, so nested
+//! attributes are parsed with the same `from_attrs()` methods as top level ones.
+
+use std::fmt::{self, Display};
+
+use consts::devlink::{
+    DevlinkAttr, DevlinkCmd, DevlinkHealthState, DevlinkParamCmode, DevlinkParamType,
+    DevlinkPortFlavour, DevlinkPortType,
+};
+use consts::{NlFamily, NlmF};
+use err::{DeError, NlError, SerError};
+use genl::Genlmsghdr;
+use nl::Nlmsghdr;
+use nlattr::{AttrHandle, Nlattr};
+use socket::NlSocket;
+
+/// Name of the devlink generic netlink family
+pub const DEVLINK_GENL_NAME: &str = "devlink";
+/// Version of the devlink generic netlink family
+const DEVLINK_GENL_VERSION: u8 = 1;
+/// Name of the devlink multicast group for configuration changes
+pub const DEVLINK_GENL_MCGRP_CONFIG_NAME: &str = "config";
+
+/// Bus name and device name identifying a devlink instance
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DevlinkHandle {
+    /// Bus name, eg. `pci`
+    pub bus_name: String,
+    /// Device name on the bus, eg. `0000:01:00.0`
+    pub dev_name: String,
+}
+
+impl DevlinkHandle {
+    /// Create a handle from a bus name and a device name
+    pub fn new(bus_name: &str, dev_name: &str) -> Self {
+        DevlinkHandle {
+            bus_name: bus_name.to_string(),
+            dev_name: dev_name.to_string(),
+        }
+    }
+
+    /// Parse the handle from the attributes of a message
+    pub fn from_attrs(handle: &AttrHandle<DevlinkAttr>) -> Result<Self, DeError> {
+        Ok(DevlinkHandle {
+            bus_name: handle.get_attr_payload_as(DevlinkAttr::BusName)?,
+            dev_name: handle.get_attr_payload_as(DevlinkAttr::DevName)?,
+        })
+    }
+
+    /// Attributes addressing the devlink instance in a request
+    pub fn to_attrs(&self) -> Result<Vec<Nlattr<DevlinkAttr, Vec<u8>>>, SerError> {
+        Ok(vec![
+            Nlattr::new(None, DevlinkAttr::BusName, self.bus_name.as_str())?,
+            Nlattr::new(None, DevlinkAttr::DevName, self.dev_name.as_str())?,
+        ])
+    }
+}
+
+impl Display for DevlinkHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.bus_name, self.dev_name)
+    }
+}
+
+/// Devlink instance (`DEVLINK_CMD_GET`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DevlinkDevice {
+    /// Handle of the instance
+    pub handle: DevlinkHandle,
+    /// Whether the last reload of the driver failed
+    pub reload_failed: bool,
+}
+
+impl DevlinkDevice {
+    /// Parse a devlink instance from message attributes
+    pub fn from_attrs(handle: &AttrHandle<DevlinkAttr>) -> Result<Self, DeError> {
+        let reload_failed = match handle.get_attribute(DevlinkAttr::ReloadFailed) {
+            Some(attr) => attr.get_payload_as::<u8>()? != 0,
+            None => false,
+        };
+        Ok(DevlinkDevice {
+            handle: DevlinkHandle::from_attrs(handle)?,
+            reload_failed,
+        })
+    }
+}
+
+/// Port of a devlink instance (`DEVLINK_CMD_PORT_GET`)
+///
+/// Fields that were not present in the message are `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DevlinkPort {
+    /// Handle of the instance the port belongs to
+    pub handle: DevlinkHandle,
+    /// Index of the port
+    pub index: u32,
+    /// Type of the network device of the port
+    pub port_type: Option<DevlinkPortType>,
+    /// Type requested by the user
+    pub desired_type: Option<DevlinkPortType>,
+    /// Interface index of the network device
+    pub netdev_ifindex: Option<u32>,
+    /// Name of the network device
+    pub netdev_name: Option<String>,
+    /// Name of the InfiniBand device
+    pub ibdev_name: Option<String>,
+    /// Flavour of the port
+    pub flavour: Option<DevlinkPortFlavour>,
+    /// Physical port number
+    pub number: Option<u32>,
+    /// Number of ports the port was split into
+    pub split_count: Option<u32>,
+    /// Port number of the port the port was split from
+    pub split_group: Option<u32>,
+    /// Subport number of a split port
+    pub split_subport_number: Option<u32>,
+    /// Whether the port can be split
+    pub splittable: Option<bool>,
+    /// Number of lanes
+    pub lanes: Option<u32>,
+    /// PCI PF number of PCI ports
+    pub pci_pf_number: Option<u16>,
+    /// PCI VF number of VF ports
+    pub pci_vf_number: Option<u16>,
+    /// PCI SF number of SF ports
+    pub pci_sf_number: Option<u32>,
+    /// Whether the port belongs to an external controller
+    pub external: Option<bool>,
+    /// Number of the controller the port belongs to
+    pub controller_number: Option<u32>,
+}
+
+impl DevlinkPort {
+    /// Parse a port from message attributes
+    pub fn from_attrs(handle: &AttrHandle<DevlinkAttr>) -> Result<Self, DeError> {
+        let mut port = DevlinkPort {
+            handle: DevlinkHandle::from_attrs(handle)?,
+            ..DevlinkPort::default()
+        };
+        for attr in handle.iter() {
+            match attr.nla_type {
+                DevlinkAttr::PortIndex => port.index = attr.get_payload_as()?,
+                DevlinkAttr::PortType => {
+                    port.port_type = Some(DevlinkPortType::from(attr.get_payload_as::<u16>()?))
+                }
+                DevlinkAttr::PortDesiredType => {
+                    port.desired_type = Some(DevlinkPortType::from(attr.get_payload_as::<u16>()?))
+                }
+                DevlinkAttr::PortNetdevIfindex => {
+                    port.netdev_ifindex = Some(attr.get_payload_as()?)
+                }
+                DevlinkAttr::PortNetdevName => port.netdev_name = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortIbdevName => port.ibdev_name = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortFlavour => {
+                    port.flavour = Some(DevlinkPortFlavour::from(attr.get_payload_as::<u16>()?))
+                }
+                DevlinkAttr::PortNumber => port.number = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortSplitCount => port.split_count = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortSplitGroup => port.split_group = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortSplitSubportNumber => {
+                    port.split_subport_number = Some(attr.get_payload_as()?)
+                }
+                DevlinkAttr::PortSplittable => {
+                    port.splittable = Some(attr.get_payload_as::<u8>()? != 0)
+                }
+                DevlinkAttr::PortLanes => port.lanes = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortPciPfNumber => port.pci_pf_number = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortPciVfNumber => port.pci_vf_number = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortPciSfNumber => port.pci_sf_number = Some(attr.get_payload_as()?),
+                DevlinkAttr::PortExternal => {
+                    port.external = Some(attr.get_payload_as::<u8>()? != 0)
+                }
+                DevlinkAttr::PortControllerNumber => {
+                    port.controller_number = Some(attr.get_payload_as()?)
+                }
+                _ => (),
+            }
+        }
+        Ok(port)
+    }
+}
+
+/// Value of a devlink parameter
+#[derive(Clone, Debug, PartialEq)]
+pub enum DevlinkParamValue {
+    /// `u8` value
+    U8(u8),
+    /// `u16` value
+    U16(u16),
+    /// `u32` value
+    U32(u32),
+    /// `u64` value
+    U64(u64),
+    /// String value
+    String(String),
+    /// Boolean value
+    Bool(bool),
+}
+
+impl DevlinkParamValue {
+    /// Type of the value
+    pub fn param_type(&self) -> DevlinkParamType {
+        match *self {
+            DevlinkParamValue::U8(_) => DevlinkParamType::U8,
+            DevlinkParamValue::U16(_) => DevlinkParamType::U16,
+            DevlinkParamValue::U32(_) => DevlinkParamType::U32,
+            DevlinkParamValue::U64(_) => DevlinkParamType::U64,
+            DevlinkParamValue::String(_) => DevlinkParamType::String,
+            DevlinkParamValue::Bool(_) => DevlinkParamType::Bool,
+        }
+    }
+
+    /// Parse the `DEVLINK_ATTR_PARAM_VALUE_DATA` attribute of a parameter of type `param_type`.
+    /// Boolean values are encoded as a flag, so `data` is `None` for `false`.
+    pub fn parse(
+        param_type: &DevlinkParamType,
+        data: Option<&Nlattr<DevlinkAttr, Vec<u8>>>,
+    ) -> Result<Self, DeError> {
+        if let DevlinkParamType::Bool = *param_type {
+            return Ok(DevlinkParamValue::Bool(data.is_some()));
+        }
+        let data = match data {
+            Some(data) => data,
+            None => return Err(DeError::new("Missing parameter value data")),
+        };
+        Ok(match *param_type {
+            DevlinkParamType::U8 => DevlinkParamValue::U8(data.get_payload_as()?),
+            DevlinkParamType::U16 => DevlinkParamValue::U16(data.get_payload_as()?),
+            DevlinkParamType::U32 => DevlinkParamValue::U32(data.get_payload_as()?),
+            DevlinkParamType::U64 => DevlinkParamValue::U64(data.get_payload_as()?),
+            DevlinkParamType::String => DevlinkParamValue::String(data.get_payload_as()?),
+            _ => return Err(DeError::new("Unknown parameter type")),
+        })
+    }
+
+    /// `DEVLINK_ATTR_PARAM_VALUE_DATA` attribute for the value, `None` for `false` booleans
+    pub fn to_attr(&self) -> Result<Option<Nlattr<DevlinkAttr, Vec<u8>>>, SerError> {
+        let data = DevlinkAttr::ParamValueData;
+        Ok(Some(match *self {
+            DevlinkParamValue::U8(v) => Nlattr::new(None, data, v)?,
+            DevlinkParamValue::U16(v) => Nlattr::new(None, data, v)?,
+            DevlinkParamValue::U32(v) => Nlattr::new(None, data, v)?,
+            DevlinkParamValue::U64(v) => Nlattr::new(None, data, v)?,
+            DevlinkParamValue::String(ref v) => Nlattr::new(None, data, v.as_str())?,
+            DevlinkParamValue::Bool(true) => Nlattr::new(None, data, Vec::<u8>::new())?,
+            DevlinkParamValue::Bool(false) => return Ok(None),
+        }))
+    }
+}
+
+/// Parameter of a devlink instance (`DEVLINK_CMD_PARAM_GET`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevlinkParam {
+    /// Handle of the instance the parameter belongs to
+    pub handle: DevlinkHandle,
+    /// Name of the parameter
+    pub name: String,
+    /// Whether the parameter is a generic one rather than driver specific
+    pub generic: bool,
+    /// Type of the parameter
+    pub param_type: DevlinkParamType,
+    /// Values of the parameter in each configuration mode the driver supports
+    pub values: Vec<(DevlinkParamCmode, DevlinkParamValue)>,
+}
+
+impl DevlinkParam {
+    /// Parse a parameter from message attributes
+    pub fn from_attrs(handle: &AttrHandle<DevlinkAttr>) -> Result<Self, DeError> {
+        let dev = DevlinkHandle::from_attrs(handle)?;
+        let param = match handle.get_attribute(DevlinkAttr::Param) {
+            Some(param) => param.get_nested_attributes::<DevlinkAttr>()?,
+            None => return Err(DeError::new("Missing parameter attribute")),
+        };
+        let param_type =
+            DevlinkParamType::from(param.get_attr_payload_as::<u8>(DevlinkAttr::ParamType)?);
+        let mut values = Vec::new();
+        if let Some(list) = param.get_attribute(DevlinkAttr::ParamValuesList) {
+            for value in list.get_nested_attributes::<DevlinkAttr>()?.iter() {
+                let value = value.get_nested_attributes::<DevlinkAttr>()?;
+                let cmode = DevlinkParamCmode::from(
+                    value.get_attr_payload_as::<u8>(DevlinkAttr::ParamValueCmode)?,
+                );
+                let data = value.get_attribute(DevlinkAttr::ParamValueData);
+                values.push((cmode, DevlinkParamValue::parse(&param_type, data)?));
+            }
+        }
+        Ok(DevlinkParam {
+            handle: dev,
+            name: param.get_attr_payload_as(DevlinkAttr::ParamName)?,
+            generic: param.get_attribute(DevlinkAttr::ParamGeneric).is_some(),
+            param_type,
+            values,
+        })
+    }
+
+    /// Value of the parameter in configuration mode `cmode`
+    pub fn value(&self, cmode: &DevlinkParamCmode) -> Option<&DevlinkParamValue> {
+        self.values
+            .iter()
+            .find(|(mode, _)| mode == cmode)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Health reporter of a devlink instance or port (`DEVLINK_CMD_HEALTH_REPORTER_GET`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevlinkHealthReporter {
+    /// Handle of the instance the reporter belongs to
+    pub handle: DevlinkHandle,
+    /// Index of the port for port reporters
+    pub port_index: Option<u32>,
+    /// Name of the reporter
+    pub name: String,
+    /// Current state
+    pub state: DevlinkHealthState,
+    /// Number of errors reported
+    pub error_count: u64,
+    /// Number of successful recoveries
+    pub recover_count: u64,
+    /// Minimum time between automatic recoveries in milliseconds
+    pub graceful_period_ms: Option<u64>,
+    /// Whether errors are recovered automatically
+    pub auto_recover: Option<bool>,
+    /// Whether a dump is taken automatically on errors
+    pub auto_dump: Option<bool>,
+    /// Time of the last dump in nanoseconds
+    pub dump_ts_ns: Option<u64>,
+}
+
+impl DevlinkHealthReporter {
+    /// Parse a health reporter from message attributes
+    pub fn from_attrs(handle: &AttrHandle<DevlinkAttr>) -> Result<Self, DeError> {
+        let port_index = match handle.get_attribute(DevlinkAttr::PortIndex) {
+            Some(attr) => Some(attr.get_payload_as()?),
+            None => None,
+        };
+        let reporter = match handle.get_attribute(DevlinkAttr::HealthReporter) {
+            Some(reporter) => reporter.get_nested_attributes::<DevlinkAttr>()?,
+            None => return Err(DeError::new("Missing health reporter attribute")),
+        };
+        let mut parsed = DevlinkHealthReporter {
+            handle: DevlinkHandle::from_attrs(handle)?,
+            port_index,
+            name: reporter.get_attr_payload_as(DevlinkAttr::HealthReporterName)?,
+            state: DevlinkHealthState::from(
+                reporter.get_attr_payload_as::<u8>(DevlinkAttr::HealthReporterState)?,
+            ),
+            error_count: reporter.get_attr_payload_as(DevlinkAttr::HealthReporterErrCount)?,
+            recover_count: reporter.get_attr_payload_as(DevlinkAttr::HealthReporterRecoverCount)?,
+            graceful_period_ms: None,
+            auto_recover: None,
+            auto_dump: None,
+            dump_ts_ns: None,
+        };
+        for attr in reporter.iter() {
+            match attr.nla_type {
+                DevlinkAttr::HealthReporterGracefulPeriod => {
+                    parsed.graceful_period_ms = Some(attr.get_payload_as()?)
+                }
+                DevlinkAttr::HealthReporterAutoRecover => {
+                    parsed.auto_recover = Some(attr.get_payload_as::<u8>()? != 0)
+                }
+                DevlinkAttr::HealthReporterAutoDump => {
+                    parsed.auto_dump = Some(attr.get_payload_as::<u8>()? != 0)
+                }
+                DevlinkAttr::HealthReporterDumpTsNs => {
+                    parsed.dump_ts_ns = Some(attr.get_payload_as()?)
+                }
+                _ => (),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Driver and firmware information of a devlink instance (`DEVLINK_CMD_INFO_GET`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DevlinkInfo {
+    /// Handle of the instance
+    pub handle: DevlinkHandle,
+    /// Name of the driver
+    pub driver_name: Option<String>,
+    /// Serial number of the device
+    pub serial_number: Option<String>,
+    /// Serial number of the board
+    pub board_serial_number: Option<String>,
+    /// Versions of fixed components such as the board revision, as name and value pairs
+    pub versions_fixed: Vec<(String, String)>,
+    /// Versions of the running firmware components
+    pub versions_running: Vec<(String, String)>,
+    /// Versions of the firmware components stored in flash, activated on the next reset
+    pub versions_stored: Vec<(String, String)>,
+}
+
+impl DevlinkInfo {
+    /// Parse device information from message attributes
+    pub fn from_attrs(handle: &AttrHandle<DevlinkAttr>) -> Result<Self, DeError> {
+        let mut info = DevlinkInfo {
+            handle: DevlinkHandle::from_attrs(handle)?,
+            ..DevlinkInfo::default()
+        };
+        for attr in handle.iter() {
+            let versions = match attr.nla_type {
+                DevlinkAttr::InfoDriverName => {
+                    info.driver_name = Some(attr.get_payload_as()?);
+                    continue;
+                }
+                DevlinkAttr::InfoSerialNumber => {
+                    info.serial_number = Some(attr.get_payload_as()?);
+                    continue;
+                }
+                DevlinkAttr::InfoBoardSerialNumber => {
+                    info.board_serial_number = Some(attr.get_payload_as()?);
+                    continue;
+                }
+                DevlinkAttr::InfoVersionFixed => &mut info.versions_fixed,
+                DevlinkAttr::InfoVersionRunning => &mut info.versions_running,
+                DevlinkAttr::InfoVersionStored => &mut info.versions_stored,
+                _ => continue,
+            };
+            let version = attr.get_nested_attributes::<DevlinkAttr>()?;
+            versions.push((
+                version.get_attr_payload_as(DevlinkAttr::InfoVersionName)?,
+                version.get_attr_payload_as(DevlinkAttr::InfoVersionValue)?,
+            ));
+        }
+        Ok(info)
+    }
+}
+
+/// Socket for the devlink family with its resolved family ID
+pub struct DevlinkSocket {
+    socket: NlSocket,
+    family_id: u16,
+}
+
+impl DevlinkSocket {
+    /// Connect to the devlink family
+    pub fn connect() -> Result<Self, NlError> {
+        let mut socket = NlSocket::connect(NlFamily::Generic, None, None, true)?;
+        let family_id = socket.resolve_genl_family(DEVLINK_GENL_NAME)?;
+        Ok(DevlinkSocket { socket, family_id })
+    }
+
+    /// Generic netlink family ID of devlink
+    pub fn family_id(&self) -> u16 {
+        self.family_id
+    }
+
+    /// Underlying socket, to send requests not covered by this type
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+
+    /// List all devlink instances
+    pub fn get_devices(&mut self) -> Result<Vec<DevlinkDevice>, NlError> {
+        let msgs = self.dump(DevlinkCmd::Get, Vec::new())?;
+        parse_all(&msgs, DevlinkDevice::from_attrs)
+    }
+
+    /// List the ports of all devlink instances
+    pub fn get_ports(&mut self) -> Result<Vec<DevlinkPort>, NlError> {
+        let msgs = self.dump(DevlinkCmd::PortGet, Vec::new())?;
+        parse_all(&msgs, DevlinkPort::from_attrs)
+    }
+
+    /// Get port `index` of a devlink instance
+    pub fn get_port(&mut self, dev: &DevlinkHandle, index: u32) -> Result<DevlinkPort, NlError> {
+        let mut attrs = dev.to_attrs()?;
+        attrs.push(Nlattr::new(None, DevlinkAttr::PortIndex, index)?);
+        let msg = self.get(DevlinkCmd::PortGet, attrs)?;
+        Ok(DevlinkPort::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// List the parameters of all devlink instances
+    pub fn get_params(&mut self) -> Result<Vec<DevlinkParam>, NlError> {
+        let msgs = self.dump(DevlinkCmd::ParamGet, Vec::new())?;
+        parse_all(&msgs, DevlinkParam::from_attrs)
+    }
+
+    /// Get the parameter `name` of a devlink instance
+    pub fn get_param(&mut self, dev: &DevlinkHandle, name: &str) -> Result<DevlinkParam, NlError> {
+        let mut attrs = dev.to_attrs()?;
+        attrs.push(Nlattr::new(None, DevlinkAttr::ParamName, name)?);
+        let msg = self.get(DevlinkCmd::ParamGet, attrs)?;
+        Ok(DevlinkParam::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// Set the value of parameter `name` of a devlink instance in configuration mode `cmode`.
+    /// Values set in `DevlinkParamCmode::Driverinit` mode take effect on the next reload.
+    pub fn set_param(
+        &mut self,
+        dev: &DevlinkHandle,
+        name: &str,
+        cmode: DevlinkParamCmode,
+        value: &DevlinkParamValue,
+    ) -> Result<(), NlError> {
+        let mut attrs = dev.to_attrs()?;
+        attrs.push(Nlattr::new(None, DevlinkAttr::ParamName, name)?);
+        attrs.push(Nlattr::new(
+            None,
+            DevlinkAttr::ParamType,
+            u8::from(value.param_type()),
+        )?);
+        if let Some(data) = value.to_attr()? {
+            attrs.push(data);
+        }
+        attrs.push(Nlattr::new(
+            None,
+            DevlinkAttr::ParamValueCmode,
+            u8::from(cmode),
+        )?);
+        self.set(DevlinkCmd::ParamSet, attrs)
+    }
+
+    /// List the health reporters of a devlink instance, including those of its ports
+    pub fn get_health_reporters(
+        &mut self,
+        dev: &DevlinkHandle,
+    ) -> Result<Vec<DevlinkHealthReporter>, NlError> {
+        // Older kernels ignore the handle and dump the reporters of all instances
+        let msgs = self.dump(DevlinkCmd::HealthReporterGet, dev.to_attrs()?)?;
+        let reporters = parse_all(&msgs, DevlinkHealthReporter::from_attrs)?;
+        Ok(reporters.into_iter().filter(|r| r.handle == *dev).collect())
+    }
+
+    /// Change the settings of health reporter `name` of a devlink instance; `None` leaves a
+    /// setting unchanged
+    pub fn set_health_reporter(
+        &mut self,
+        dev: &DevlinkHandle,
+        name: &str,
+        graceful_period_ms: Option<u64>,
+        auto_recover: Option<bool>,
+        auto_dump: Option<bool>,
+    ) -> Result<(), NlError> {
+        let mut attrs = dev.to_attrs()?;
+        attrs.push(Nlattr::new(None, DevlinkAttr::HealthReporterName, name)?);
+        if let Some(period) = graceful_period_ms {
+            attrs.push(Nlattr::new(
+                None,
+                DevlinkAttr::HealthReporterGracefulPeriod,
+                period,
+            )?);
+        }
+        if let Some(recover) = auto_recover {
+            attrs.push(Nlattr::new(
+                None,
+                DevlinkAttr::HealthReporterAutoRecover,
+                recover as u8,
+            )?);
+        }
+        if let Some(dump) = auto_dump {
+            attrs.push(Nlattr::new(
+                None,
+                DevlinkAttr::HealthReporterAutoDump,
+                dump as u8,
+            )?);
+        }
+        self.set(DevlinkCmd::HealthReporterSet, attrs)
+    }
+
+    /// Trigger recovery by health reporter `name` of a devlink instance
+    pub fn recover_health_reporter(
+        &mut self,
+        dev: &DevlinkHandle,
+        name: &str,
+    ) -> Result<(), NlError> {
+        let mut attrs = dev.to_attrs()?;
+        attrs.push(Nlattr::new(None, DevlinkAttr::HealthReporterName, name)?);
+        self.set(DevlinkCmd::HealthReporterRecover, attrs)
+    }
+
+    /// Get the driver and firmware information of a devlink instance
+    pub fn get_info(&mut self, dev: &DevlinkHandle) -> Result<DevlinkInfo, NlError> {
+        let msg = self.get(DevlinkCmd::InfoGet, dev.to_attrs()?)?;
+        Ok(DevlinkInfo::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// Subscribe to notifications of configuration changes, received with `recv_event()`
+    pub fn subscribe(&mut self) -> Result<(), NlError> {
+        let id = self
+            .socket
+            .resolve_nl_mcast_group(DEVLINK_GENL_NAME, DEVLINK_GENL_MCGRP_CONFIG_NAME)?;
+        self.socket.set_mcast_groups(vec![id])?;
+        Ok(())
+    }
+
+    /// Receive the next notification. The attributes can be parsed with the `from_attrs()`
+    /// methods, eg. `DevlinkPort::from_attrs()` for `DevlinkCmd::PortNew`.
+    pub fn recv_event(&mut self) -> Result<Genlmsghdr<DevlinkCmd, DevlinkAttr>, NlError> {
+        Ok(self
+            .socket
+            .recv_nl::<u16, Genlmsghdr<DevlinkCmd, DevlinkAttr>>(None)?
+            .nl_payload)
+    }
+
+    fn send(
+        &mut self,
+        cmd: DevlinkCmd,
+        flags: Vec<NlmF>,
+        attrs: Vec<Nlattr<DevlinkAttr, Vec<u8>>>,
+    ) -> Result<(), NlError> {
+        let genlhdr = Genlmsghdr::new(cmd, DEVLINK_GENL_VERSION, attrs)?;
+        let nlhdr = Nlmsghdr::new(None, self.family_id, flags, None, None, genlhdr);
+        self.socket.send_nl(nlhdr)
+    }
+
+    fn get(
+        &mut self,
+        cmd: DevlinkCmd,
+        attrs: Vec<Nlattr<DevlinkAttr, Vec<u8>>>,
+    ) -> Result<Genlmsghdr<DevlinkCmd, DevlinkAttr>, NlError> {
+        self.send(cmd, vec![NlmF::Request], attrs)?;
+        match self
+            .socket
+            .recv_all::<u16, Genlmsghdr<DevlinkCmd, DevlinkAttr>>()?
+            .pop()
+        {
+            Some(msg) => Ok(msg.nl_payload),
+            None => Err(NlError::new("No reply returned")),
+        }
+    }
+
+    fn dump(
+        &mut self,
+        cmd: DevlinkCmd,
+        attrs: Vec<Nlattr<DevlinkAttr, Vec<u8>>>,
+    ) -> Result<Vec<Genlmsghdr<DevlinkCmd, DevlinkAttr>>, NlError> {
+        self.send(cmd, vec![NlmF::Request, NlmF::Dump], attrs)?;
+        Ok(self
+            .socket
+            .recv_all::<u16, Genlmsghdr<DevlinkCmd, DevlinkAttr>>()?
+            .into_iter()
+            .map(|msg| msg.nl_payload)
+            .collect())
+    }
+
+    fn set(
+        &mut self,
+        cmd: DevlinkCmd,
+        attrs: Vec<Nlattr<DevlinkAttr, Vec<u8>>>,
+    ) -> Result<(), NlError> {
+        self.send(cmd, vec![NlmF::Request, NlmF::Ack], attrs)?;
+        self.socket.recv_ack()
+    }
+}
+
+fn parse_all<R>(
+    msgs: &[Genlmsghdr<DevlinkCmd, DevlinkAttr>],
+    parse: fn(&AttrHandle<DevlinkAttr>) -> Result<R, DeError>,
+) -> Result<Vec<R>, NlError> {
+    let mut parsed = Vec::with_capacity(msgs.len());
+    for msg in msgs.iter() {
+        parsed.push(parse(&msg.get_attr_handle())?);
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(
+        cmd: DevlinkCmd,
+        attrs: Vec<Nlattr<DevlinkAttr, Vec<u8>>>,
+    ) -> Genlmsghdr<DevlinkCmd, DevlinkAttr> {
+        ::round_trip(&Genlmsghdr::new(cmd, DEVLINK_GENL_VERSION, attrs).unwrap())
+    }
+
+    fn dev() -> DevlinkHandle {
+        DevlinkHandle::new("pci", "0000:01:00.0")
+    }
+
+    #[test]
+    fn test_port() {
+        let mut attrs = dev().to_attrs().unwrap();
+        attrs.extend(vec![
+            Nlattr::new(None, DevlinkAttr::PortIndex, 1u32).unwrap(),
+            Nlattr::new(None, DevlinkAttr::PortType, 2u16).unwrap(),
+            Nlattr::new(None, DevlinkAttr::PortNetdevName, "eth0").unwrap(),
+            Nlattr::new(None, DevlinkAttr::PortFlavour, 4u16).unwrap(),
+            Nlattr::new(None, DevlinkAttr::PortPciVfNumber, 3u16).unwrap(),
+        ]);
+        let msg = round_trip(DevlinkCmd::PortNew, attrs);
+        let port = DevlinkPort::from_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(port.handle.to_string(), "pci/0000:01:00.0");
+        assert_eq!(port.index, 1);
+        assert_eq!(port.port_type, Some(DevlinkPortType::Eth));
+        assert_eq!(port.netdev_name, Some("eth0".to_string()));
+        assert_eq!(port.flavour, Some(DevlinkPortFlavour::PciVf));
+        assert_eq!(port.pci_vf_number, Some(3));
+    }
+
+    #[test]
+    fn test_param() {
+        let value = |cmode: u8, data: Option<Nlattr<DevlinkAttr, Vec<u8>>>| {
+            let mut attrs = vec![Nlattr::new(None, DevlinkAttr::ParamValueCmode, cmode).unwrap()];
+            attrs.extend(data);
+            Nlattr::new_nested(DevlinkAttr::ParamValue, &attrs).unwrap()
+        };
+        let param = Nlattr::new_nested(
+            DevlinkAttr::Param,
+            &[
+                Nlattr::new(None, DevlinkAttr::ParamName, "enable_roce").unwrap(),
+                Nlattr::new(None, DevlinkAttr::ParamGeneric, Vec::<u8>::new()).unwrap(),
+                Nlattr::new(None, DevlinkAttr::ParamType, 6u8).unwrap(),
+                Nlattr::new_nested(
+                    DevlinkAttr::ParamValuesList,
+                    &[
+                        value(0, DevlinkParamValue::Bool(true).to_attr().unwrap()),
+                        value(1, DevlinkParamValue::Bool(false).to_attr().unwrap()),
+                    ],
+                )
+                .unwrap(),
+            ],
+        )
+        .unwrap();
+        let mut attrs = dev().to_attrs().unwrap();
+        attrs.push(param);
+        let msg = round_trip(DevlinkCmd::ParamNew, attrs);
+        let param = DevlinkParam::from_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(param.name, "enable_roce");
+        assert!(param.generic);
+        assert_eq!(param.param_type, DevlinkParamType::Bool);
+        assert_eq!(
+            param.value(&DevlinkParamCmode::Runtime),
+            Some(&DevlinkParamValue::Bool(true))
+        );
+        assert_eq!(
+            param.value(&DevlinkParamCmode::Driverinit),
+            Some(&DevlinkParamValue::Bool(false))
+        );
+
+        let data = DevlinkParamValue::String("flash".to_string())
+            .to_attr()
+            .unwrap();
+        assert_eq!(
+            DevlinkParamValue::parse(&DevlinkParamType::String, data.as_ref()).unwrap(),
+            DevlinkParamValue::String("flash".to_string())
+        );
+    }
+
+    #[test]
+    fn test_health_reporter() {
+        let reporter = Nlattr::new_nested(
+            DevlinkAttr::HealthReporter,
+            &[
+                Nlattr::new(None, DevlinkAttr::HealthReporterName, "tx").unwrap(),
+                Nlattr::new(None, DevlinkAttr::HealthReporterState, 1u8).unwrap(),
+                Nlattr::new(None, DevlinkAttr::HealthReporterErrCount, 3u64).unwrap(),
+                Nlattr::new(None, DevlinkAttr::HealthReporterRecoverCount, 2u64).unwrap(),
+                Nlattr::new(None, DevlinkAttr::HealthReporterGracefulPeriod, 500u64).unwrap(),
+                Nlattr::new(None, DevlinkAttr::HealthReporterAutoRecover, 1u8).unwrap(),
+            ],
+        )
+        .unwrap();
+        let mut attrs = dev().to_attrs().unwrap();
+        attrs.push(Nlattr::new(None, DevlinkAttr::PortIndex, 7u32).unwrap());
+        attrs.push(reporter);
+        let msg = round_trip(DevlinkCmd::HealthReporterGet, attrs);
+        let reporter = DevlinkHealthReporter::from_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(reporter.port_index, Some(7));
+        assert_eq!(reporter.name, "tx");
+        assert_eq!(reporter.state, DevlinkHealthState::Error);
+        assert_eq!(reporter.error_count, 3);
+        assert_eq!(reporter.recover_count, 2);
+        assert_eq!(reporter.graceful_period_ms, Some(500));
+        assert_eq!(reporter.auto_recover, Some(true));
+        assert_eq!(reporter.auto_dump, None);
+    }
+
+    #[test]
+    fn test_info() {
+        let version = |attr_type: DevlinkAttr, name: &str, value: &str| {
+            Nlattr::new_nested(
+                attr_type,
+                &[
+                    Nlattr::new(None, DevlinkAttr::InfoVersionName, name).unwrap(),
+                    Nlattr::new(None, DevlinkAttr::InfoVersionValue, value).unwrap(),
+                ],
+            )
+            .unwrap()
+        };
+        let mut attrs = dev().to_attrs().unwrap();
+        attrs.extend(vec![
+            Nlattr::new(None, DevlinkAttr::InfoDriverName, "mlx5_core").unwrap(),
+            Nlattr::new(None, DevlinkAttr::InfoSerialNumber, "MT1234").unwrap(),
+            version(DevlinkAttr::InfoVersionFixed, "board.id", "MT_0000000008"),
+            version(DevlinkAttr::InfoVersionRunning, "fw.version", "16.35.2000"),
+            version(DevlinkAttr::InfoVersionRunning, "fw.psid", "MT_0000000008"),
+            version(DevlinkAttr::InfoVersionStored, "fw.version", "16.35.3006"),
+        ]);
+        let msg = round_trip(DevlinkCmd::InfoGet, attrs);
+        let info = DevlinkInfo::from_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(info.driver_name, Some("mlx5_core".to_string()));
+        assert_eq!(info.serial_number, Some("MT1234".to_string()));
+        assert_eq!(info.versions_fixed.len(), 1);
+        assert_eq!(
+            info.versions_running,
+            vec![
+                ("fw.version".to_string(), "16.35.2000".to_string()),
+                ("fw.psid".to_string(), "MT_0000000008".to_string()),
+            ]
+        );
+        assert_eq!(info.versions_stored[0].1, "16.35.3006");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_get_devices() {
+        let mut socket = DevlinkSocket::connect().unwrap();
+        for device in socket.get_devices().unwrap() {
+            let info = socket.get_info(&device.handle).unwrap();
+            assert_eq!(info.handle, device.handle);
+        }
+        socket.get_ports().unwrap();
+        socket.get_params().unwrap();
+    }
+}
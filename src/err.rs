@@ -10,9 +10,10 @@
 //!
 //! # Design decisions
 //!
-//! `NlError` can either be created with a custom `String` message or using three variants, one for
-//! no ACK received, one for a bad PID that does not correspond to that assigned to the socket, or
-//! one for a bad sequence number that does not correspond to the request sequence number.
+//! `NlError` can either be created with a custom `String` message or using four variants, one for
+//! no ACK received, one for a bad PID that does not correspond to that assigned to the socket, one
+//! for a bad sequence number that does not correspond to the request sequence number, or one for
+//! messages dropped by the kernel because the socket receive buffer overflowed (`ENOBUFS`).
 
 use std;
 use std::error::Error;
@@ -88,9 +89,20 @@ pub enum NlError {
     BadSeq,
     /// Incorrect PID socket identifier in received message
     BadPid,
+    /// The socket receive buffer overflowed (`ENOBUFS`) and the kernel dropped messages
+    Overrun,
 }
 
-try_err_compat!(NlError, io::Error, SerError, DeError);
+try_err_compat!(NlError, SerError, DeError);
+
+impl From<io::Error> for NlError {
+    fn from(v: io::Error) -> Self {
+        match v.raw_os_error() {
+            Some(libc::ENOBUFS) => NlError::Overrun,
+            _ => NlError::new(&v.to_string()),
+        }
+    }
+}
 
 impl NlError {
     /// Create new error from `&str`
@@ -107,6 +119,7 @@ impl Display for NlError {
             NlError::NoAck => "No ack received",
             NlError::BadSeq => "Sequence number does not match the request",
             NlError::BadPid => "PID does not match the socket",
+            NlError::Overrun => "Socket receive buffer overrun - messages were lost",
         };
         write!(f, "{}", msg)
     }
@@ -119,6 +132,7 @@ impl Error for NlError {
             NlError::NoAck => "No ack received",
             NlError::BadSeq => "Sequence number does not match the request",
             NlError::BadPid => "PID does not match the socket",
+            NlError::Overrun => "Socket receive buffer overrun - messages were lost",
         }
     }
 }
@@ -178,3 +192,20 @@ impl Error for DeError {
         self.0.as_str()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_enobufs_is_overrun() {
+        match NlError::from(io::Error::from_raw_os_error(libc::ENOBUFS)) {
+            NlError::Overrun => (),
+            e => panic!("Expected overrun, got {:?}", e),
+        }
+        match NlError::from(io::Error::from_raw_os_error(libc::EINVAL)) {
+            NlError::Msg(_) => (),
+            e => panic!("Expected message, got {:?}", e),
+        }
+    }
+}
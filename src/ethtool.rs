@@ -0,0 +1,961 @@
+//! This module provides typed access to the ethtool generic netlink family, which replaces the
+//! `SIOCETHTOOL` ioctl for querying and configuring network devices.
+//!
+//! ```no_run
+//! use neli::ethtool::{EthtoolHeader, EthtoolSocket};
+//!
+//! let mut socket = EthtoolSocket::connect().unwrap();
+//! let dev = EthtoolHeader::by_name("eth0");
+//! let channels = socket.get_channels(&dev).unwrap();
+//! println!("combined channels: {:?}", channels.combined_count);
+//! let features = socket.get_features(&dev).unwrap();
+//! println!("rx-checksum: {:?}", features.active.is_set("rx-checksum"));
+//! ```
+//!
+//! # Design decisions
+//!
+//! Every ethtool message starts with a nested request header identifying the device; it is
+//! represented by `EthtoolHeader` and serialized with `EthtoolHeader.to_attr()`.
+//!
+//! Bitsets are decoded from both the compact and the verbose format into an `EthtoolBitset`
+//! listing the bits with their index, name (verbose format only) and value. Bitsets are always
+//! sent in the verbose format so that bits can be named, eg. features are changed by name.
+//!
+//! Set requests ask the kernel to omit its optional reply, so success is reported by the ACK.
+
+use consts::ethtool::{
+    EthtoolBitsetAttr, EthtoolBitsetBitAttr, EthtoolBitsetBitsAttr, EthtoolChannelsAttr,
+    EthtoolFeaturesAttr, EthtoolFlag, EthtoolHeaderAttr, EthtoolKernelMsg, EthtoolLinkinfoAttr,
+    EthtoolLinkmodesAttr, EthtoolMsg, EthtoolRingsAttr,
+};
+use consts::{NlAttrType, NlFamily, NlmF};
+use err::{DeError, NlError, SerError};
+use genl::Genlmsghdr;
+use nl::Nlmsghdr;
+use nlattr::{AttrHandle, Nlattr};
+use socket::NlSocket;
+
+/// Name of the ethtool generic netlink family
+pub const ETHTOOL_GENL_NAME: &str = "ethtool";
+/// Version of the ethtool generic netlink family
+const ETHTOOL_GENL_VERSION: u8 = 1;
+
+/// Request header identifying the device a message applies to
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EthtoolHeader {
+    /// Interface index of the device
+    pub dev_index: Option<u32>,
+    /// Name of the device
+    pub dev_name: Option<String>,
+    /// Request flags
+    pub flags: Vec<EthtoolFlag>,
+}
+
+impl EthtoolHeader {
+    /// Header for the device with interface index `index`
+    pub fn by_index(index: u32) -> Self {
+        EthtoolHeader {
+            dev_index: Some(index),
+            ..EthtoolHeader::default()
+        }
+    }
+
+    /// Header for the device named `name`
+    pub fn by_name(name: &str) -> Self {
+        EthtoolHeader {
+            dev_name: Some(name.to_string()),
+            ..EthtoolHeader::default()
+        }
+    }
+
+    /// Add a request flag to the header
+    pub fn with_flag(mut self, flag: EthtoolFlag) -> Self {
+        if !self.flags.contains(&flag) {
+            self.flags.push(flag);
+        }
+        self
+    }
+
+    /// Parse a header from its nested attributes
+    pub fn from_attrs(handle: &AttrHandle<EthtoolHeaderAttr>) -> Result<Self, DeError> {
+        let mut header = EthtoolHeader::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                EthtoolHeaderAttr::DevIndex => header.dev_index = Some(attr.get_payload_as()?),
+                EthtoolHeaderAttr::DevName => header.dev_name = Some(attr.get_payload_as()?),
+                EthtoolHeaderAttr::Flags => {
+                    let flags = attr.get_payload_as::<u32>()?;
+                    header.flags = (0..32)
+                        .map(|i| 1 << i)
+                        .filter(|f| flags & f != 0)
+                        .map(EthtoolFlag::from)
+                        .collect();
+                }
+                _ => (),
+            }
+        }
+        Ok(header)
+    }
+
+    /// Serialize the header as the nested attribute `attr_type`, the `HEADER` attribute of a
+    /// message
+    pub fn to_attr<T>(&self, attr_type: T) -> Result<Nlattr<T, Vec<u8>>, SerError>
+    where
+        T: NlAttrType,
+    {
+        let mut attrs = Vec::new();
+        if let Some(index) = self.dev_index {
+            attrs.push(Nlattr::new(None, EthtoolHeaderAttr::DevIndex, index)?);
+        }
+        if let Some(ref name) = self.dev_name {
+            attrs.push(Nlattr::new(
+                None,
+                EthtoolHeaderAttr::DevName,
+                name.as_str(),
+            )?);
+        }
+        if !self.flags.is_empty() {
+            let flags = self
+                .flags
+                .iter()
+                .fold(0u32, |acc, flag| acc | u32::from(flag.clone()));
+            attrs.push(Nlattr::new(None, EthtoolHeaderAttr::Flags, flags)?);
+        }
+        Ok(Nlattr::new_nested(attr_type, &attrs)?.with_nested_flag())
+    }
+}
+
+/// Bit of an `EthtoolBitset`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EthtoolBit {
+    /// Index of the bit
+    pub index: u32,
+    /// Name of the bit, only included in verbose bitsets
+    pub name: Option<String>,
+    /// Value of the bit
+    pub value: bool,
+}
+
+/// Bitset, eg. the supported link modes or the enabled features of a device
+///
+/// Bitsets with a mask only list the bits in the mask; bitsets without a mask (`nomask`) list
+/// the set bits.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EthtoolBitset {
+    /// Number of bits in the bitset, only reported by the kernel
+    pub size: Option<u32>,
+    /// Whether the bitset is a plain list of bits instead of a value and mask pair
+    pub nomask: bool,
+    /// Bits of the bitset
+    pub bits: Vec<EthtoolBit>,
+}
+
+impl EthtoolBitset {
+    /// Create a bitset with a mask, for changing bits by name
+    pub fn new() -> Self {
+        EthtoolBitset::default()
+    }
+
+    /// Set the bit named `name` to `value`
+    pub fn set(&mut self, name: &str, value: bool) {
+        match self
+            .bits
+            .iter_mut()
+            .find(|b| b.name.as_deref() == Some(name))
+        {
+            Some(bit) => bit.value = value,
+            None => self.bits.push(EthtoolBit {
+                index: 0,
+                name: Some(name.to_string()),
+                value,
+            }),
+        }
+    }
+
+    /// Value of the bit named `name`. Bits that are not listed are unset in bitsets without a
+    /// mask and unknown (`None`) otherwise.
+    pub fn is_set(&self, name: &str) -> Option<bool> {
+        match self.bits.iter().find(|b| b.name.as_deref() == Some(name)) {
+            Some(bit) => Some(bit.value),
+            None if self.nomask => Some(false),
+            None => None,
+        }
+    }
+
+    /// Value of the bit with index `index`, with the same rules as `is_set()`
+    pub fn is_set_index(&self, index: u32) -> Option<bool> {
+        match self.bits.iter().find(|b| b.index == index) {
+            Some(bit) => Some(bit.value),
+            None if self.nomask => Some(false),
+            None => None,
+        }
+    }
+
+    /// Names of the set bits
+    pub fn set_names(&self) -> Vec<&str> {
+        self.bits
+            .iter()
+            .filter(|b| b.value)
+            .filter_map(|b| b.name.as_deref())
+            .collect()
+    }
+
+    /// Parse a bitset in the compact or verbose format from its nested attributes
+    pub fn from_attrs(handle: &AttrHandle<EthtoolBitsetAttr>) -> Result<Self, DeError> {
+        let mut bitset = EthtoolBitset::default();
+        let mut value = None;
+        let mut mask = None;
+        for attr in handle.iter() {
+            match attr.nla_type {
+                EthtoolBitsetAttr::Nomask => bitset.nomask = true,
+                EthtoolBitsetAttr::Size => bitset.size = Some(attr.get_payload_as()?),
+                EthtoolBitsetAttr::Bits => {
+                    for bit in attr
+                        .get_nested_attributes::<EthtoolBitsetBitsAttr>()?
+                        .iter()
+                    {
+                        if bit.nla_type != EthtoolBitsetBitsAttr::Bit {
+                            continue;
+                        }
+                        let bit = bit.get_nested_attributes::<EthtoolBitsetBitAttr>()?;
+                        bitset.bits.push(EthtoolBit {
+                            index: bit.get_attr_payload_as(EthtoolBitsetBitAttr::Index)?,
+                            name: match bit.get_attribute(EthtoolBitsetBitAttr::Name) {
+                                Some(name) => Some(name.get_payload_as()?),
+                                None => None,
+                            },
+                            value: bit.get_attribute(EthtoolBitsetBitAttr::Value).is_some(),
+                        });
+                    }
+                }
+                EthtoolBitsetAttr::Value => value = Some(words(&attr.payload)),
+                EthtoolBitsetAttr::Mask => mask = Some(words(&attr.payload)),
+                _ => (),
+            }
+        }
+        if bitset.nomask {
+            // The kernel omits the value flag of verbose bits when all listed bits are set
+            for bit in bitset.bits.iter_mut() {
+                bit.value = true;
+            }
+        }
+        if let Some(value) = value {
+            let size = bitset.size.unwrap_or(value.len() as u32 * 32);
+            let is_set = |words: &[u32], i: u32| {
+                words
+                    .get((i / 32) as usize)
+                    .map(|w| w & (1 << (i % 32)) != 0)
+                    .unwrap_or(false)
+            };
+            for i in 0..size {
+                let listed = match mask {
+                    Some(ref mask) if !bitset.nomask => is_set(mask, i),
+                    _ => is_set(&value, i),
+                };
+                if listed {
+                    bitset.bits.push(EthtoolBit {
+                        index: i,
+                        name: None,
+                        value: is_set(&value, i),
+                    });
+                }
+            }
+        }
+        Ok(bitset)
+    }
+
+    /// Serialize the bitset in the verbose format as the nested attribute `attr_type`. Bits are
+    /// identified by name if they have one and by index otherwise.
+    pub fn to_attr<T>(&self, attr_type: T) -> Result<Nlattr<T, Vec<u8>>, SerError>
+    where
+        T: NlAttrType,
+    {
+        let mut bits = Vec::with_capacity(self.bits.len());
+        for bit in self.bits.iter() {
+            let mut attrs = Vec::new();
+            match bit.name {
+                Some(ref name) => attrs.push(Nlattr::new(
+                    None,
+                    EthtoolBitsetBitAttr::Name,
+                    name.as_str(),
+                )?),
+                None => attrs.push(Nlattr::new(None, EthtoolBitsetBitAttr::Index, bit.index)?),
+            }
+            if bit.value {
+                attrs.push(Nlattr::new(
+                    None,
+                    EthtoolBitsetBitAttr::Value,
+                    Vec::<u8>::new(),
+                )?);
+            }
+            bits.push(Nlattr::new_nested(EthtoolBitsetBitsAttr::Bit, &attrs)?.with_nested_flag());
+        }
+        let mut attrs = Vec::new();
+        if self.nomask {
+            attrs.push(Nlattr::new(
+                None,
+                EthtoolBitsetAttr::Nomask,
+                Vec::<u8>::new(),
+            )?);
+        }
+        attrs.push(Nlattr::new_nested(EthtoolBitsetAttr::Bits, &bits)?.with_nested_flag());
+        Ok(Nlattr::new_nested(attr_type, &attrs)?.with_nested_flag())
+    }
+}
+
+fn words(payload: &[u8]) -> Vec<u32> {
+    payload
+        .chunks_exact(4)
+        .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Port and transceiver information (`ETHTOOL_MSG_LINKINFO_*`)
+///
+/// When setting, only `port`, `phyaddr` and `tp_mdix_ctrl` are sent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EthtoolLinkInfo {
+    /// Physical connector type (`PORT_*`)
+    pub port: Option<u8>,
+    /// MDIO address of the PHY
+    pub phyaddr: Option<u8>,
+    /// MDI(-X) status of twisted pair ports
+    pub tp_mdix: Option<u8>,
+    /// MDI(-X) control of twisted pair ports
+    pub tp_mdix_ctrl: Option<u8>,
+    /// Transceiver type (`XCVR_*`)
+    pub transceiver: Option<u8>,
+}
+
+impl EthtoolLinkInfo {
+    /// Parse link information from message attributes
+    pub fn from_attrs(handle: &AttrHandle<EthtoolLinkinfoAttr>) -> Result<Self, DeError> {
+        let mut info = EthtoolLinkInfo::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                EthtoolLinkinfoAttr::Port => info.port = Some(attr.get_payload_as()?),
+                EthtoolLinkinfoAttr::Phyaddr => info.phyaddr = Some(attr.get_payload_as()?),
+                EthtoolLinkinfoAttr::TpMdix => info.tp_mdix = Some(attr.get_payload_as()?),
+                EthtoolLinkinfoAttr::TpMdixCtrl => info.tp_mdix_ctrl = Some(attr.get_payload_as()?),
+                EthtoolLinkinfoAttr::Transceiver => info.transceiver = Some(attr.get_payload_as()?),
+                _ => (),
+            }
+        }
+        Ok(info)
+    }
+
+    fn to_attrs(&self) -> Result<Vec<Nlattr<EthtoolLinkinfoAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(port) = self.port {
+            attrs.push(Nlattr::new(None, EthtoolLinkinfoAttr::Port, port)?);
+        }
+        if let Some(phyaddr) = self.phyaddr {
+            attrs.push(Nlattr::new(None, EthtoolLinkinfoAttr::Phyaddr, phyaddr)?);
+        }
+        if let Some(ctrl) = self.tp_mdix_ctrl {
+            attrs.push(Nlattr::new(None, EthtoolLinkinfoAttr::TpMdixCtrl, ctrl)?);
+        }
+        Ok(attrs)
+    }
+}
+
+/// Link speed, duplex and autonegotiation (`ETHTOOL_MSG_LINKMODES_*`)
+///
+/// When setting, only `autoneg`, `ours`, `speed`, `duplex` and `lanes` are sent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EthtoolLinkModes {
+    /// Whether autonegotiation is enabled
+    pub autoneg: Option<bool>,
+    /// Supported and advertised link modes
+    pub ours: Option<EthtoolBitset>,
+    /// Link modes advertised by the link partner
+    pub peer: Option<EthtoolBitset>,
+    /// Speed in Mb/s
+    pub speed: Option<u32>,
+    /// Duplex mode (`DUPLEX_*`)
+    pub duplex: Option<u8>,
+    /// Number of lanes
+    pub lanes: Option<u32>,
+}
+
+impl EthtoolLinkModes {
+    /// Parse link modes from message attributes
+    pub fn from_attrs(handle: &AttrHandle<EthtoolLinkmodesAttr>) -> Result<Self, DeError> {
+        let mut modes = EthtoolLinkModes::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                EthtoolLinkmodesAttr::Autoneg => {
+                    modes.autoneg = Some(attr.get_payload_as::<u8>()? != 0)
+                }
+                EthtoolLinkmodesAttr::Ours => {
+                    modes.ours = Some(EthtoolBitset::from_attrs(&attr.get_nested_attributes()?)?)
+                }
+                EthtoolLinkmodesAttr::Peer => {
+                    modes.peer = Some(EthtoolBitset::from_attrs(&attr.get_nested_attributes()?)?)
+                }
+                EthtoolLinkmodesAttr::Speed => modes.speed = Some(attr.get_payload_as()?),
+                EthtoolLinkmodesAttr::Duplex => modes.duplex = Some(attr.get_payload_as()?),
+                EthtoolLinkmodesAttr::Lanes => modes.lanes = Some(attr.get_payload_as()?),
+                _ => (),
+            }
+        }
+        Ok(modes)
+    }
+
+    fn to_attrs(&self) -> Result<Vec<Nlattr<EthtoolLinkmodesAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(autoneg) = self.autoneg {
+            attrs.push(Nlattr::new(
+                None,
+                EthtoolLinkmodesAttr::Autoneg,
+                autoneg as u8,
+            )?);
+        }
+        if let Some(ref ours) = self.ours {
+            attrs.push(ours.to_attr(EthtoolLinkmodesAttr::Ours)?);
+        }
+        if let Some(speed) = self.speed {
+            attrs.push(Nlattr::new(None, EthtoolLinkmodesAttr::Speed, speed)?);
+        }
+        if let Some(duplex) = self.duplex {
+            attrs.push(Nlattr::new(None, EthtoolLinkmodesAttr::Duplex, duplex)?);
+        }
+        if let Some(lanes) = self.lanes {
+            attrs.push(Nlattr::new(None, EthtoolLinkmodesAttr::Lanes, lanes)?);
+        }
+        Ok(attrs)
+    }
+}
+
+/// Ring sizes (`ETHTOOL_MSG_RINGS_*`)
+///
+/// When setting, the maximum sizes are not sent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EthtoolRings {
+    /// Maximum size of the RX ring
+    pub rx_max: Option<u32>,
+    /// Maximum size of the RX mini ring
+    pub rx_mini_max: Option<u32>,
+    /// Maximum size of the RX jumbo ring
+    pub rx_jumbo_max: Option<u32>,
+    /// Maximum size of the TX ring
+    pub tx_max: Option<u32>,
+    /// Size of the RX ring
+    pub rx: Option<u32>,
+    /// Size of the RX mini ring
+    pub rx_mini: Option<u32>,
+    /// Size of the RX jumbo ring
+    pub rx_jumbo: Option<u32>,
+    /// Size of the TX ring
+    pub tx: Option<u32>,
+    /// Size of the buffers on the RX ring
+    pub rx_buf_len: Option<u32>,
+    /// TCP header and data split (`EthtoolTcpDataSplit`)
+    pub tcp_data_split: Option<u8>,
+    /// Size of completion queue events
+    pub cqe_size: Option<u32>,
+    /// Whether TX push is enabled
+    pub tx_push: Option<bool>,
+}
+
+impl EthtoolRings {
+    /// Parse ring sizes from message attributes
+    pub fn from_attrs(handle: &AttrHandle<EthtoolRingsAttr>) -> Result<Self, DeError> {
+        let mut rings = EthtoolRings::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                EthtoolRingsAttr::RxMax => rings.rx_max = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::RxMiniMax => rings.rx_mini_max = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::RxJumboMax => rings.rx_jumbo_max = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::TxMax => rings.tx_max = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::Rx => rings.rx = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::RxMini => rings.rx_mini = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::RxJumbo => rings.rx_jumbo = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::Tx => rings.tx = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::RxBufLen => rings.rx_buf_len = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::TcpDataSplit => {
+                    rings.tcp_data_split = Some(attr.get_payload_as()?)
+                }
+                EthtoolRingsAttr::CqeSize => rings.cqe_size = Some(attr.get_payload_as()?),
+                EthtoolRingsAttr::TxPush => rings.tx_push = Some(attr.get_payload_as::<u8>()? != 0),
+                _ => (),
+            }
+        }
+        Ok(rings)
+    }
+
+    fn to_attrs(&self) -> Result<Vec<Nlattr<EthtoolRingsAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        let sizes = [
+            (EthtoolRingsAttr::Rx, self.rx),
+            (EthtoolRingsAttr::RxMini, self.rx_mini),
+            (EthtoolRingsAttr::RxJumbo, self.rx_jumbo),
+            (EthtoolRingsAttr::Tx, self.tx),
+            (EthtoolRingsAttr::RxBufLen, self.rx_buf_len),
+            (EthtoolRingsAttr::CqeSize, self.cqe_size),
+        ];
+        for (attr_type, size) in sizes.iter() {
+            if let Some(size) = size {
+                attrs.push(Nlattr::new(None, attr_type.clone(), *size)?);
+            }
+        }
+        if let Some(split) = self.tcp_data_split {
+            attrs.push(Nlattr::new(None, EthtoolRingsAttr::TcpDataSplit, split)?);
+        }
+        if let Some(push) = self.tx_push {
+            attrs.push(Nlattr::new(None, EthtoolRingsAttr::TxPush, push as u8)?);
+        }
+        Ok(attrs)
+    }
+}
+
+/// Channel counts (`ETHTOOL_MSG_CHANNELS_*`)
+///
+/// When setting, the maximum counts are not sent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EthtoolChannels {
+    /// Maximum number of RX only channels
+    pub rx_max: Option<u32>,
+    /// Maximum number of TX only channels
+    pub tx_max: Option<u32>,
+    /// Maximum number of other channels
+    pub other_max: Option<u32>,
+    /// Maximum number of combined RX and TX channels
+    pub combined_max: Option<u32>,
+    /// Number of RX only channels
+    pub rx_count: Option<u32>,
+    /// Number of TX only channels
+    pub tx_count: Option<u32>,
+    /// Number of other channels
+    pub other_count: Option<u32>,
+    /// Number of combined RX and TX channels
+    pub combined_count: Option<u32>,
+}
+
+impl EthtoolChannels {
+    /// Parse channel counts from message attributes
+    pub fn from_attrs(handle: &AttrHandle<EthtoolChannelsAttr>) -> Result<Self, DeError> {
+        let mut channels = EthtoolChannels::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                EthtoolChannelsAttr::RxMax => channels.rx_max = Some(attr.get_payload_as()?),
+                EthtoolChannelsAttr::TxMax => channels.tx_max = Some(attr.get_payload_as()?),
+                EthtoolChannelsAttr::OtherMax => channels.other_max = Some(attr.get_payload_as()?),
+                EthtoolChannelsAttr::CombinedMax => {
+                    channels.combined_max = Some(attr.get_payload_as()?)
+                }
+                EthtoolChannelsAttr::RxCount => channels.rx_count = Some(attr.get_payload_as()?),
+                EthtoolChannelsAttr::TxCount => channels.tx_count = Some(attr.get_payload_as()?),
+                EthtoolChannelsAttr::OtherCount => {
+                    channels.other_count = Some(attr.get_payload_as()?)
+                }
+                EthtoolChannelsAttr::CombinedCount => {
+                    channels.combined_count = Some(attr.get_payload_as()?)
+                }
+                _ => (),
+            }
+        }
+        Ok(channels)
+    }
+
+    fn to_attrs(&self) -> Result<Vec<Nlattr<EthtoolChannelsAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        let counts = [
+            (EthtoolChannelsAttr::RxCount, self.rx_count),
+            (EthtoolChannelsAttr::TxCount, self.tx_count),
+            (EthtoolChannelsAttr::OtherCount, self.other_count),
+            (EthtoolChannelsAttr::CombinedCount, self.combined_count),
+        ];
+        for (attr_type, count) in counts.iter() {
+            if let Some(count) = count {
+                attrs.push(Nlattr::new(None, attr_type.clone(), *count)?);
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+/// Offload features (`ETHTOOL_MSG_FEATURES_GET_REPLY`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EthtoolFeatures {
+    /// Features the device supports and that can be changed
+    pub hw: EthtoolBitset,
+    /// Features requested by the user
+    pub wanted: EthtoolBitset,
+    /// Features currently enabled
+    pub active: EthtoolBitset,
+    /// Features that can never be changed
+    pub nochange: EthtoolBitset,
+}
+
+impl EthtoolFeatures {
+    /// Parse features from message attributes
+    pub fn from_attrs(handle: &AttrHandle<EthtoolFeaturesAttr>) -> Result<Self, DeError> {
+        let mut features = EthtoolFeatures::default();
+        for attr in handle.iter() {
+            let bitset = match attr.nla_type {
+                EthtoolFeaturesAttr::Hw => &mut features.hw,
+                EthtoolFeaturesAttr::Wanted => &mut features.wanted,
+                EthtoolFeaturesAttr::Active => &mut features.active,
+                EthtoolFeaturesAttr::Nochange => &mut features.nochange,
+                _ => continue,
+            };
+            *bitset = EthtoolBitset::from_attrs(&attr.get_nested_attributes()?)?;
+        }
+        Ok(features)
+    }
+}
+
+/// Socket for the ethtool family with its resolved family ID
+pub struct EthtoolSocket {
+    socket: NlSocket,
+    family_id: u16,
+}
+
+impl EthtoolSocket {
+    /// Connect to the ethtool family
+    pub fn connect() -> Result<Self, NlError> {
+        let mut socket = NlSocket::connect(NlFamily::Generic, None, None, true)?;
+        let family_id = socket.resolve_genl_family(ETHTOOL_GENL_NAME)?;
+        Ok(EthtoolSocket { socket, family_id })
+    }
+
+    /// Generic netlink family ID of ethtool
+    pub fn family_id(&self) -> u16 {
+        self.family_id
+    }
+
+    /// Underlying socket, to send requests not covered by this type
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+
+    /// Get the port and transceiver information of a device
+    pub fn get_link_info(&mut self, dev: &EthtoolHeader) -> Result<EthtoolLinkInfo, NlError> {
+        let msg = self.get(EthtoolMsg::LinkinfoGet, EthtoolLinkinfoAttr::Header, dev)?;
+        Ok(EthtoolLinkInfo::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// Change the port and transceiver settings of a device
+    pub fn set_link_info(
+        &mut self,
+        dev: &EthtoolHeader,
+        info: &EthtoolLinkInfo,
+    ) -> Result<(), NlError> {
+        let attrs = info.to_attrs()?;
+        self.set(
+            EthtoolMsg::LinkinfoSet,
+            EthtoolLinkinfoAttr::Header,
+            dev,
+            attrs,
+        )
+    }
+
+    /// Get the speed, duplex, autonegotiation and link modes of a device
+    pub fn get_link_modes(&mut self, dev: &EthtoolHeader) -> Result<EthtoolLinkModes, NlError> {
+        let msg = self.get(EthtoolMsg::LinkmodesGet, EthtoolLinkmodesAttr::Header, dev)?;
+        Ok(EthtoolLinkModes::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// Change the speed, duplex, autonegotiation or advertised link modes of a device
+    pub fn set_link_modes(
+        &mut self,
+        dev: &EthtoolHeader,
+        modes: &EthtoolLinkModes,
+    ) -> Result<(), NlError> {
+        let attrs = modes.to_attrs()?;
+        self.set(
+            EthtoolMsg::LinkmodesSet,
+            EthtoolLinkmodesAttr::Header,
+            dev,
+            attrs,
+        )
+    }
+
+    /// Get the ring sizes of a device
+    pub fn get_rings(&mut self, dev: &EthtoolHeader) -> Result<EthtoolRings, NlError> {
+        let msg = self.get(EthtoolMsg::RingsGet, EthtoolRingsAttr::Header, dev)?;
+        Ok(EthtoolRings::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// Change the ring sizes of a device
+    pub fn set_rings(&mut self, dev: &EthtoolHeader, rings: &EthtoolRings) -> Result<(), NlError> {
+        let attrs = rings.to_attrs()?;
+        self.set(EthtoolMsg::RingsSet, EthtoolRingsAttr::Header, dev, attrs)
+    }
+
+    /// Get the channel counts of a device
+    pub fn get_channels(&mut self, dev: &EthtoolHeader) -> Result<EthtoolChannels, NlError> {
+        let msg = self.get(EthtoolMsg::ChannelsGet, EthtoolChannelsAttr::Header, dev)?;
+        Ok(EthtoolChannels::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// Change the channel counts of a device
+    pub fn set_channels(
+        &mut self,
+        dev: &EthtoolHeader,
+        channels: &EthtoolChannels,
+    ) -> Result<(), NlError> {
+        let attrs = channels.to_attrs()?;
+        self.set(
+            EthtoolMsg::ChannelsSet,
+            EthtoolChannelsAttr::Header,
+            dev,
+            attrs,
+        )
+    }
+
+    /// Get the offload features of a device
+    pub fn get_features(&mut self, dev: &EthtoolHeader) -> Result<EthtoolFeatures, NlError> {
+        let msg = self.get(EthtoolMsg::FeaturesGet, EthtoolFeaturesAttr::Header, dev)?;
+        Ok(EthtoolFeatures::from_attrs(&msg.get_attr_handle())?)
+    }
+
+    /// Enable or disable offload features of a device. `wanted` lists the features to change by
+    /// name, eg. built with `EthtoolBitset.set("rx-checksum", false)`.
+    pub fn set_features(
+        &mut self,
+        dev: &EthtoolHeader,
+        wanted: &EthtoolBitset,
+    ) -> Result<(), NlError> {
+        let attrs = vec![wanted.to_attr(EthtoolFeaturesAttr::Wanted)?];
+        self.set(
+            EthtoolMsg::FeaturesSet,
+            EthtoolFeaturesAttr::Header,
+            dev,
+            attrs,
+        )
+    }
+
+    fn get<T>(
+        &mut self,
+        cmd: EthtoolMsg,
+        header_type: T,
+        dev: &EthtoolHeader,
+    ) -> Result<Genlmsghdr<EthtoolKernelMsg, T>, NlError>
+    where
+        T: NlAttrType,
+    {
+        let attrs = vec![dev.to_attr(header_type)?];
+        let genlhdr = Genlmsghdr::new(cmd, ETHTOOL_GENL_VERSION, attrs)?;
+        let nlhdr = Nlmsghdr::new(
+            None,
+            self.family_id,
+            vec![NlmF::Request],
+            None,
+            None,
+            genlhdr,
+        );
+        self.socket.send_nl(nlhdr)?;
+        match self
+            .socket
+            .recv_all::<u16, Genlmsghdr<EthtoolKernelMsg, T>>()?
+            .pop()
+        {
+            Some(msg) => Ok(msg.nl_payload),
+            None => Err(NlError::new("No reply returned")),
+        }
+    }
+
+    fn set<T>(
+        &mut self,
+        cmd: EthtoolMsg,
+        header_type: T,
+        dev: &EthtoolHeader,
+        mut attrs: Vec<Nlattr<T, Vec<u8>>>,
+    ) -> Result<(), NlError>
+    where
+        T: NlAttrType,
+    {
+        let header = dev.clone().with_flag(EthtoolFlag::OmitReply);
+        attrs.insert(0, header.to_attr(header_type)?);
+        let genlhdr = Genlmsghdr::new(cmd, ETHTOOL_GENL_VERSION, attrs)?;
+        let nlhdr = Nlmsghdr::new(
+            None,
+            self.family_id,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            genlhdr,
+        );
+        self.socket.send_nl(nlhdr)?;
+        self.socket.recv_ack()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use buffering::{StreamReadBuffer, StreamWriteBuffer};
+    use {round_trip, Nl};
+
+    #[test]
+    fn test_header() {
+        let header = EthtoolHeader::by_name("eth0").with_flag(EthtoolFlag::CompactBitsets);
+        let attr = header.to_attr(EthtoolRingsAttr::Header).unwrap();
+        let nested_flag = u16::from(EthtoolRingsAttr::Header) | libc::NLA_F_NESTED as u16;
+        assert_eq!(u16::from(attr.nla_type.clone()), nested_flag);
+        let attr = round_trip(&attr);
+        assert_eq!(attr.nla_type, EthtoolRingsAttr::Header);
+        let parsed = EthtoolHeader::from_attrs(&attr.get_nested_attributes().unwrap()).unwrap();
+        assert_eq!(parsed, header);
+    }
+
+    #[test]
+    fn test_verbose_bitset() {
+        let mut bitset = EthtoolBitset::new();
+        bitset.set("rx-checksum", true);
+        bitset.set("tx-scatter-gather", false);
+        let attr = round_trip(&bitset.to_attr(EthtoolFeaturesAttr::Wanted).unwrap());
+        let mut handle = attr.get_nested_attributes::<EthtoolBitsetAttr>().unwrap();
+        let parsed = handle
+            .get_nested_attributes::<EthtoolBitsetBitsAttr>(EthtoolBitsetAttr::Bits)
+            .unwrap();
+        assert_eq!(parsed.iter().count(), 2);
+
+        // The kernel reports both the index and the name of each bit
+        let bit = |index: u32, name: &str, value: bool| {
+            let mut attrs = vec![
+                Nlattr::new(None, EthtoolBitsetBitAttr::Index, index).unwrap(),
+                Nlattr::new(None, EthtoolBitsetBitAttr::Name, name).unwrap(),
+            ];
+            if value {
+                attrs.push(
+                    Nlattr::new(None, EthtoolBitsetBitAttr::Value, Vec::<u8>::new()).unwrap(),
+                );
+            }
+            Nlattr::new_nested(EthtoolBitsetBitsAttr::Bit, &attrs)
+                .unwrap()
+                .with_nested_flag()
+        };
+        let bits = vec![
+            bit(0, "tx-scatter-gather", false),
+            bit(7, "rx-checksum", true),
+        ];
+        let attrs = vec![
+            Nlattr::new(None, EthtoolBitsetAttr::Size, 64u32).unwrap(),
+            Nlattr::new_nested(EthtoolBitsetAttr::Bits, &bits)
+                .unwrap()
+                .with_nested_flag(),
+        ];
+        let attr = round_trip(
+            &Nlattr::new_nested(EthtoolFeaturesAttr::Active, &attrs)
+                .unwrap()
+                .with_nested_flag(),
+        );
+        let bitset = EthtoolBitset::from_attrs(&attr.get_nested_attributes().unwrap()).unwrap();
+        assert_eq!(bitset.size, Some(64));
+        assert_eq!(bitset.is_set("rx-checksum"), Some(true));
+        assert_eq!(bitset.is_set("tx-scatter-gather"), Some(false));
+        assert_eq!(bitset.is_set_index(7), Some(true));
+        assert_eq!(bitset.set_names(), vec!["rx-checksum"]);
+    }
+
+    #[test]
+    fn test_compact_bitset() {
+        let value: Vec<u8> = [0b1001u32, 1 << 1]
+            .iter()
+            .flat_map(|w| w.to_ne_bytes().to_vec())
+            .collect();
+        let mask: Vec<u8> = [0b1011u32, 1 << 1]
+            .iter()
+            .flat_map(|w| w.to_ne_bytes().to_vec())
+            .collect();
+        let attrs = vec![
+            Nlattr::new(None, EthtoolBitsetAttr::Size, 40u32).unwrap(),
+            Nlattr::new(None, EthtoolBitsetAttr::Value, value.clone()).unwrap(),
+            Nlattr::new(None, EthtoolBitsetAttr::Mask, mask).unwrap(),
+        ];
+        let attr = round_trip(
+            &Nlattr::new_nested(EthtoolLinkmodesAttr::Ours, &attrs)
+                .unwrap()
+                .with_nested_flag(),
+        );
+        let bitset = EthtoolBitset::from_attrs(&attr.get_nested_attributes().unwrap()).unwrap();
+        let bits: Vec<_> = bitset.bits.iter().map(|b| (b.index, b.value)).collect();
+        assert_eq!(bits, vec![(0, true), (1, false), (3, true), (33, true)]);
+
+        let attrs = vec![
+            Nlattr::new(None, EthtoolBitsetAttr::Nomask, Vec::<u8>::new()).unwrap(),
+            Nlattr::new(None, EthtoolBitsetAttr::Size, 40u32).unwrap(),
+            Nlattr::new(None, EthtoolBitsetAttr::Value, value).unwrap(),
+        ];
+        let attr = round_trip(
+            &Nlattr::new_nested(EthtoolLinkmodesAttr::Peer, &attrs)
+                .unwrap()
+                .with_nested_flag(),
+        );
+        let bitset = EthtoolBitset::from_attrs(&attr.get_nested_attributes().unwrap()).unwrap();
+        let bits: Vec<_> = bitset.bits.iter().map(|b| b.index).collect();
+        assert_eq!(bits, vec![0, 3, 33]);
+    }
+
+    #[test]
+    fn test_rings() {
+        let rings = EthtoolRings {
+            rx_max: Some(4096),
+            rx: Some(512),
+            tx: Some(256),
+            tx_push: Some(true),
+            ..EthtoolRings::default()
+        };
+        let mut attrs = rings.to_attrs().unwrap();
+        attrs.push(Nlattr::new(None, EthtoolRingsAttr::RxMax, 4096u32).unwrap());
+        let genl = Genlmsghdr::new(EthtoolMsg::RingsSet, ETHTOOL_GENL_VERSION, attrs).unwrap();
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        genl.serialize(&mut mem).unwrap();
+        let mut buf = StreamReadBuffer::new(mem.as_ref());
+        buf.set_size_hint(mem.as_ref().len());
+        let genl = Genlmsghdr::<EthtoolKernelMsg, EthtoolRingsAttr>::deserialize(&mut buf).unwrap();
+        assert_eq!(
+            EthtoolRings::from_attrs(&genl.get_attr_handle()).unwrap(),
+            rings
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_get_features() {
+        let mut socket = EthtoolSocket::connect().unwrap();
+        let dev = EthtoolHeader::by_name("lo");
+        let features = socket.get_features(&dev).unwrap();
+        assert_eq!(features.active.is_set("loopback"), Some(true));
+
+        let compact = dev.with_flag(EthtoolFlag::CompactBitsets);
+        let compact = socket.get_features(&compact).unwrap();
+        let active: Vec<_> = features
+            .active
+            .bits
+            .iter()
+            .filter(|b| b.value)
+            .map(|b| b.index)
+            .collect();
+        let compact_active: Vec<_> = compact
+            .active
+            .bits
+            .iter()
+            .filter(|b| b.value)
+            .map(|b| b.index)
+            .collect();
+        assert_eq!(active, compact_active);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_set_features() {
+        let mut socket = EthtoolSocket::connect().unwrap();
+        let dev = EthtoolHeader::by_name("lo");
+        let features = socket.get_features(&dev).unwrap();
+        let gro = features.active.is_set("rx-gro").unwrap();
+
+        let mut wanted = EthtoolBitset::new();
+        wanted.set("rx-gro", !gro);
+        socket.set_features(&dev, &wanted).unwrap();
+        let features = socket.get_features(&dev).unwrap();
+        assert_eq!(features.active.is_set("rx-gro"), Some(!gro));
+
+        wanted.set("rx-gro", gro);
+        socket.set_features(&dev, &wanted).unwrap();
+    }
+}
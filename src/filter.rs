@@ -0,0 +1,343 @@
+//! This module provides classic BPF socket filters so that the kernel can drop messages that
+//! are not of interest before they are ever copied to userspace. This is particularly useful for
+//! high rate multicast subscriptions like conntrack events or NFLOG.
+//!
+//! Filters are attached with `NlSocket.attach_filter()`, removed with
+//! `NlSocket.detach_filter()` and can be made permanent with `NlSocket.lock_filter()`.
+//!
+//! ```no_run
+//! use neli::consts::{NlFamily, Rtm};
+//! use neli::filter::FilterBuilder;
+//! use neli::socket::NlSocket;
+//!
+//! let mut socket = NlSocket::connect(NlFamily::Route, None, None, true).unwrap();
+//! // Only accept messages announcing new or deleted links
+//! let filter = FilterBuilder::new()
+//!     .nl_types(vec![Rtm::Newlink, Rtm::Dellink])
+//!     .build()
+//!     .unwrap();
+//! socket.attach_filter(&filter).unwrap();
+//! ```
+//!
+//! # Design decisions
+//!
+//! Classic BPF loads halfwords and words in network byte order while netlink headers are in host
+//! byte order. `FilterBuilder` converts all values it compares against so that the matching
+//! values can be provided as they would appear in the Rust data structures.
+//!
+//! The filter program is run once per socket buffer. The kernel sends each multicast message in
+//! its own buffer so offsets are relative to the start of the `Nlmsghdr`.
+
+use std::mem;
+
+use consts::{Cmd, NlType};
+use err::SerError;
+
+const BPF_LD: u16 = 0x00;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_W: u16 = 0x00;
+const BPF_H: u16 = 0x08;
+const BPF_B: u16 = 0x10;
+const BPF_ABS: u16 = 0x20;
+const BPF_AND: u16 = 0x50;
+const BPF_JEQ: u16 = 0x10;
+const BPF_K: u16 = 0x00;
+
+// Socket options from `asm-generic/socket.h` - not exported by `libc` for every target
+pub(crate) const SO_ATTACH_FILTER: libc::c_int = 26;
+pub(crate) const SO_DETACH_FILTER: libc::c_int = 27;
+pub(crate) const SO_LOCK_FILTER: libc::c_int = 44;
+
+/// Offset of `nl_type` in `Nlmsghdr`
+const NL_TYPE_OFFSET: u32 = 4;
+/// Offset of `cmd` in a `Genlmsghdr` that directly follows an `Nlmsghdr`
+const GENL_CMD_OFFSET: u32 = 16;
+
+/// Return value telling the kernel to keep the whole message
+const ACCEPT: u32 = 0xffff_ffff;
+/// Return value telling the kernel to drop the message
+const REJECT: u32 = 0;
+
+/// Single classic BPF instruction as defined by `struct sock_filter` in `linux/filter.h`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SockFilter {
+    /// Opcode
+    pub code: u16,
+    /// Jump offset if the condition is true
+    pub jt: u8,
+    /// Jump offset if the condition is false
+    pub jf: u8,
+    /// Generic operand
+    pub k: u32,
+}
+
+impl SockFilter {
+    /// Equivalent of the `BPF_STMT` C macro
+    pub fn stmt(code: u16, k: u32) -> Self {
+        SockFilter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    /// Equivalent of the `BPF_JUMP` C macro
+    pub fn jump(code: u16, k: u32, jt: u8, jf: u8) -> Self {
+        SockFilter { code, jt, jf, k }
+    }
+}
+
+/// Program structure passed to `setsockopt()` as defined by `struct sock_fprog` in
+/// `linux/filter.h`
+#[repr(C)]
+pub(crate) struct SockFprog {
+    pub(crate) len: u16,
+    pub(crate) filter: *const SockFilter,
+}
+
+#[derive(Debug)]
+struct Check {
+    offset: u32,
+    size: u16,
+    mask: Option<u32>,
+    values: Vec<u32>,
+}
+
+impl Check {
+    fn len(&self) -> usize {
+        1 + self.mask.map(|_| 1).unwrap_or(0) + self.values.len()
+    }
+}
+
+/// Builder for filters that only accept messages matching all of the checks that were added
+#[derive(Debug, Default)]
+pub struct FilterBuilder {
+    checks: Vec<Check>,
+}
+
+impl FilterBuilder {
+    /// Create a builder for a filter that accepts everything
+    pub fn new() -> Self {
+        FilterBuilder { checks: Vec::new() }
+    }
+
+    /// Only accept messages with the given `nl_type`
+    pub fn nl_type<T>(self, nl_type: T) -> Self
+    where
+        T: NlType,
+    {
+        self.nl_types(vec![nl_type])
+    }
+
+    /// Only accept messages with any of the given `nl_type`s
+    pub fn nl_types<T>(self, nl_types: Vec<T>) -> Self
+    where
+        T: NlType,
+    {
+        let values = nl_types.into_iter().map(|t| t.into()).collect();
+        self.match_u16_any(NL_TYPE_OFFSET, values)
+    }
+
+    /// Only accept generic netlink messages with the given `Genlmsghdr.cmd`
+    pub fn genl_cmd<C>(self, cmd: C) -> Self
+    where
+        C: Cmd,
+    {
+        self.genl_cmds(vec![cmd])
+    }
+
+    /// Only accept generic netlink messages with any of the given `Genlmsghdr.cmd`s
+    pub fn genl_cmds<C>(self, cmds: Vec<C>) -> Self
+    where
+        C: Cmd,
+    {
+        let values = cmds.into_iter().map(|c| c.into()).collect();
+        self.match_u8_any(GENL_CMD_OFFSET, values)
+    }
+
+    /// Only accept messages where the byte at `offset` from the start of the message is `value`
+    pub fn match_u8(self, offset: u32, value: u8) -> Self {
+        self.match_u8_any(offset, vec![value])
+    }
+
+    /// Only accept messages where the byte at `offset` from the start of the message is any of
+    /// `values`
+    pub fn match_u8_any(mut self, offset: u32, values: Vec<u8>) -> Self {
+        self.checks.push(Check {
+            offset,
+            size: BPF_B,
+            mask: None,
+            values: values.into_iter().map(u32::from).collect(),
+        });
+        self
+    }
+
+    /// Only accept messages where the host byte order `u16` at `offset` from the start of the
+    /// message is `value`
+    pub fn match_u16(self, offset: u32, value: u16) -> Self {
+        self.match_u16_any(offset, vec![value])
+    }
+
+    /// Only accept messages where the host byte order `u16` at `offset` from the start of the
+    /// message is any of `values`
+    pub fn match_u16_any(mut self, offset: u32, values: Vec<u16>) -> Self {
+        self.checks.push(Check {
+            offset,
+            size: BPF_H,
+            mask: None,
+            values: values
+                .into_iter()
+                .map(|v| u32::from(u16::from_be(v)))
+                .collect(),
+        });
+        self
+    }
+
+    /// Only accept messages where the host byte order `u32` at `offset` from the start of the
+    /// message is `value`
+    pub fn match_u32(self, offset: u32, value: u32) -> Self {
+        self.match_u32_any(offset, vec![value])
+    }
+
+    /// Only accept messages where the host byte order `u32` at `offset` from the start of the
+    /// message is any of `values`
+    pub fn match_u32_any(mut self, offset: u32, values: Vec<u32>) -> Self {
+        self.checks.push(Check {
+            offset,
+            size: BPF_W,
+            mask: None,
+            values: values.into_iter().map(u32::from_be).collect(),
+        });
+        self
+    }
+
+    /// Only accept messages where the network byte order `u32` at `offset` from the start of
+    /// the message, masked with `mask`, is `value` - useful for netfilter attributes which are
+    /// big endian
+    pub fn match_be_u32_masked(mut self, offset: u32, mask: u32, value: u32) -> Self {
+        self.checks.push(Check {
+            offset,
+            size: BPF_W,
+            mask: Some(mask),
+            values: vec![value & mask],
+        });
+        self
+    }
+
+    /// Compile the checks into a BPF program
+    pub fn build(self) -> Result<Vec<SockFilter>, SerError> {
+        let total = self.checks.iter().fold(0, |acc, c| acc + c.len());
+        let mut prog = Vec::with_capacity(total + 2);
+        for check in self.checks.iter() {
+            if check.values.is_empty() {
+                return Err(SerError::new("Filter check requires at least one value"));
+            }
+            prog.push(SockFilter::stmt(
+                BPF_LD | check.size | BPF_ABS,
+                check.offset,
+            ));
+            if let Some(mask) = check.mask {
+                prog.push(SockFilter::stmt(BPF_ALU | BPF_AND | BPF_K, mask));
+            }
+            let num_values = check.values.len();
+            for (i, value) in check.values.iter().enumerate() {
+                // On a match skip the remaining comparisons for this check
+                let jt = num_values - 1 - i;
+                // On a mismatch of the last comparison jump past the accept instruction
+                let jf = if i == num_values - 1 {
+                    total - prog.len()
+                } else {
+                    0
+                };
+                if jt > u8::MAX as usize || jf > u8::MAX as usize {
+                    return Err(SerError::new("Filter is too long to encode jump offsets"));
+                }
+                prog.push(SockFilter::jump(
+                    BPF_JMP | BPF_JEQ | BPF_K,
+                    *value,
+                    jt as u8,
+                    jf as u8,
+                ));
+            }
+        }
+        prog.push(SockFilter::stmt(BPF_RET | BPF_K, ACCEPT));
+        prog.push(SockFilter::stmt(BPF_RET | BPF_K, REJECT));
+        if prog.len() > u16::MAX as usize {
+            return Err(SerError::new("Filter contains too many instructions"));
+        }
+        Ok(prog)
+    }
+}
+
+pub(crate) fn sock_fprog(filter: &[SockFilter]) -> SockFprog {
+    SockFprog {
+        len: filter.len() as u16,
+        filter: filter.as_ptr(),
+    }
+}
+
+pub(crate) fn sock_fprog_size() -> usize {
+    mem::size_of::<SockFprog>()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use consts::{CtrlCmd, NlFamily, Rtm};
+    use socket::NlSocket;
+
+    #[test]
+    fn test_build_nl_types() {
+        let filter = FilterBuilder::new()
+            .nl_types(vec![Rtm::Newlink, Rtm::Dellink])
+            .build()
+            .unwrap();
+        assert_eq!(
+            filter,
+            vec![
+                SockFilter::stmt(BPF_LD | BPF_H | BPF_ABS, 4),
+                SockFilter::jump(
+                    BPF_JMP | BPF_JEQ | BPF_K,
+                    u32::from(u16::from_be(libc::RTM_NEWLINK)),
+                    1,
+                    0
+                ),
+                SockFilter::jump(
+                    BPF_JMP | BPF_JEQ | BPF_K,
+                    u32::from(u16::from_be(libc::RTM_DELLINK)),
+                    0,
+                    1
+                ),
+                SockFilter::stmt(BPF_RET | BPF_K, ACCEPT),
+                SockFilter::stmt(BPF_RET | BPF_K, REJECT),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_multiple_checks() {
+        let filter = FilterBuilder::new()
+            .nl_type(0x10u16)
+            .genl_cmd(CtrlCmd::Newfamily)
+            .build()
+            .unwrap();
+        assert_eq!(filter.len(), 6);
+        // First check jumps past the second check and the accept instruction on mismatch
+        assert_eq!(filter[1].jf, 3);
+        assert_eq!(filter[2], SockFilter::stmt(BPF_LD | BPF_B | BPF_ABS, 16));
+        assert_eq!(filter[3].jf, 1);
+    }
+
+    #[test]
+    fn test_attach_detach_filter() {
+        let mut s = NlSocket::connect(NlFamily::Generic, None, None, true).unwrap();
+        let filter = FilterBuilder::new().nl_type(0x10u16).build().unwrap();
+        s.attach_filter(&filter).unwrap();
+        s.detach_filter().unwrap();
+    }
+}
@@ -0,0 +1,543 @@
+//! This module provides typed event streams for generic netlink families that only send
+//! multicast events, such as `thermal`, `acpi_event` and the notifications of `nlctrl`.
+//!
+//! ```no_run
+//! use neli::genl_event::{GenlEventListener, ThermalEvent, THERMAL_EVENT_GROUP};
+//!
+//! let mut listener = GenlEventListener::<ThermalEvent>::subscribe(&[THERMAL_EVENT_GROUP]).unwrap();
+//! loop {
+//!     if let ThermalEvent::TripUp { tz_id, temp, .. } = listener.recv_event().unwrap() {
+//!         println!("Thermal zone {} reached {}m°C", tz_id, temp);
+//!     }
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! `GenlEventListener` resolves the family and its multicast groups by name, joins the groups
+//! and decodes every message of the family with `GenlEvent::from_genl()`. Supporting another
+//! event-only family only requires its command and attribute enums in `consts` and an
+//! implementation of `GenlEvent` for its event type.
+//!
+//! Events are decoded leniently: commands without a typed variant are returned as `Other` so
+//! that new kernel events do not end the stream.
+
+use std::marker::PhantomData;
+
+use consts::acpi::{AcpiGenlAttr, AcpiGenlCmd};
+use consts::thermal::{ThermalGenlAttr, ThermalGenlEvent, ThermalTripType};
+use consts::{Cmd, CtrlAttr, CtrlAttrMcastGrp, CtrlCmd, NlAttrType, NlFamily};
+use err::{DeError, NlError};
+use genl::Genlmsghdr;
+use nlattr::AttrHandle;
+use socket::NlSocket;
+
+/// Name of the thermal generic netlink family
+pub const THERMAL_GENL_NAME: &str = "thermal";
+/// Multicast group of thermal zone, trip point and cooling device events
+pub const THERMAL_EVENT_GROUP: &str = "event";
+/// Multicast group of thermal zone temperature samples
+pub const THERMAL_SAMPLING_GROUP: &str = "sampling";
+/// Name of the ACPI event generic netlink family
+pub const ACPI_GENL_NAME: &str = "acpi_event";
+/// Multicast group of ACPI events
+pub const ACPI_EVENT_GROUP: &str = "acpi_mc_group";
+/// Name of the generic netlink controller family
+pub const NLCTRL_GENL_NAME: &str = "nlctrl";
+/// Multicast group of family and multicast group registrations
+pub const NLCTRL_NOTIFY_GROUP: &str = "notify";
+
+/// Size of `device_class` in `struct acpi_genl_event`
+const ACPI_DEVICE_CLASS_LEN: usize = 20;
+/// Size of `bus_id` in `struct acpi_genl_event`
+const ACPI_BUS_ID_LEN: usize = 15;
+/// Size of `struct acpi_genl_event`
+const ACPI_EVENT_LEN: usize = 44;
+
+/// Event of a generic netlink family that can be decoded from a multicast message
+pub trait GenlEvent: Sized {
+    /// Command type of the family
+    type Cmd: Cmd;
+    /// Top level attribute type of the family
+    type Attr: NlAttrType;
+    /// Name of the family
+    const FAMILY: &'static str;
+
+    /// Decode an event from a message of the family
+    fn from_genl(msg: &Genlmsghdr<Self::Cmd, Self::Attr>) -> Result<Self, DeError>;
+}
+
+/// Socket subscribed to multicast groups of a family that decodes the messages into `E`
+pub struct GenlEventListener<E> {
+    socket: NlSocket,
+    family_id: u16,
+    event: PhantomData<E>,
+}
+
+impl<E> GenlEventListener<E>
+where
+    E: GenlEvent,
+{
+    /// Resolve the family of `E` and join its multicast groups named `groups`
+    pub fn subscribe(groups: &[&str]) -> Result<Self, NlError> {
+        let mut socket = NlSocket::connect(NlFamily::Generic, None, None, true)?;
+        let family_id = socket.resolve_genl_family(E::FAMILY)?;
+        let mut ids = Vec::with_capacity(groups.len());
+        for group in groups.iter() {
+            ids.push(socket.resolve_nl_mcast_group(E::FAMILY, group)?);
+        }
+        socket.set_mcast_groups(ids)?;
+        Ok(GenlEventListener {
+            socket,
+            family_id,
+            event: PhantomData,
+        })
+    }
+
+    /// Generic netlink family ID of the family
+    pub fn family_id(&self) -> u16 {
+        self.family_id
+    }
+
+    /// Underlying socket, eg. to join more multicast groups
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+
+    /// Receive the next event, skipping messages of other families
+    pub fn recv_event(&mut self) -> Result<E, NlError> {
+        loop {
+            let msg = self
+                .socket
+                .recv_nl::<u16, Genlmsghdr<E::Cmd, E::Attr>>(None)?;
+            if msg.nl_type == self.family_id {
+                return Ok(E::from_genl(&msg.nl_payload)?);
+            }
+        }
+    }
+}
+
+/// Trip point of a thermal zone
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThermalTrip {
+    /// Thermal zone ID
+    pub tz_id: u32,
+    /// Trip point ID within the zone
+    pub trip_id: u32,
+    /// Type of the trip point
+    pub trip_type: ThermalTripType,
+    /// Temperature of the trip point in millidegrees Celsius
+    pub temp: i32,
+    /// Hysteresis in millidegrees Celsius
+    pub hyst: i32,
+}
+
+impl ThermalTrip {
+    fn from_attrs(handle: &AttrHandle<ThermalGenlAttr>) -> Result<Self, DeError> {
+        Ok(ThermalTrip {
+            tz_id: handle.get_attr_payload_as(ThermalGenlAttr::TzId)?,
+            trip_id: handle.get_attr_payload_as(ThermalGenlAttr::TzTripId)?,
+            trip_type: handle.get_attr_payload_as(ThermalGenlAttr::TzTripType)?,
+            temp: handle.get_attr_payload_as(ThermalGenlAttr::TzTripTemp)?,
+            hyst: handle.get_attr_payload_as(ThermalGenlAttr::TzTripHyst)?,
+        })
+    }
+}
+
+/// Event of the thermal family. Temperatures are in millidegrees Celsius.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ThermalEvent {
+    /// A thermal zone was created
+    TzCreate {
+        /// Thermal zone ID
+        tz_id: u32,
+        /// Name of the zone
+        name: String,
+    },
+    /// A thermal zone was deleted
+    TzDelete {
+        /// Thermal zone ID
+        tz_id: u32,
+    },
+    /// A thermal zone was disabled
+    TzDisable {
+        /// Thermal zone ID
+        tz_id: u32,
+    },
+    /// A thermal zone was enabled
+    TzEnable {
+        /// Thermal zone ID
+        tz_id: u32,
+    },
+    /// The temperature crossed a trip point on the way up
+    TripUp {
+        /// Thermal zone ID
+        tz_id: u32,
+        /// Trip point ID
+        trip_id: u32,
+        /// Current temperature
+        temp: i32,
+    },
+    /// The temperature crossed a trip point on the way down
+    TripDown {
+        /// Thermal zone ID
+        tz_id: u32,
+        /// Trip point ID
+        trip_id: u32,
+        /// Current temperature
+        temp: i32,
+    },
+    /// A trip point changed
+    TripChange(ThermalTrip),
+    /// A trip point was added
+    TripAdd(ThermalTrip),
+    /// A trip point was deleted
+    TripDelete {
+        /// Thermal zone ID
+        tz_id: u32,
+        /// Trip point ID
+        trip_id: u32,
+    },
+    /// A cooling device was added
+    CdevAdd {
+        /// Cooling device ID
+        cdev_id: u32,
+        /// Name of the cooling device
+        name: String,
+        /// Highest cooling state
+        max_state: u32,
+    },
+    /// A cooling device was deleted
+    CdevDelete {
+        /// Cooling device ID
+        cdev_id: u32,
+    },
+    /// The state of a cooling device changed
+    CdevStateUpdate {
+        /// Cooling device ID
+        cdev_id: u32,
+        /// New cooling state
+        cur_state: u32,
+    },
+    /// The governor of a thermal zone changed
+    GovChange {
+        /// Thermal zone ID
+        tz_id: u32,
+        /// Name of the new governor
+        governor: String,
+    },
+    /// Temperature sample from the sampling group
+    Sample {
+        /// Thermal zone ID
+        tz_id: u32,
+        /// Current temperature
+        temp: i32,
+    },
+    /// Event without a typed variant
+    Other(ThermalGenlEvent),
+}
+
+impl GenlEvent for ThermalEvent {
+    type Cmd = ThermalGenlEvent;
+    type Attr = ThermalGenlAttr;
+    const FAMILY: &'static str = THERMAL_GENL_NAME;
+
+    fn from_genl(msg: &Genlmsghdr<ThermalGenlEvent, ThermalGenlAttr>) -> Result<Self, DeError> {
+        let handle = msg.get_attr_handle();
+        let tz_id = || handle.get_attr_payload_as::<u32>(ThermalGenlAttr::TzId);
+        let cdev_id = || handle.get_attr_payload_as::<u32>(ThermalGenlAttr::CdevId);
+        Ok(match msg.cmd {
+            // THERMAL_GENL_SAMPLING_TEMP
+            ThermalGenlEvent::Unspec => ThermalEvent::Sample {
+                tz_id: tz_id()?,
+                temp: handle.get_attr_payload_as(ThermalGenlAttr::TzTemp)?,
+            },
+            ThermalGenlEvent::TzCreate => ThermalEvent::TzCreate {
+                tz_id: tz_id()?,
+                name: handle.get_attr_payload_as(ThermalGenlAttr::TzName)?,
+            },
+            ThermalGenlEvent::TzDelete => ThermalEvent::TzDelete { tz_id: tz_id()? },
+            ThermalGenlEvent::TzDisable => ThermalEvent::TzDisable { tz_id: tz_id()? },
+            ThermalGenlEvent::TzEnable => ThermalEvent::TzEnable { tz_id: tz_id()? },
+            ThermalGenlEvent::TzTripUp => ThermalEvent::TripUp {
+                tz_id: tz_id()?,
+                trip_id: handle.get_attr_payload_as(ThermalGenlAttr::TzTripId)?,
+                temp: handle.get_attr_payload_as(ThermalGenlAttr::TzTemp)?,
+            },
+            ThermalGenlEvent::TzTripDown => ThermalEvent::TripDown {
+                tz_id: tz_id()?,
+                trip_id: handle.get_attr_payload_as(ThermalGenlAttr::TzTripId)?,
+                temp: handle.get_attr_payload_as(ThermalGenlAttr::TzTemp)?,
+            },
+            ThermalGenlEvent::TzTripChange => {
+                ThermalEvent::TripChange(ThermalTrip::from_attrs(&handle)?)
+            }
+            ThermalGenlEvent::TzTripAdd => ThermalEvent::TripAdd(ThermalTrip::from_attrs(&handle)?),
+            ThermalGenlEvent::TzTripDelete => ThermalEvent::TripDelete {
+                tz_id: tz_id()?,
+                trip_id: handle.get_attr_payload_as(ThermalGenlAttr::TzTripId)?,
+            },
+            ThermalGenlEvent::CdevAdd => ThermalEvent::CdevAdd {
+                cdev_id: cdev_id()?,
+                name: handle.get_attr_payload_as(ThermalGenlAttr::CdevName)?,
+                max_state: handle.get_attr_payload_as(ThermalGenlAttr::CdevMaxState)?,
+            },
+            ThermalGenlEvent::CdevDelete => ThermalEvent::CdevDelete {
+                cdev_id: cdev_id()?,
+            },
+            ThermalGenlEvent::CdevStateUpdate => ThermalEvent::CdevStateUpdate {
+                cdev_id: cdev_id()?,
+                cur_state: handle.get_attr_payload_as(ThermalGenlAttr::CdevCurState)?,
+            },
+            ThermalGenlEvent::TzGovChange => ThermalEvent::GovChange {
+                tz_id: tz_id()?,
+                governor: handle.get_attr_payload_as(ThermalGenlAttr::GovName)?,
+            },
+            ref cmd => ThermalEvent::Other(cmd.clone()),
+        })
+    }
+}
+
+/// ACPI event (`struct acpi_genl_event`), eg. a power button press or AC adapter change
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcpiEvent {
+    /// Class of the device, eg. `button/power`
+    pub device_class: String,
+    /// Bus ID of the device, eg. `PNP0C0C:00`
+    pub bus_id: String,
+    /// Event type, specific to the device class
+    pub event_type: u32,
+    /// Event data, specific to the device class
+    pub data: u32,
+}
+
+impl AcpiEvent {
+    fn parse(payload: &[u8]) -> Result<Self, DeError> {
+        if payload.len() < ACPI_EVENT_LEN {
+            return Err(DeError::new("ACPI event is truncated"));
+        }
+        let string = |bytes: &[u8]| {
+            let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..len]).into_owned()
+        };
+        let u32_at = |offset: usize| {
+            let mut buf = [0; 4];
+            buf.copy_from_slice(&payload[offset..offset + 4]);
+            u32::from_ne_bytes(buf)
+        };
+        let bus_id_end = ACPI_DEVICE_CLASS_LEN + ACPI_BUS_ID_LEN;
+        Ok(AcpiEvent {
+            device_class: string(&payload[..ACPI_DEVICE_CLASS_LEN]),
+            bus_id: string(&payload[ACPI_DEVICE_CLASS_LEN..bus_id_end]),
+            event_type: u32_at(36),
+            data: u32_at(40),
+        })
+    }
+}
+
+impl GenlEvent for AcpiEvent {
+    type Cmd = AcpiGenlCmd;
+    type Attr = AcpiGenlAttr;
+    const FAMILY: &'static str = ACPI_GENL_NAME;
+
+    fn from_genl(msg: &Genlmsghdr<AcpiGenlCmd, AcpiGenlAttr>) -> Result<Self, DeError> {
+        match msg.get_attr_handle().get_attribute(AcpiGenlAttr::Event) {
+            Some(attr) => AcpiEvent::parse(&attr.payload),
+            None => Err(DeError::new("Missing ACPI event")),
+        }
+    }
+}
+
+/// Notification of the generic netlink controller
+#[derive(Clone, Debug, PartialEq)]
+pub enum NlctrlEvent {
+    /// A family was registered
+    NewFamily {
+        /// Family ID
+        id: u16,
+        /// Family name
+        name: String,
+    },
+    /// A family was unregistered
+    DelFamily {
+        /// Family ID
+        id: u16,
+        /// Family name
+        name: String,
+    },
+    /// A multicast group was registered
+    NewMcastGrp {
+        /// Family ID
+        family_id: u16,
+        /// Family name
+        family_name: String,
+        /// Group name
+        group: String,
+        /// Group ID
+        group_id: u32,
+    },
+    /// A multicast group was unregistered
+    DelMcastGrp {
+        /// Family ID
+        family_id: u16,
+        /// Family name
+        family_name: String,
+        /// Group name
+        group: String,
+        /// Group ID
+        group_id: u32,
+    },
+    /// Notification without a typed variant
+    Other(CtrlCmd),
+}
+
+impl GenlEvent for NlctrlEvent {
+    type Cmd = CtrlCmd;
+    type Attr = CtrlAttr;
+    const FAMILY: &'static str = NLCTRL_GENL_NAME;
+
+    fn from_genl(msg: &Genlmsghdr<CtrlCmd, CtrlAttr>) -> Result<Self, DeError> {
+        let handle = msg.get_attr_handle();
+        let id = handle.get_attr_payload_as::<u16>(CtrlAttr::FamilyId)?;
+        let name = handle.get_attr_payload_as::<String>(CtrlAttr::FamilyName)?;
+        // Group notifications carry the group as the only entry of the group list
+        let group = || -> Result<(String, u32), DeError> {
+            let groups = match handle.get_attribute(CtrlAttr::McastGroups) {
+                Some(attr) => attr.get_nested_attributes::<u16>()?,
+                None => return Err(DeError::new("Missing multicast group")),
+            };
+            let group = match groups.iter().next() {
+                Some(attr) => attr.get_nested_attributes::<CtrlAttrMcastGrp>()?,
+                None => return Err(DeError::new("Missing multicast group")),
+            };
+            Ok((
+                group.get_attr_payload_as(CtrlAttrMcastGrp::Name)?,
+                group.get_attr_payload_as(CtrlAttrMcastGrp::Id)?,
+            ))
+        };
+        Ok(match msg.cmd {
+            CtrlCmd::Newfamily => NlctrlEvent::NewFamily { id, name },
+            CtrlCmd::Delfamily => NlctrlEvent::DelFamily { id, name },
+            CtrlCmd::NewmcastGrp => {
+                let (group, group_id) = group()?;
+                NlctrlEvent::NewMcastGrp {
+                    family_id: id,
+                    family_name: name,
+                    group,
+                    group_id,
+                }
+            }
+            CtrlCmd::DelmcastGrp => {
+                let (group, group_id) = group()?;
+                NlctrlEvent::DelMcastGrp {
+                    family_id: id,
+                    family_name: name,
+                    group,
+                    group_id,
+                }
+            }
+            ref cmd => NlctrlEvent::Other(cmd.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use nlattr::Nlattr;
+
+    #[test]
+    fn test_thermal_event() {
+        let attrs = vec![
+            Nlattr::new(None, ThermalGenlAttr::TzId, 1u32).unwrap(),
+            Nlattr::new(None, ThermalGenlAttr::TzTripId, 2u32).unwrap(),
+            Nlattr::new(None, ThermalGenlAttr::TzTemp, 85000i32).unwrap(),
+        ];
+        let msg = Genlmsghdr::new(ThermalGenlEvent::TzTripUp, 1, attrs).unwrap();
+        assert_eq!(
+            ThermalEvent::from_genl(&msg).unwrap(),
+            ThermalEvent::TripUp {
+                tz_id: 1,
+                trip_id: 2,
+                temp: 85000
+            }
+        );
+
+        let attrs = vec![
+            Nlattr::new(None, ThermalGenlAttr::TzId, 1u32).unwrap(),
+            Nlattr::new(None, ThermalGenlAttr::TzTripId, 0u32).unwrap(),
+            Nlattr::new(None, ThermalGenlAttr::TzTripType, 3u32).unwrap(),
+            Nlattr::new(None, ThermalGenlAttr::TzTripTemp, 105000i32).unwrap(),
+            Nlattr::new(None, ThermalGenlAttr::TzTripHyst, 0i32).unwrap(),
+        ];
+        let msg = Genlmsghdr::new(ThermalGenlEvent::TzTripAdd, 1, attrs).unwrap();
+        match ThermalEvent::from_genl(&msg).unwrap() {
+            ThermalEvent::TripAdd(trip) => {
+                assert_eq!(trip.trip_type, ThermalTripType::Critical);
+                assert_eq!(trip.temp, 105000);
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+
+        let msg = Genlmsghdr::new(ThermalGenlEvent::CpuCapabilityChange, 1, vec![]).unwrap();
+        assert_eq!(
+            ThermalEvent::from_genl(&msg).unwrap(),
+            ThermalEvent::Other(ThermalGenlEvent::CpuCapabilityChange)
+        );
+    }
+
+    #[test]
+    fn test_acpi_event() {
+        let mut payload = vec![0; ACPI_EVENT_LEN];
+        payload[..12].copy_from_slice(b"button/power");
+        payload[20..27].copy_from_slice(b"PNP0C0C");
+        payload[36..40].copy_from_slice(&0x80u32.to_ne_bytes());
+        payload[40..44].copy_from_slice(&1u32.to_ne_bytes());
+        let attrs = vec![Nlattr::new(None, AcpiGenlAttr::Event, payload).unwrap()];
+        let msg = Genlmsghdr::new(AcpiGenlCmd::Event, 1, attrs).unwrap();
+        assert_eq!(
+            AcpiEvent::from_genl(&msg).unwrap(),
+            AcpiEvent {
+                device_class: "button/power".to_string(),
+                bus_id: "PNP0C0C".to_string(),
+                event_type: 0x80,
+                data: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_nlctrl_event() {
+        let group = Nlattr::new_nested(
+            1u16,
+            &[
+                Nlattr::new(None, CtrlAttrMcastGrp::Name, "event").unwrap(),
+                Nlattr::new(None, CtrlAttrMcastGrp::Id, 9u32).unwrap(),
+            ],
+        )
+        .unwrap();
+        let groups = Nlattr::new_nested(CtrlAttr::McastGroups, &[group]).unwrap();
+        let attrs = vec![
+            Nlattr::new(None, CtrlAttr::FamilyId, 30u16).unwrap(),
+            Nlattr::new(None, CtrlAttr::FamilyName, "thermal").unwrap(),
+            groups,
+        ];
+        let msg = Genlmsghdr::new(CtrlCmd::NewmcastGrp, 2, attrs).unwrap();
+        assert_eq!(
+            NlctrlEvent::from_genl(&msg).unwrap(),
+            NlctrlEvent::NewMcastGrp {
+                family_id: 30,
+                family_name: "thermal".to_string(),
+                group: "event".to_string(),
+                group_id: 9,
+            }
+        );
+    }
+
+    #[test]
+    fn test_subscribe_nlctrl() {
+        let listener = GenlEventListener::<NlctrlEvent>::subscribe(&[NLCTRL_NOTIFY_GROUP]).unwrap();
+        assert_eq!(listener.family_id(), 0x10);
+    }
+}
@@ -7,20 +7,35 @@
 //! constants in the context of netlink.
 //!
 //! ## The project is broken down into the following modules:
+//! * `audit` - Client for the Linux audit subsystem (status, rules and records).
+//! * `connector` - Connector message framing and a listener for process events.
 //! * `consts` - This is where all of the C-defined constants are wrapped into type safe enums for
 //! use in the library.
+//! * `devlink` - Ports, parameters, health reporters and information of devices over devlink.
 //! * `err` - This module contains all of the protocol and library-level errors encountered in the
 //! code.
+//! * `ethtool` - Link, ring, channel and feature settings of network devices over ethtool.
+//! * `filter` - Classic BPF socket filters for dropping uninteresting messages in the kernel.
 //! * `genl` - This code provides parsing for the generic netlink subsystem of the netlink
 //! protocol.
+//! * `genl_event` - Event streams of event-only generic netlink families (thermal, ACPI, nlctrl).
+//! * `mock` - Scriptable in-process mock of the kernel for unit tests without root.
 //! * `netfilter` - Netfilter related protocols (NFLOG, NFQUEUE, CONNTRACK).
+//! * `nl80211` - Wireless interfaces, wiphys, scans and stations over nl80211.
 //! * `nlattr` - This code provides more granular parsing methods for the generic netlink
 //! attributes in the context of generic netlink requests and responses.
 //! * `nl` - This is the top level netlink header code that handles the header that all netlink
 //! messages are encapsulated in.
+//! * `record` - Recording of the datagrams exchanged by a socket and their replay.
 //! * `rtnl` - This module is for the routing netlink subsystem of the netlink protocol.
+//! * `server` - Server side of userspace netlink protocols such as `NETLINK_USERSOCK`.
+//! * `sock_diag` - Socket diagnostics (`inet_diag` and `unix_diag`).
 //! * `socket` - This provides a socket structure for use in sending and receiving messages and a
 //! number of convenience functions for commonly encountered use cases.
+//! * `taskstats` - Per-task delay accounting, CPU and IO statistics and exit notifications.
+//! * `uevent` - Listener for kernel and udev device events.
+//! * `wireguard` - WireGuard device and peer configuration.
+//! * `xfrm` - IPsec state and policy management.
 //!
 //! ## Traits
 //!
@@ -63,21 +78,51 @@ extern crate mio;
 #[cfg(feature = "stream")]
 extern crate tokio;
 
+/// Linux audit client
+pub mod audit;
+/// Kernel connector and process event listener
+pub mod connector;
 /// C constants defined as types
 pub mod consts;
+/// Device management (devlink)
+pub mod devlink;
 /// Error module
 pub mod err;
+/// Network device configuration (ethtool)
+pub mod ethtool;
+/// Socket-level BPF filters
+pub mod filter;
 /// Genetlink (generic netlink) header and attribute helpers
 pub mod genl;
+/// Event streams of event-only generic netlink families
+pub mod genl_event;
+/// Mock kernel for testing code built on `NlSocket`
+pub mod mock;
 pub mod netfilter;
 /// Top-level netlink header
 pub mod nl;
+/// Wireless device management (nl80211)
+pub mod nl80211;
 /// Netlink attribute handler
 pub mod nlattr;
+/// Recording and replay of netlink traffic
+pub mod record;
 /// Route netlink bindings
 pub mod rtnl;
+/// Netlink servers for userspace protocols such as `NETLINK_USERSOCK`
+pub mod server;
+/// Socket diagnostics (`inet_diag` and `unix_diag`)
+pub mod sock_diag;
 /// Wrapper for `libc` sockets
 pub mod socket;
+/// Per-task accounting (taskstats)
+pub mod taskstats;
+/// Kernel and udev device event listener
+pub mod uevent;
+/// WireGuard configuration
+pub mod wireguard;
+/// IPsec (XFRM) state and policy management
+pub mod xfrm;
 
 use std::ffi::CString;
 use std::io::{Read, Write};
@@ -134,6 +179,20 @@ pub trait Nl: Sized {
     }
 }
 
+/// Serialize `value` and deserialize it again, using the encoded length as the size hint
+#[cfg(test)]
+pub(crate) fn round_trip<T>(value: &T) -> T
+where
+    T: Nl,
+{
+    let mut mem = StreamWriteBuffer::new_growable(Some(value.asize()));
+    value.serialize(&mut mem).unwrap();
+    assert_eq!(mem.as_ref().len(), value.asize());
+    let mut buf = StreamReadBuffer::new(mem.as_ref());
+    buf.set_size_hint(mem.as_ref().len());
+    T::deserialize(&mut buf).unwrap()
+}
+
 /// Deserialize trait that allows a buffer to be passed in so that references with appropriate
 /// lifetimes can be returned
 pub trait NlBuf<'a>: Sized {
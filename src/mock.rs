@@ -0,0 +1,429 @@
+//! In-process mock of the kernel side of netlink for unit testing code built on `NlSocket`
+//! without root privileges or a real kernel.
+//!
+//! A `MockKernel` holds a script of expected requests and their replies. `MockKernel::socket`
+//! creates an `NlSocket` running over an in-memory `NlTransport`: every request sent on the
+//! socket is matched against the next expectation and the reply is queued for the socket to
+//! receive. Replies follow the same conventions as `server::NlServer`, so sequence numbers,
+//! `NLMSG_DONE` terminated dumps, errors and ACKs are handled by `recv_all` and `recv_ack` as with
+//! the kernel.
+//!
+//! ```
+//! use neli::consts::{Arphrd, NlmF, RtAddrFamily, Rtm};
+//! use neli::mock::MockKernel;
+//! use neli::nl::Nlmsghdr;
+//! use neli::rtnl::{Ifinfomsg, Rtattrs};
+//! use neli::server::NlReply;
+//!
+//! # fn main() -> Result<(), neli::err::NlError> {
+//! let link = |index| {
+//!     Ifinfomsg::new(RtAddrFamily::Packet, Arphrd::Ether, index, vec![], Rtattrs::empty())
+//! };
+//!
+//! let kernel = MockKernel::new();
+//! kernel.expect(Rtm::Getlink, NlReply::dump(Rtm::Newlink, vec![link(2)])?);
+//!
+//! let mut socket = kernel.socket(true);
+//! let flags = vec![NlmF::Request, NlmF::Dump];
+//! socket.send_nl(Nlmsghdr::new(None, Rtm::Getlink, flags, None, None, link(0)))?;
+//! let links = socket.recv_all::<Rtm, Ifinfomsg>()?;
+//! assert_eq!(links[0].nl_payload.ifi_index, 2);
+//!
+//! kernel.verify()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Design decisions
+//!
+//! Expectations are matched strictly in order. A request that does not match the next
+//! expectation is answered with `EOPNOTSUPP`, leaves the expectation in place and is reported by
+//! `verify()`. When no reply is queued, receiving fails with `WouldBlock` instead of blocking the
+//! test forever.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use buffering::StreamReadBuffer;
+use libc;
+
+use consts::NlType;
+use err::{DeError, NlError, SerError};
+use nl::Nlmsghdr;
+use server::{payload_bytes, NlReply, NlRequest};
+use socket::{NlSocket, NlTransport};
+use Nl;
+
+/// Port ID of sockets created by a `MockKernel`
+pub const MOCK_PORT_ID: u32 = 0x4e4c;
+
+type Handler = Box<dyn FnMut(&NlRequest) -> Result<NlReply, NlError> + Send>;
+
+struct Expectation {
+    nl_type: u16,
+    payload: Option<Vec<u8>>,
+    handler: Handler,
+}
+
+impl Expectation {
+    fn matches(&self, req: &NlRequest) -> bool {
+        self.nl_type == req.msg.nl_type
+            && self
+                .payload
+                .as_ref()
+                .map(|payload| payload == &req.msg.nl_payload)
+                .unwrap_or(true)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    expectations: VecDeque<Expectation>,
+    requests: Vec<NlRequest>,
+    responses: VecDeque<Vec<u8>>,
+    unexpected: Vec<String>,
+}
+
+fn lock(state: &Arc<Mutex<State>>) -> MutexGuard<'_, State> {
+    // A panicking handler poisons the lock but leaves the state usable
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Scriptable stand-in for the kernel side of a netlink socket
+#[derive(Clone, Default)]
+pub struct MockKernel {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockKernel {
+    /// Create a mock kernel without expectations
+    pub fn new() -> Self {
+        MockKernel::default()
+    }
+
+    /// Create a socket talking to the mock kernel - all sockets of a mock kernel share its
+    /// script and queue of replies
+    pub fn socket(&self, track_seq: bool) -> NlSocket {
+        NlSocket::with_transport(
+            MockTransport {
+                state: Arc::clone(&self.state),
+            },
+            track_seq,
+        )
+    }
+
+    /// Expect a request of type `nl_type` with any payload and answer it with `reply`
+    pub fn expect<T>(&self, nl_type: T, reply: NlReply) -> &Self
+    where
+        T: NlType,
+    {
+        self.push(nl_type.into(), None, move |_| Ok(reply.clone()))
+    }
+
+    /// Expect a request of type `nl_type` with exactly the given payload and answer it with
+    /// `reply`
+    pub fn expect_request<T, P>(
+        &self,
+        nl_type: T,
+        payload: P,
+        reply: NlReply,
+    ) -> Result<&Self, SerError>
+    where
+        T: NlType,
+        P: Nl,
+    {
+        // Requests carry the payload without trailing padding
+        let mut bytes = payload_bytes(&payload)?;
+        bytes.truncate(payload.size());
+        Ok(self.push(nl_type.into(), Some(bytes), move |_| Ok(reply.clone())))
+    }
+
+    /// Expect a request of type `nl_type` and compute the reply with `handler`, eg. to inspect
+    /// the request. Errors returned by the handler are answered with `EINVAL`.
+    pub fn expect_with<T, F>(&self, nl_type: T, handler: F) -> &Self
+    where
+        T: NlType,
+        F: FnMut(&NlRequest) -> Result<NlReply, NlError> + Send + 'static,
+    {
+        self.push(nl_type.into(), None, handler)
+    }
+
+    fn push<F>(&self, nl_type: u16, payload: Option<Vec<u8>>, handler: F) -> &Self
+    where
+        F: FnMut(&NlRequest) -> Result<NlReply, NlError> + Send + 'static,
+    {
+        lock(&self.state).expectations.push_back(Expectation {
+            nl_type,
+            payload,
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Queue an unsolicited message, such as a multicast notification, for the sockets to
+    /// receive
+    pub fn event<T, P>(&self, nl_type: T, payload: P) -> Result<(), SerError>
+    where
+        T: NlType,
+        P: Nl,
+    {
+        let msg = payload_bytes(&Nlmsghdr::new(None, nl_type, vec![], None, None, payload))?;
+        lock(&self.state).responses.push_back(msg);
+        Ok(())
+    }
+
+    /// Take the requests received so far
+    pub fn take_requests(&self) -> Vec<NlRequest> {
+        lock(&self.state).requests.drain(..).collect()
+    }
+
+    /// Check that every expectation was met and that no unexpected request was received
+    pub fn verify(&self) -> Result<(), NlError> {
+        let state = lock(&self.state);
+        if let Some(msg) = state.unexpected.first() {
+            return Err(NlError::new(msg));
+        }
+        if let Some(expectation) = state.expectations.front() {
+            return Err(NlError::new(&format!(
+                "{} expected requests were not received, the next one of type {}",
+                state.expectations.len(),
+                expectation.nl_type
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct MockTransport {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockTransport {
+    fn handle(&self, req: &NlRequest) -> NlReply {
+        let expectation = {
+            let mut state = lock(&self.state);
+            let matches = state
+                .expectations
+                .front()
+                .map(|expectation| expectation.matches(req))
+                .unwrap_or(false);
+            if matches {
+                state.expectations.pop_front()
+            } else {
+                state.unexpected.push(format!(
+                    "Unexpected request of type {} with flags {:?}",
+                    req.msg.nl_type, req.msg.nl_flags
+                ));
+                None
+            }
+        };
+        match expectation {
+            // The lock is released while the handler runs so that it may use the mock kernel
+            Some(mut expectation) => {
+                (expectation.handler)(req).unwrap_or(NlReply::Error(libc::EINVAL))
+            }
+            None => NlReply::Error(libc::EOPNOTSUPP),
+        }
+    }
+}
+
+impl NlTransport for MockTransport {
+    fn send(&self, buf: &[u8], _flags: i32) -> Result<usize, io::Error> {
+        let to_io = |e: DeError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let mut mem = StreamReadBuffer::new(buf);
+        while !mem.at_end() {
+            let msg = Nlmsghdr::<u16, Vec<u8>>::deserialize(&mut mem).map_err(to_io)?;
+            let req = NlRequest {
+                port_id: MOCK_PORT_ID,
+                msg,
+            };
+            let reply = self.handle(&req);
+            let msgs = reply
+                .encode(&req)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+            let mut state = lock(&self.state);
+            state.responses.extend(msgs);
+            state.requests.push(req);
+        }
+        Ok(buf.len())
+    }
+
+    fn recv(&self, buf: &mut [u8], flags: i32) -> Result<usize, io::Error> {
+        let mut state = lock(&self.state);
+        let msg = if flags & libc::MSG_PEEK != 0 {
+            state.responses.front().cloned()
+        } else {
+            state.responses.pop_front()
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "No reply queued by the mock kernel",
+            )
+        })?;
+        let len = msg.len().min(buf.len());
+        buf[..len].copy_from_slice(&msg[..len]);
+        Ok(len)
+    }
+
+    fn port_id(&self) -> Result<u32, io::Error> {
+        Ok(MOCK_PORT_ID)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use consts::{Arphrd, Ifla, NlmF, RtAddrFamily, Rtm};
+    use rtnl::{Ifinfomsg, Rtattr, Rtattrs};
+
+    fn link(index: i32, name: &str) -> Ifinfomsg {
+        let mut payload = name.as_bytes().to_vec();
+        payload.push(0);
+        let attrs = vec![Rtattr {
+            rta_len: 4 + payload.len() as u16,
+            rta_type: Ifla::Ifname,
+            rta_payload: payload,
+        }];
+        Ifinfomsg::new(
+            RtAddrFamily::Packet,
+            Arphrd::Ether,
+            index,
+            vec![],
+            Rtattrs::new(attrs),
+        )
+    }
+
+    fn request<P>(socket: &mut NlSocket, nl_type: Rtm, flags: Vec<NlmF>, payload: P)
+    where
+        P: Nl,
+    {
+        socket
+            .send_nl(Nlmsghdr::new(None, nl_type, flags, None, None, payload))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_mock_script() {
+        let kernel = MockKernel::new();
+        kernel
+            .expect(
+                Rtm::Getlink,
+                NlReply::dump(Rtm::Newlink, vec![link(1, "lo"), link(2, "eth0")]).unwrap(),
+            )
+            .expect(Rtm::Setlink, NlReply::Ack)
+            .expect(Rtm::Dellink, NlReply::Error(libc::EPERM));
+        let mut socket = kernel.socket(true);
+
+        request(
+            &mut socket,
+            Rtm::Getlink,
+            vec![NlmF::Request, NlmF::Dump],
+            link(0, ""),
+        );
+        let links = socket.recv_all::<Rtm, Ifinfomsg>().unwrap();
+        let names = links
+            .iter()
+            .flat_map(|msg| msg.nl_payload.rtattrs.iter())
+            .filter(|attr| attr.rta_type == Ifla::Ifname)
+            .map(|attr| attr.rta_payload.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![b"lo\0".to_vec(), b"eth0\0".to_vec()]);
+
+        request(
+            &mut socket,
+            Rtm::Setlink,
+            vec![NlmF::Request, NlmF::Ack],
+            link(2, "eth0"),
+        );
+        socket.recv_ack().unwrap();
+
+        request(
+            &mut socket,
+            Rtm::Dellink,
+            vec![NlmF::Request, NlmF::Ack],
+            link(2, "eth0"),
+        );
+        match socket.recv_ack() {
+            Err(NlError::Msg(msg)) => {
+                assert_eq!(msg, io::Error::from_raw_os_error(libc::EPERM).to_string())
+            }
+            _ => panic!("Expected error"),
+        }
+
+        let requests = kernel.take_requests();
+        let seqs = requests
+            .iter()
+            .map(|req| req.msg.nl_seq)
+            .collect::<Vec<_>>();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        kernel.verify().unwrap();
+    }
+
+    #[test]
+    fn test_mock_request_payload() {
+        let kernel = MockKernel::new();
+        kernel
+            .expect_request(Rtm::Getlink, link(2, "eth0"), NlReply::Ack)
+            .unwrap();
+        kernel.expect_with(Rtm::Newlink, |req| {
+            let ifinfo = req.payload::<Ifinfomsg>()?;
+            NlReply::msg(Rtm::Newlink, link(ifinfo.ifi_index + 1, "eth1"))
+        });
+        let mut socket = kernel.socket(false);
+
+        request(
+            &mut socket,
+            Rtm::Getlink,
+            vec![NlmF::Request, NlmF::Ack],
+            link(2, "eth0"),
+        );
+        socket.recv_ack().unwrap();
+
+        request(
+            &mut socket,
+            Rtm::Newlink,
+            vec![NlmF::Request],
+            link(2, "eth0"),
+        );
+        let reply = socket.recv_all::<Rtm, Ifinfomsg>().unwrap();
+        assert_eq!(reply[0].nl_payload.ifi_index, 3);
+
+        kernel.event(Rtm::Dellink, link(3, "eth1")).unwrap();
+        let event = socket.recv_nl::<Rtm, Ifinfomsg>(None).unwrap();
+        assert_eq!(event.nl_type, Rtm::Dellink);
+        assert_eq!(event.nl_payload.ifi_index, 3);
+
+        kernel.verify().unwrap();
+    }
+
+    #[test]
+    fn test_mock_unexpected() {
+        let kernel = MockKernel::new();
+        kernel.expect(Rtm::Getlink, NlReply::Ack);
+        let mut socket = kernel.socket(false);
+
+        // Nothing queued yet
+        assert!(socket.recv_nl::<Rtm, Ifinfomsg>(None).is_err());
+
+        request(
+            &mut socket,
+            Rtm::Getaddr,
+            vec![NlmF::Request, NlmF::Ack],
+            link(0, ""),
+        );
+        assert!(socket.recv_ack().is_err());
+        assert!(kernel.verify().is_err());
+
+        request(
+            &mut socket,
+            Rtm::Getlink,
+            vec![NlmF::Request, NlmF::Ack],
+            link(0, ""),
+        );
+        socket.recv_ack().unwrap();
+        assert_eq!(kernel.take_requests().len(), 2);
+    }
+}
@@ -0,0 +1,253 @@
+//! Extended accounting (nfacct)
+//!
+//! This module contains the data structures for the `NFNL_SUBSYS_ACCT` netfilter subsystem and
+//! convenience methods on `NlSocket` for creating, querying, listing and deleting named packet
+//! and byte counters. The counters are updated by `nfacct` matches in iptables or nftables
+//! rules. The socket must be created with `NlFamily::Netfilter`.
+//!
+//! ```no_run
+//! use neli::consts::NlFamily;
+//! use neli::netfilter::acct::NfAcct;
+//! use neli::socket::NlSocket;
+//!
+//! let mut socket = NlSocket::connect(NlFamily::Netfilter, Some(0), None, true).unwrap();
+//! socket.nfacct_create(&NfAcct::new("http")).unwrap();
+//! // Read and zero the counters in one step
+//! let acct = socket.nfacct_get_and_reset("http").unwrap();
+//! println!("{} packets, {} bytes", acct.pkts, acct.bytes);
+//! ```
+//!
+//! # Design decisions
+//!
+//! Objects that exceed their quota are reported in `NetfilterMsg::AcctOverquota` messages to the
+//! `NfnlGrp::AcctQuota` multicast group. Their payload can be parsed with
+//! `NfAcct::from_nfgenmsg()`.
+
+use crate::consts::netfilter::{NetfilterMsg, NfAcctAttr, NfAcctF};
+use crate::consts::NlmF;
+use crate::err::{DeError, NlError, SerError};
+use crate::netfilter::{be32, be32_attr, be64, be64_attr, recv_reply, Nfgenmsg};
+use crate::nl::Nlmsghdr;
+use crate::nlattr::Nlattr;
+use crate::socket::NlSocket;
+
+type NfAcctAttrs = Vec<Nlattr<NfAcctAttr, Vec<u8>>>;
+
+/// Quota of an accounting object. Once the counter exceeds the quota, the object is marked as
+/// over quota and an event is sent to the `NfnlGrp::AcctQuota` group.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NfAcctQuota {
+    /// Quota in packets.
+    Packets(u64),
+    /// Quota in bytes.
+    Bytes(u64),
+}
+
+/// A named accounting object.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NfAcct {
+    /// Name of the object.
+    pub name: String,
+    /// Packet counter.
+    pub pkts: u64,
+    /// Byte counter.
+    pub bytes: u64,
+    /// Optional quota.
+    pub quota: Option<NfAcctQuota>,
+    /// Whether the quota has been exceeded.
+    pub overquota: bool,
+    /// Reference count of the object, including the reference held by the kernel itself. Only
+    /// set in messages from the kernel.
+    pub use_count: Option<u32>,
+}
+
+impl NfAcct {
+    /// Create an accounting object with zeroed counters and no quota.
+    pub fn new(name: &str) -> Self {
+        NfAcct {
+            name: name.to_string(),
+            pkts: 0,
+            bytes: 0,
+            quota: None,
+            overquota: false,
+            use_count: None,
+        }
+    }
+
+    /// Encode the object as the attributes of a `NetfilterMsg::AcctNew` message.
+    pub fn to_attrs(&self) -> Result<NfAcctAttrs, SerError> {
+        let mut attrs = vec![
+            Nlattr::new(None, NfAcctAttr::Name, self.name.as_str())?,
+            be64_attr(NfAcctAttr::Pkts, self.pkts)?,
+            be64_attr(NfAcctAttr::Bytes, self.bytes)?,
+        ];
+        if let Some(ref quota) = self.quota {
+            let (flag, value) = match *quota {
+                NfAcctQuota::Packets(value) => (NfAcctF::QuotaPkts, value),
+                NfAcctQuota::Bytes(value) => (NfAcctF::QuotaBytes, value),
+            };
+            attrs.push(be32_attr(NfAcctAttr::Flags, u32::from(flag))?);
+            attrs.push(be64_attr(NfAcctAttr::Quota, value)?);
+        }
+        Ok(attrs)
+    }
+
+    /// Parse an accounting object from a message sent by the kernel.
+    pub fn from_nfgenmsg(msg: &Nfgenmsg<NfAcctAttr>) -> Result<Self, DeError> {
+        let mut acct = NfAcct::new("");
+        let mut flags = 0;
+        let mut quota = None;
+        for attr in msg.get_attr_handle().iter() {
+            match attr.nla_type {
+                NfAcctAttr::Name => acct.name = attr.get_payload_as()?,
+                NfAcctAttr::Pkts => acct.pkts = be64(attr)?,
+                NfAcctAttr::Bytes => acct.bytes = be64(attr)?,
+                NfAcctAttr::Use => acct.use_count = Some(be32(attr)?),
+                NfAcctAttr::Flags => flags = be32(attr)?,
+                NfAcctAttr::Quota => quota = Some(be64(attr)?),
+                _ => (),
+            }
+        }
+        acct.overquota = flags & u32::from(NfAcctF::Overquota) != 0;
+        acct.quota = quota.and_then(|value| {
+            if flags & u32::from(NfAcctF::QuotaPkts) != 0 {
+                Some(NfAcctQuota::Packets(value))
+            } else if flags & u32::from(NfAcctF::QuotaBytes) != 0 {
+                Some(NfAcctQuota::Bytes(value))
+            } else {
+                None
+            }
+        });
+        Ok(acct)
+    }
+}
+
+fn name_attrs(name: &str) -> Result<NfAcctAttrs, SerError> {
+    Ok(vec![Nlattr::new(None, NfAcctAttr::Name, name)?])
+}
+
+impl NlSocket {
+    /// Create the accounting object `acct`. Fails with `EBUSY` if an object with the same name
+    /// already exists.
+    pub fn nfacct_create(&mut self, acct: &NfAcct) -> Result<(), NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::AcctNew,
+            vec![NlmF::Request, NlmF::Create, NlmF::Ack],
+            None,
+            None,
+            Nfgenmsg::new(libc::AF_UNSPEC as u8, 0, acct.to_attrs()?),
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+
+    /// Query the accounting object `name`.
+    pub fn nfacct_get(&mut self, name: &str) -> Result<NfAcct, NlError> {
+        self.nfacct_query(NetfilterMsg::AcctGet, name)
+    }
+
+    /// Query the accounting object `name` and atomically reset its counters.
+    pub fn nfacct_get_and_reset(&mut self, name: &str) -> Result<NfAcct, NlError> {
+        self.nfacct_query(NetfilterMsg::AcctGetCtrZero, name)
+    }
+
+    /// List all accounting objects, atomically resetting their counters if `reset` is `true`.
+    pub fn nfacct_list(&mut self, reset: bool) -> Result<Vec<NfAcct>, NlError> {
+        let nl_type = if reset {
+            NetfilterMsg::AcctGetCtrZero
+        } else {
+            NetfilterMsg::AcctGet
+        };
+        let nlhdr = Nlmsghdr::new(
+            None,
+            nl_type,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            Nfgenmsg::new(libc::AF_UNSPEC as u8, 0, NfAcctAttrs::new()),
+        );
+        self.send_nl(nlhdr)?;
+        let msgs = self.recv_all::<NetfilterMsg, Nfgenmsg<NfAcctAttr>>()?;
+        let mut objs = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            objs.push(NfAcct::from_nfgenmsg(&msg.nl_payload)?);
+        }
+        Ok(objs)
+    }
+
+    /// Delete the accounting object `name`, or all objects that are not referenced by any rule
+    /// if `name` is `None`.
+    pub fn nfacct_delete(&mut self, name: Option<&str>) -> Result<(), NlError> {
+        let attrs = match name {
+            Some(name) => name_attrs(name)?,
+            None => NfAcctAttrs::new(),
+        };
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::AcctDel,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            Nfgenmsg::new(libc::AF_UNSPEC as u8, 0, attrs),
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+
+    fn nfacct_query(&mut self, nl_type: NetfilterMsg, name: &str) -> Result<NfAcct, NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            nl_type,
+            vec![NlmF::Request],
+            None,
+            None,
+            Nfgenmsg::new(libc::AF_UNSPEC as u8, 0, name_attrs(name)?),
+        );
+        self.send_nl(nlhdr)?;
+        let reply = recv_reply::<NfAcctAttr>(self)?;
+        Ok(NfAcct::from_nfgenmsg(&reply)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::round_trip;
+
+    #[test]
+    fn test_nfacct_round_trip() {
+        let acct = NfAcct {
+            pkts: 10,
+            bytes: 1500,
+            quota: Some(NfAcctQuota::Bytes(1 << 20)),
+            ..NfAcct::new("http")
+        };
+        let msg = Nfgenmsg::new(libc::AF_UNSPEC as u8, 0, acct.to_attrs().unwrap());
+        let parsed = round_trip(&msg);
+        assert_eq!(NfAcct::from_nfgenmsg(&parsed).unwrap(), acct);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_nfacct() {
+        let mut s =
+            NlSocket::connect(crate::consts::NlFamily::Netfilter, Some(0), None, true).unwrap();
+        let acct = NfAcct {
+            pkts: 3,
+            ..NfAcct::new("neli_test")
+        };
+        // Left over by an earlier failed run
+        let _ = s.nfacct_delete(Some("neli_test"));
+        s.nfacct_create(&acct).unwrap();
+        assert!(s
+            .nfacct_list(false)
+            .unwrap()
+            .iter()
+            .any(|a| a.name == "neli_test" && a.pkts == 3));
+        assert_eq!(s.nfacct_get_and_reset("neli_test").unwrap().pkts, 3);
+        assert_eq!(s.nfacct_get("neli_test").unwrap().pkts, 0);
+        s.nfacct_delete(Some("neli_test")).unwrap();
+    }
+}
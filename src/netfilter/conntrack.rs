@@ -0,0 +1,659 @@
+//! Connection tracking (ctnetlink)
+//!
+//! This module contains the data structures for the `NFNL_SUBSYS_CTNETLINK` netfilter subsystem
+//! and convenience methods on `NlSocket` for dumping, querying and deleting conntrack entries as
+//! well as subscribing to conntrack events. The socket must be created with
+//! `NlFamily::Netfilter`.
+//!
+//! ```no_run
+//! use neli::consts::NlFamily;
+//! use neli::netfilter::conntrack::CtEventType;
+//! use neli::socket::NlSocket;
+//!
+//! let mut socket = NlSocket::connect(NlFamily::Netfilter, Some(0), None, true).unwrap();
+//! for ct in socket.ct_dump(libc::AF_INET as u8).unwrap() {
+//!     println!("{:?}", ct);
+//! }
+//!
+//! let mut events = NlSocket::connect(NlFamily::Netfilter, None, None, false).unwrap();
+//! events
+//!     .ct_subscribe(vec![CtEventType::New, CtEventType::Destroy])
+//!     .unwrap();
+//! ```
+//!
+//! # Design decisions
+//!
+//! Conntrack messages are `Nfgenmsg<CtAttr>` payloads. `Conntrack` is a parsed view of the
+//! attributes - attributes that are not decoded are ignored. Multi-byte values in ctnetlink
+//! attributes are big endian and are converted to host byte order in `Conntrack`.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::consts::netfilter::{
+    CtAttr, CtCountersAttr, CtIpAttr, CtProtoAttr, CtProtoinfoAttr, CtProtoinfoTcpAttr, CtStatus,
+    CtTupleAttr, NetfilterMsg, NfnlGrp, TcpConntrackState,
+};
+use crate::consts::NlmF;
+use crate::err::{DeError, NlError, SerError};
+use crate::netfilter::{recv_reply, Nfgenmsg};
+use crate::nl::Nlmsghdr;
+use crate::nlattr::{AttrHandle, Nlattr};
+use crate::socket::NlSocket;
+
+type CtAttrs = Vec<Nlattr<CtAttr, Vec<u8>>>;
+
+fn addr_from_payload(payload: &[u8]) -> Result<IpAddr, DeError> {
+    if payload.len() == 4 {
+        let mut octets = [0; 4];
+        octets.copy_from_slice(payload);
+        Ok(IpAddr::from(Ipv4Addr::from(octets)))
+    } else if payload.len() == 16 {
+        let mut octets = [0; 16];
+        octets.copy_from_slice(payload);
+        Ok(IpAddr::from(Ipv6Addr::from(octets)))
+    } else {
+        Err(DeError::new("Invalid IP address length in conntrack tuple"))
+    }
+}
+
+/// Layer 4 part of a conntrack tuple.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CtProtoTuple {
+    /// IP protocol number (eg. `IPPROTO_TCP`).
+    pub num: u8,
+    /// Source port for protocols with ports.
+    pub src_port: Option<u16>,
+    /// Destination port for protocols with ports.
+    pub dst_port: Option<u16>,
+    /// ICMP (or ICMPv6) echo ID.
+    pub icmp_id: Option<u16>,
+    /// ICMP (or ICMPv6) type.
+    pub icmp_type: Option<u8>,
+    /// ICMP (or ICMPv6) code.
+    pub icmp_code: Option<u8>,
+}
+
+impl CtProtoTuple {
+    fn to_attr(&self) -> Result<Nlattr<CtTupleAttr, Vec<u8>>, SerError> {
+        let mut attrs = vec![Nlattr::new(None, CtProtoAttr::Num, self.num)?];
+        if let Some(port) = self.src_port {
+            attrs.push(Nlattr::new(None, CtProtoAttr::SrcPort, port.to_be())?);
+        }
+        if let Some(port) = self.dst_port {
+            attrs.push(Nlattr::new(None, CtProtoAttr::DstPort, port.to_be())?);
+        }
+        let (id, ty, code) = if self.num == libc::IPPROTO_ICMPV6 as u8 {
+            (
+                CtProtoAttr::Icmpv6Id,
+                CtProtoAttr::Icmpv6Type,
+                CtProtoAttr::Icmpv6Code,
+            )
+        } else {
+            (
+                CtProtoAttr::IcmpId,
+                CtProtoAttr::IcmpType,
+                CtProtoAttr::IcmpCode,
+            )
+        };
+        if let Some(icmp_id) = self.icmp_id {
+            attrs.push(Nlattr::new(None, id, icmp_id.to_be())?);
+        }
+        if let Some(icmp_type) = self.icmp_type {
+            attrs.push(Nlattr::new(None, ty, icmp_type)?);
+        }
+        if let Some(icmp_code) = self.icmp_code {
+            attrs.push(Nlattr::new(None, code, icmp_code)?);
+        }
+        Nlattr::new_nested(CtTupleAttr::Proto, &attrs)
+    }
+
+    fn from_attrs(handle: &AttrHandle<CtProtoAttr>) -> Result<Self, DeError> {
+        let mut proto = CtProtoTuple::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                CtProtoAttr::Num => proto.num = attr.get_payload_as()?,
+                CtProtoAttr::SrcPort => proto.src_port = Some(u16::from_be(attr.get_payload_as()?)),
+                CtProtoAttr::DstPort => proto.dst_port = Some(u16::from_be(attr.get_payload_as()?)),
+                CtProtoAttr::IcmpId | CtProtoAttr::Icmpv6Id => {
+                    proto.icmp_id = Some(u16::from_be(attr.get_payload_as()?))
+                }
+                CtProtoAttr::IcmpType | CtProtoAttr::Icmpv6Type => {
+                    proto.icmp_type = Some(attr.get_payload_as()?)
+                }
+                CtProtoAttr::IcmpCode | CtProtoAttr::Icmpv6Code => {
+                    proto.icmp_code = Some(attr.get_payload_as()?)
+                }
+                _ => (),
+            }
+        }
+        Ok(proto)
+    }
+}
+
+/// A conntrack tuple, describing one direction of a connection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CtTuple {
+    /// Source address.
+    pub src: IpAddr,
+    /// Destination address.
+    pub dst: IpAddr,
+    /// Layer 4 protocol information.
+    pub proto: CtProtoTuple,
+    /// Conntrack zone of this direction, if it differs from the default.
+    pub zone: Option<u16>,
+}
+
+impl CtTuple {
+    /// Encode the tuple as the nested attribute `attr_type` (eg. [CtAttr::TupleOrig]).
+    pub fn to_attr(&self, attr_type: CtAttr) -> Result<Nlattr<CtAttr, Vec<u8>>, SerError> {
+        let ip = match (self.src, self.dst) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => [
+                Nlattr::new(None, CtIpAttr::V4Src, &src.octets()[..])?,
+                Nlattr::new(None, CtIpAttr::V4Dst, &dst.octets()[..])?,
+            ],
+            (IpAddr::V6(src), IpAddr::V6(dst)) => [
+                Nlattr::new(None, CtIpAttr::V6Src, &src.octets()[..])?,
+                Nlattr::new(None, CtIpAttr::V6Dst, &dst.octets()[..])?,
+            ],
+            _ => {
+                return Err(SerError::new(
+                    "Source and destination of a conntrack tuple must be of the same family",
+                ))
+            }
+        };
+        let mut attrs = vec![
+            Nlattr::new_nested(CtTupleAttr::Ip, &ip)?,
+            self.proto.to_attr()?,
+        ];
+        if let Some(zone) = self.zone {
+            attrs.push(Nlattr::new(None, CtTupleAttr::Zone, zone.to_be())?);
+        }
+        Nlattr::new_nested(attr_type, &attrs)
+    }
+
+    /// Decode the tuple from the attributes nested in a tuple attribute.
+    pub fn from_attrs(handle: &AttrHandle<CtTupleAttr>) -> Result<Self, DeError> {
+        let mut src = None;
+        let mut dst = None;
+        let mut proto = None;
+        let mut zone = None;
+        for attr in handle.iter() {
+            match attr.nla_type {
+                CtTupleAttr::Ip => {
+                    for ip in attr.get_nested_attributes::<CtIpAttr>()?.iter() {
+                        match ip.nla_type {
+                            CtIpAttr::V4Src | CtIpAttr::V6Src => {
+                                src = Some(addr_from_payload(&ip.payload)?)
+                            }
+                            CtIpAttr::V4Dst | CtIpAttr::V6Dst => {
+                                dst = Some(addr_from_payload(&ip.payload)?)
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                CtTupleAttr::Proto => {
+                    proto = Some(CtProtoTuple::from_attrs(
+                        &attr.get_nested_attributes::<CtProtoAttr>()?,
+                    )?)
+                }
+                CtTupleAttr::Zone => zone = Some(u16::from_be(attr.get_payload_as()?)),
+                _ => (),
+            }
+        }
+        match (src, dst) {
+            (Some(src), Some(dst)) => Ok(CtTuple {
+                src,
+                dst,
+                proto: proto.unwrap_or_default(),
+                zone,
+            }),
+            _ => Err(DeError::new("Conntrack tuple is missing an address")),
+        }
+    }
+}
+
+/// Packet and byte counters of one direction of a connection.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CtCounters {
+    /// Number of packets.
+    pub packets: u64,
+    /// Number of bytes.
+    pub bytes: u64,
+}
+
+impl CtCounters {
+    fn from_attrs(handle: &AttrHandle<CtCountersAttr>) -> Result<Self, DeError> {
+        let mut counters = CtCounters::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                CtCountersAttr::Packets => counters.packets = u64::from_be(attr.get_payload_as()?),
+                CtCountersAttr::Bytes => counters.bytes = u64::from_be(attr.get_payload_as()?),
+                CtCountersAttr::Packets32 => {
+                    counters.packets = u64::from(u32::from_be(attr.get_payload_as()?))
+                }
+                CtCountersAttr::Bytes32 => {
+                    counters.bytes = u64::from(u32::from_be(attr.get_payload_as()?))
+                }
+                _ => (),
+            }
+        }
+        Ok(counters)
+    }
+}
+
+/// TCP specific protocol information of a connection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CtProtoinfoTcp {
+    /// State of the TCP connection.
+    pub state: TcpConntrackState,
+    /// Window scale of the original direction.
+    pub wscale_orig: Option<u8>,
+    /// Window scale of the reply direction.
+    pub wscale_reply: Option<u8>,
+}
+
+impl CtProtoinfoTcp {
+    fn from_attrs(handle: &AttrHandle<CtProtoinfoTcpAttr>) -> Result<Self, DeError> {
+        let mut tcp = CtProtoinfoTcp {
+            state: TcpConntrackState::None,
+            wscale_orig: None,
+            wscale_reply: None,
+        };
+        for attr in handle.iter() {
+            match attr.nla_type {
+                CtProtoinfoTcpAttr::State => tcp.state = attr.get_payload_as()?,
+                CtProtoinfoTcpAttr::WscaleOriginal => {
+                    tcp.wscale_orig = Some(attr.get_payload_as()?)
+                }
+                CtProtoinfoTcpAttr::WscaleReply => tcp.wscale_reply = Some(attr.get_payload_as()?),
+                _ => (),
+            }
+        }
+        Ok(tcp)
+    }
+}
+
+/// A conntrack entry.
+///
+/// Fields that were not present in the message are `None` or empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Conntrack {
+    /// Address family of the connection (`AF_INET` or `AF_INET6`).
+    pub family: u8,
+    /// Tuple of the original direction.
+    pub orig: Option<CtTuple>,
+    /// Tuple of the reply direction.
+    pub reply: Option<CtTuple>,
+    /// Status bits of the connection.
+    pub status: Vec<CtStatus>,
+    /// Seconds until the entry expires.
+    pub timeout: Option<u32>,
+    /// Connection mark.
+    pub mark: Option<u32>,
+    /// Unique ID of the entry.
+    pub id: Option<u32>,
+    /// Reference count of the entry.
+    pub use_count: Option<u32>,
+    /// Counters of the original direction, if accounting is enabled.
+    pub counters_orig: Option<CtCounters>,
+    /// Counters of the reply direction, if accounting is enabled.
+    pub counters_reply: Option<CtCounters>,
+    /// Conntrack zone.
+    pub zone: Option<u16>,
+    /// TCP protocol information, for TCP connections.
+    pub tcp: Option<CtProtoinfoTcp>,
+}
+
+impl Conntrack {
+    /// Parse a conntrack entry from a ctnetlink message payload.
+    pub fn from_nfgenmsg(msg: &Nfgenmsg<CtAttr>) -> Result<Self, DeError> {
+        let mut ct = Conntrack::from_attrs(&msg.get_attr_handle())?;
+        ct.family = msg.family;
+        Ok(ct)
+    }
+
+    /// Parse a conntrack entry from ctnetlink attributes.
+    pub fn from_attrs(handle: &AttrHandle<CtAttr>) -> Result<Self, DeError> {
+        let mut ct = Conntrack::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                CtAttr::TupleOrig => {
+                    ct.orig = Some(CtTuple::from_attrs(&attr.get_nested_attributes()?)?)
+                }
+                CtAttr::TupleReply => {
+                    ct.reply = Some(CtTuple::from_attrs(&attr.get_nested_attributes()?)?)
+                }
+                CtAttr::Status => {
+                    let status = u32::from_be(attr.get_payload_as()?);
+                    for i in 0..32 {
+                        let bit = 1 << i;
+                        if bit & status == bit {
+                            ct.status.push(bit.into());
+                        }
+                    }
+                }
+                CtAttr::Timeout => ct.timeout = Some(u32::from_be(attr.get_payload_as()?)),
+                CtAttr::Mark => ct.mark = Some(u32::from_be(attr.get_payload_as()?)),
+                CtAttr::Id => ct.id = Some(u32::from_be(attr.get_payload_as()?)),
+                CtAttr::Use => ct.use_count = Some(u32::from_be(attr.get_payload_as()?)),
+                CtAttr::CountersOrig => {
+                    ct.counters_orig = Some(CtCounters::from_attrs(&attr.get_nested_attributes()?)?)
+                }
+                CtAttr::CountersReply => {
+                    ct.counters_reply =
+                        Some(CtCounters::from_attrs(&attr.get_nested_attributes()?)?)
+                }
+                CtAttr::Zone => ct.zone = Some(u16::from_be(attr.get_payload_as()?)),
+                CtAttr::Protoinfo => {
+                    let protoinfo = attr.get_nested_attributes::<CtProtoinfoAttr>()?;
+                    if let Some(tcp) = protoinfo.get_attribute(CtProtoinfoAttr::Tcp) {
+                        ct.tcp = Some(CtProtoinfoTcp::from_attrs(&tcp.get_nested_attributes()?)?);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(ct)
+    }
+
+    /// Encode the entry as ctnetlink attributes - only fields that are set are encoded and the
+    /// read-only counters, use count and protocol information are never encoded.
+    pub fn to_attrs(&self) -> Result<Vec<Nlattr<CtAttr, Vec<u8>>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ref orig) = self.orig {
+            attrs.push(orig.to_attr(CtAttr::TupleOrig)?);
+        }
+        if let Some(ref reply) = self.reply {
+            attrs.push(reply.to_attr(CtAttr::TupleReply)?);
+        }
+        if !self.status.is_empty() {
+            let status = self.status.iter().fold(0, |acc: u32, next| {
+                let v: u32 = next.into();
+                acc | v
+            });
+            attrs.push(Nlattr::new(None, CtAttr::Status, status.to_be())?);
+        }
+        if let Some(timeout) = self.timeout {
+            attrs.push(Nlattr::new(None, CtAttr::Timeout, timeout.to_be())?);
+        }
+        if let Some(mark) = self.mark {
+            attrs.push(Nlattr::new(None, CtAttr::Mark, mark.to_be())?);
+        }
+        if let Some(id) = self.id {
+            attrs.push(Nlattr::new(None, CtAttr::Id, id.to_be())?);
+        }
+        if let Some(zone) = self.zone {
+            attrs.push(Nlattr::new(None, CtAttr::Zone, zone.to_be())?);
+        }
+        Ok(attrs)
+    }
+
+    fn msg_family(&self) -> u8 {
+        if self.family != 0 {
+            return self.family;
+        }
+        match self.orig.as_ref().or(self.reply.as_ref()) {
+            Some(CtTuple {
+                src: IpAddr::V6(_), ..
+            }) => libc::AF_INET6 as u8,
+            Some(_) => libc::AF_INET as u8,
+            None => libc::AF_UNSPEC as u8,
+        }
+    }
+}
+
+/// Type of a conntrack event.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CtEventType {
+    /// A new connection was created.
+    New,
+    /// An existing connection was updated.
+    Update,
+    /// A connection was destroyed.
+    Destroy,
+}
+
+impl CtEventType {
+    fn group(&self) -> NfnlGrp {
+        match *self {
+            CtEventType::New => NfnlGrp::ConntrackNew,
+            CtEventType::Update => NfnlGrp::ConntrackUpdate,
+            CtEventType::Destroy => NfnlGrp::ConntrackDestroy,
+        }
+    }
+}
+
+/// A conntrack event received from a socket subscribed with `NlSocket.ct_subscribe()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CtEvent {
+    /// What happened to the entry.
+    pub event: CtEventType,
+    /// The entry the event is about.
+    pub conntrack: Conntrack,
+}
+
+impl CtEvent {
+    /// Parse a conntrack event from a received message.
+    pub fn from_msg(msg: &Nlmsghdr<NetfilterMsg, Nfgenmsg<CtAttr>>) -> Result<Self, DeError> {
+        let event = match msg.nl_type {
+            NetfilterMsg::CtNew => {
+                let flags = msg.nl_flags.iter().fold(0, |acc: u16, next| {
+                    let v: u16 = next.into();
+                    acc | v
+                });
+                if flags & (libc::NLM_F_CREATE | libc::NLM_F_EXCL) as u16 != 0 {
+                    CtEventType::New
+                } else {
+                    CtEventType::Update
+                }
+            }
+            NetfilterMsg::CtDelete => CtEventType::Destroy,
+            _ => return Err(DeError::new("Message is not a conntrack event")),
+        };
+        Ok(CtEvent {
+            event,
+            conntrack: Conntrack::from_nfgenmsg(&msg.nl_payload)?,
+        })
+    }
+}
+
+impl NlSocket {
+    /// Dump all conntrack entries of the given address family (`AF_UNSPEC` for all of them).
+    pub fn ct_dump(&mut self, family: u8) -> Result<Vec<Conntrack>, NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::CtGet,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            Nfgenmsg::new(family, 0, CtAttrs::new()),
+        );
+        self.send_nl(nlhdr)?;
+        let msgs = self.recv_all::<NetfilterMsg, Nfgenmsg<CtAttr>>()?;
+        let mut entries = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            entries.push(Conntrack::from_nfgenmsg(&msg.nl_payload)?);
+        }
+        Ok(entries)
+    }
+
+    /// Look up the conntrack entry matching the original tuple (or reply tuple) and zone of `ct`.
+    pub fn ct_get(&mut self, ct: &Conntrack) -> Result<Conntrack, NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::CtGet,
+            vec![NlmF::Request],
+            None,
+            None,
+            Nfgenmsg::new(ct.msg_family(), 0, ct.to_attrs()?),
+        );
+        self.send_nl(nlhdr)?;
+        // The reply is flagged NLM_F_MULTI but not followed by NLMSG_DONE
+        let reply = recv_reply::<CtAttr>(self)?;
+        Ok(Conntrack::from_nfgenmsg(&reply)?)
+    }
+
+    /// Delete the conntrack entry matching the tuples, ID and zone of `ct`.
+    pub fn ct_delete(&mut self, ct: &Conntrack) -> Result<(), NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::CtDelete,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            Nfgenmsg::new(ct.msg_family(), 0, ct.to_attrs()?),
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+
+    /// Delete all conntrack entries of the given address family (`AF_UNSPEC` for all of them).
+    pub fn ct_flush(&mut self, family: u8) -> Result<(), NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::CtDelete,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            Nfgenmsg::new(family, 0, CtAttrs::new()),
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+
+    /// Subscribe to conntrack events of the given types. Events can be parsed with
+    /// `CtEvent::from_msg()`.
+    pub fn ct_subscribe(&mut self, events: Vec<CtEventType>) -> Result<(), NlError> {
+        let groups = events.iter().map(|e| e.group().into()).collect();
+        self.set_mcast_groups(groups)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::os::unix::net::UnixDatagram;
+
+    use crate::{round_trip, Nl, StreamReadBuffer, StreamWriteBuffer};
+
+    fn tcp_tuple(src: [u8; 4], dst: [u8; 4], sport: u16, dport: u16) -> CtTuple {
+        CtTuple {
+            src: IpAddr::from(src),
+            dst: IpAddr::from(dst),
+            proto: CtProtoTuple {
+                num: libc::IPPROTO_TCP as u8,
+                src_port: Some(sport),
+                dst_port: Some(dport),
+                ..CtProtoTuple::default()
+            },
+            zone: None,
+        }
+    }
+
+    #[test]
+    fn test_conntrack_round_trip() {
+        let ct = Conntrack {
+            family: libc::AF_INET as u8,
+            orig: Some(tcp_tuple([10, 0, 0, 1], [10, 0, 0, 2], 40000, 80)),
+            reply: Some(tcp_tuple([10, 0, 0, 2], [10, 0, 0, 1], 80, 40000)),
+            status: vec![CtStatus::SeenReply, CtStatus::Assured],
+            timeout: Some(120),
+            mark: Some(5),
+            id: Some(1234),
+            zone: Some(2),
+            ..Conntrack::default()
+        };
+        let msg = Nfgenmsg::new(libc::AF_INET as u8, 0, ct.to_attrs().unwrap());
+        let parsed = round_trip(&msg);
+        assert_eq!(Conntrack::from_nfgenmsg(&parsed).unwrap(), ct);
+    }
+
+    #[test]
+    fn test_conntrack_nested_flag() {
+        let mut tuple = tcp_tuple([10, 0, 0, 1], [10, 0, 0, 2], 40000, 80)
+            .to_attr(CtAttr::TupleOrig)
+            .unwrap();
+        let mut counters = Nlattr::new_nested(
+            CtAttr::CountersOrig,
+            &[
+                Nlattr::new(None, CtCountersAttr::Packets, 3u64.to_be()).unwrap(),
+                Nlattr::new(None, CtCountersAttr::Bytes, 180u64.to_be()).unwrap(),
+            ],
+        )
+        .unwrap();
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        for attr in [&mut tuple, &mut counters].iter_mut() {
+            attr.nla_type =
+                CtAttr::UnrecognizedVariant(u16::from(&attr.nla_type) | libc::NLA_F_NESTED as u16);
+            attr.serialize(&mut mem).unwrap();
+        }
+
+        let mut buf = StreamReadBuffer::new(mem.as_ref());
+        let attrs = CtAttrs::deserialize(&mut buf).unwrap();
+        let ct = Conntrack::from_attrs(&AttrHandle::new(attrs)).unwrap();
+        assert_eq!(
+            ct.orig,
+            Some(tcp_tuple([10, 0, 0, 1], [10, 0, 0, 2], 40000, 80))
+        );
+        assert_eq!(
+            ct.counters_orig,
+            Some(CtCounters {
+                packets: 3,
+                bytes: 180
+            })
+        );
+    }
+
+    #[test]
+    fn test_ct_event_type() {
+        let msg = Nlmsghdr::new(
+            None,
+            NetfilterMsg::CtNew,
+            vec![NlmF::Create, NlmF::Excl],
+            None,
+            None,
+            Nfgenmsg::new(libc::AF_INET as u8, 0, CtAttrs::new()),
+        );
+        assert_eq!(CtEvent::from_msg(&msg).unwrap().event, CtEventType::New);
+    }
+
+    #[test]
+    fn test_ct_get_multi_reply() {
+        let (transport, kernel) = UnixDatagram::pair().unwrap();
+        // Fail instead of blocking if the socket waits for an NLMSG_DONE that never comes
+        transport.set_nonblocking(true).unwrap();
+        let mut s = NlSocket::with_transport(transport, false);
+
+        let ct = Conntrack {
+            family: libc::AF_INET as u8,
+            orig: Some(tcp_tuple([10, 0, 0, 1], [10, 0, 0, 2], 40000, 80)),
+            reply: Some(tcp_tuple([10, 0, 0, 2], [10, 0, 0, 1], 80, 40000)),
+            id: Some(1234),
+            ..Conntrack::default()
+        };
+        let reply = Nlmsghdr::new(
+            None,
+            NetfilterMsg::CtNew,
+            vec![NlmF::Multi],
+            None,
+            None,
+            Nfgenmsg::new(libc::AF_INET as u8, 0, ct.to_attrs().unwrap()),
+        );
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        reply.serialize(&mut mem).unwrap();
+        kernel.send(mem.as_ref()).unwrap();
+
+        assert_eq!(s.ct_get(&ct).unwrap(), ct);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_ct_dump() {
+        let mut s =
+            NlSocket::connect(crate::consts::NlFamily::Netfilter, Some(0), None, true).unwrap();
+        s.ct_dump(libc::AF_INET as u8).unwrap();
+    }
+}
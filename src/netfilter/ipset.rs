@@ -0,0 +1,639 @@
+//! IP sets (ipset)
+//!
+//! This module contains the data structures for the `NFNL_SUBSYS_IPSET` netfilter subsystem and
+//! convenience methods on `NlSocket` for creating, destroying, flushing and listing sets as well
+//! as adding, deleting and testing entries. The `hash:ip`, `hash:net` and `bitmap:port` set types
+//! are supported. The socket must be created with `NlFamily::Netfilter`.
+//!
+//! ```no_run
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! use neli::consts::netfilter::NfProto;
+//! use neli::consts::NlFamily;
+//! use neli::netfilter::ipset::{Ipset, IpsetEntry, IpsetType};
+//! use neli::socket::NlSocket;
+//!
+//! let mut socket = NlSocket::connect(NlFamily::Netfilter, Some(0), None, true).unwrap();
+//! let set = Ipset::new("blocklist", IpsetType::HashNet, NfProto::Ipv4);
+//! socket.ipset_create(&set).unwrap();
+//! let entry = IpsetEntry::net(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 0)), 24);
+//! socket.ipset_add("blocklist", &entry).unwrap();
+//! assert!(socket
+//!     .ipset_test("blocklist", &IpsetEntry::ip(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))))
+//!     .unwrap());
+//! for set in socket.ipset_list(None).unwrap() {
+//!     println!("{}: {:?}", set.name, set.entries);
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! The ipset kernel module is stricter than the rest of netfilter: nested attributes must carry
+//! `NLA_F_NESTED` and big endian integers and addresses must carry `NLA_F_NET_BYTEORDER`. The
+//! encoders in this module set these flags. A set that does not fit into a single message is
+//! listed over several messages, `Ipset::from_dump()` merges them back into one `Ipset`.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::consts::netfilter::{
+    IpsetAdtAttr, IpsetAttr, IpsetCreateAttr, IpsetIpAttr, NetfilterMsg, NfProto,
+};
+use crate::consts::{self, NlmF};
+use crate::err::{DeError, NlError, Nlmsgerr, SerError};
+use crate::netfilter::Nfgenmsg;
+use crate::nl::Nlmsghdr;
+use crate::nlattr::{AttrHandle, Nlattr};
+use crate::socket::NlSocket;
+use crate::{Nl, StreamReadBuffer};
+
+/// Protocol version spoken to the kernel. Version 6 is understood by all kernels with ipset
+/// support.
+const IPSET_PROTOCOL: u8 = 6;
+
+// Error codes specific to ipset from `linux/netfilter/ipset/ip_set.h`
+const IPSET_ERR_PROTOCOL: i32 = 4097;
+const IPSET_ERR_FIND_TYPE: i32 = 4098;
+const IPSET_ERR_MAX_SETS: i32 = 4099;
+const IPSET_ERR_BUSY: i32 = 4100;
+const IPSET_ERR_EXIST_SETNAME2: i32 = 4101;
+const IPSET_ERR_TYPE_MISMATCH: i32 = 4102;
+const IPSET_ERR_EXIST: i32 = 4103;
+const IPSET_ERR_INVALID_CIDR: i32 = 4104;
+const IPSET_ERR_INVALID_NETMASK: i32 = 4105;
+const IPSET_ERR_INVALID_FAMILY: i32 = 4106;
+const IPSET_ERR_TIMEOUT: i32 = 4107;
+const IPSET_ERR_REFERENCED: i32 = 4108;
+const IPSET_ERR_IPADDR_IPV4: i32 = 4109;
+const IPSET_ERR_IPADDR_IPV6: i32 = 4110;
+
+type IpsetAttrs = Vec<Nlattr<IpsetAttr, Vec<u8>>>;
+
+fn ipset_error(code: i32) -> NlError {
+    let msg = match code {
+        IPSET_ERR_PROTOCOL => "Kernel does not support the ipset protocol version",
+        IPSET_ERR_FIND_TYPE => "Set type is not supported by the kernel",
+        IPSET_ERR_MAX_SETS => "Maximal number of sets reached",
+        IPSET_ERR_BUSY => "Set is busy",
+        IPSET_ERR_EXIST_SETNAME2 => "Second set does not exist",
+        IPSET_ERR_TYPE_MISMATCH => "Set types do not match",
+        IPSET_ERR_EXIST => "Set or entry already exists",
+        IPSET_ERR_INVALID_CIDR => "Invalid CIDR prefix length",
+        IPSET_ERR_INVALID_NETMASK => "Invalid netmask",
+        IPSET_ERR_INVALID_FAMILY => "Invalid family",
+        IPSET_ERR_TIMEOUT => "Set was created without timeout support",
+        IPSET_ERR_REFERENCED => "Set is referenced and cannot be destroyed",
+        IPSET_ERR_IPADDR_IPV4 => "Invalid IPv4 address",
+        IPSET_ERR_IPADDR_IPV6 => "Invalid IPv6 address",
+        _ => return NlError::Msg(std::io::Error::from_raw_os_error(code).to_string()),
+    };
+    NlError::new(msg)
+}
+
+fn protocol_attr() -> Result<Nlattr<IpsetAttr, Vec<u8>>, SerError> {
+    Nlattr::new(None, IpsetAttr::Protocol, IPSET_PROTOCOL)
+}
+
+fn setname_attr(name: &str) -> Result<Nlattr<IpsetAttr, Vec<u8>>, SerError> {
+    Nlattr::new(None, IpsetAttr::Setname, name)
+}
+
+fn addr_attr<T>(nla_type: T, addr: IpAddr) -> Result<Nlattr<T, Vec<u8>>, SerError>
+where
+    T: consts::NlAttrType,
+{
+    let ip = match addr {
+        IpAddr::V4(v4) => Nlattr::new(None, IpsetIpAttr::Ipv4, &v4.octets()[..])?,
+        IpAddr::V6(v6) => Nlattr::new(None, IpsetIpAttr::Ipv6, &v6.octets()[..])?,
+    };
+    Ok(Nlattr::new_nested(nla_type, &[ip.with_net_byteorder_flag()])?.with_nested_flag())
+}
+
+fn addr_from_attr<T>(attr: &Nlattr<T, Vec<u8>>) -> Result<IpAddr, DeError>
+where
+    T: consts::NlAttrType,
+{
+    for ip in attr.get_nested_attributes::<IpsetIpAttr>()?.iter() {
+        match ip.nla_type {
+            IpsetIpAttr::Ipv4 if ip.payload.len() == 4 => {
+                let mut octets = [0; 4];
+                octets.copy_from_slice(&ip.payload);
+                return Ok(IpAddr::from(Ipv4Addr::from(octets)));
+            }
+            IpsetIpAttr::Ipv6 if ip.payload.len() == 16 => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&ip.payload);
+                return Ok(IpAddr::from(Ipv6Addr::from(octets)));
+            }
+            _ => (),
+        }
+    }
+    Err(DeError::new(
+        "Invalid IP address attribute in ipset message",
+    ))
+}
+
+fn be16_attr<T>(nla_type: T, v: u16) -> Result<Nlattr<T, Vec<u8>>, SerError>
+where
+    T: consts::NlAttrType,
+{
+    Ok(Nlattr::new(None, nla_type, v.to_be())?.with_net_byteorder_flag())
+}
+
+fn be32_attr<T>(nla_type: T, v: u32) -> Result<Nlattr<T, Vec<u8>>, SerError>
+where
+    T: consts::NlAttrType,
+{
+    Ok(Nlattr::new(None, nla_type, v.to_be())?.with_net_byteorder_flag())
+}
+
+/// Type of an IP set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IpsetType {
+    /// `hash:ip` - a set of IP addresses.
+    HashIp,
+    /// `hash:net` - a set of networks of different sizes.
+    HashNet,
+    /// `bitmap:port` - a set of ports in a fixed range.
+    BitmapPort,
+    /// Any other set type, only produced when listing sets.
+    Other(String),
+}
+
+impl IpsetType {
+    /// Name of the set type as used by the kernel.
+    pub fn name(&self) -> &str {
+        match *self {
+            IpsetType::HashIp => "hash:ip",
+            IpsetType::HashNet => "hash:net",
+            IpsetType::BitmapPort => "bitmap:port",
+            IpsetType::Other(ref name) => name,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for IpsetType {
+    fn from(name: &'a str) -> Self {
+        match name {
+            "hash:ip" => IpsetType::HashIp,
+            "hash:net" => IpsetType::HashNet,
+            "bitmap:port" => IpsetType::BitmapPort,
+            _ => IpsetType::Other(name.to_string()),
+        }
+    }
+}
+
+/// An entry of an IP set. Which fields are required depends on the set type: `hash:ip` entries
+/// have an address, `hash:net` entries an address and optionally a prefix length and
+/// `bitmap:port` entries a port.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IpsetEntry {
+    /// IP address.
+    pub ip: Option<IpAddr>,
+    /// Prefix length of the network starting at `ip`.
+    pub cidr: Option<u8>,
+    /// Port number.
+    pub port: Option<u16>,
+    /// IP protocol number of the port (eg. `IPPROTO_TCP`).
+    pub proto: Option<u8>,
+    /// Timeout of the entry in seconds, for sets created with a timeout.
+    pub timeout: Option<u32>,
+}
+
+impl IpsetEntry {
+    /// Create an entry for a single IP address.
+    pub fn ip(ip: IpAddr) -> Self {
+        IpsetEntry {
+            ip: Some(ip),
+            ..IpsetEntry::default()
+        }
+    }
+
+    /// Create an entry for the network `ip/cidr`.
+    pub fn net(ip: IpAddr, cidr: u8) -> Self {
+        IpsetEntry {
+            ip: Some(ip),
+            cidr: Some(cidr),
+            ..IpsetEntry::default()
+        }
+    }
+
+    /// Create an entry for a port.
+    pub fn port(port: u16) -> Self {
+        IpsetEntry {
+            port: Some(port),
+            ..IpsetEntry::default()
+        }
+    }
+
+    /// Encode the entry as an [IpsetAttr::Data] attribute.
+    pub fn to_attr(&self) -> Result<Nlattr<IpsetAttr, Vec<u8>>, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ip) = self.ip {
+            attrs.push(addr_attr(IpsetAdtAttr::Ip, ip)?);
+        }
+        if let Some(cidr) = self.cidr {
+            attrs.push(Nlattr::new(None, IpsetAdtAttr::Cidr, cidr)?);
+        }
+        if let Some(port) = self.port {
+            attrs.push(be16_attr(IpsetAdtAttr::Port, port)?);
+        }
+        if let Some(proto) = self.proto {
+            attrs.push(Nlattr::new(None, IpsetAdtAttr::Proto, proto)?);
+        }
+        if let Some(timeout) = self.timeout {
+            attrs.push(be32_attr(IpsetAdtAttr::Timeout, timeout)?);
+        }
+        Ok(Nlattr::new_nested(IpsetAttr::Data, &attrs)?.with_nested_flag())
+    }
+
+    /// Decode the entry from the attributes nested in an [IpsetAttr::Data] attribute.
+    pub fn from_attrs(handle: &AttrHandle<IpsetAdtAttr>) -> Result<Self, DeError> {
+        let mut entry = IpsetEntry::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                IpsetAdtAttr::Ip => entry.ip = Some(addr_from_attr(attr)?),
+                IpsetAdtAttr::Cidr => entry.cidr = Some(attr.get_payload_as()?),
+                IpsetAdtAttr::Port => entry.port = Some(u16::from_be(attr.get_payload_as()?)),
+                IpsetAdtAttr::Proto => entry.proto = Some(attr.get_payload_as()?),
+                IpsetAdtAttr::Timeout => entry.timeout = Some(u32::from_be(attr.get_payload_as()?)),
+                _ => (),
+            }
+        }
+        Ok(entry)
+    }
+}
+
+/// An IP set with its parameters and, when listed, its entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ipset {
+    /// Name of the set.
+    pub name: String,
+    /// Type of the set.
+    pub set_type: IpsetType,
+    /// Family of the addresses in the set, `NfProto::Unspec` for `bitmap:port`.
+    pub family: NfProto,
+    /// Revision of the set type. When creating a set, `None` selects the newest revision
+    /// supported by the kernel.
+    pub revision: Option<u8>,
+    /// Initial size of the hash table of hash sets.
+    pub hashsize: Option<u32>,
+    /// Maximal number of entries of hash sets.
+    pub maxelem: Option<u32>,
+    /// Default timeout of entries in seconds. Entries only expire in sets created with a timeout.
+    pub timeout: Option<u32>,
+    /// Inclusive port range of `bitmap:port` sets.
+    pub port_range: Option<(u16, u16)>,
+    /// Number of references to the set (eg. from iptables rules), only set when listing.
+    pub references: Option<u32>,
+    /// Memory used by the set in bytes, only set when listing.
+    pub memsize: Option<u32>,
+    /// Entries of the set, only set when listing.
+    pub entries: Vec<IpsetEntry>,
+}
+
+impl Ipset {
+    /// Create a set description with default parameters.
+    pub fn new(name: &str, set_type: IpsetType, family: NfProto) -> Self {
+        Ipset {
+            name: name.to_string(),
+            set_type,
+            family,
+            revision: None,
+            hashsize: None,
+            maxelem: None,
+            timeout: None,
+            port_range: None,
+            references: None,
+            memsize: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Encode the attributes of a create message for this set with the given type revision.
+    pub fn to_attrs(&self, revision: u8) -> Result<IpsetAttrs, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(hashsize) = self.hashsize {
+            attrs.push(be32_attr(IpsetCreateAttr::Hashsize, hashsize)?);
+        }
+        if let Some(maxelem) = self.maxelem {
+            attrs.push(be32_attr(IpsetCreateAttr::Maxelem, maxelem)?);
+        }
+        if let Some(timeout) = self.timeout {
+            attrs.push(be32_attr(IpsetCreateAttr::Timeout, timeout)?);
+        }
+        if let Some((from, to)) = self.port_range {
+            attrs.push(be16_attr(IpsetCreateAttr::Port, from)?);
+            attrs.push(be16_attr(IpsetCreateAttr::PortTo, to)?);
+        }
+        Ok(vec![
+            protocol_attr()?,
+            setname_attr(&self.name)?,
+            Nlattr::new(None, IpsetAttr::Typename, self.set_type.name())?,
+            Nlattr::new(None, IpsetAttr::Revision, revision)?,
+            Nlattr::new(None, IpsetAttr::Family, u8::from(&self.family))?,
+            Nlattr::new_nested(IpsetAttr::Data, &attrs)?.with_nested_flag(),
+        ])
+    }
+
+    /// Parse the messages of a list dump. Sets that span several messages are merged.
+    pub fn from_dump(msgs: &[Nfgenmsg<IpsetAttr>]) -> Result<Vec<Self>, DeError> {
+        let mut sets: Vec<Ipset> = Vec::new();
+        for msg in msgs {
+            let handle = msg.get_attr_handle();
+            let name: String = handle
+                .get_attribute(IpsetAttr::Setname)
+                .ok_or_else(|| DeError::new("Missing set name in ipset list message"))?
+                .get_payload_as()?;
+            let set = match sets.iter().position(|s| s.name == name) {
+                Some(i) => &mut sets[i],
+                None => {
+                    sets.push(Ipset::new(
+                        &name,
+                        IpsetType::Other(String::new()),
+                        NfProto::Unspec,
+                    ));
+                    sets.last_mut().expect("Set was just added")
+                }
+            };
+            for attr in handle.iter() {
+                match attr.nla_type {
+                    IpsetAttr::Typename => {
+                        let typename: String = attr.get_payload_as()?;
+                        set.set_type = IpsetType::from(typename.as_str());
+                    }
+                    IpsetAttr::Revision => set.revision = Some(attr.get_payload_as()?),
+                    IpsetAttr::Family => set.family = NfProto::from(attr.get_payload_as::<u8>()?),
+                    IpsetAttr::Data => set.parse_header(&attr.get_nested_attributes()?)?,
+                    IpsetAttr::Adt => {
+                        for data in attr.get_nested_attributes::<IpsetAttr>()?.iter() {
+                            if data.nla_type == IpsetAttr::Data {
+                                set.entries
+                                    .push(IpsetEntry::from_attrs(&data.get_nested_attributes()?)?);
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(sets)
+    }
+
+    fn parse_header(&mut self, handle: &AttrHandle<IpsetCreateAttr>) -> Result<(), DeError> {
+        let mut port = None;
+        let mut port_to = None;
+        for attr in handle.iter() {
+            match attr.nla_type {
+                IpsetCreateAttr::Hashsize => {
+                    self.hashsize = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                IpsetCreateAttr::Maxelem => {
+                    self.maxelem = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                IpsetCreateAttr::Timeout => {
+                    self.timeout = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                IpsetCreateAttr::Port => port = Some(u16::from_be(attr.get_payload_as()?)),
+                IpsetCreateAttr::PortTo => port_to = Some(u16::from_be(attr.get_payload_as()?)),
+                IpsetCreateAttr::References => {
+                    self.references = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                IpsetCreateAttr::Memsize => {
+                    self.memsize = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                _ => (),
+            }
+        }
+        if let (Some(from), Some(to)) = (port, port_to) {
+            self.port_range = Some((from, to));
+        }
+        Ok(())
+    }
+}
+
+impl NlSocket {
+    /// Create the IP set `set`.
+    pub fn ipset_create(&mut self, set: &Ipset) -> Result<(), NlError> {
+        if set.set_type == IpsetType::BitmapPort && set.port_range.is_none() {
+            return Err(NlError::new("bitmap:port sets require a port range"));
+        }
+        let revision = match set.revision {
+            Some(revision) => revision,
+            None => self.ipset_type_revision(&set.set_type, &set.family)?,
+        };
+        self.ipset_cmd(NetfilterMsg::IpsetCreate, set.to_attrs(revision)?)
+    }
+
+    /// Destroy the IP set `name`. The set must not be referenced.
+    pub fn ipset_destroy(&mut self, name: &str) -> Result<(), NlError> {
+        self.ipset_cmd(
+            NetfilterMsg::IpsetDestroy,
+            vec![protocol_attr()?, setname_attr(name)?],
+        )
+    }
+
+    /// Remove all entries from the IP set `name`.
+    pub fn ipset_flush(&mut self, name: &str) -> Result<(), NlError> {
+        self.ipset_cmd(
+            NetfilterMsg::IpsetFlush,
+            vec![protocol_attr()?, setname_attr(name)?],
+        )
+    }
+
+    /// List the IP set `name` or all sets if `name` is `None`, including their entries.
+    pub fn ipset_list(&mut self, name: Option<&str>) -> Result<Vec<Ipset>, NlError> {
+        let mut attrs = vec![protocol_attr()?];
+        if let Some(name) = name {
+            attrs.push(setname_attr(name)?);
+        }
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::IpsetList,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            Nfgenmsg::new(libc::NFPROTO_IPV4 as u8, 0, attrs),
+        );
+        self.send_nl(nlhdr)?;
+        let msgs = self.recv_all::<NetfilterMsg, Nfgenmsg<IpsetAttr>>()?;
+        let payloads: Vec<_> = msgs.into_iter().map(|msg| msg.nl_payload).collect();
+        Ok(Ipset::from_dump(&payloads)?)
+    }
+
+    /// Add `entry` to the IP set `name`.
+    pub fn ipset_add(&mut self, name: &str, entry: &IpsetEntry) -> Result<(), NlError> {
+        self.ipset_cmd(
+            NetfilterMsg::IpsetAdd,
+            vec![protocol_attr()?, setname_attr(name)?, entry.to_attr()?],
+        )
+    }
+
+    /// Delete `entry` from the IP set `name`.
+    pub fn ipset_del(&mut self, name: &str, entry: &IpsetEntry) -> Result<(), NlError> {
+        self.ipset_cmd(
+            NetfilterMsg::IpsetDel,
+            vec![protocol_attr()?, setname_attr(name)?, entry.to_attr()?],
+        )
+    }
+
+    /// Test whether `entry` is in the IP set `name`.
+    pub fn ipset_test(&mut self, name: &str, entry: &IpsetEntry) -> Result<bool, NlError> {
+        let attrs = vec![protocol_attr()?, setname_attr(name)?, entry.to_attr()?];
+        self.send_nl(Self::ipset_request(NetfilterMsg::IpsetTest, attrs))?;
+        match self.ipset_ack()? {
+            0 => Ok(true),
+            IPSET_ERR_EXIST => Ok(false),
+            code => Err(ipset_error(code)),
+        }
+    }
+
+    fn ipset_request(
+        nl_type: NetfilterMsg,
+        attrs: IpsetAttrs,
+    ) -> Nlmsghdr<NetfilterMsg, Nfgenmsg<IpsetAttr>> {
+        Nlmsghdr::new(
+            None,
+            nl_type,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            Nfgenmsg::new(libc::NFPROTO_IPV4 as u8, 0, attrs),
+        )
+    }
+
+    fn ipset_cmd(&mut self, nl_type: NetfilterMsg, attrs: IpsetAttrs) -> Result<(), NlError> {
+        self.send_nl(Self::ipset_request(nl_type, attrs))?;
+        match self.ipset_ack()? {
+            0 => Ok(()),
+            code => Err(ipset_error(code)),
+        }
+    }
+
+    /// Receive an acknowledgement and return the (positive) error code, `0` on success.
+    fn ipset_ack(&mut self) -> Result<i32, NlError> {
+        // Error acknowledgements echo the request, parse them from the raw message so that the
+        // echoed payload does not stay behind in the socket buffer
+        let msg = self.recv_nl::<u16, Vec<u8>>(None)?;
+        if consts::Nlmsg::from(msg.nl_type) != consts::Nlmsg::Error {
+            return Err(NlError::NoAck);
+        }
+        let mut buf = StreamReadBuffer::new(&msg.nl_payload);
+        buf.set_size_hint(msg.nl_payload.len());
+        Ok(-Nlmsgerr::<u16>::deserialize(&mut buf)?.error)
+    }
+
+    /// Query the newest revision of `set_type` supported by the kernel.
+    fn ipset_type_revision(
+        &mut self,
+        set_type: &IpsetType,
+        family: &NfProto,
+    ) -> Result<u8, NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            NetfilterMsg::IpsetType,
+            vec![NlmF::Request],
+            None,
+            None,
+            Nfgenmsg::new(
+                libc::NFPROTO_IPV4 as u8,
+                0,
+                vec![
+                    protocol_attr()?,
+                    Nlattr::new(None, IpsetAttr::Typename, set_type.name())?,
+                    Nlattr::new(None, IpsetAttr::Family, u8::from(family))?,
+                ],
+            ),
+        );
+        self.send_nl(nlhdr)?;
+        let msg = self.recv_nl::<u16, Vec<u8>>(None)?;
+        let mut buf = StreamReadBuffer::new(&msg.nl_payload);
+        buf.set_size_hint(msg.nl_payload.len());
+        if consts::Nlmsg::from(msg.nl_type) == consts::Nlmsg::Error {
+            let err = Nlmsgerr::<u16>::deserialize(&mut buf)?;
+            return Err(ipset_error(-err.error));
+        }
+        let reply = Nfgenmsg::<IpsetAttr>::deserialize(&mut buf)?;
+        Ok(reply
+            .get_attr_handle()
+            .get_attr_payload_as(IpsetAttr::Revision)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{round_trip, StreamWriteBuffer};
+
+    #[test]
+    fn test_entry_round_trip() {
+        let entry = IpsetEntry {
+            timeout: Some(600),
+            ..IpsetEntry::net(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 0)), 24)
+        };
+        let attr = entry.to_attr().unwrap();
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        attr.serialize(&mut mem).unwrap();
+        let bytes = mem.as_ref();
+        // Data attribute is flagged as nested
+        assert_eq!(
+            u16::from_ne_bytes([bytes[2], bytes[3]]),
+            7 | libc::NLA_F_NESTED as u16
+        );
+
+        let mut buf = StreamReadBuffer::new(bytes);
+        let parsed = Nlattr::<IpsetAttr, Vec<u8>>::deserialize(&mut buf).unwrap();
+        assert_eq!(parsed.nla_type, IpsetAttr::Data);
+        let decoded = IpsetEntry::from_attrs(&parsed.get_nested_attributes().unwrap()).unwrap();
+        assert_eq!(decoded, entry);
+    }
+
+    #[test]
+    fn test_list_dump() {
+        let mut set = Ipset::new("ports", IpsetType::BitmapPort, NfProto::Unspec);
+        set.port_range = Some((1000, 2000));
+        let mut first = set.to_attrs(3).unwrap();
+        let adt = Nlattr::new_nested(IpsetAttr::Adt, &[IpsetEntry::port(1022).to_attr().unwrap()]);
+        first.push(adt.unwrap().with_nested_flag());
+
+        let adt = Nlattr::new_nested(IpsetAttr::Adt, &[IpsetEntry::port(1500).to_attr().unwrap()]);
+        let second = vec![
+            protocol_attr().unwrap(),
+            setname_attr("ports").unwrap(),
+            adt.unwrap().with_nested_flag(),
+        ];
+
+        let mut msgs = Vec::new();
+        for attrs in [first, second] {
+            msgs.push(round_trip(&Nfgenmsg::new(
+                libc::NFPROTO_IPV4 as u8,
+                0,
+                attrs,
+            )));
+        }
+
+        let sets = Ipset::from_dump(&msgs).unwrap();
+        assert_eq!(sets.len(), 1);
+        set.revision = Some(3);
+        set.entries = vec![IpsetEntry::port(1022), IpsetEntry::port(1500)];
+        assert_eq!(sets[0], set);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_ipset() {
+        let mut s =
+            NlSocket::connect(crate::consts::NlFamily::Netfilter, Some(0), None, true).unwrap();
+        let set = Ipset::new("neli_test", IpsetType::HashIp, NfProto::Ipv4);
+        s.ipset_create(&set).unwrap();
+        let entry = IpsetEntry::ip(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        s.ipset_add("neli_test", &entry).unwrap();
+        assert!(s.ipset_test("neli_test", &entry).unwrap());
+        let sets = s.ipset_list(Some("neli_test")).unwrap();
+        assert_eq!(sets[0].entries, vec![entry.clone()]);
+        s.ipset_del("neli_test", &entry).unwrap();
+        assert!(!s.ipset_test("neli_test", &entry).unwrap());
+        s.ipset_flush("neli_test").unwrap();
+        s.ipset_destroy("neli_test").unwrap();
+    }
+}
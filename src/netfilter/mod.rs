@@ -0,0 +1,1365 @@
+//! Netfilter protocols
+//!
+//! Protocols used for communicating with netfilter. Currently, this contains (partial) support for
+//! NFLOG and NFQUEUE in this module, CONNTRACK in the `conntrack` submodule, nftables in the
+//! `nftables` submodule, IP sets in the `ipset` submodule, accounting objects in the `acct`
+//! submodule and conntrack timeout policies in the `cttimeout` submodule.
+//!
+//! Netfilter subsystems that follow the common `nfgenmsg` layout of a small header followed by
+//! attributes can use the `Nfgenmsg` payload type.
+//!
+//! Logged packets can be exported to pcapng files with the `pcapng` submodule.
+//!
+//! See the examples in the git repository for actual, working code.
+
+use std::ffi::CString;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use libc::c_int;
+
+use crate::consts::netfilter::{
+    CtAttr, LogCfgF, LogCmd, LogCopyMode, NfLogAttr, NfLogCfg, NfQueueAttr, NfQueueCfg, QueueCfgF,
+    QueueCmd, QueueCopyMode, Verdict,
+};
+use crate::consts::{alignto, NlAttrType, Nlmsg};
+use crate::err::{DeError, NlError, Nlmsgerr, SerError};
+use crate::nlattr::{AttrHandle, Nlattr};
+use crate::socket::NlSocket;
+use crate::{Nl, StreamReadBuffer, StreamWriteBuffer};
+
+pub mod acct;
+pub mod conntrack;
+pub mod cttimeout;
+pub mod ipset;
+pub mod nftables;
+pub mod pcapng;
+
+use self::conntrack::Conntrack;
+
+type Nlattrs = Vec<Nlattr<NfLogAttr, Vec<u8>>>;
+
+#[derive(Copy, Clone, Debug)]
+struct Timestamp {
+    secs: u64,
+    usecs: u64,
+}
+
+impl Nl for Timestamp {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        u64::to_be(self.secs).serialize(m)?;
+        u64::to_be(self.usecs).serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let secs = u64::from_be(u64::deserialize(m)?);
+        let usecs = u64::from_be(u64::deserialize(m)?);
+        Ok(Self { secs, usecs })
+    }
+    fn size(&self) -> usize {
+        self.secs.size() + self.usecs.size()
+    }
+}
+
+impl Timestamp {
+    fn from_system_time(time: SystemTime) -> Result<Self, SerError> {
+        let dur = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| SerError::new("Timestamp is before the UNIX epoch"))?;
+        Ok(Timestamp {
+            secs: dur.as_secs(),
+            usecs: u64::from(dur.subsec_micros()),
+        })
+    }
+}
+
+impl Into<SystemTime> for Timestamp {
+    fn into(self) -> SystemTime {
+        let dur = Duration::new(self.secs, (self.usecs * 1000) as u32);
+        UNIX_EPOCH + dur
+    }
+}
+
+/// Netfilter message header (`struct nfgenmsg`) followed by attributes.
+///
+/// This is the payload of most netfilter subsystem messages. The attribute type `T` depends on
+/// the subsystem, see the `nl_type` of the enclosing `Nlmsghdr`.
+#[derive(Debug, PartialEq)]
+pub struct Nfgenmsg<T> {
+    /// Address family the message applies to (`AF_*`), `AF_UNSPEC` for all of them.
+    pub family: u8,
+    /// Version of the netfilter netlink protocol, always `NFNETLINK_V0`.
+    pub version: u8,
+    /// Resource ID - queue or group number for some subsystems, otherwise `0`.
+    pub res_id: u16,
+    attrs: Vec<Nlattr<T, Vec<u8>>>,
+}
+
+impl<T> Nfgenmsg<T>
+where
+    T: NlAttrType,
+{
+    /// Create a new netfilter message.
+    pub fn new(family: u8, res_id: u16, attrs: Vec<Nlattr<T, Vec<u8>>>) -> Self {
+        Nfgenmsg {
+            family,
+            version: 0,
+            res_id,
+            attrs,
+        }
+    }
+
+    /// Get handle for attribute parsing and traversal
+    pub fn get_attr_handle(&self) -> AttrHandle<'_, T> {
+        AttrHandle::new_borrowed(&self.attrs)
+    }
+}
+
+/// Create an attribute holding `v` in big endian.
+fn be32_attr<T>(nla_type: T, v: u32) -> Result<Nlattr<T, Vec<u8>>, SerError>
+where
+    T: NlAttrType,
+{
+    Nlattr::new(None, nla_type, v.to_be())
+}
+
+/// Create an attribute holding `v` in big endian.
+fn be64_attr<T>(nla_type: T, v: u64) -> Result<Nlattr<T, Vec<u8>>, SerError>
+where
+    T: NlAttrType,
+{
+    Nlattr::new(None, nla_type, v.to_be())
+}
+
+/// Read a big endian `u32` attribute payload.
+fn be32<T>(attr: &Nlattr<T, Vec<u8>>) -> Result<u32, DeError>
+where
+    T: NlAttrType,
+{
+    Ok(u32::from_be(attr.get_payload_as()?))
+}
+
+/// Read a big endian `u64` attribute payload.
+fn be64<T>(attr: &Nlattr<T, Vec<u8>>) -> Result<u64, DeError>
+where
+    T: NlAttrType,
+{
+    Ok(u64::from_be(attr.get_payload_as()?))
+}
+
+/// Receive the reply to a request for a single object. Some subsystems flag these replies with
+/// `NLM_F_MULTI` without ending them with `NLMSG_DONE`, so `NlSocket.recv_all()` would block.
+fn recv_reply<T>(socket: &mut NlSocket) -> Result<Nfgenmsg<T>, NlError>
+where
+    T: NlAttrType,
+{
+    let msg = socket.recv_nl::<u16, Vec<u8>>(None)?;
+    let mut buf = StreamReadBuffer::new(&msg.nl_payload);
+    buf.set_size_hint(msg.nl_payload.len());
+    if Nlmsg::from(msg.nl_type) == Nlmsg::Error {
+        let err = Nlmsgerr::<u16>::deserialize(&mut buf)?;
+        let err = std::io::Error::from_raw_os_error(-err.error);
+        return Err(NlError::Msg(err.to_string()));
+    }
+    Ok(Nfgenmsg::deserialize(&mut buf)?)
+}
+
+impl<T> Nl for Nfgenmsg<T>
+where
+    T: NlAttrType,
+{
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.family.serialize(m)?;
+        self.version.serialize(m)?;
+        u16::to_be(self.res_id).serialize(m)?;
+        self.attrs.serialize(m)?;
+        self.pad(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let hint = m
+            .take_size_hint()
+            .ok_or_else(|| DeError::new("Must provide size hint to deserialize Nfgenmsg"))?;
+        let family = u8::deserialize(m)?;
+        let version = u8::deserialize(m)?;
+        let res_id = u16::from_be(u16::deserialize(m)?);
+        let attrs_len = hint
+            .checked_sub(family.size() + version.size() + res_id.size())
+            .ok_or_else(|| DeError::new("Nfgenmsg is shorter than its header"))?;
+        m.set_size_hint(attrs_len);
+        let attrs = Vec::<Nlattr<T, Vec<u8>>>::deserialize(m)?;
+        let msg = Nfgenmsg {
+            family,
+            version,
+            res_id,
+            attrs,
+        };
+        msg.strip(m)?;
+        Ok(msg)
+    }
+    fn size(&self) -> usize {
+        self.family.size() + self.version.size() + self.res_id.size() + self.attrs.asize()
+    }
+}
+
+/// Parse the hardware address attribute shared by NFLOG and NFQUEUE packets.
+fn parse_hwaddr(payload: Vec<u8>) -> Result<Vec<u8>, DeError> {
+    let mut buffer = StreamReadBuffer::new(&payload);
+    let len = u16::from_be(u16::deserialize(&mut buffer)?);
+    let mut hwaddr = payload;
+    // Drop the len and padding
+    hwaddr.drain(..4);
+    hwaddr.truncate(len as usize);
+    hwaddr.shrink_to_fit();
+    Ok(hwaddr)
+}
+
+/// Size of an attribute with a payload of `len` bytes, including padding.
+fn attr_size(len: usize) -> usize {
+    alignto(4 + len)
+}
+
+/// Encode the hardware address attribute (`struct nfulnl_msg_packet_hw`).
+fn hwaddr_payload(hwaddr: &[u8]) -> Result<Vec<u8>, SerError> {
+    if hwaddr.len() > 8 {
+        return Err(SerError::new("Hardware address is longer than 8 bytes"));
+    }
+    let mut payload = Vec::with_capacity(12);
+    payload.extend_from_slice(&(hwaddr.len() as u16).to_be_bytes());
+    // Padding
+    payload.extend_from_slice(&[0, 0]);
+    payload.extend_from_slice(hwaddr);
+    payload.resize(12, 0);
+    Ok(payload)
+}
+
+/// A logged packet sent from the kernel to userspace.
+///
+/// Packets can also be serialized again, eg. to replay captured NFLOG traffic in tests.
+/// Attributes are only encoded if they are set - a `mark` of 0 and a `timestamp` of `UNIX_EPOCH`
+/// count as not set, like the kernel does.
+///
+/// The struct is non-exhaustive so that adding more fields won't be a breaking change, use
+/// [LogPacket::dummy_instance] to create one.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct LogPacket {
+    /// Protocol family of the packet (`AF_*`).
+    pub family: u8,
+    /// The logging group the packet was sent to.
+    pub group: u16,
+    /// Link layer protocol of the packet (`ETH_P_*`).
+    pub hw_protocol: u16,
+    /// Netfilter hook the packet was logged at.
+    pub hook: u8,
+    /// A packet mark.
+    ///
+    /// A mark used through the netfilter, working as kind of scratch memory. 0 and no mark set are
+    /// considered equivalent.
+    pub mark: u32,
+    /// A timestamp when the packet has been captured.
+    pub timestamp: SystemTime,
+    /// Source hardware address (eg. MAC).
+    ///
+    /// This might be missing in case it is not yet known at the point of packet capture (outgoing
+    /// packets before routing decisions) or on interfaces that don't have hardware addresses
+    /// (`lo`).
+    pub hwaddr: Vec<u8>,
+    /// Payload of the packet.
+    pub payload: Vec<u8>,
+    /// Prefix, set at the capturing rule. May be empty.
+    pub prefix: CString,
+    /// Index of the inbound interface, if any.
+    pub ifindex_in: Option<u32>,
+    /// Index of the outbound interface, if any.
+    pub ifindex_out: Option<u32>,
+    /// Index of the physical inbound interface, if any.
+    pub ifindex_physin: Option<u32>,
+    /// Index of the physical outbound interface, if any.
+    pub ifindex_physout: Option<u32>,
+    /// UID of the socket this packet belongs to.
+    pub uid: Option<u32>,
+    /// GID of the socket this packet belongs to.
+    pub gid: Option<u32>,
+    /// Sequence number of the packet in the logging group, if enabled with [LogCfgF::Seq].
+    pub seq: Option<u32>,
+    /// Sequence number of the packet across all logging groups, if enabled with
+    /// [LogCfgF::SeqGlobal].
+    pub seq_global: Option<u32>,
+    /// Hardware type of the inbound interface (`ARPHRD_*`).
+    pub hwtype: Option<u16>,
+    /// Link layer header of the packet. May be empty.
+    pub hwheader: Vec<u8>,
+    /// Length of the link layer header.
+    pub hwlen: Option<u16>,
+    /// Encoded conntrack attributes of the packet, if enabled with [LogCfgF::Conntrack]. See
+    /// [LogPacket::conntrack].
+    pub ct: Option<Vec<u8>>,
+    /// Conntrack state of the packet (`enum ip_conntrack_info`).
+    pub ct_info: Option<u32>,
+}
+
+impl LogPacket {
+    /// Creates a dummy instance.
+    ///
+    /// This can be used in eg. tests, or to create an instance and set certain fields. This is
+    /// similar to the [Default] trait, except unlike default instances, this one doesn't actually
+    /// make much sense.
+    pub fn dummy_instance() -> Self {
+        Self {
+            family: 0,
+            group: 0,
+            hw_protocol: 0,
+            hook: 0,
+            mark: 0,
+            timestamp: UNIX_EPOCH,
+            hwaddr: Vec::new(),
+            payload: Vec::new(),
+            prefix: CString::default(),
+            ifindex_in: None,
+            ifindex_out: None,
+            ifindex_physin: None,
+            ifindex_physout: None,
+            uid: None,
+            gid: None,
+            seq: None,
+            seq_global: None,
+            hwtype: None,
+            hwheader: Vec::new(),
+            hwlen: None,
+            ct: None,
+            ct_info: None,
+        }
+    }
+
+    /// Parse the conntrack entry of the packet, if any.
+    pub fn conntrack(&self) -> Result<Option<Conntrack>, DeError> {
+        match self.ct {
+            Some(ref ct) => {
+                let attrs =
+                    Vec::<Nlattr<CtAttr, Vec<u8>>>::deserialize(&mut StreamReadBuffer::new(ct))?;
+                let mut conntrack = Conntrack::from_attrs(&AttrHandle::new(attrs))?;
+                conntrack.family = self.family;
+                Ok(Some(conntrack))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn attrs(&self) -> Result<Nlattrs, SerError> {
+        let mut hdr = Vec::with_capacity(4);
+        hdr.extend_from_slice(&self.hw_protocol.to_be_bytes());
+        hdr.extend_from_slice(&[self.hook, 0]);
+        let mut attrs = vec![Nlattr::new(None, NfLogAttr::PacketHdr, hdr)?];
+        if self.mark != 0 {
+            attrs.push(Nlattr::new(None, NfLogAttr::Mark, u32::to_be(self.mark))?);
+        }
+        if self.timestamp != UNIX_EPOCH {
+            let ts = Timestamp::from_system_time(self.timestamp)?;
+            attrs.push(Nlattr::new(None, NfLogAttr::Timestamp, ts)?);
+        }
+        let ifindexes = [
+            (NfLogAttr::IfindexIndev, self.ifindex_in),
+            (NfLogAttr::IfindexOutdev, self.ifindex_out),
+            (NfLogAttr::IfindexPhyindev, self.ifindex_physin),
+            (NfLogAttr::IfindexPhyoutdev, self.ifindex_physout),
+        ];
+        for &(ref nla_type, ifindex) in ifindexes.iter() {
+            if let Some(ifindex) = ifindex {
+                attrs.push(Nlattr::new(None, nla_type.clone(), u32::to_be(ifindex))?);
+            }
+        }
+        if !self.hwaddr.is_empty() {
+            attrs.push(Nlattr::new(
+                None,
+                NfLogAttr::Hwaddr,
+                hwaddr_payload(&self.hwaddr)?,
+            )?);
+        }
+        if let Some(hwtype) = self.hwtype {
+            attrs.push(Nlattr::new(None, NfLogAttr::Hwtype, u16::to_be(hwtype))?);
+        }
+        if let Some(hwlen) = self.hwlen {
+            attrs.push(Nlattr::new(None, NfLogAttr::Hwlen, u16::to_be(hwlen))?);
+        }
+        if !self.hwheader.is_empty() {
+            attrs.push(Nlattr::new(
+                None,
+                NfLogAttr::Hwheader,
+                self.hwheader.clone(),
+            )?);
+        }
+        if !self.payload.is_empty() {
+            attrs.push(Nlattr::new(None, NfLogAttr::Payload, self.payload.clone())?);
+        }
+        if !self.prefix.as_bytes().is_empty() {
+            let prefix = self.prefix.as_bytes_with_nul().to_vec();
+            attrs.push(Nlattr::new(None, NfLogAttr::Prefix, prefix)?);
+        }
+        let optional = [
+            (NfLogAttr::Uid, self.uid),
+            (NfLogAttr::Gid, self.gid),
+            (NfLogAttr::Seq, self.seq),
+            (NfLogAttr::SeqGlobal, self.seq_global),
+        ];
+        for &(ref nla_type, value) in optional.iter() {
+            if let Some(value) = value {
+                attrs.push(Nlattr::new(None, nla_type.clone(), u32::to_be(value))?);
+            }
+        }
+        if let Some(ref ct) = self.ct {
+            attrs.push(Nlattr::new(None, NfLogAttr::Ct, ct.clone())?);
+        }
+        if let Some(ct_info) = self.ct_info {
+            attrs.push(Nlattr::new(None, NfLogAttr::CtInfo, u32::to_be(ct_info))?);
+        }
+        Ok(attrs)
+    }
+}
+
+impl Nl for LogPacket {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.family.serialize(m)?;
+        // protocol version
+        0u8.serialize(m)?;
+        u16::to_be(self.group).serialize(m)?;
+        self.attrs()?.serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let hint = m.take_size_hint().map(|h| h.saturating_sub(4));
+        let family = u8::deserialize(m)?;
+        let _version = u8::deserialize(m)?;
+        let group = u16::from_be(u16::deserialize(m)?);
+        m.set_size_hint(hint.unwrap_or_default());
+        let attrs = Nlattrs::deserialize(m)?;
+        let mut result = Self::dummy_instance();
+        result.family = family;
+        result.group = group;
+
+        for attr in attrs {
+            match attr.nla_type {
+                NfLogAttr::PacketHdr => {
+                    let mut buffer = StreamReadBuffer::new(&attr.payload);
+                    result.hw_protocol = u16::from_be(u16::deserialize(&mut buffer)?);
+                    result.hook = u8::deserialize(&mut buffer)?;
+                }
+                NfLogAttr::Mark => result.mark = u32::from_be(attr.get_payload_as()?),
+                NfLogAttr::Timestamp => {
+                    result.timestamp = attr.get_payload_as::<Timestamp>()?.into();
+                }
+                NfLogAttr::Hwaddr => result.hwaddr = parse_hwaddr(attr.payload)?,
+                NfLogAttr::Payload => result.payload = attr.payload,
+                NfLogAttr::Prefix => {
+                    let mut bytes = attr.payload;
+                    // get rid of null byte, CString::new adds it and wants it not to have it there.
+                    // Usually, there's only one null byte, but who knows what comes from the
+                    // kernel, therefore we just make sure to do *something* in case there are
+                    // nulls in the middle too.
+                    bytes.retain(|b| *b != 0);
+                    result.prefix = CString::new(bytes).expect("Leftover null byte");
+                }
+                NfLogAttr::IfindexIndev => {
+                    result.ifindex_in = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfLogAttr::IfindexOutdev => {
+                    result.ifindex_out = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfLogAttr::IfindexPhyindev => {
+                    result.ifindex_physin = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfLogAttr::IfindexPhyoutdev => {
+                    result.ifindex_physout = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfLogAttr::Uid => result.uid = Some(u32::from_be(attr.get_payload_as()?)),
+                NfLogAttr::Gid => result.gid = Some(u32::from_be(attr.get_payload_as()?)),
+                NfLogAttr::Seq => result.seq = Some(u32::from_be(attr.get_payload_as()?)),
+                NfLogAttr::SeqGlobal => {
+                    result.seq_global = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfLogAttr::Hwtype => result.hwtype = Some(u16::from_be(attr.get_payload_as()?)),
+                NfLogAttr::Hwheader => result.hwheader = attr.payload,
+                NfLogAttr::Hwlen => result.hwlen = Some(u16::from_be(attr.get_payload_as()?)),
+                NfLogAttr::Ct => result.ct = Some(attr.payload),
+                NfLogAttr::CtInfo => result.ct_info = Some(u32::from_be(attr.get_payload_as()?)),
+                _ => (),
+            }
+        }
+        Ok(result)
+    }
+    fn size(&self) -> usize {
+        let optional = [
+            self.ifindex_in,
+            self.ifindex_out,
+            self.ifindex_physin,
+            self.ifindex_physout,
+            self.uid,
+            self.gid,
+            self.seq,
+            self.seq_global,
+            self.ct_info,
+        ];
+        let mut size = 4 + attr_size(4);
+        size += optional.iter().filter(|v| v.is_some()).count() * attr_size(4);
+        if self.mark != 0 {
+            size += attr_size(4);
+        }
+        if self.timestamp != UNIX_EPOCH {
+            size += attr_size(16);
+        }
+        if !self.hwaddr.is_empty() {
+            size += attr_size(12);
+        }
+        if self.hwtype.is_some() {
+            size += attr_size(2);
+        }
+        if self.hwlen.is_some() {
+            size += attr_size(2);
+        }
+        if !self.hwheader.is_empty() {
+            size += attr_size(self.hwheader.len());
+        }
+        if !self.payload.is_empty() {
+            size += attr_size(self.payload.len());
+        }
+        if !self.prefix.as_bytes().is_empty() {
+            size += attr_size(self.prefix.as_bytes_with_nul().len());
+        }
+        if let Some(ref ct) = self.ct {
+            size += attr_size(ct.len());
+        }
+        size
+    }
+}
+
+/// A configuration request, to bind a socket to specific logging group.
+#[derive(Debug)]
+pub struct LogConfigReq {
+    family: u8,
+    group: u16,
+    attrs: Vec<Nlattr<NfLogCfg, Vec<u8>>>,
+}
+
+impl LogConfigReq {
+    /// Creates a new log configuration request.
+    ///
+    /// It should be sent to the kernel in a
+    /// [NetfilterMsg::LogConfig][crate::consts::netfilter::NetfilterMsg::LogConfig] message.
+    ///
+    /// ```rust
+    /// # use neli::consts::netfilter::{NfLogCfg, LogCmd, LogCopyMode};
+    /// # use neli::nlattr::Nlattr;
+    /// # use neli::netfilter::{LogConfigMode, LogConfigReq};
+    /// // A request to attach the socket to log group 10 on the AF_INET protocol.
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cfg = vec![
+    ///     Nlattr::new(None, NfLogCfg::Cmd, LogCmd::PfUnbind)?,
+    ///     Nlattr::new(None, NfLogCfg::Mode, LogConfigMode {
+    ///         copy_mode: LogCopyMode::Packet,
+    ///         copy_range: 50,
+    ///     })?,
+    ///     Nlattr::new(None, NfLogCfg::Cmd, LogCmd::PfBind)?,
+    ///     Nlattr::new(None, NfLogCfg::Cmd, LogCmd::Bind)?,
+    /// ];
+    /// let req = LogConfigReq::new(libc::AF_INET, 10, cfg);
+    /// # Ok(()) }
+    /// ```
+    pub fn new(family: c_int, group: u16, cfg: Vec<Nlattr<NfLogCfg, Vec<u8>>>) -> Self {
+        assert!(family >= 0);
+        assert!(family <= 255);
+        Self {
+            family: family as u8,
+            group,
+            attrs: cfg,
+        }
+    }
+
+    /// Adds a [NfLogCfg::Cmd] attribute. Commands are executed in the order they were added.
+    ///
+    /// ```rust
+    /// # use neli::consts::netfilter::{LogCfgF, LogCmd, LogCopyMode};
+    /// # use neli::netfilter::LogConfigReq;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let req = LogConfigReq::new(libc::AF_INET, 10, Vec::new())
+    ///     .cmd(LogCmd::Bind)?
+    ///     .mode(LogCopyMode::Packet, 0xffff)?
+    ///     .flags(vec![LogCfgF::Seq, LogCfgF::Conntrack])?;
+    /// # Ok(()) }
+    /// ```
+    pub fn cmd(mut self, cmd: LogCmd) -> Result<Self, SerError> {
+        self.attrs.push(Nlattr::new(None, NfLogCfg::Cmd, cmd)?);
+        Ok(self)
+    }
+
+    /// Adds a [NfLogCfg::Mode] attribute, selecting what and how many bytes of each packet are
+    /// copied.
+    pub fn mode(mut self, copy_mode: LogCopyMode, copy_range: u32) -> Result<Self, SerError> {
+        let mode = LogConfigMode {
+            copy_range,
+            copy_mode,
+        };
+        self.attrs.push(Nlattr::new(None, NfLogCfg::Mode, mode)?);
+        Ok(self)
+    }
+
+    /// Adds a [NfLogCfg::NlBufSize] attribute, the size in bytes of the buffer packets are
+    /// batched in before they are sent.
+    pub fn nl_buf_size(mut self, size: u32) -> Result<Self, SerError> {
+        self.attrs
+            .push(Nlattr::new(None, NfLogCfg::NlBufSize, u32::to_be(size))?);
+        Ok(self)
+    }
+
+    /// Adds a [NfLogCfg::Timeout] attribute, the maximum time in 1/100 s packets are batched
+    /// for before they are sent.
+    pub fn timeout(mut self, timeout: u32) -> Result<Self, SerError> {
+        self.attrs
+            .push(Nlattr::new(None, NfLogCfg::Timeout, u32::to_be(timeout))?);
+        Ok(self)
+    }
+
+    /// Adds a [NfLogCfg::QThresh] attribute, the maximum number of packets batched before they
+    /// are sent.
+    pub fn qthresh(mut self, qthresh: u32) -> Result<Self, SerError> {
+        self.attrs
+            .push(Nlattr::new(None, NfLogCfg::QThresh, u32::to_be(qthresh))?);
+        Ok(self)
+    }
+
+    /// Adds a [NfLogCfg::Flags] attribute, enabling sequence numbers and conntrack information
+    /// in the logged packets.
+    pub fn flags(mut self, flags: Vec<LogCfgF>) -> Result<Self, SerError> {
+        let flags = flags.iter().fold(0, |acc: u16, next| {
+            let v: u16 = next.into();
+            acc | v
+        });
+        self.attrs
+            .push(Nlattr::new(None, NfLogCfg::Flags, u16::to_be(flags))?);
+        Ok(self)
+    }
+}
+
+impl Nl for LogConfigReq {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.family.serialize(m)?;
+        // protocol version
+        0u8.serialize(m)?;
+        u16::to_be(self.group).serialize(m)?;
+        self.attrs.serialize(m)?;
+        self.pad(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(_m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        unimplemented!("Config requests are never sent by the kernel")
+    }
+    fn size(&self) -> usize {
+        self.family.size() + 0u8.size() + self.group.size() + self.attrs.asize()
+    }
+}
+
+/// Configuration mode, as a parameter to [NfLogCfg::Mode].
+#[derive(Clone, Debug)]
+pub struct LogConfigMode {
+    /// Range of bytes to copy.
+    ///
+    /// TODO: All lengths in netlink are u16, why is this u32? Does it mean one should specify both
+    /// ends of the range somehow? How?
+    pub copy_range: u32,
+    /// What parts should be sent.
+    pub copy_mode: LogCopyMode,
+}
+
+impl Nl for LogConfigMode {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        u32::to_be(self.copy_range).serialize(m)?;
+        self.copy_mode.serialize(m)?;
+        // A padding
+        0u8.serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let copy_range = u32::from_be(u32::deserialize(m)?);
+        let copy_mode = LogCopyMode::deserialize(m)?;
+        // A padding
+        u8::deserialize(m)?;
+        Ok(Self {
+            copy_range,
+            copy_mode,
+        })
+    }
+    fn size(&self) -> usize {
+        self.copy_range.size() + self.copy_mode.size() + 0u8.size()
+    }
+}
+
+/// A packet queued to userspace by the kernel, waiting for a verdict.
+///
+/// Packets can also be serialized again, eg. to replay captured NFQUEUE traffic in tests.
+/// Attributes are only encoded if they are set.
+///
+/// The struct is non-exhaustive so that adding more fields won't be a breaking change, use
+/// [QueuePacket::dummy_instance] to create one.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct QueuePacket {
+    /// Protocol family of the queue the packet was sent to.
+    pub family: u8,
+    /// Number of the queue the packet was sent to.
+    pub queue_num: u16,
+    /// ID of the packet, used to refer to it when issuing a verdict.
+    pub packet_id: u32,
+    /// Ethernet protocol of the packet (eg. `ETH_P_IP`).
+    pub hw_protocol: u16,
+    /// Netfilter hook the packet was queued from.
+    pub hook: u8,
+    /// A packet mark, if set.
+    pub mark: Option<u32>,
+    /// A timestamp when the packet has been queued, if known.
+    pub timestamp: Option<SystemTime>,
+    /// Source hardware address (eg. MAC). May be empty, see [LogPacket::hwaddr].
+    pub hwaddr: Vec<u8>,
+    /// Payload of the packet, possibly truncated according to the copy range of the queue.
+    pub payload: Vec<u8>,
+    /// Original length of the packet if the payload has been truncated.
+    pub cap_len: Option<u32>,
+    /// `NFQA_SKB_*` flags describing the socket buffer, if requested.
+    pub skb_info: Option<u32>,
+    /// Index of the inbound interface, if any.
+    pub ifindex_in: Option<u32>,
+    /// Index of the outbound interface, if any.
+    pub ifindex_out: Option<u32>,
+    /// Index of the physical inbound interface, if any.
+    pub ifindex_physin: Option<u32>,
+    /// Index of the physical outbound interface, if any.
+    pub ifindex_physout: Option<u32>,
+    /// Nested conntrack attributes of the packet's connection, if
+    /// [QueueCfgF::Conntrack] is set.
+    pub ct: Option<Vec<u8>>,
+    /// Conntrack state of the packet's connection (`IP_CT_*`), if known.
+    pub ct_info: Option<u32>,
+    /// UID of the socket this packet belongs to.
+    pub uid: Option<u32>,
+    /// GID of the socket this packet belongs to.
+    pub gid: Option<u32>,
+}
+
+impl QueuePacket {
+    /// Creates a dummy instance, see [LogPacket::dummy_instance].
+    pub fn dummy_instance() -> Self {
+        Self {
+            family: 0,
+            queue_num: 0,
+            packet_id: 0,
+            hw_protocol: 0,
+            hook: 0,
+            mark: None,
+            timestamp: None,
+            hwaddr: Vec::new(),
+            payload: Vec::new(),
+            cap_len: None,
+            skb_info: None,
+            ifindex_in: None,
+            ifindex_out: None,
+            ifindex_physin: None,
+            ifindex_physout: None,
+            ct: None,
+            ct_info: None,
+            uid: None,
+            gid: None,
+        }
+    }
+
+    fn attrs(&self) -> Result<Vec<Nlattr<NfQueueAttr, Vec<u8>>>, SerError> {
+        let mut hdr = Vec::with_capacity(7);
+        hdr.extend_from_slice(&self.packet_id.to_be_bytes());
+        hdr.extend_from_slice(&self.hw_protocol.to_be_bytes());
+        hdr.push(self.hook);
+        let mut attrs = vec![Nlattr::new(None, NfQueueAttr::PacketHdr, hdr)?];
+        if let Some(mark) = self.mark {
+            attrs.push(Nlattr::new(None, NfQueueAttr::Mark, u32::to_be(mark))?);
+        }
+        if let Some(timestamp) = self.timestamp {
+            let ts = Timestamp::from_system_time(timestamp)?;
+            attrs.push(Nlattr::new(None, NfQueueAttr::Timestamp, ts)?);
+        }
+        if !self.hwaddr.is_empty() {
+            attrs.push(Nlattr::new(
+                None,
+                NfQueueAttr::Hwaddr,
+                hwaddr_payload(&self.hwaddr)?,
+            )?);
+        }
+        if !self.payload.is_empty() {
+            attrs.push(Nlattr::new(
+                None,
+                NfQueueAttr::Payload,
+                self.payload.clone(),
+            )?);
+        }
+        if let Some(ref ct) = self.ct {
+            attrs.push(Nlattr::new(None, NfQueueAttr::Ct, ct.clone())?);
+        }
+        let optional = [
+            (NfQueueAttr::CapLen, self.cap_len),
+            (NfQueueAttr::SkbInfo, self.skb_info),
+            (NfQueueAttr::IfindexIndev, self.ifindex_in),
+            (NfQueueAttr::IfindexOutdev, self.ifindex_out),
+            (NfQueueAttr::IfindexPhyindev, self.ifindex_physin),
+            (NfQueueAttr::IfindexPhyoutdev, self.ifindex_physout),
+            (NfQueueAttr::CtInfo, self.ct_info),
+            (NfQueueAttr::Uid, self.uid),
+            (NfQueueAttr::Gid, self.gid),
+        ];
+        for &(ref nla_type, value) in optional.iter() {
+            if let Some(value) = value {
+                attrs.push(Nlattr::new(None, nla_type.clone(), u32::to_be(value))?);
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+impl Nl for QueuePacket {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.family.serialize(m)?;
+        // protocol version
+        0u8.serialize(m)?;
+        u16::to_be(self.queue_num).serialize(m)?;
+        self.attrs()?.serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let hint = m.take_size_hint().map(|h| h.saturating_sub(4));
+        let family = u8::deserialize(m)?;
+        let _version = u8::deserialize(m)?;
+        let queue_num = u16::from_be(u16::deserialize(m)?);
+        m.set_size_hint(hint.unwrap_or_default());
+        let attrs = Vec::<Nlattr<NfQueueAttr, Vec<u8>>>::deserialize(m)?;
+        let mut result = Self::dummy_instance();
+        result.family = family;
+        result.queue_num = queue_num;
+
+        for attr in attrs {
+            match attr.nla_type {
+                NfQueueAttr::PacketHdr => {
+                    let mut buffer = StreamReadBuffer::new(&attr.payload);
+                    result.packet_id = u32::from_be(u32::deserialize(&mut buffer)?);
+                    result.hw_protocol = u16::from_be(u16::deserialize(&mut buffer)?);
+                    result.hook = u8::deserialize(&mut buffer)?;
+                }
+                NfQueueAttr::Mark => result.mark = Some(u32::from_be(attr.get_payload_as()?)),
+                NfQueueAttr::Timestamp => {
+                    result.timestamp = Some(attr.get_payload_as::<Timestamp>()?.into());
+                }
+                NfQueueAttr::Hwaddr => result.hwaddr = parse_hwaddr(attr.payload)?,
+                NfQueueAttr::Payload => result.payload = attr.payload,
+                NfQueueAttr::CapLen => result.cap_len = Some(u32::from_be(attr.get_payload_as()?)),
+                NfQueueAttr::SkbInfo => {
+                    result.skb_info = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfQueueAttr::IfindexIndev => {
+                    result.ifindex_in = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfQueueAttr::IfindexOutdev => {
+                    result.ifindex_out = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfQueueAttr::IfindexPhyindev => {
+                    result.ifindex_physin = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfQueueAttr::IfindexPhyoutdev => {
+                    result.ifindex_physout = Some(u32::from_be(attr.get_payload_as()?))
+                }
+                NfQueueAttr::Ct => result.ct = Some(attr.payload),
+                NfQueueAttr::CtInfo => result.ct_info = Some(u32::from_be(attr.get_payload_as()?)),
+                NfQueueAttr::Uid => result.uid = Some(u32::from_be(attr.get_payload_as()?)),
+                NfQueueAttr::Gid => result.gid = Some(u32::from_be(attr.get_payload_as()?)),
+                _ => (),
+            }
+        }
+        Ok(result)
+    }
+    fn size(&self) -> usize {
+        let optional = [
+            self.mark,
+            self.cap_len,
+            self.skb_info,
+            self.ifindex_in,
+            self.ifindex_out,
+            self.ifindex_physin,
+            self.ifindex_physout,
+            self.ct_info,
+            self.uid,
+            self.gid,
+        ];
+        let mut size = 4 + attr_size(7);
+        size += optional.iter().filter(|v| v.is_some()).count() * attr_size(4);
+        if self.timestamp.is_some() {
+            size += attr_size(16);
+        }
+        if !self.hwaddr.is_empty() {
+            size += attr_size(12);
+        }
+        if !self.payload.is_empty() {
+            size += attr_size(self.payload.len());
+        }
+        if let Some(ref ct) = self.ct {
+            size += attr_size(ct.len());
+        }
+        size
+    }
+}
+
+/// A configuration request, to bind a socket to a specific queue.
+#[derive(Debug, PartialEq)]
+pub struct QueueConfigReq {
+    family: u8,
+    queue_num: u16,
+    attrs: Vec<Nlattr<NfQueueCfg, Vec<u8>>>,
+}
+
+impl QueueConfigReq {
+    /// Creates a new queue configuration request.
+    ///
+    /// It should be sent to the kernel in a
+    /// [NetfilterMsg::QueueConfig][crate::consts::netfilter::NetfilterMsg::QueueConfig] message.
+    ///
+    /// ```rust
+    /// # use neli::consts::netfilter::{NfQueueCfg, QueueCfgF, QueueCmd, QueueCopyMode};
+    /// # use neli::nlattr::Nlattr;
+    /// # use neli::netfilter::{QueueConfigCmd, QueueConfigParams, QueueConfigReq, QueueFlags};
+    /// // A request to bind the socket to queue 0, copying whole packets and accepting packets
+    /// // instead of dropping them when the queue is full.
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cfg = vec![
+    ///     Nlattr::new(None, NfQueueCfg::Cmd, QueueConfigCmd {
+    ///         command: QueueCmd::Bind,
+    ///         pf: libc::AF_INET as u16,
+    ///     })?,
+    ///     Nlattr::new(None, NfQueueCfg::Params, QueueConfigParams {
+    ///         copy_mode: QueueCopyMode::Packet,
+    ///         copy_range: 0xffff,
+    ///     })?,
+    ///     Nlattr::new(None, NfQueueCfg::QueueMaxlen, u32::to_be(1024))?,
+    ///     Nlattr::new(None, NfQueueCfg::Flags, QueueFlags(vec![QueueCfgF::FailOpen]))?,
+    ///     Nlattr::new(None, NfQueueCfg::Mask, QueueFlags(vec![QueueCfgF::FailOpen]))?,
+    /// ];
+    /// let req = QueueConfigReq::new(libc::AF_INET, 0, cfg);
+    /// # Ok(()) }
+    /// ```
+    pub fn new(family: c_int, queue_num: u16, cfg: Vec<Nlattr<NfQueueCfg, Vec<u8>>>) -> Self {
+        assert!(family >= 0);
+        assert!(family <= 255);
+        Self {
+            family: family as u8,
+            queue_num,
+            attrs: cfg,
+        }
+    }
+}
+
+impl Nl for QueueConfigReq {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.family.serialize(m)?;
+        // protocol version
+        0u8.serialize(m)?;
+        u16::to_be(self.queue_num).serialize(m)?;
+        self.attrs.serialize(m)?;
+        self.pad(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let hint = m.take_size_hint().map(|h| h.saturating_sub(4));
+        let family = u8::deserialize(m)?;
+        let _version = u8::deserialize(m)?;
+        let queue_num = u16::from_be(u16::deserialize(m)?);
+        m.set_size_hint(hint.unwrap_or_default());
+        let attrs = Vec::<Nlattr<NfQueueCfg, Vec<u8>>>::deserialize(m)?;
+        Ok(Self {
+            family,
+            queue_num,
+            attrs,
+        })
+    }
+    fn size(&self) -> usize {
+        self.family.size() + 0u8.size() + self.queue_num.size() + self.attrs.asize()
+    }
+}
+
+/// Queue command, as a parameter to [NfQueueCfg::Cmd].
+#[derive(Clone, Debug)]
+pub struct QueueConfigCmd {
+    /// The command to execute.
+    pub command: QueueCmd,
+    /// Protocol family the command applies to.
+    pub pf: u16,
+}
+
+impl Nl for QueueConfigCmd {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.command.serialize(m)?;
+        // A padding
+        0u8.serialize(m)?;
+        u16::to_be(self.pf).serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let command = QueueCmd::deserialize(m)?;
+        // A padding
+        u8::deserialize(m)?;
+        let pf = u16::from_be(u16::deserialize(m)?);
+        Ok(Self { command, pf })
+    }
+    fn size(&self) -> usize {
+        self.command.size() + 0u8.size() + self.pf.size()
+    }
+}
+
+/// Queue parameters, as a parameter to [NfQueueCfg::Params].
+#[derive(Clone, Debug)]
+pub struct QueueConfigParams {
+    /// Maximum number of bytes of each packet to copy to userspace.
+    pub copy_range: u32,
+    /// What parts should be sent.
+    pub copy_mode: QueueCopyMode,
+}
+
+impl Nl for QueueConfigParams {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        u32::to_be(self.copy_range).serialize(m)?;
+        self.copy_mode.serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let copy_range = u32::from_be(u32::deserialize(m)?);
+        let copy_mode = QueueCopyMode::deserialize(m)?;
+        Ok(Self {
+            copy_range,
+            copy_mode,
+        })
+    }
+    fn size(&self) -> usize {
+        self.copy_range.size() + self.copy_mode.size()
+    }
+}
+
+/// Set of queue flags, as a parameter to [NfQueueCfg::Flags] and [NfQueueCfg::Mask].
+///
+/// Only the flags present in the mask are changed by the kernel so usually both attributes are
+/// sent.
+#[derive(Clone, Debug)]
+pub struct QueueFlags(pub Vec<QueueCfgF>);
+
+impl Nl for QueueFlags {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        let flags = self.0.iter().fold(0, |acc: u32, next| {
+            let v: u32 = next.into();
+            acc | v
+        });
+        u32::to_be(flags).serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let flags = u32::from_be(u32::deserialize(m)?);
+        let mut result = Vec::new();
+        for i in 0..32 {
+            let bit = 1 << i;
+            if bit & flags == bit {
+                result.push(bit.into());
+            }
+        }
+        Ok(QueueFlags(result))
+    }
+    fn size(&self) -> usize {
+        0u32.size()
+    }
+}
+
+/// A verdict on a queued packet, telling the kernel what to do with it.
+#[derive(Debug, PartialEq)]
+pub struct QueueVerdictReq {
+    queue_num: u16,
+    attrs: Vec<Nlattr<NfQueueAttr, Vec<u8>>>,
+}
+
+impl QueueVerdictReq {
+    /// Creates a new verdict for the packet with ID `packet_id` from queue `queue_num`.
+    ///
+    /// Optionally, the mark of the packet can be changed and the payload replaced.
+    ///
+    /// It should be sent to the kernel in a
+    /// [NetfilterMsg::QueueVerdict][crate::consts::netfilter::NetfilterMsg::QueueVerdict]
+    /// message. When sent in a
+    /// [NetfilterMsg::QueueVerdictBatch][crate::consts::netfilter::NetfilterMsg::QueueVerdictBatch]
+    /// message instead, the verdict (and mark) applies to all packets in the queue with an ID
+    /// smaller or equal to `packet_id`; the payload can't be replaced in this case.
+    pub fn new(
+        queue_num: u16,
+        packet_id: u32,
+        verdict: Verdict,
+        mark: Option<u32>,
+        payload: Option<Vec<u8>>,
+    ) -> Result<Self, SerError> {
+        let mut attrs = vec![Nlattr::new(
+            None,
+            NfQueueAttr::VerdictHdr,
+            VerdictHdr { verdict, packet_id },
+        )?];
+        if let Some(mark) = mark {
+            attrs.push(Nlattr::new(None, NfQueueAttr::Mark, u32::to_be(mark))?);
+        }
+        if let Some(payload) = payload {
+            attrs.push(Nlattr::new(None, NfQueueAttr::Payload, payload)?);
+        }
+        Ok(Self { queue_num, attrs })
+    }
+}
+
+impl Nl for QueueVerdictReq {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        (libc::AF_UNSPEC as u8).serialize(m)?;
+        // protocol version
+        0u8.serialize(m)?;
+        u16::to_be(self.queue_num).serialize(m)?;
+        self.attrs.serialize(m)?;
+        self.pad(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let hint = m.take_size_hint().map(|h| h.saturating_sub(4));
+        // Always AF_UNSPEC
+        let _family = u8::deserialize(m)?;
+        let _version = u8::deserialize(m)?;
+        let queue_num = u16::from_be(u16::deserialize(m)?);
+        m.set_size_hint(hint.unwrap_or_default());
+        let attrs = Vec::<Nlattr<NfQueueAttr, Vec<u8>>>::deserialize(m)?;
+        Ok(Self { queue_num, attrs })
+    }
+    fn size(&self) -> usize {
+        0u8.size() + 0u8.size() + self.queue_num.size() + self.attrs.asize()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct VerdictHdr {
+    verdict: Verdict,
+    packet_id: u32,
+}
+
+impl Nl for VerdictHdr {
+    fn serialize(&self, m: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        u32::to_be(self.verdict.clone().into()).serialize(m)?;
+        u32::to_be(self.packet_id).serialize(m)?;
+        Ok(())
+    }
+    fn deserialize<B: AsRef<[u8]>>(m: &mut StreamReadBuffer<B>) -> Result<Self, DeError> {
+        let verdict = u32::from_be(u32::deserialize(m)?).into();
+        let packet_id = u32::from_be(u32::deserialize(m)?);
+        Ok(Self { verdict, packet_id })
+    }
+    fn size(&self) -> usize {
+        0u32.size() + self.packet_id.size()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::round_trip;
+
+    #[test]
+    fn test_log_packet_round_trip() {
+        let mut pkt = LogPacket::dummy_instance();
+        pkt.family = libc::AF_INET as u8;
+        pkt.group = 10;
+        pkt.hw_protocol = 0x0800;
+        pkt.hook = 1;
+        pkt.mark = 0x1_0000;
+        pkt.timestamp = UNIX_EPOCH + Duration::new(1_500_000_000, 250_000_000);
+        pkt.hwaddr = vec![0, 1, 2, 3, 4, 5];
+        pkt.payload = vec![0x45, 0, 0, 20];
+        pkt.prefix = CString::new("A packet").unwrap();
+        pkt.ifindex_in = Some(2);
+        pkt.seq = Some(5);
+        pkt.seq_global = Some(17);
+        pkt.hwtype = Some(1);
+        pkt.hwlen = Some(14);
+        pkt.hwheader = vec![0xff; 14];
+        pkt.ct = Some(vec![8, 0, 7, 0, 0, 0, 0, 120]);
+        pkt.ct_info = Some(2);
+
+        let mut buf = Vec::new();
+        {
+            let mut mem = StreamWriteBuffer::new_growable_ref(&mut buf);
+            pkt.serialize(&mut mem).unwrap();
+        }
+        assert_eq!(pkt.size(), buf.len());
+        let mut mem = StreamReadBuffer::new(&buf);
+        mem.set_size_hint(buf.len());
+        let parsed = LogPacket::deserialize(&mut mem).unwrap();
+        assert_eq!(parsed, pkt);
+        assert_eq!(parsed.conntrack().unwrap().unwrap().timeout, Some(120));
+    }
+
+    #[test]
+    fn test_log_packet_size() {
+        let mut pkt = LogPacket::dummy_instance();
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        pkt.serialize(&mut mem).unwrap();
+        assert_eq!(pkt.size(), mem.as_ref().len());
+
+        pkt.payload = vec![0x45, 0, 0];
+        pkt.prefix = CString::new("odd").unwrap();
+        pkt.hwtype = Some(1);
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        pkt.serialize(&mut mem).unwrap();
+        assert_eq!(pkt.size(), mem.as_ref().len());
+
+        // Encoding errors are reported by serialize rather than hidden by size
+        pkt.hwaddr = vec![0; 9];
+        assert!(pkt
+            .serialize(&mut StreamWriteBuffer::new_growable(None))
+            .is_err());
+    }
+
+    #[test]
+    fn test_log_config_flags() {
+        let req = LogConfigReq::new(libc::AF_INET, 10, Vec::new())
+            .flags(vec![LogCfgF::Seq, LogCfgF::Conntrack])
+            .unwrap();
+        let mut buf = Vec::new();
+        {
+            let mut mem = StreamWriteBuffer::new_growable_ref(&mut buf);
+            req.serialize(&mut mem).unwrap();
+        }
+        assert_eq!(
+            buf,
+            vec![
+                libc::AF_INET as u8,
+                0,
+                0,
+                10,
+                6,
+                0,
+                libc::NFULA_CFG_FLAGS as u8,
+                0,
+                0,
+                5,
+                0,
+                0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_queue_packet_deserialize() {
+        let mut hdr = Vec::new();
+        hdr.extend_from_slice(&u32::to_be(42).to_ne_bytes());
+        hdr.extend_from_slice(&u16::to_be(0x0800).to_ne_bytes());
+        hdr.push(1);
+        let attrs = vec![
+            Nlattr::new(None, NfQueueAttr::PacketHdr, hdr).unwrap(),
+            Nlattr::new(None, NfQueueAttr::Mark, u32::to_be(7)).unwrap(),
+            Nlattr::new(None, NfQueueAttr::IfindexIndev, u32::to_be(2)).unwrap(),
+            Nlattr::new(None, NfQueueAttr::Payload, vec![0x45, 0, 0, 20]).unwrap(),
+        ];
+        let mut buf = Vec::new();
+        {
+            let mut mem = StreamWriteBuffer::new_growable_ref(&mut buf);
+            (libc::AF_INET as u8).serialize(&mut mem).unwrap();
+            0u8.serialize(&mut mem).unwrap();
+            u16::to_be(3).serialize(&mut mem).unwrap();
+            attrs.serialize(&mut mem).unwrap();
+        }
+        let mut mem = StreamReadBuffer::new(&buf);
+        mem.set_size_hint(buf.len());
+        let pkt = QueuePacket::deserialize(&mut mem).unwrap();
+        assert_eq!(pkt.family, libc::AF_INET as u8);
+        assert_eq!(pkt.queue_num, 3);
+        assert_eq!(pkt.packet_id, 42);
+        assert_eq!(pkt.hw_protocol, 0x0800);
+        assert_eq!(pkt.hook, 1);
+        assert_eq!(pkt.mark, Some(7));
+        assert_eq!(pkt.ifindex_in, Some(2));
+        assert_eq!(pkt.ifindex_out, None);
+        assert_eq!(pkt.payload, vec![0x45, 0, 0, 20]);
+        assert_eq!(pkt.size(), buf.len());
+    }
+
+    #[test]
+    fn test_queue_packet_round_trip() {
+        let mut pkt = QueuePacket::dummy_instance();
+        pkt.family = libc::AF_INET as u8;
+        pkt.queue_num = 3;
+        pkt.packet_id = 42;
+        pkt.hw_protocol = 0x0800;
+        pkt.hook = 1;
+        pkt.mark = Some(7);
+        pkt.timestamp = Some(UNIX_EPOCH + Duration::new(1_500_000_000, 250_000_000));
+        pkt.hwaddr = vec![0, 1, 2, 3, 4, 5];
+        pkt.payload = vec![0x45, 0, 0, 20, 0];
+        pkt.cap_len = Some(1500);
+        pkt.ifindex_out = Some(3);
+        pkt.ct = Some(vec![8, 0, 7, 0, 0, 0, 0, 120]);
+        pkt.ct_info = Some(2);
+        pkt.uid = Some(1000);
+
+        let mut buf = Vec::new();
+        {
+            let mut mem = StreamWriteBuffer::new_growable_ref(&mut buf);
+            pkt.serialize(&mut mem).unwrap();
+        }
+        assert_eq!(pkt.size(), buf.len());
+        let mut mem = StreamReadBuffer::new(&buf);
+        mem.set_size_hint(buf.len());
+        assert_eq!(QueuePacket::deserialize(&mut mem).unwrap(), pkt);
+    }
+
+    #[test]
+    fn test_queue_config_round_trip() {
+        let cfg = vec![
+            Nlattr::new(
+                None,
+                NfQueueCfg::Cmd,
+                QueueConfigCmd {
+                    command: QueueCmd::Bind,
+                    pf: libc::AF_INET as u16,
+                },
+            )
+            .unwrap(),
+            Nlattr::new(None, NfQueueCfg::QueueMaxlen, u32::to_be(1024)).unwrap(),
+        ];
+        let req = QueueConfigReq::new(libc::AF_INET, 4, cfg);
+        assert_eq!(round_trip(&req), req);
+    }
+
+    #[test]
+    fn test_queue_verdict_serialize() {
+        let req = QueueVerdictReq::new(3, 42, Verdict::Accept, Some(7), None).unwrap();
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        req.serialize(&mut mem).unwrap();
+        let mut expected = vec![libc::AF_UNSPEC as u8, 0, 0, 3];
+        expected.extend_from_slice(&12u16.to_ne_bytes());
+        expected.extend_from_slice(&(libc::NFQA_VERDICT_HDR as u16).to_ne_bytes());
+        expected.extend_from_slice(&[0, 0, 0, libc::NF_ACCEPT as u8, 0, 0, 0, 42]);
+        expected.extend_from_slice(&8u16.to_ne_bytes());
+        expected.extend_from_slice(&(libc::NFQA_MARK as u16).to_ne_bytes());
+        expected.extend_from_slice(&[0, 0, 0, 7]);
+        assert_eq!(mem.as_ref(), expected.as_slice());
+        assert_eq!(req.size(), expected.len());
+        assert_eq!(round_trip(&req), req);
+    }
+
+    #[test]
+    fn test_queue_config_params_size() {
+        let params = QueueConfigParams {
+            copy_range: 0xffff,
+            copy_mode: QueueCopyMode::Packet,
+        };
+        let attr = Nlattr::new(None, NfQueueCfg::Params, params).unwrap();
+        assert_eq!(attr.nla_len, 9);
+        assert_eq!(
+            attr.payload,
+            vec![0, 0, 0xff, 0xff, libc::NFQNL_COPY_PACKET as u8]
+        );
+    }
+}
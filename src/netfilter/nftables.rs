@@ -0,0 +1,1327 @@
+//! nftables (nf_tables)
+//!
+//! This module contains a model of the `NFNL_SUBSYS_NFTABLES` netfilter subsystem messages -
+//! tables, chains, rules, sets and set elements - as well as an encoder for the most common rule
+//! expressions. The socket must be created with `NlFamily::Netfilter`.
+//!
+//! Changes to the ruleset are transactional: they are collected in a `Batch` which is wrapped in
+//! `NFNL_MSG_BATCH_BEGIN` and `NFNL_MSG_BATCH_END` messages and sent in a single datagram with
+//! `NlSocket.nft_commit()`. Either all of the changes are applied or none of them.
+//!
+//! ```no_run
+//! use neli::consts::netfilter::{NfInetHook, NfProto, NftCmpOp, NftPayloadBase, NftRegister};
+//! use neli::consts::netfilter::NftVerdictCode;
+//! use neli::consts::NlFamily;
+//! use neli::netfilter::nftables::{Batch, Chain, Expr, Hook, NftData, Rule, Table};
+//! use neli::socket::NlSocket;
+//!
+//! let table = Table::new(NfProto::Inet, "filter");
+//! let mut chain = Chain::new(NfProto::Inet, "filter", "input");
+//! chain.hook = Some(Hook::new(NfInetHook::LocalIn.into(), 0));
+//! chain.chain_type = Some("filter".to_string());
+//! // Drop TCP traffic to port 23
+//! let mut rule = Rule::new(NfProto::Inet, "filter", "input");
+//! rule.exprs = vec![
+//!     Expr::Payload {
+//!         dreg: NftRegister::Reg1,
+//!         base: NftPayloadBase::Transport,
+//!         offset: 2,
+//!         len: 2,
+//!     },
+//!     Expr::Cmp {
+//!         sreg: NftRegister::Reg1,
+//!         op: NftCmpOp::Eq,
+//!         data: 23u16.to_be_bytes().to_vec(),
+//!     },
+//!     Expr::Counter {
+//!         packets: 0,
+//!         bytes: 0,
+//!     },
+//!     Expr::Immediate {
+//!         dreg: NftRegister::Verdict,
+//!         data: NftData::Verdict {
+//!             code: NftVerdictCode::Drop,
+//!             chain: None,
+//!         },
+//!     },
+//! ];
+//!
+//! let mut batch = Batch::new();
+//! batch.add_table(&table).unwrap();
+//! batch.add_chain(&chain).unwrap();
+//! batch.add_rule(&rule).unwrap();
+//!
+//! let mut socket = NlSocket::connect(NlFamily::Netfilter, Some(0), None, true).unwrap();
+//! socket.nft_commit(batch).unwrap();
+//! ```
+//!
+//! # Design decisions
+//!
+//! Like conntrack, nftables attributes carry multi-byte integers in big endian. The structures in
+//! this module hold host byte order values and convert them when encoding and decoding.
+//! Expressions that are not covered by `Expr` are decoded as `Expr::Other` with their raw data so
+//! that dumped rules can still be inspected and sent back to the kernel unchanged.
+
+use crate::consts::netfilter::{
+    NetfilterMsg, NfProto, NftChainAttr, NftCmpAttr, NftCmpOp, NftCounterAttr, NftDataAttr,
+    NftExprAttr, NftHookAttr, NftImmediateAttr, NftListAttr, NftLookupAttr, NftMetaAttr,
+    NftMetaKey, NftNatAttr, NftNatType, NftPayloadAttr, NftPayloadBase, NftRegister, NftRuleAttr,
+    NftSetAttr, NftSetElemAttr, NftSetElemListAttr, NftSetFlags, NftTableAttr, NftVerdictAttr,
+    NftVerdictCode, Verdict,
+};
+use crate::consts::{self, NlAttrType, NlmF};
+use crate::err::{DeError, NlError, Nlmsgerr, SerError};
+use crate::netfilter::{be32, be32_attr, be64, be64_attr, Nfgenmsg};
+use crate::nl::Nlmsghdr;
+use crate::nlattr::Nlattr;
+use crate::socket::NlSocket;
+use crate::{Nl, StreamReadBuffer, StreamWriteBuffer};
+
+/// Flag of the `lookup` expression to invert the match, from `linux/netfilter/nf_tables.h`
+const NFT_LOOKUP_F_INV: u32 = 1;
+
+fn str_attr<T>(nla_type: T, s: &str) -> Result<Nlattr<T, Vec<u8>>, SerError>
+where
+    T: NlAttrType,
+{
+    Nlattr::new(None, nla_type, s)
+}
+
+fn missing(what: &str) -> DeError {
+    DeError::new(&format!("Missing {} attribute", what))
+}
+
+/// Data of an `immediate` expression, a `cmp` expression or a set element.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NftData {
+    /// A value in network byte order.
+    Value(Vec<u8>),
+    /// A verdict, with the target chain for `Jump` and `Goto`.
+    Verdict {
+        /// Verdict code.
+        code: NftVerdictCode,
+        /// Target chain of `Jump` and `Goto` verdicts.
+        chain: Option<String>,
+    },
+}
+
+impl NftData {
+    fn to_attr<T>(&self, nla_type: T) -> Result<Nlattr<T, Vec<u8>>, SerError>
+    where
+        T: NlAttrType,
+    {
+        match *self {
+            NftData::Value(ref value) => Nlattr::new_nested(
+                nla_type,
+                &[Nlattr::new(None, NftDataAttr::Value, value.clone())?],
+            ),
+            NftData::Verdict {
+                ref code,
+                ref chain,
+            } => {
+                let mut verdict = vec![Nlattr::new(
+                    None,
+                    NftVerdictAttr::Code,
+                    i32::from(code).to_be(),
+                )?];
+                if let Some(ref chain) = *chain {
+                    verdict.push(str_attr(NftVerdictAttr::Chain, chain)?);
+                }
+                Nlattr::new_nested(
+                    nla_type,
+                    &[Nlattr::new_nested(NftDataAttr::Verdict, &verdict)?],
+                )
+            }
+        }
+    }
+
+    fn from_attr<T>(attr: &Nlattr<T, Vec<u8>>) -> Result<Self, DeError>
+    where
+        T: NlAttrType,
+    {
+        let handle = attr.get_nested_attributes::<NftDataAttr>()?;
+        if let Some(value) = handle.get_attribute(NftDataAttr::Value) {
+            return Ok(NftData::Value(value.payload.clone()));
+        }
+        let verdict = handle
+            .get_attribute(NftDataAttr::Verdict)
+            .ok_or_else(|| missing("data value or verdict"))?
+            .get_nested_attributes::<NftVerdictAttr>()?;
+        let code = verdict
+            .get_attribute(NftVerdictAttr::Code)
+            .ok_or_else(|| missing("verdict code"))?;
+        Ok(NftData::Verdict {
+            code: i32::from_be(code.get_payload_as()?).into(),
+            chain: match verdict.get_attribute(NftVerdictAttr::Chain) {
+                Some(chain) => Some(chain.get_payload_as()?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// A rule expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// Load `len` bytes at `offset` from the start of the `base` header into `dreg`.
+    Payload {
+        /// Destination register.
+        dreg: NftRegister,
+        /// Header the offset is relative to.
+        base: NftPayloadBase,
+        /// Offset in bytes.
+        offset: u32,
+        /// Number of bytes to load.
+        len: u32,
+    },
+    /// Load packet meta information into `dreg`, or set it from `sreg`.
+    Meta {
+        /// Meta information to load or set.
+        key: NftMetaKey,
+        /// Destination register when loading.
+        dreg: Option<NftRegister>,
+        /// Source register when setting.
+        sreg: Option<NftRegister>,
+    },
+    /// Compare the contents of `sreg` with `data` and stop evaluating the rule if the comparison
+    /// fails.
+    Cmp {
+        /// Source register.
+        sreg: NftRegister,
+        /// Comparison operator.
+        op: NftCmpOp,
+        /// Value to compare against, in network byte order.
+        data: Vec<u8>,
+    },
+    /// Load a constant value or verdict into `dreg`.
+    Immediate {
+        /// Destination register - [NftRegister::Verdict] for verdicts.
+        dreg: NftRegister,
+        /// Value or verdict.
+        data: NftData,
+    },
+    /// Count packets and bytes.
+    Counter {
+        /// Number of packets.
+        packets: u64,
+        /// Number of bytes.
+        bytes: u64,
+    },
+    /// Look up the contents of `sreg` in a set, loading the mapped data into `dreg` for maps.
+    Lookup {
+        /// Name of the set.
+        set: String,
+        /// ID of a set that was added in the same batch.
+        set_id: Option<u32>,
+        /// Source register.
+        sreg: NftRegister,
+        /// Destination register for maps.
+        dreg: Option<NftRegister>,
+        /// Match if the lookup fails instead.
+        invert: bool,
+    },
+    /// Source or destination NAT with addresses and ports taken from registers.
+    Nat {
+        /// SNAT or DNAT.
+        nat_type: NftNatType,
+        /// Family of the addresses.
+        family: NfProto,
+        /// Register holding the lowest address of the range.
+        addr_min: Option<NftRegister>,
+        /// Register holding the highest address of the range.
+        addr_max: Option<NftRegister>,
+        /// Register holding the lowest port of the range.
+        proto_min: Option<NftRegister>,
+        /// Register holding the highest port of the range.
+        proto_max: Option<NftRegister>,
+        /// NAT range flags (`NF_NAT_RANGE_*`).
+        flags: Option<u32>,
+    },
+    /// Any other expression, with its attributes left encoded.
+    Other {
+        /// Name of the expression.
+        name: String,
+        /// Encoded attributes of the expression.
+        data: Vec<u8>,
+    },
+}
+
+impl Expr {
+    /// Name of the expression as known to the kernel.
+    pub fn name(&self) -> &str {
+        match *self {
+            Expr::Payload { .. } => "payload",
+            Expr::Meta { .. } => "meta",
+            Expr::Cmp { .. } => "cmp",
+            Expr::Immediate { .. } => "immediate",
+            Expr::Counter { .. } => "counter",
+            Expr::Lookup { .. } => "lookup",
+            Expr::Nat { .. } => "nat",
+            Expr::Other { ref name, .. } => name,
+        }
+    }
+
+    /// Encode the expression as an element of [NftRuleAttr::Expressions].
+    pub fn to_attr(&self) -> Result<Nlattr<NftListAttr, Vec<u8>>, SerError> {
+        let data = match *self {
+            Expr::Payload {
+                ref dreg,
+                ref base,
+                offset,
+                len,
+            } => Nlattr::new_nested(
+                NftExprAttr::Data,
+                &[
+                    be32_attr(NftPayloadAttr::Dreg, dreg.into())?,
+                    be32_attr(NftPayloadAttr::Base, base.into())?,
+                    be32_attr(NftPayloadAttr::Offset, offset)?,
+                    be32_attr(NftPayloadAttr::Len, len)?,
+                ],
+            )?,
+            Expr::Meta {
+                ref key,
+                ref dreg,
+                ref sreg,
+            } => {
+                let mut attrs = vec![be32_attr(NftMetaAttr::Key, key.into())?];
+                if let Some(ref dreg) = *dreg {
+                    attrs.push(be32_attr(NftMetaAttr::Dreg, dreg.into())?);
+                }
+                if let Some(ref sreg) = *sreg {
+                    attrs.push(be32_attr(NftMetaAttr::Sreg, sreg.into())?);
+                }
+                Nlattr::new_nested(NftExprAttr::Data, &attrs)?
+            }
+            Expr::Cmp {
+                ref sreg,
+                ref op,
+                data: ref value,
+            } => Nlattr::new_nested(
+                NftExprAttr::Data,
+                &[
+                    be32_attr(NftCmpAttr::Sreg, sreg.into())?,
+                    be32_attr(NftCmpAttr::Op, op.into())?,
+                    NftData::Value(value.clone()).to_attr(NftCmpAttr::Data)?,
+                ],
+            )?,
+            Expr::Immediate {
+                ref dreg,
+                data: ref value,
+            } => Nlattr::new_nested(
+                NftExprAttr::Data,
+                &[
+                    be32_attr(NftImmediateAttr::Dreg, dreg.into())?,
+                    value.to_attr(NftImmediateAttr::Data)?,
+                ],
+            )?,
+            Expr::Counter { packets, bytes } => Nlattr::new_nested(
+                NftExprAttr::Data,
+                &[
+                    be64_attr(NftCounterAttr::Bytes, bytes)?,
+                    be64_attr(NftCounterAttr::Packets, packets)?,
+                ],
+            )?,
+            Expr::Lookup {
+                ref set,
+                set_id,
+                ref sreg,
+                ref dreg,
+                invert,
+            } => {
+                let mut attrs = vec![str_attr(NftLookupAttr::Set, set)?];
+                if let Some(set_id) = set_id {
+                    attrs.push(be32_attr(NftLookupAttr::SetId, set_id)?);
+                }
+                attrs.push(be32_attr(NftLookupAttr::Sreg, sreg.into())?);
+                if let Some(ref dreg) = *dreg {
+                    attrs.push(be32_attr(NftLookupAttr::Dreg, dreg.into())?);
+                }
+                if invert {
+                    attrs.push(be32_attr(NftLookupAttr::Flags, NFT_LOOKUP_F_INV)?);
+                }
+                Nlattr::new_nested(NftExprAttr::Data, &attrs)?
+            }
+            Expr::Nat {
+                ref nat_type,
+                ref family,
+                ref addr_min,
+                ref addr_max,
+                ref proto_min,
+                ref proto_max,
+                flags,
+            } => {
+                let mut attrs = vec![
+                    be32_attr(NftNatAttr::Type, nat_type.into())?,
+                    be32_attr(NftNatAttr::Family, u32::from(u8::from(family)))?,
+                ];
+                let regs = [
+                    (NftNatAttr::RegAddrMin, addr_min),
+                    (NftNatAttr::RegAddrMax, addr_max),
+                    (NftNatAttr::RegProtoMin, proto_min),
+                    (NftNatAttr::RegProtoMax, proto_max),
+                ];
+                for &(ref nla_type, reg) in regs.iter() {
+                    if let Some(ref reg) = *reg {
+                        attrs.push(be32_attr(nla_type.clone(), reg.into())?);
+                    }
+                }
+                if let Some(flags) = flags {
+                    attrs.push(be32_attr(NftNatAttr::Flags, flags)?);
+                }
+                Nlattr::new_nested(NftExprAttr::Data, &attrs)?
+            }
+            Expr::Other {
+                data: ref value, ..
+            } => Nlattr::new(None, NftExprAttr::Data, value.clone())?,
+        };
+        Nlattr::new_nested(
+            NftListAttr::Elem,
+            &[str_attr(NftExprAttr::Name, self.name())?, data],
+        )
+    }
+
+    /// Decode an expression from an element of [NftRuleAttr::Expressions].
+    pub fn from_attr(attr: &Nlattr<NftListAttr, Vec<u8>>) -> Result<Self, DeError> {
+        let handle = attr.get_nested_attributes::<NftExprAttr>()?;
+        let name = handle
+            .get_attribute(NftExprAttr::Name)
+            .ok_or_else(|| missing("expression name"))?
+            .get_payload_as::<String>()?;
+        let data = match handle.get_attribute(NftExprAttr::Data) {
+            Some(data) => data,
+            None => {
+                return Ok(Expr::Other {
+                    name,
+                    data: Vec::new(),
+                })
+            }
+        };
+        let expr = match name.as_str() {
+            "payload" => {
+                let attrs = data.get_nested_attributes::<NftPayloadAttr>()?;
+                let get = |t: NftPayloadAttr| match attrs.get_attribute(t) {
+                    Some(attr) => be32(attr),
+                    None => Err(missing("payload expression")),
+                };
+                Expr::Payload {
+                    dreg: get(NftPayloadAttr::Dreg)?.into(),
+                    base: get(NftPayloadAttr::Base)?.into(),
+                    offset: get(NftPayloadAttr::Offset)?,
+                    len: get(NftPayloadAttr::Len)?,
+                }
+            }
+            "meta" => {
+                let attrs = data.get_nested_attributes::<NftMetaAttr>()?;
+                let get = |t: NftMetaAttr| match attrs.get_attribute(t) {
+                    Some(attr) => be32(attr).map(Some),
+                    None => Ok(None),
+                };
+                Expr::Meta {
+                    key: get(NftMetaAttr::Key)?
+                        .ok_or_else(|| missing("meta key"))?
+                        .into(),
+                    dreg: get(NftMetaAttr::Dreg)?.map(NftRegister::from),
+                    sreg: get(NftMetaAttr::Sreg)?.map(NftRegister::from),
+                }
+            }
+            "cmp" => {
+                let attrs = data.get_nested_attributes::<NftCmpAttr>()?;
+                let get = |t: NftCmpAttr| match attrs.get_attribute(t) {
+                    Some(attr) => be32(attr),
+                    None => Err(missing("cmp expression")),
+                };
+                let value = match attrs.get_attribute(NftCmpAttr::Data) {
+                    Some(attr) => match NftData::from_attr(attr)? {
+                        NftData::Value(value) => value,
+                        NftData::Verdict { .. } => {
+                            return Err(DeError::new("cmp expression data is a verdict"))
+                        }
+                    },
+                    None => return Err(missing("cmp data")),
+                };
+                Expr::Cmp {
+                    sreg: get(NftCmpAttr::Sreg)?.into(),
+                    op: get(NftCmpAttr::Op)?.into(),
+                    data: value,
+                }
+            }
+            "immediate" => {
+                let attrs = data.get_nested_attributes::<NftImmediateAttr>()?;
+                Expr::Immediate {
+                    dreg: be32(
+                        attrs
+                            .get_attribute(NftImmediateAttr::Dreg)
+                            .ok_or_else(|| missing("immediate register"))?,
+                    )?
+                    .into(),
+                    data: NftData::from_attr(
+                        attrs
+                            .get_attribute(NftImmediateAttr::Data)
+                            .ok_or_else(|| missing("immediate data"))?,
+                    )?,
+                }
+            }
+            "counter" => {
+                let attrs = data.get_nested_attributes::<NftCounterAttr>()?;
+                let get = |t: NftCounterAttr| match attrs.get_attribute(t) {
+                    Some(attr) => be64(attr),
+                    None => Ok(0),
+                };
+                Expr::Counter {
+                    packets: get(NftCounterAttr::Packets)?,
+                    bytes: get(NftCounterAttr::Bytes)?,
+                }
+            }
+            "lookup" => {
+                let attrs = data.get_nested_attributes::<NftLookupAttr>()?;
+                let get = |t: NftLookupAttr| match attrs.get_attribute(t) {
+                    Some(attr) => be32(attr).map(Some),
+                    None => Ok(None),
+                };
+                Expr::Lookup {
+                    set: attrs
+                        .get_attribute(NftLookupAttr::Set)
+                        .ok_or_else(|| missing("lookup set"))?
+                        .get_payload_as()?,
+                    set_id: get(NftLookupAttr::SetId)?,
+                    sreg: get(NftLookupAttr::Sreg)?
+                        .ok_or_else(|| missing("lookup register"))?
+                        .into(),
+                    dreg: get(NftLookupAttr::Dreg)?.map(NftRegister::from),
+                    invert: get(NftLookupAttr::Flags)?.unwrap_or(0) & NFT_LOOKUP_F_INV != 0,
+                }
+            }
+            "nat" => {
+                let attrs = data.get_nested_attributes::<NftNatAttr>()?;
+                let get = |t: NftNatAttr| match attrs.get_attribute(t) {
+                    Some(attr) => be32(attr).map(Some),
+                    None => Ok(None),
+                };
+                Expr::Nat {
+                    nat_type: get(NftNatAttr::Type)?
+                        .ok_or_else(|| missing("nat type"))?
+                        .into(),
+                    family: NfProto::from(
+                        get(NftNatAttr::Family)?.ok_or_else(|| missing("nat family"))? as u8,
+                    ),
+                    addr_min: get(NftNatAttr::RegAddrMin)?.map(NftRegister::from),
+                    addr_max: get(NftNatAttr::RegAddrMax)?.map(NftRegister::from),
+                    proto_min: get(NftNatAttr::RegProtoMin)?.map(NftRegister::from),
+                    proto_max: get(NftNatAttr::RegProtoMax)?.map(NftRegister::from),
+                    flags: get(NftNatAttr::Flags)?,
+                }
+            }
+            _ => Expr::Other {
+                name,
+                data: data.payload.clone(),
+            },
+        };
+        Ok(expr)
+    }
+}
+
+/// An nftables table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    /// Family of the table.
+    pub family: NfProto,
+    /// Name of the table.
+    pub name: String,
+    /// Table flags (`NFT_TABLE_F_*`).
+    pub flags: Option<u32>,
+    /// Handle assigned by the kernel.
+    pub handle: Option<u64>,
+    /// Number of chains in the table.
+    pub use_count: Option<u32>,
+}
+
+impl Table {
+    /// Create a table description with only the family and name set.
+    pub fn new(family: NfProto, name: &str) -> Self {
+        Table {
+            family,
+            name: name.to_string(),
+            flags: None,
+            handle: None,
+            use_count: None,
+        }
+    }
+
+    /// Encode the table as a message payload.
+    pub fn to_nfgenmsg(&self) -> Result<Nfgenmsg<NftTableAttr>, SerError> {
+        let mut attrs = vec![str_attr(NftTableAttr::Name, &self.name)?];
+        if let Some(flags) = self.flags {
+            attrs.push(be32_attr(NftTableAttr::Flags, flags)?);
+        }
+        if let Some(handle) = self.handle {
+            attrs.push(be64_attr(NftTableAttr::Handle, handle)?);
+        }
+        Ok(Nfgenmsg::new(u8::from(&self.family), 0, attrs))
+    }
+
+    /// Parse a table from a message payload.
+    pub fn from_nfgenmsg(msg: &Nfgenmsg<NftTableAttr>) -> Result<Self, DeError> {
+        let mut table = Table::new(msg.family.into(), "");
+        for attr in msg.get_attr_handle().iter() {
+            match attr.nla_type {
+                NftTableAttr::Name => table.name = attr.get_payload_as()?,
+                NftTableAttr::Flags => table.flags = Some(be32(attr)?),
+                NftTableAttr::Handle => table.handle = Some(be64(attr)?),
+                NftTableAttr::Use => table.use_count = Some(be32(attr)?),
+                _ => (),
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// Hook a base chain is attached to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hook {
+    /// Hook number, see [NfInetHook][crate::consts::netfilter::NfInetHook].
+    pub hooknum: u32,
+    /// Priority of the chain on the hook.
+    pub priority: i32,
+    /// Device for chains of the netdev family.
+    pub dev: Option<String>,
+}
+
+impl Hook {
+    /// Create a hook description without a device.
+    pub fn new(hooknum: u32, priority: i32) -> Self {
+        Hook {
+            hooknum,
+            priority,
+            dev: None,
+        }
+    }
+}
+
+/// An nftables chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chain {
+    /// Family of the chain.
+    pub family: NfProto,
+    /// Name of the table the chain belongs to.
+    pub table: String,
+    /// Name of the chain.
+    pub name: String,
+    /// Handle assigned by the kernel.
+    pub handle: Option<u64>,
+    /// Hook of base chains - regular chains are only reached by jumps.
+    pub hook: Option<Hook>,
+    /// Default policy of base chains.
+    pub policy: Option<Verdict>,
+    /// Type of base chains (`filter`, `nat` or `route`).
+    pub chain_type: Option<String>,
+    /// Number of rules referencing the chain.
+    pub use_count: Option<u32>,
+}
+
+impl Chain {
+    /// Create a regular chain description.
+    pub fn new(family: NfProto, table: &str, name: &str) -> Self {
+        Chain {
+            family,
+            table: table.to_string(),
+            name: name.to_string(),
+            handle: None,
+            hook: None,
+            policy: None,
+            chain_type: None,
+            use_count: None,
+        }
+    }
+
+    /// Encode the chain as a message payload.
+    pub fn to_nfgenmsg(&self) -> Result<Nfgenmsg<NftChainAttr>, SerError> {
+        let mut attrs = vec![
+            str_attr(NftChainAttr::Table, &self.table)?,
+            str_attr(NftChainAttr::Name, &self.name)?,
+        ];
+        if let Some(handle) = self.handle {
+            attrs.push(be64_attr(NftChainAttr::Handle, handle)?);
+        }
+        if let Some(ref hook) = self.hook {
+            let mut hook_attrs = vec![
+                be32_attr(NftHookAttr::Hooknum, hook.hooknum)?,
+                be32_attr(NftHookAttr::Priority, hook.priority as u32)?,
+            ];
+            if let Some(ref dev) = hook.dev {
+                hook_attrs.push(str_attr(NftHookAttr::Dev, dev)?);
+            }
+            attrs.push(Nlattr::new_nested(NftChainAttr::Hook, &hook_attrs)?);
+        }
+        if let Some(ref policy) = self.policy {
+            attrs.push(be32_attr(NftChainAttr::Policy, policy.into())?);
+        }
+        if let Some(ref chain_type) = self.chain_type {
+            attrs.push(str_attr(NftChainAttr::Type, chain_type)?);
+        }
+        Ok(Nfgenmsg::new(u8::from(&self.family), 0, attrs))
+    }
+
+    /// Parse a chain from a message payload.
+    pub fn from_nfgenmsg(msg: &Nfgenmsg<NftChainAttr>) -> Result<Self, DeError> {
+        let mut chain = Chain::new(msg.family.into(), "", "");
+        for attr in msg.get_attr_handle().iter() {
+            match attr.nla_type {
+                NftChainAttr::Table => chain.table = attr.get_payload_as()?,
+                NftChainAttr::Name => chain.name = attr.get_payload_as()?,
+                NftChainAttr::Handle => chain.handle = Some(be64(attr)?),
+                NftChainAttr::Hook => {
+                    let mut hook = Hook::new(0, 0);
+                    for hook_attr in attr.get_nested_attributes::<NftHookAttr>()?.iter() {
+                        match hook_attr.nla_type {
+                            NftHookAttr::Hooknum => hook.hooknum = be32(hook_attr)?,
+                            NftHookAttr::Priority => hook.priority = be32(hook_attr)? as i32,
+                            NftHookAttr::Dev => hook.dev = Some(hook_attr.get_payload_as()?),
+                            _ => (),
+                        }
+                    }
+                    chain.hook = Some(hook);
+                }
+                NftChainAttr::Policy => chain.policy = Some(be32(attr)?.into()),
+                NftChainAttr::Type => chain.chain_type = Some(attr.get_payload_as()?),
+                NftChainAttr::Use => chain.use_count = Some(be32(attr)?),
+                _ => (),
+            }
+        }
+        Ok(chain)
+    }
+}
+
+/// An nftables rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    /// Family of the rule.
+    pub family: NfProto,
+    /// Name of the table the rule belongs to.
+    pub table: String,
+    /// Name of the chain the rule belongs to.
+    pub chain: String,
+    /// Handle assigned by the kernel.
+    pub handle: Option<u64>,
+    /// Handle of the rule to add the new rule after.
+    pub position: Option<u64>,
+    /// Expressions evaluated in order.
+    pub exprs: Vec<Expr>,
+}
+
+impl Rule {
+    /// Create a rule description without any expressions.
+    pub fn new(family: NfProto, table: &str, chain: &str) -> Self {
+        Rule {
+            family,
+            table: table.to_string(),
+            chain: chain.to_string(),
+            handle: None,
+            position: None,
+            exprs: Vec::new(),
+        }
+    }
+
+    /// Encode the rule as a message payload.
+    pub fn to_nfgenmsg(&self) -> Result<Nfgenmsg<NftRuleAttr>, SerError> {
+        let mut attrs = vec![
+            str_attr(NftRuleAttr::Table, &self.table)?,
+            str_attr(NftRuleAttr::Chain, &self.chain)?,
+        ];
+        if let Some(handle) = self.handle {
+            attrs.push(be64_attr(NftRuleAttr::Handle, handle)?);
+        }
+        if let Some(position) = self.position {
+            attrs.push(be64_attr(NftRuleAttr::Position, position)?);
+        }
+        if !self.exprs.is_empty() {
+            let exprs = self
+                .exprs
+                .iter()
+                .map(Expr::to_attr)
+                .collect::<Result<Vec<_>, _>>()?;
+            attrs.push(Nlattr::new_nested(NftRuleAttr::Expressions, &exprs)?);
+        }
+        Ok(Nfgenmsg::new(u8::from(&self.family), 0, attrs))
+    }
+
+    /// Parse a rule from a message payload.
+    pub fn from_nfgenmsg(msg: &Nfgenmsg<NftRuleAttr>) -> Result<Self, DeError> {
+        let mut rule = Rule::new(msg.family.into(), "", "");
+        for attr in msg.get_attr_handle().iter() {
+            match attr.nla_type {
+                NftRuleAttr::Table => rule.table = attr.get_payload_as()?,
+                NftRuleAttr::Chain => rule.chain = attr.get_payload_as()?,
+                NftRuleAttr::Handle => rule.handle = Some(be64(attr)?),
+                NftRuleAttr::Position => rule.position = Some(be64(attr)?),
+                NftRuleAttr::Expressions => {
+                    for expr in attr.get_nested_attributes::<NftListAttr>()?.iter() {
+                        rule.exprs.push(Expr::from_attr(expr)?);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(rule)
+    }
+}
+
+/// An nftables set or map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Set {
+    /// Family of the set.
+    pub family: NfProto,
+    /// Name of the table the set belongs to.
+    pub table: String,
+    /// Name of the set.
+    pub name: String,
+    /// Set flags.
+    pub flags: Vec<NftSetFlags>,
+    /// Data type of the keys, as defined by the `nft` tool.
+    pub key_type: Option<u32>,
+    /// Length of the keys in bytes.
+    pub key_len: u32,
+    /// Data type of the mapped data for maps.
+    pub data_type: Option<u32>,
+    /// Length of the mapped data for maps.
+    pub data_len: Option<u32>,
+    /// ID to reference a set added in the same batch, see [Expr::Lookup].
+    pub id: Option<u32>,
+    /// Handle assigned by the kernel.
+    pub handle: Option<u64>,
+}
+
+impl Set {
+    /// Create a set description with keys of length `key_len`.
+    pub fn new(family: NfProto, table: &str, name: &str, key_len: u32) -> Self {
+        Set {
+            family,
+            table: table.to_string(),
+            name: name.to_string(),
+            flags: Vec::new(),
+            key_type: None,
+            key_len,
+            data_type: None,
+            data_len: None,
+            id: None,
+            handle: None,
+        }
+    }
+
+    /// Encode the set as a message payload.
+    pub fn to_nfgenmsg(&self) -> Result<Nfgenmsg<NftSetAttr>, SerError> {
+        let mut attrs = vec![
+            str_attr(NftSetAttr::Table, &self.table)?,
+            str_attr(NftSetAttr::Name, &self.name)?,
+            be32_attr(NftSetAttr::KeyLen, self.key_len)?,
+        ];
+        if !self.flags.is_empty() {
+            let flags = self.flags.iter().fold(0, |acc: u32, next| {
+                let v: u32 = next.into();
+                acc | v
+            });
+            attrs.push(be32_attr(NftSetAttr::Flags, flags)?);
+        }
+        let optional = [
+            (NftSetAttr::KeyType, self.key_type),
+            (NftSetAttr::DataType, self.data_type),
+            (NftSetAttr::DataLen, self.data_len),
+            (NftSetAttr::Id, self.id),
+        ];
+        for &(ref nla_type, value) in optional.iter() {
+            if let Some(value) = value {
+                attrs.push(be32_attr(nla_type.clone(), value)?);
+            }
+        }
+        if let Some(handle) = self.handle {
+            attrs.push(be64_attr(NftSetAttr::Handle, handle)?);
+        }
+        Ok(Nfgenmsg::new(u8::from(&self.family), 0, attrs))
+    }
+
+    /// Parse a set from a message payload.
+    pub fn from_nfgenmsg(msg: &Nfgenmsg<NftSetAttr>) -> Result<Self, DeError> {
+        let mut set = Set::new(msg.family.into(), "", "", 0);
+        for attr in msg.get_attr_handle().iter() {
+            match attr.nla_type {
+                NftSetAttr::Table => set.table = attr.get_payload_as()?,
+                NftSetAttr::Name => set.name = attr.get_payload_as()?,
+                NftSetAttr::Flags => {
+                    let flags = be32(attr)?;
+                    for i in 0..32 {
+                        let bit = 1 << i;
+                        if bit & flags == bit {
+                            set.flags.push(bit.into());
+                        }
+                    }
+                }
+                NftSetAttr::KeyType => set.key_type = Some(be32(attr)?),
+                NftSetAttr::KeyLen => set.key_len = be32(attr)?,
+                NftSetAttr::DataType => set.data_type = Some(be32(attr)?),
+                NftSetAttr::DataLen => set.data_len = Some(be32(attr)?),
+                NftSetAttr::Id => set.id = Some(be32(attr)?),
+                NftSetAttr::Handle => set.handle = Some(be64(attr)?),
+                _ => (),
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// An element of an nftables set or map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetElem {
+    /// Key in network byte order.
+    pub key: Vec<u8>,
+    /// Mapped data for maps.
+    pub data: Option<NftData>,
+    /// Element flags (`NFT_SET_ELEM_*`).
+    pub flags: Option<u32>,
+}
+
+impl SetElem {
+    /// Create a set element without mapped data.
+    pub fn new(key: Vec<u8>) -> Self {
+        SetElem {
+            key,
+            data: None,
+            flags: None,
+        }
+    }
+}
+
+/// A list of elements of an nftables set, as used for adding and deleting elements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetElems {
+    /// Family of the set.
+    pub family: NfProto,
+    /// Name of the table the set belongs to.
+    pub table: String,
+    /// Name of the set.
+    pub set: String,
+    /// The elements.
+    pub elems: Vec<SetElem>,
+}
+
+impl SetElems {
+    /// Create a list of elements of a set.
+    pub fn new(family: NfProto, table: &str, set: &str, elems: Vec<SetElem>) -> Self {
+        SetElems {
+            family,
+            table: table.to_string(),
+            set: set.to_string(),
+            elems,
+        }
+    }
+
+    /// Encode the elements as a message payload.
+    pub fn to_nfgenmsg(&self) -> Result<Nfgenmsg<NftSetElemListAttr>, SerError> {
+        let mut elems = Vec::with_capacity(self.elems.len());
+        for elem in self.elems.iter() {
+            let mut attrs = vec![NftData::Value(elem.key.clone()).to_attr(NftSetElemAttr::Key)?];
+            if let Some(ref data) = elem.data {
+                attrs.push(data.to_attr(NftSetElemAttr::Data)?);
+            }
+            if let Some(flags) = elem.flags {
+                attrs.push(be32_attr(NftSetElemAttr::Flags, flags)?);
+            }
+            elems.push(Nlattr::new_nested(NftListAttr::Elem, &attrs)?);
+        }
+        let attrs = vec![
+            str_attr(NftSetElemListAttr::Table, &self.table)?,
+            str_attr(NftSetElemListAttr::Set, &self.set)?,
+            Nlattr::new_nested(NftSetElemListAttr::Elements, &elems)?,
+        ];
+        Ok(Nfgenmsg::new(u8::from(&self.family), 0, attrs))
+    }
+
+    /// Parse a list of elements from a message payload.
+    pub fn from_nfgenmsg(msg: &Nfgenmsg<NftSetElemListAttr>) -> Result<Self, DeError> {
+        let mut list = SetElems::new(msg.family.into(), "", "", Vec::new());
+        for attr in msg.get_attr_handle().iter() {
+            match attr.nla_type {
+                NftSetElemListAttr::Table => list.table = attr.get_payload_as()?,
+                NftSetElemListAttr::Set => list.set = attr.get_payload_as()?,
+                NftSetElemListAttr::Elements => {
+                    for elem in attr.get_nested_attributes::<NftListAttr>()?.iter() {
+                        let mut set_elem = SetElem::new(Vec::new());
+                        for elem_attr in elem.get_nested_attributes::<NftSetElemAttr>()?.iter() {
+                            match elem_attr.nla_type {
+                                NftSetElemAttr::Key => match NftData::from_attr(elem_attr)? {
+                                    NftData::Value(key) => set_elem.key = key,
+                                    NftData::Verdict { .. } => {
+                                        return Err(DeError::new("Set element key is a verdict"))
+                                    }
+                                },
+                                NftSetElemAttr::Data => {
+                                    set_elem.data = Some(NftData::from_attr(elem_attr)?)
+                                }
+                                NftSetElemAttr::Flags => set_elem.flags = Some(be32(elem_attr)?),
+                                _ => (),
+                            }
+                        }
+                        list.elems.push(set_elem);
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(list)
+    }
+}
+
+/// A transaction of nftables changes, applied atomically with `NlSocket.nft_commit()`.
+#[derive(Debug, Default)]
+pub struct Batch {
+    msgs: Vec<Nlmsghdr<NetfilterMsg, Vec<u8>>>,
+}
+
+impl Batch {
+    /// Create an empty batch.
+    pub fn new() -> Self {
+        Batch { msgs: Vec::new() }
+    }
+
+    /// Number of messages in the batch, not counting the begin and end markers.
+    pub fn len(&self) -> usize {
+        self.msgs.len()
+    }
+
+    /// Whether the batch contains no messages.
+    pub fn is_empty(&self) -> bool {
+        self.msgs.is_empty()
+    }
+
+    /// Add an arbitrary nftables message to the batch - `NLM_F_REQUEST` and `NLM_F_ACK` are
+    /// always set so that every message is acknowledged.
+    pub fn add<T>(
+        &mut self,
+        nl_type: NetfilterMsg,
+        mut flags: Vec<NlmF>,
+        payload: Nfgenmsg<T>,
+    ) -> Result<(), SerError>
+    where
+        T: NlAttrType,
+    {
+        for flag in [NlmF::Request, NlmF::Ack].iter() {
+            if !flags.contains(flag) {
+                flags.push(flag.clone());
+            }
+        }
+        let mut mem = StreamWriteBuffer::new_growable(Some(payload.asize()));
+        payload.serialize(&mut mem)?;
+        self.msgs.push(Nlmsghdr::new(
+            None,
+            nl_type,
+            flags,
+            None,
+            None,
+            mem.as_ref().to_vec(),
+        ));
+        Ok(())
+    }
+
+    /// Create a table.
+    pub fn add_table(&mut self, table: &Table) -> Result<(), SerError> {
+        self.add(
+            NetfilterMsg::NftNewTable,
+            vec![NlmF::Create],
+            table.to_nfgenmsg()?,
+        )
+    }
+
+    /// Delete a table and everything in it.
+    pub fn del_table(&mut self, table: &Table) -> Result<(), SerError> {
+        self.add(NetfilterMsg::NftDelTable, vec![], table.to_nfgenmsg()?)
+    }
+
+    /// Create a chain.
+    pub fn add_chain(&mut self, chain: &Chain) -> Result<(), SerError> {
+        self.add(
+            NetfilterMsg::NftNewChain,
+            vec![NlmF::Create],
+            chain.to_nfgenmsg()?,
+        )
+    }
+
+    /// Delete a chain.
+    pub fn del_chain(&mut self, chain: &Chain) -> Result<(), SerError> {
+        self.add(NetfilterMsg::NftDelChain, vec![], chain.to_nfgenmsg()?)
+    }
+
+    /// Append a rule to its chain, or insert it after the rule with the handle `rule.position`.
+    pub fn add_rule(&mut self, rule: &Rule) -> Result<(), SerError> {
+        self.add(
+            NetfilterMsg::NftNewRule,
+            vec![NlmF::Create, NlmF::Append],
+            rule.to_nfgenmsg()?,
+        )
+    }
+
+    /// Delete the rule with the handle `rule.handle`.
+    pub fn del_rule(&mut self, rule: &Rule) -> Result<(), SerError> {
+        self.add(NetfilterMsg::NftDelRule, vec![], rule.to_nfgenmsg()?)
+    }
+
+    /// Create a set.
+    pub fn add_set(&mut self, set: &Set) -> Result<(), SerError> {
+        self.add(
+            NetfilterMsg::NftNewSet,
+            vec![NlmF::Create],
+            set.to_nfgenmsg()?,
+        )
+    }
+
+    /// Delete a set.
+    pub fn del_set(&mut self, set: &Set) -> Result<(), SerError> {
+        self.add(NetfilterMsg::NftDelSet, vec![], set.to_nfgenmsg()?)
+    }
+
+    /// Add elements to a set.
+    pub fn add_set_elems(&mut self, elems: &SetElems) -> Result<(), SerError> {
+        self.add(
+            NetfilterMsg::NftNewSetElem,
+            vec![NlmF::Create],
+            elems.to_nfgenmsg()?,
+        )
+    }
+
+    /// Delete elements from a set.
+    pub fn del_set_elems(&mut self, elems: &SetElems) -> Result<(), SerError> {
+        self.add(NetfilterMsg::NftDelSetElem, vec![], elems.to_nfgenmsg()?)
+    }
+
+    /// Wrap the messages in `NFNL_MSG_BATCH_BEGIN` and `NFNL_MSG_BATCH_END` messages.
+    fn into_msgs(self) -> Result<Vec<Nlmsghdr<NetfilterMsg, Vec<u8>>>, SerError> {
+        let marker = |nl_type| -> Result<_, SerError> {
+            let payload = Nfgenmsg::<NftTableAttr>::new(
+                libc::AF_UNSPEC as u8,
+                libc::NFNL_SUBSYS_NFTABLES as u16,
+                Vec::new(),
+            );
+            let mut mem = StreamWriteBuffer::new_growable(Some(payload.asize()));
+            payload.serialize(&mut mem)?;
+            Ok(Nlmsghdr::new(
+                None,
+                nl_type,
+                vec![NlmF::Request],
+                None,
+                None,
+                mem.as_ref().to_vec(),
+            ))
+        };
+        let mut msgs = Vec::with_capacity(self.msgs.len() + 2);
+        msgs.push(marker(NetfilterMsg::BatchBegin)?);
+        msgs.extend(self.msgs);
+        msgs.push(marker(NetfilterMsg::BatchEnd)?);
+        Ok(msgs)
+    }
+}
+
+impl NlSocket {
+    /// Apply all changes in `batch` atomically. The first error reported by the kernel is
+    /// returned, in which case none of the changes were applied.
+    pub fn nft_commit(&mut self, batch: Batch) -> Result<(), NlError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let mut msgs = batch.into_msgs()?;
+        // Number the messages in case the socket does not track sequence numbers
+        for (i, msg) in msgs.iter_mut().enumerate() {
+            msg.nl_seq = i as u32;
+        }
+        self.send_batch(&mut msgs)?;
+        let begin_seq = msgs[0].nl_seq;
+        let last_seq = msgs[msgs.len() - 2].nl_seq;
+
+        let mut result = Ok(());
+        loop {
+            let msg = self.recv_nl::<u16, Vec<u8>>(None)?;
+            if consts::Nlmsg::from(msg.nl_type) != consts::Nlmsg::Error {
+                continue;
+            }
+            let mut buf = StreamReadBuffer::new(&msg.nl_payload);
+            buf.set_size_hint(msg.nl_payload.len());
+            let err = Nlmsgerr::<u16>::deserialize(&mut buf)?;
+            if err.error != 0 && result.is_ok() {
+                let err = std::io::Error::from_raw_os_error(-err.error as _);
+                result = Err(NlError::Msg(err.to_string()));
+            }
+            // Every message in the batch is answered in order, a failure of the batch begin
+            // message aborts the whole batch
+            if msg.nl_seq == begin_seq || msg.nl_seq == last_seq {
+                break;
+            }
+        }
+        result
+    }
+
+    fn nft_dump<A, R, F>(
+        &mut self,
+        nl_type: NetfilterMsg,
+        family: NfProto,
+        parse: F,
+    ) -> Result<Vec<R>, NlError>
+    where
+        A: NlAttrType,
+        F: Fn(&Nfgenmsg<A>) -> Result<R, DeError>,
+    {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            nl_type,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            Nfgenmsg::<A>::new(u8::from(&family), 0, Vec::new()),
+        );
+        self.send_nl(nlhdr)?;
+        let msgs = self.recv_all::<NetfilterMsg, Nfgenmsg<A>>()?;
+        let mut objs = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            objs.push(parse(&msg.nl_payload)?);
+        }
+        Ok(objs)
+    }
+
+    /// List all tables of the given family ([NfProto::Unspec] for all of them).
+    pub fn nft_list_tables(&mut self, family: NfProto) -> Result<Vec<Table>, NlError> {
+        self.nft_dump(NetfilterMsg::NftGetTable, family, Table::from_nfgenmsg)
+    }
+
+    /// List all chains of the given family ([NfProto::Unspec] for all of them).
+    pub fn nft_list_chains(&mut self, family: NfProto) -> Result<Vec<Chain>, NlError> {
+        self.nft_dump(NetfilterMsg::NftGetChain, family, Chain::from_nfgenmsg)
+    }
+
+    /// List all rules of the given family ([NfProto::Unspec] for all of them).
+    pub fn nft_list_rules(&mut self, family: NfProto) -> Result<Vec<Rule>, NlError> {
+        self.nft_dump(NetfilterMsg::NftGetRule, family, Rule::from_nfgenmsg)
+    }
+
+    /// List all sets of the given family ([NfProto::Unspec] for all of them).
+    pub fn nft_list_sets(&mut self, family: NfProto) -> Result<Vec<Set>, NlError> {
+        self.nft_dump(NetfilterMsg::NftGetSet, family, Set::from_nfgenmsg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::round_trip;
+
+    #[test]
+    fn test_rule_round_trip() {
+        let mut rule = Rule::new(NfProto::Inet, "filter", "input");
+        rule.handle = Some(7);
+        rule.exprs = vec![
+            Expr::Meta {
+                key: NftMetaKey::L4proto,
+                dreg: Some(NftRegister::Reg1),
+                sreg: None,
+            },
+            Expr::Cmp {
+                sreg: NftRegister::Reg1,
+                op: NftCmpOp::Eq,
+                data: vec![libc::IPPROTO_TCP as u8],
+            },
+            Expr::Payload {
+                dreg: NftRegister::Reg1,
+                base: NftPayloadBase::Transport,
+                offset: 2,
+                len: 2,
+            },
+            Expr::Lookup {
+                set: "ports".to_string(),
+                set_id: None,
+                sreg: NftRegister::Reg1,
+                dreg: None,
+                invert: true,
+            },
+            Expr::Counter {
+                packets: 1,
+                bytes: 60,
+            },
+            Expr::Nat {
+                nat_type: NftNatType::Dnat,
+                family: NfProto::Ipv4,
+                addr_min: Some(NftRegister::Reg2),
+                addr_max: None,
+                proto_min: Some(NftRegister::Reg3),
+                proto_max: None,
+                flags: None,
+            },
+            Expr::Immediate {
+                dreg: NftRegister::Verdict,
+                data: NftData::Verdict {
+                    code: NftVerdictCode::Jump,
+                    chain: Some("other".to_string()),
+                },
+            },
+            Expr::Other {
+                name: "notrack".to_string(),
+                data: Vec::new(),
+            },
+        ];
+        let msg = round_trip(&rule.to_nfgenmsg().unwrap());
+        assert_eq!(Rule::from_nfgenmsg(&msg).unwrap(), rule);
+    }
+
+    #[test]
+    fn test_set_elems_round_trip() {
+        let mut set = Set::new(NfProto::Ipv4, "filter", "ports", 2);
+        set.flags = vec![NftSetFlags::Constant, NftSetFlags::Map];
+        set.data_type = Some(0xffff_ff00);
+        set.data_len = Some(16);
+        let msg = round_trip(&set.to_nfgenmsg().unwrap());
+        assert_eq!(Set::from_nfgenmsg(&msg).unwrap(), set);
+
+        let mut elem = SetElem::new(vec![0, 22]);
+        elem.data = Some(NftData::Verdict {
+            code: NftVerdictCode::Accept,
+            chain: None,
+        });
+        let elems = SetElems::new(NfProto::Ipv4, "filter", "ports", vec![elem]);
+        let msg = round_trip(&elems.to_nfgenmsg().unwrap());
+        assert_eq!(SetElems::from_nfgenmsg(&msg).unwrap(), elems);
+    }
+
+    #[test]
+    fn test_batch_markers() {
+        let mut batch = Batch::new();
+        batch
+            .add_table(&Table::new(NfProto::Inet, "filter"))
+            .unwrap();
+        let msgs = batch.into_msgs().unwrap();
+        assert_eq!(msgs.len(), 3);
+        assert_eq!(msgs[0].nl_type, NetfilterMsg::BatchBegin);
+        assert_eq!(msgs[2].nl_type, NetfilterMsg::BatchEnd);
+        // Resource ID of the markers selects the nftables subsystem
+        assert_eq!(
+            &msgs[0].nl_payload[..],
+            &[
+                libc::AF_UNSPEC as u8,
+                0,
+                0,
+                libc::NFNL_SUBSYS_NFTABLES as u8
+            ]
+        );
+        assert_eq!(msgs[1].nl_type, NetfilterMsg::NftNewTable);
+        assert!(msgs[1].nl_flags.contains(&NlmF::Ack));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_nft_commit() {
+        let mut s =
+            NlSocket::connect(crate::consts::NlFamily::Netfilter, Some(0), None, true).unwrap();
+        let table = Table::new(NfProto::Inet, "neli_test");
+        let mut batch = Batch::new();
+        batch.add_table(&table).unwrap();
+        s.nft_commit(batch).unwrap();
+        let tables = s.nft_list_tables(NfProto::Inet).unwrap();
+        assert!(tables.iter().any(|t| t.name == "neli_test"));
+        let mut batch = Batch::new();
+        batch.del_table(&table).unwrap();
+        s.nft_commit(batch).unwrap();
+    }
+}
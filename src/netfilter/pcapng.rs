@@ -0,0 +1,302 @@
+//! Export of NFLOG packets to pcapng files
+//!
+//! `PcapngWriter` turns a stream of `LogPacket`s into a pcapng file that can be opened with
+//! Wireshark or tcpdump:
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufWriter;
+//!
+//! use neli::netfilter::pcapng::PcapngWriter;
+//! use neli::netfilter::LogPacket;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let file = BufWriter::new(File::create("nflog.pcapng")?);
+//! let mut writer = PcapngWriter::new(file)?;
+//! # let packets: Vec<LogPacket> = Vec::new();
+//! for pkt in packets.iter() {
+//!     writer.write_packet(pkt)?;
+//! }
+//! # Ok(()) }
+//! ```
+//!
+//! # Design decisions
+//!
+//! pcapng stores the link type per interface, so an interface description block is written for
+//! every combination of interface index and link type the first time a packet uses it. The
+//! inbound interface is used if the packet has one, otherwise the outbound interface, and the
+//! direction is recorded in the packet flags.
+//!
+//! NFLOG payloads start at the network header. If the packet carries an Ethernet link layer
+//! header (`hwheader`) it is prepended and the interface gets the Ethernet link type, otherwise
+//! the IPv4 or IPv6 link type is derived from the family or `hw_protocol` of the packet. The
+//! prefix of the logging rule is stored as a comment on the packet.
+//!
+//! All blocks are written in host byte order, as allowed by the format.
+
+use std::ffi::CStr;
+use std::io::{self, Write};
+use std::time::UNIX_EPOCH;
+
+use crate::netfilter::LogPacket;
+
+// Block types and options from the pcapng specification
+pub(crate) const SHB_TYPE: u32 = 0x0A0D_0D0A;
+pub(crate) const IDB_TYPE: u32 = 0x0000_0001;
+pub(crate) const EPB_TYPE: u32 = 0x0000_0006;
+pub(crate) const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+pub(crate) const OPT_ENDOFOPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const IF_NAME: u16 = 2;
+pub(crate) const EPB_FLAGS: u16 = 2;
+pub(crate) const EPB_FLAG_INBOUND: u32 = 1;
+pub(crate) const EPB_FLAG_OUTBOUND: u32 = 2;
+
+// Link types from https://www.tcpdump.org/linktypes.html
+const LINKTYPE_ETHERNET: u16 = 1;
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_IPV4: u16 = 228;
+const LINKTYPE_IPV6: u16 = 229;
+
+const ETH_HLEN: usize = 14;
+
+/// Writer of pcapng files containing NFLOG packets.
+pub struct PcapngWriter<W> {
+    writer: W,
+    // (ifindex, link type) of every interface description block written so far, the position
+    // is the interface ID
+    interfaces: Vec<(u32, u16)>,
+}
+
+impl<W> PcapngWriter<W>
+where
+    W: Write,
+{
+    /// Create a writer and write the section header to `writer`.
+    pub fn new(writer: W) -> Result<Self, io::Error> {
+        let mut pcapng = PcapngWriter {
+            writer,
+            interfaces: Vec::new(),
+        };
+        let mut body = Vec::with_capacity(16);
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_ne_bytes());
+        // Version 1.0
+        body.extend_from_slice(&1u16.to_ne_bytes());
+        body.extend_from_slice(&0u16.to_ne_bytes());
+        // Unknown section length
+        body.extend_from_slice(&(-1i64).to_ne_bytes());
+        pcapng.write_block(SHB_TYPE, &body)?;
+        Ok(pcapng)
+    }
+
+    /// Write a packet, preceded by an interface description block if it is the first packet on
+    /// its interface.
+    pub fn write_packet(&mut self, pkt: &LogPacket) -> Result<(), io::Error> {
+        let (ifindex, flags) = match (pkt.ifindex_in, pkt.ifindex_out) {
+            (Some(ifindex), _) => (ifindex, EPB_FLAG_INBOUND),
+            (None, Some(ifindex)) => (ifindex, EPB_FLAG_OUTBOUND),
+            (None, None) => (0, 0),
+        };
+        let ethernet = pkt.hwtype == Some(libc::ARPHRD_ETHER) && pkt.hwheader.len() == ETH_HLEN;
+        let link_type = if ethernet {
+            LINKTYPE_ETHERNET
+        } else {
+            ip_link_type(pkt)
+        };
+        let interface_id = self.interface_id(ifindex, link_type)?;
+
+        let mut data = Vec::with_capacity(pkt.hwheader.len() + pkt.payload.len());
+        if ethernet {
+            data.extend_from_slice(&pkt.hwheader);
+        }
+        data.extend_from_slice(&pkt.payload);
+        let orig_len = data.len() - pkt.payload.len() + ip_len(pkt).max(pkt.payload.len());
+
+        let usecs = pkt
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() * 1_000_000 + u64::from(d.subsec_micros()))
+            .unwrap_or(0);
+
+        let mut body = Vec::with_capacity(32 + data.len());
+        body.extend_from_slice(&interface_id.to_ne_bytes());
+        body.extend_from_slice(&((usecs >> 32) as u32).to_ne_bytes());
+        body.extend_from_slice(&(usecs as u32).to_ne_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_ne_bytes());
+        body.extend_from_slice(&(orig_len as u32).to_ne_bytes());
+        body.extend_from_slice(&data);
+        pad(&mut body);
+        if !pkt.prefix.as_bytes().is_empty() {
+            push_option(&mut body, OPT_COMMENT, pkt.prefix.as_bytes());
+        }
+        if flags != 0 {
+            push_option(&mut body, EPB_FLAGS, &flags.to_ne_bytes());
+        }
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        self.write_block(EPB_TYPE, &body)
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn interface_id(&mut self, ifindex: u32, link_type: u16) -> Result<u32, io::Error> {
+        if let Some(id) = self
+            .interfaces
+            .iter()
+            .position(|&iface| iface == (ifindex, link_type))
+        {
+            return Ok(id as u32);
+        }
+        let mut body = Vec::with_capacity(32);
+        body.extend_from_slice(&link_type.to_ne_bytes());
+        // Reserved
+        body.extend_from_slice(&0u16.to_ne_bytes());
+        // No snap length limit
+        body.extend_from_slice(&0u32.to_ne_bytes());
+        if let Some(name) = if_name(ifindex) {
+            push_option(&mut body, IF_NAME, name.as_bytes());
+        }
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        self.write_block(IDB_TYPE, &body)?;
+        self.interfaces.push((ifindex, link_type));
+        Ok(self.interfaces.len() as u32 - 1)
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> Result<(), io::Error> {
+        let total_len = (body.len() + 12) as u32;
+        self.writer.write_all(&block_type.to_ne_bytes())?;
+        self.writer.write_all(&total_len.to_ne_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&total_len.to_ne_bytes())?;
+        Ok(())
+    }
+}
+
+/// Link type for packets that start at the network header.
+fn ip_link_type(pkt: &LogPacket) -> u16 {
+    match (i32::from(pkt.family), pkt.hw_protocol as i32) {
+        (libc::AF_INET, _) | (_, libc::ETH_P_IP) => LINKTYPE_IPV4,
+        (libc::AF_INET6, _) | (_, libc::ETH_P_IPV6) => LINKTYPE_IPV6,
+        _ => LINKTYPE_RAW,
+    }
+}
+
+/// Length of the packet according to its IP header, which is larger than the payload if it was
+/// truncated to the copy range.
+fn ip_len(pkt: &LogPacket) -> usize {
+    let payload = &pkt.payload;
+    match payload.first().map(|b| b >> 4) {
+        Some(4) if payload.len() >= 4 => usize::from(u16::from_be_bytes([payload[2], payload[3]])),
+        Some(6) if payload.len() >= 6 => {
+            40 + usize::from(u16::from_be_bytes([payload[4], payload[5]]))
+        }
+        _ => 0,
+    }
+}
+
+fn if_name(ifindex: u32) -> Option<String> {
+    if ifindex == 0 {
+        return None;
+    }
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let name = unsafe { libc::if_indextoname(ifindex, buf.as_mut_ptr()) };
+    if name.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+pub(crate) fn pad(buf: &mut Vec<u8>) {
+    let len = (buf.len() + 3) & !3;
+    buf.resize(len, 0);
+}
+
+pub(crate) fn push_option(buf: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buf.extend_from_slice(&code.to_ne_bytes());
+    buf.extend_from_slice(&(value.len() as u16).to_ne_bytes());
+    buf.extend_from_slice(value);
+    pad(buf);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::ffi::CString;
+    use std::time::Duration;
+
+    fn u32_at(buf: &[u8], offset: usize) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&buf[offset..offset + 4]);
+        u32::from_ne_bytes(bytes)
+    }
+
+    /// Split the file into (block type, body) pairs, checking the block lengths.
+    fn blocks(buf: &[u8]) -> Vec<(u32, &[u8])> {
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        while offset < buf.len() {
+            let len = u32_at(buf, offset + 4) as usize;
+            assert_eq!(len % 4, 0);
+            assert_eq!(u32_at(buf, offset + len - 4) as usize, len);
+            blocks.push((u32_at(buf, offset), &buf[offset + 8..offset + len - 4]));
+            offset += len;
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_write_packets() {
+        let mut pkt = LogPacket::dummy_instance();
+        pkt.family = libc::AF_INET as u8;
+        pkt.timestamp = UNIX_EPOCH + Duration::new(1, 5000);
+        pkt.ifindex_in = Some(1_000_000);
+        // Truncated IPv4 header with a total length of 60
+        pkt.payload = vec![0x45, 0, 0, 60, 0];
+        pkt.prefix = CString::new("A packet").unwrap();
+
+        let mut ether = pkt.clone();
+        ether.hwtype = Some(libc::ARPHRD_ETHER);
+        ether.hwheader = vec![0xff; ETH_HLEN];
+
+        let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+        writer.write_packet(&pkt).unwrap();
+        writer.write_packet(&pkt).unwrap();
+        writer.write_packet(&ether).unwrap();
+        let buf = writer.into_inner();
+
+        let blocks = blocks(&buf);
+        let types: Vec<u32> = blocks.iter().map(|b| b.0).collect();
+        assert_eq!(
+            types,
+            vec![SHB_TYPE, IDB_TYPE, EPB_TYPE, EPB_TYPE, IDB_TYPE, EPB_TYPE]
+        );
+        assert_eq!(u32_at(blocks[0].1, 0), BYTE_ORDER_MAGIC);
+        assert_eq!(&blocks[1].1[..2], &LINKTYPE_IPV4.to_ne_bytes());
+        assert_eq!(&blocks[4].1[..2], &LINKTYPE_ETHERNET.to_ne_bytes());
+
+        let epb = blocks[2].1;
+        assert_eq!(u32_at(epb, 0), 0);
+        assert_eq!(u32_at(epb, 4), 0);
+        assert_eq!(u32_at(epb, 8), 1_000_005);
+        assert_eq!(u32_at(epb, 12), 5);
+        assert_eq!(u32_at(epb, 16), 60);
+        assert_eq!(&epb[20..25], &pkt.payload[..]);
+        // Comment option follows the padded packet data
+        assert_eq!(&epb[28..30], &OPT_COMMENT.to_ne_bytes());
+        assert_eq!(&epb[32..40], b"A packet");
+
+        let epb = blocks[5].1;
+        assert_eq!(u32_at(epb, 0), 1);
+        assert_eq!(u32_at(epb, 12), (ETH_HLEN + 5) as u32);
+        assert_eq!(u32_at(epb, 16), (ETH_HLEN + 60) as u32);
+    }
+}
@@ -0,0 +1,908 @@
+//! This module provides typed access to the nl80211 generic netlink family used to manage
+//! wireless devices: interfaces, wiphy capabilities, scans and stations.
+//!
+//! ```no_run
+//! use neli::nl80211::Nl80211Socket;
+//!
+//! let mut socket = Nl80211Socket::connect().unwrap();
+//! for interface in socket.get_interfaces().unwrap() {
+//!     println!("{:?} {:?}", interface.name, interface.iftype);
+//!     for bss in socket.get_scan(interface.ifindex).unwrap() {
+//!         let ssid = bss.ssid().map(String::from_utf8_lossy);
+//!         println!("  {:02x?} {:?} {} MHz", bss.bssid, ssid, bss.frequency);
+//!     }
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! Wiphy information is requested as a split dump, which the kernel sends as several messages
+//! per wiphy. `Nl80211Socket.get_wiphys()` merges them into one `Nl80211Wiphy` each.
+//!
+//! Events are received with `Nl80211Socket.recv_event()` after subscribing to multicast groups
+//! with `Nl80211Socket.subscribe()`. As events are not matched to requests, a socket used for
+//! events should not be used for requests at the same time.
+
+use consts::nl80211::{
+    Nl80211Attr, Nl80211Band, Nl80211BandAttr, Nl80211BitrateAttr, Nl80211BssAttr,
+    Nl80211BssStatus, Nl80211ChanWidth, Nl80211Cmd, Nl80211FrequencyAttr, Nl80211Iftype,
+    Nl80211RateInfo, Nl80211StaInfo,
+};
+use consts::{NlFamily, NlmF};
+use err::{DeError, NlError};
+use genl::Genlmsghdr;
+use nl::Nlmsghdr;
+use nlattr::{AttrHandle, Nlattr};
+use socket::NlSocket;
+
+/// Name of the nl80211 generic netlink family
+pub const NL80211_GENL_NAME: &str = "nl80211";
+/// Version of the nl80211 generic netlink family
+const NL80211_GENL_VERSION: u8 = 0;
+
+/// Information element ID of the SSID
+const WLAN_EID_SSID: u8 = 0;
+
+/// Multicast groups of the nl80211 family
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Nl80211McastGrp {
+    /// Wiphy and interface configuration changes
+    Config,
+    /// Scan started, finished and aborted
+    Scan,
+    /// Regulatory domain changes
+    Regulatory,
+    /// Authentication, association, connection and disconnection events
+    Mlme,
+    /// Vendor events
+    Vendor,
+    /// NAN events
+    Nan,
+    /// Test mode events
+    Testmode,
+}
+
+impl Nl80211McastGrp {
+    /// Name of the group as registered by the kernel
+    pub fn name(self) -> &'static str {
+        match self {
+            Nl80211McastGrp::Config => "config",
+            Nl80211McastGrp::Scan => "scan",
+            Nl80211McastGrp::Regulatory => "regulatory",
+            Nl80211McastGrp::Mlme => "mlme",
+            Nl80211McastGrp::Vendor => "vendor",
+            Nl80211McastGrp::Nan => "nan",
+            Nl80211McastGrp::Testmode => "testmode",
+        }
+    }
+}
+
+/// MAC address
+pub type MacAddr = [u8; 6];
+
+fn mac_from_payload(payload: &[u8]) -> Result<MacAddr, DeError> {
+    if payload.len() < 6 {
+        return Err(DeError::new("MAC address attribute is too short"));
+    }
+    let mut mac = [0; 6];
+    mac.copy_from_slice(&payload[..6]);
+    Ok(mac)
+}
+
+/// Wireless interface (`NL80211_CMD_NEW_INTERFACE`)
+///
+/// Fields that were not present in the message are `None`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nl80211Interface {
+    /// Network interface index, `0` for interfaces without a netdev such as P2P devices
+    pub ifindex: u32,
+    /// Network interface name
+    pub name: Option<String>,
+    /// Interface type
+    pub iftype: Option<Nl80211Iftype>,
+    /// Index of the wiphy the interface belongs to
+    pub wiphy: Option<u32>,
+    /// Wireless device identifier
+    pub wdev: Option<u64>,
+    /// MAC address
+    pub mac: Option<MacAddr>,
+    /// SSID of the network the interface is connected to or operating
+    pub ssid: Option<Vec<u8>>,
+    /// Operating frequency in MHz
+    pub frequency: Option<u32>,
+    /// Operating channel width
+    pub channel_width: Option<Nl80211ChanWidth>,
+    /// Center frequency of the first segment of the channel in MHz
+    pub center_freq1: Option<u32>,
+    /// Transmit power in mBm (100 * dBm)
+    pub tx_power_mbm: Option<u32>,
+    /// Whether four address mode is enabled
+    pub use_4addr: bool,
+}
+
+impl Nl80211Interface {
+    /// Parse an interface from nl80211 attributes
+    pub fn from_attrs(handle: &AttrHandle<Nl80211Attr>) -> Result<Self, DeError> {
+        let mut interface = Nl80211Interface::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211Attr::Ifindex => interface.ifindex = attr.get_payload_as()?,
+                Nl80211Attr::Ifname => interface.name = Some(attr.get_payload_as()?),
+                Nl80211Attr::Iftype => {
+                    interface.iftype = Some(Nl80211Iftype::from(attr.get_payload_as::<u32>()?))
+                }
+                Nl80211Attr::Wiphy => interface.wiphy = Some(attr.get_payload_as()?),
+                Nl80211Attr::Wdev => interface.wdev = Some(attr.get_payload_as()?),
+                Nl80211Attr::Mac => interface.mac = Some(mac_from_payload(&attr.payload)?),
+                Nl80211Attr::Ssid => interface.ssid = Some(attr.payload.clone()),
+                Nl80211Attr::WiphyFreq => interface.frequency = Some(attr.get_payload_as()?),
+                Nl80211Attr::ChannelWidth => {
+                    interface.channel_width =
+                        Some(Nl80211ChanWidth::from(attr.get_payload_as::<u32>()?))
+                }
+                Nl80211Attr::CenterFreq1 => interface.center_freq1 = Some(attr.get_payload_as()?),
+                Nl80211Attr::WiphyTxPowerLevel => {
+                    interface.tx_power_mbm = Some(attr.get_payload_as()?)
+                }
+                Nl80211Attr::Addr4 => interface.use_4addr = attr.get_payload_as::<u8>()? != 0,
+                _ => (),
+            }
+        }
+        Ok(interface)
+    }
+}
+
+/// Channel of a band supported by a wiphy
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nl80211Frequency {
+    /// Center frequency in MHz
+    pub freq: u32,
+    /// Whether the channel is disabled by regulatory rules
+    pub disabled: bool,
+    /// Whether initiating radiation (beaconing, probing) is forbidden
+    pub no_ir: bool,
+    /// Whether radar detection is required
+    pub radar: bool,
+    /// Maximum transmit power in mBm
+    pub max_tx_power_mbm: Option<u32>,
+}
+
+impl Nl80211Frequency {
+    fn from_attrs(handle: &AttrHandle<Nl80211FrequencyAttr>) -> Result<Self, DeError> {
+        let mut freq = Nl80211Frequency::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211FrequencyAttr::Freq => freq.freq = attr.get_payload_as()?,
+                Nl80211FrequencyAttr::Disabled => freq.disabled = true,
+                Nl80211FrequencyAttr::NoIr => freq.no_ir = true,
+                Nl80211FrequencyAttr::Radar => freq.radar = true,
+                Nl80211FrequencyAttr::MaxTxPower => {
+                    freq.max_tx_power_mbm = Some(attr.get_payload_as()?)
+                }
+                _ => (),
+            }
+        }
+        Ok(freq)
+    }
+}
+
+/// Band supported by a wiphy
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nl80211BandInfo {
+    /// Band
+    pub band: Nl80211Band,
+    /// Channels of the band
+    pub frequencies: Vec<Nl80211Frequency>,
+    /// Legacy bitrates in units of 100 kbps
+    pub bitrates: Vec<u32>,
+    /// HT capabilities, if HT is supported
+    pub ht_capa: Option<u16>,
+    /// VHT capabilities, if VHT is supported
+    pub vht_capa: Option<u32>,
+}
+
+impl Nl80211BandInfo {
+    fn merge_attrs(&mut self, handle: &AttrHandle<Nl80211BandAttr>) -> Result<(), DeError> {
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211BandAttr::Freqs => {
+                    for freq in attr.get_nested_attributes::<u16>()?.iter() {
+                        self.frequencies.push(Nl80211Frequency::from_attrs(
+                            &freq.get_nested_attributes()?,
+                        )?);
+                    }
+                }
+                Nl80211BandAttr::Rates => {
+                    for rate in attr.get_nested_attributes::<u16>()?.iter() {
+                        let rate = rate.get_nested_attributes::<Nl80211BitrateAttr>()?;
+                        self.bitrates
+                            .push(rate.get_attr_payload_as(Nl80211BitrateAttr::Rate)?);
+                    }
+                }
+                Nl80211BandAttr::HtCapa => self.ht_capa = Some(attr.get_payload_as()?),
+                Nl80211BandAttr::VhtCapa => self.vht_capa = Some(attr.get_payload_as()?),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wireless hardware device and its capabilities (`NL80211_CMD_NEW_WIPHY`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nl80211Wiphy {
+    /// Index of the wiphy
+    pub index: u32,
+    /// Name of the wiphy, eg. `phy0`
+    pub name: String,
+    /// Interface types the wiphy supports
+    pub supported_iftypes: Vec<Nl80211Iftype>,
+    /// Bands the wiphy supports
+    pub bands: Vec<Nl80211BandInfo>,
+    /// Maximum number of SSIDs in a scan request
+    pub max_scan_ssids: Option<u8>,
+    /// Maximum number of SSIDs in a scheduled scan request
+    pub max_sched_scan_ssids: Option<u8>,
+    /// Maximum length of information elements added to probe requests
+    pub max_scan_ie_len: Option<u16>,
+    /// Supported cipher suites as IEEE 802.11 suite selectors
+    pub cipher_suites: Vec<u32>,
+    /// Commands the wiphy supports
+    pub supported_commands: Vec<Nl80211Cmd>,
+}
+
+impl Nl80211Wiphy {
+    /// Parse a wiphy from nl80211 attributes
+    pub fn from_attrs(handle: &AttrHandle<Nl80211Attr>) -> Result<Self, DeError> {
+        let mut wiphy = Nl80211Wiphy::default();
+        wiphy.merge_attrs(handle)?;
+        Ok(wiphy)
+    }
+
+    /// Add the information of one message of a split wiphy dump to the wiphy
+    pub fn merge_attrs(&mut self, handle: &AttrHandle<Nl80211Attr>) -> Result<(), DeError> {
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211Attr::Wiphy => self.index = attr.get_payload_as()?,
+                Nl80211Attr::WiphyName => self.name = attr.get_payload_as()?,
+                Nl80211Attr::SupportedIftypes => {
+                    for iftype in attr.get_nested_attributes::<u16>()?.iter() {
+                        let iftype = Nl80211Iftype::from(u32::from(iftype.nla_type));
+                        if !self.supported_iftypes.contains(&iftype) {
+                            self.supported_iftypes.push(iftype);
+                        }
+                    }
+                }
+                Nl80211Attr::WiphyBands => {
+                    for band_attr in attr.get_nested_attributes::<u16>()?.iter() {
+                        let band = Nl80211Band::from(band_attr.nla_type);
+                        let pos = match self.bands.iter().position(|b| b.band == band) {
+                            Some(pos) => pos,
+                            None => {
+                                self.bands.push(Nl80211BandInfo {
+                                    band,
+                                    frequencies: Vec::new(),
+                                    bitrates: Vec::new(),
+                                    ht_capa: None,
+                                    vht_capa: None,
+                                });
+                                self.bands.len() - 1
+                            }
+                        };
+                        self.bands[pos].merge_attrs(&band_attr.get_nested_attributes()?)?;
+                    }
+                }
+                Nl80211Attr::MaxNumScanSsids => self.max_scan_ssids = Some(attr.get_payload_as()?),
+                Nl80211Attr::MaxNumSchedScanSsids => {
+                    self.max_sched_scan_ssids = Some(attr.get_payload_as()?)
+                }
+                Nl80211Attr::MaxScanIeLen => self.max_scan_ie_len = Some(attr.get_payload_as()?),
+                Nl80211Attr::CipherSuites => {
+                    self.cipher_suites.extend(
+                        attr.payload
+                            .chunks_exact(4)
+                            .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]])),
+                    );
+                }
+                Nl80211Attr::SupportedCommands => {
+                    for cmd in attr.get_nested_attributes::<u16>()?.iter() {
+                        let cmd = cmd.get_payload_as::<u32>()?;
+                        self.supported_commands.push(Nl80211Cmd::from(cmd as u8));
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// IEEE 802.11 information element
+#[derive(Clone, Debug, PartialEq)]
+pub struct InformationElement {
+    /// Element ID
+    pub id: u8,
+    /// Element data
+    pub data: Vec<u8>,
+}
+
+impl InformationElement {
+    /// Parse a sequence of information elements. Parsing stops at a truncated element, which
+    /// some drivers report at the end of the data.
+    pub fn parse_all(mut ies: &[u8]) -> Vec<Self> {
+        let mut elements = Vec::new();
+        while ies.len() >= 2 {
+            let len = ies[1] as usize;
+            if ies.len() < 2 + len {
+                break;
+            }
+            elements.push(InformationElement {
+                id: ies[0],
+                data: ies[2..2 + len].to_vec(),
+            });
+            ies = &ies[2 + len..];
+        }
+        elements
+    }
+}
+
+/// BSS found by a scan (`NL80211_CMD_NEW_SCAN_RESULTS`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nl80211Bss {
+    /// Interface the scan result was reported for
+    pub ifindex: u32,
+    /// BSSID
+    pub bssid: MacAddr,
+    /// Frequency in MHz
+    pub frequency: u32,
+    /// Timing synchronization function value
+    pub tsf: Option<u64>,
+    /// Beacon interval in time units (1024 µs)
+    pub beacon_interval: Option<u16>,
+    /// Capability information field
+    pub capability: Option<u16>,
+    /// Information elements from the last probe response or beacon
+    pub ies: Vec<InformationElement>,
+    /// Signal strength in mBm (100 * dBm)
+    pub signal_mbm: Option<i32>,
+    /// Signal strength in driver specific units from 0 to 100
+    pub signal_unspec: Option<u8>,
+    /// Association status of the interface with the BSS
+    pub status: Option<Nl80211BssStatus>,
+    /// Milliseconds since the BSS was last seen
+    pub seen_ms_ago: Option<u32>,
+}
+
+impl Nl80211Bss {
+    /// Parse a scan result from nl80211 attributes
+    pub fn from_attrs(handle: &AttrHandle<Nl80211Attr>) -> Result<Self, DeError> {
+        let mut bss = Nl80211Bss::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211Attr::Ifindex => bss.ifindex = attr.get_payload_as()?,
+                Nl80211Attr::Bss => bss.merge_bss_attrs(&attr.get_nested_attributes()?)?,
+                _ => (),
+            }
+        }
+        Ok(bss)
+    }
+
+    fn merge_bss_attrs(&mut self, handle: &AttrHandle<Nl80211BssAttr>) -> Result<(), DeError> {
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211BssAttr::Bssid => self.bssid = mac_from_payload(&attr.payload)?,
+                Nl80211BssAttr::Frequency => self.frequency = attr.get_payload_as()?,
+                Nl80211BssAttr::Tsf => self.tsf = Some(attr.get_payload_as()?),
+                Nl80211BssAttr::BeaconInterval => {
+                    self.beacon_interval = Some(attr.get_payload_as()?)
+                }
+                Nl80211BssAttr::Capability => self.capability = Some(attr.get_payload_as()?),
+                Nl80211BssAttr::InformationElements => {
+                    self.ies = InformationElement::parse_all(&attr.payload)
+                }
+                Nl80211BssAttr::SignalMbm => self.signal_mbm = Some(attr.get_payload_as()?),
+                Nl80211BssAttr::SignalUnspec => self.signal_unspec = Some(attr.get_payload_as()?),
+                Nl80211BssAttr::Status => {
+                    self.status = Some(Nl80211BssStatus::from(attr.get_payload_as::<u32>()?))
+                }
+                Nl80211BssAttr::SeenMsAgo => self.seen_ms_ago = Some(attr.get_payload_as()?),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// SSID of the BSS, from its information elements
+    pub fn ssid(&self) -> Option<&[u8]> {
+        self.ies
+            .iter()
+            .find(|ie| ie.id == WLAN_EID_SSID)
+            .map(|ie| ie.data.as_slice())
+    }
+}
+
+/// Bitrate information of a station
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nl80211RateInfoData {
+    /// Bitrate in units of 100 kbps
+    pub bitrate: Option<u32>,
+    /// HT MCS index
+    pub mcs: Option<u8>,
+    /// VHT MCS index
+    pub vht_mcs: Option<u8>,
+    /// VHT number of spatial streams
+    pub vht_nss: Option<u8>,
+    /// HE MCS index
+    pub he_mcs: Option<u8>,
+    /// HE number of spatial streams
+    pub he_nss: Option<u8>,
+    /// Whether a short guard interval is used
+    pub short_gi: bool,
+    /// Channel width in MHz
+    pub width_mhz: u32,
+}
+
+impl Nl80211RateInfoData {
+    fn from_attrs(handle: &AttrHandle<Nl80211RateInfo>) -> Result<Self, DeError> {
+        let mut rate = Nl80211RateInfoData {
+            width_mhz: 20,
+            ..Nl80211RateInfoData::default()
+        };
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211RateInfo::Bitrate if rate.bitrate.is_none() => {
+                    rate.bitrate = Some(u32::from(attr.get_payload_as::<u16>()?))
+                }
+                Nl80211RateInfo::Bitrate32 => rate.bitrate = Some(attr.get_payload_as()?),
+                Nl80211RateInfo::Mcs => rate.mcs = Some(attr.get_payload_as()?),
+                Nl80211RateInfo::VhtMcs => rate.vht_mcs = Some(attr.get_payload_as()?),
+                Nl80211RateInfo::VhtNss => rate.vht_nss = Some(attr.get_payload_as()?),
+                Nl80211RateInfo::HeMcs => rate.he_mcs = Some(attr.get_payload_as()?),
+                Nl80211RateInfo::HeNss => rate.he_nss = Some(attr.get_payload_as()?),
+                Nl80211RateInfo::ShortGi => rate.short_gi = true,
+                Nl80211RateInfo::Width5Mhz => rate.width_mhz = 5,
+                Nl80211RateInfo::Width10Mhz => rate.width_mhz = 10,
+                Nl80211RateInfo::Width40Mhz => rate.width_mhz = 40,
+                Nl80211RateInfo::Width80Mhz => rate.width_mhz = 80,
+                Nl80211RateInfo::Width80p80Mhz | Nl80211RateInfo::Width160Mhz => {
+                    rate.width_mhz = 160
+                }
+                Nl80211RateInfo::Width320Mhz => rate.width_mhz = 320,
+                _ => (),
+            }
+        }
+        Ok(rate)
+    }
+}
+
+/// Station associated with an interface (`NL80211_CMD_NEW_STATION`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Nl80211Station {
+    /// Interface the station is associated with
+    pub ifindex: u32,
+    /// MAC address of the station
+    pub mac: MacAddr,
+    /// Milliseconds since the last activity
+    pub inactive_time_ms: Option<u32>,
+    /// Received bytes
+    pub rx_bytes: Option<u64>,
+    /// Transmitted bytes
+    pub tx_bytes: Option<u64>,
+    /// Received packets
+    pub rx_packets: Option<u32>,
+    /// Transmitted packets
+    pub tx_packets: Option<u32>,
+    /// Transmission retries
+    pub tx_retries: Option<u32>,
+    /// Failed transmissions
+    pub tx_failed: Option<u32>,
+    /// Signal strength of the last received frame in dBm
+    pub signal_dbm: Option<i8>,
+    /// Average signal strength in dBm
+    pub signal_avg_dbm: Option<i8>,
+    /// Transmit bitrate
+    pub tx_bitrate: Option<Nl80211RateInfoData>,
+    /// Receive bitrate
+    pub rx_bitrate: Option<Nl80211RateInfoData>,
+    /// Seconds since the station connected
+    pub connected_time_s: Option<u32>,
+}
+
+impl Nl80211Station {
+    /// Parse a station from nl80211 attributes
+    pub fn from_attrs(handle: &AttrHandle<Nl80211Attr>) -> Result<Self, DeError> {
+        let mut station = Nl80211Station::default();
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211Attr::Ifindex => station.ifindex = attr.get_payload_as()?,
+                Nl80211Attr::Mac => station.mac = mac_from_payload(&attr.payload)?,
+                Nl80211Attr::StaInfo => station.merge_sta_info(&attr.get_nested_attributes()?)?,
+                _ => (),
+            }
+        }
+        Ok(station)
+    }
+
+    fn merge_sta_info(&mut self, handle: &AttrHandle<Nl80211StaInfo>) -> Result<(), DeError> {
+        for attr in handle.iter() {
+            match attr.nla_type {
+                Nl80211StaInfo::InactiveTime => {
+                    self.inactive_time_ms = Some(attr.get_payload_as()?)
+                }
+                Nl80211StaInfo::RxBytes if self.rx_bytes.is_none() => {
+                    self.rx_bytes = Some(u64::from(attr.get_payload_as::<u32>()?))
+                }
+                Nl80211StaInfo::TxBytes if self.tx_bytes.is_none() => {
+                    self.tx_bytes = Some(u64::from(attr.get_payload_as::<u32>()?))
+                }
+                Nl80211StaInfo::RxBytes64 => self.rx_bytes = Some(attr.get_payload_as()?),
+                Nl80211StaInfo::TxBytes64 => self.tx_bytes = Some(attr.get_payload_as()?),
+                Nl80211StaInfo::RxPackets => self.rx_packets = Some(attr.get_payload_as()?),
+                Nl80211StaInfo::TxPackets => self.tx_packets = Some(attr.get_payload_as()?),
+                Nl80211StaInfo::TxRetries => self.tx_retries = Some(attr.get_payload_as()?),
+                Nl80211StaInfo::TxFailed => self.tx_failed = Some(attr.get_payload_as()?),
+                Nl80211StaInfo::Signal => {
+                    self.signal_dbm = Some(attr.get_payload_as::<u8>()? as i8)
+                }
+                Nl80211StaInfo::SignalAvg => {
+                    self.signal_avg_dbm = Some(attr.get_payload_as::<u8>()? as i8)
+                }
+                Nl80211StaInfo::TxBitrate => {
+                    self.tx_bitrate = Some(Nl80211RateInfoData::from_attrs(
+                        &attr.get_nested_attributes()?,
+                    )?)
+                }
+                Nl80211StaInfo::RxBitrate => {
+                    self.rx_bitrate = Some(Nl80211RateInfoData::from_attrs(
+                        &attr.get_nested_attributes()?,
+                    )?)
+                }
+                Nl80211StaInfo::ConnectedTime => {
+                    self.connected_time_s = Some(attr.get_payload_as()?)
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Socket for the nl80211 family with its resolved family ID
+pub struct Nl80211Socket {
+    socket: NlSocket,
+    family_id: u16,
+}
+
+impl Nl80211Socket {
+    /// Connect to the nl80211 family
+    pub fn connect() -> Result<Self, NlError> {
+        let mut socket = NlSocket::connect(NlFamily::Generic, None, None, true)?;
+        let family_id = socket.resolve_genl_family(NL80211_GENL_NAME)?;
+        Ok(Nl80211Socket { socket, family_id })
+    }
+
+    /// Generic netlink family ID of nl80211
+    pub fn family_id(&self) -> u16 {
+        self.family_id
+    }
+
+    /// Underlying socket, to send requests not covered by this type
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+
+    /// List all wireless interfaces
+    pub fn get_interfaces(&mut self) -> Result<Vec<Nl80211Interface>, NlError> {
+        let msgs = self.dump(Nl80211Cmd::GetInterface, Vec::new())?;
+        let mut interfaces = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            interfaces.push(Nl80211Interface::from_attrs(&msg.get_attr_handle())?);
+        }
+        Ok(interfaces)
+    }
+
+    /// Get the wireless interface with index `ifindex`
+    pub fn get_interface(&mut self, ifindex: u32) -> Result<Nl80211Interface, NlError> {
+        let attrs = vec![Nlattr::new(None, Nl80211Attr::Ifindex, ifindex)?];
+        let msgs = self.request(Nl80211Cmd::GetInterface, vec![NlmF::Request], attrs)?;
+        match msgs.first() {
+            Some(msg) => Ok(Nl80211Interface::from_attrs(&msg.get_attr_handle())?),
+            None => Err(NlError::new("No interface returned")),
+        }
+    }
+
+    /// List all wiphys with their capabilities
+    pub fn get_wiphys(&mut self) -> Result<Vec<Nl80211Wiphy>, NlError> {
+        let attrs = vec![Nlattr::new(
+            None,
+            Nl80211Attr::SplitWiphyDump,
+            Vec::<u8>::new(),
+        )?];
+        let msgs = self.dump(Nl80211Cmd::GetWiphy, attrs)?;
+        let mut wiphys: Vec<Nl80211Wiphy> = Vec::new();
+        for msg in msgs.iter() {
+            let handle = msg.get_attr_handle();
+            let index = handle.get_attr_payload_as::<u32>(Nl80211Attr::Wiphy)?;
+            match wiphys.iter_mut().find(|w| w.index == index) {
+                Some(wiphy) => wiphy.merge_attrs(&handle)?,
+                None => wiphys.push(Nl80211Wiphy::from_attrs(&handle)?),
+            }
+        }
+        Ok(wiphys)
+    }
+
+    /// Start a scan on interface `ifindex`. Active scans probe for `ssids`, an empty SSID
+    /// probing for all networks; no SSIDs requests a passive scan. No frequencies scans all
+    /// channels. The end of the scan is reported on the `Nl80211McastGrp::Scan` group.
+    pub fn trigger_scan(
+        &mut self,
+        ifindex: u32,
+        ssids: &[&[u8]],
+        frequencies: &[u32],
+    ) -> Result<(), NlError> {
+        let mut attrs = vec![Nlattr::new(None, Nl80211Attr::Ifindex, ifindex)?];
+        if !ssids.is_empty() {
+            let ssids = ssids
+                .iter()
+                .enumerate()
+                .map(|(i, ssid)| Nlattr::new(None, i as u16 + 1, *ssid))
+                .collect::<Result<Vec<_>, _>>()?;
+            attrs.push(Nlattr::new_nested(Nl80211Attr::ScanSsids, &ssids)?);
+        }
+        if !frequencies.is_empty() {
+            let freqs = frequencies
+                .iter()
+                .enumerate()
+                .map(|(i, freq)| Nlattr::new(None, i as u16 + 1, *freq))
+                .collect::<Result<Vec<_>, _>>()?;
+            attrs.push(Nlattr::new_nested(Nl80211Attr::ScanFrequencies, &freqs)?);
+        }
+        self.send(
+            Nl80211Cmd::TriggerScan,
+            vec![NlmF::Request, NlmF::Ack],
+            attrs,
+        )?;
+        self.socket.recv_ack()
+    }
+
+    /// Get the scan results of interface `ifindex`
+    pub fn get_scan(&mut self, ifindex: u32) -> Result<Vec<Nl80211Bss>, NlError> {
+        let attrs = vec![Nlattr::new(None, Nl80211Attr::Ifindex, ifindex)?];
+        let msgs = self.dump(Nl80211Cmd::GetScan, attrs)?;
+        let mut bss = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            bss.push(Nl80211Bss::from_attrs(&msg.get_attr_handle())?);
+        }
+        Ok(bss)
+    }
+
+    /// List the stations associated with interface `ifindex`
+    pub fn get_stations(&mut self, ifindex: u32) -> Result<Vec<Nl80211Station>, NlError> {
+        let attrs = vec![Nlattr::new(None, Nl80211Attr::Ifindex, ifindex)?];
+        let msgs = self.dump(Nl80211Cmd::GetStation, attrs)?;
+        let mut stations = Vec::with_capacity(msgs.len());
+        for msg in msgs.iter() {
+            stations.push(Nl80211Station::from_attrs(&msg.get_attr_handle())?);
+        }
+        Ok(stations)
+    }
+
+    /// Subscribe to the events of multicast group `group`
+    pub fn subscribe(&mut self, group: Nl80211McastGrp) -> Result<(), NlError> {
+        let id = self
+            .socket
+            .resolve_nl_mcast_group(NL80211_GENL_NAME, group.name())?;
+        self.socket.set_mcast_groups(vec![id])?;
+        Ok(())
+    }
+
+    /// Receive the next event of the subscribed multicast groups. The attributes can be parsed
+    /// with the `from_attrs()` methods, eg. `Nl80211Interface::from_attrs()` for
+    /// `Nl80211Cmd::NewInterface`.
+    pub fn recv_event(&mut self) -> Result<Genlmsghdr<Nl80211Cmd, Nl80211Attr>, NlError> {
+        Ok(self
+            .socket
+            .recv_nl::<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>(None)?
+            .nl_payload)
+    }
+
+    fn send(
+        &mut self,
+        cmd: Nl80211Cmd,
+        flags: Vec<NlmF>,
+        attrs: Vec<Nlattr<Nl80211Attr, Vec<u8>>>,
+    ) -> Result<(), NlError> {
+        let genlhdr = Genlmsghdr::new(cmd, NL80211_GENL_VERSION, attrs)?;
+        let nlhdr = Nlmsghdr::new(None, self.family_id, flags, None, None, genlhdr);
+        self.socket.send_nl(nlhdr)
+    }
+
+    fn request(
+        &mut self,
+        cmd: Nl80211Cmd,
+        flags: Vec<NlmF>,
+        attrs: Vec<Nlattr<Nl80211Attr, Vec<u8>>>,
+    ) -> Result<Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, NlError> {
+        self.send(cmd, flags, attrs)?;
+        Ok(self
+            .socket
+            .recv_all::<u16, Genlmsghdr<Nl80211Cmd, Nl80211Attr>>()?
+            .into_iter()
+            .map(|msg| msg.nl_payload)
+            .collect())
+    }
+
+    fn dump(
+        &mut self,
+        cmd: Nl80211Cmd,
+        attrs: Vec<Nlattr<Nl80211Attr, Vec<u8>>>,
+    ) -> Result<Vec<Genlmsghdr<Nl80211Cmd, Nl80211Attr>>, NlError> {
+        self.request(cmd, vec![NlmF::Request, NlmF::Dump], attrs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(
+        cmd: Nl80211Cmd,
+        attrs: Vec<Nlattr<Nl80211Attr, Vec<u8>>>,
+    ) -> Genlmsghdr<Nl80211Cmd, Nl80211Attr> {
+        ::round_trip(&Genlmsghdr::new(cmd, NL80211_GENL_VERSION, attrs).unwrap())
+    }
+
+    #[test]
+    fn test_interface() {
+        let msg = round_trip(
+            Nl80211Cmd::NewInterface,
+            vec![
+                Nlattr::new(None, Nl80211Attr::Ifindex, 3u32).unwrap(),
+                Nlattr::new(None, Nl80211Attr::Ifname, "wlan0").unwrap(),
+                Nlattr::new(None, Nl80211Attr::Iftype, 2u32).unwrap(),
+                Nlattr::new(None, Nl80211Attr::Mac, vec![2, 0, 0, 0, 0, 1]).unwrap(),
+                Nlattr::new(None, Nl80211Attr::WiphyFreq, 2412u32).unwrap(),
+                Nlattr::new(None, Nl80211Attr::ChannelWidth, 1u32).unwrap(),
+            ],
+        );
+        let interface = Nl80211Interface::from_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(interface.ifindex, 3);
+        assert_eq!(interface.name, Some("wlan0".to_string()));
+        assert_eq!(interface.iftype, Some(Nl80211Iftype::Station));
+        assert_eq!(interface.mac, Some([2, 0, 0, 0, 0, 1]));
+        assert_eq!(interface.frequency, Some(2412));
+        assert_eq!(interface.channel_width, Some(Nl80211ChanWidth::Width20));
+    }
+
+    #[test]
+    fn test_split_wiphy() {
+        let freq = |f: u32, disabled: bool| {
+            let mut attrs = vec![Nlattr::new(None, Nl80211FrequencyAttr::Freq, f).unwrap()];
+            if disabled {
+                attrs.push(
+                    Nlattr::new(None, Nl80211FrequencyAttr::Disabled, Vec::<u8>::new()).unwrap(),
+                );
+            }
+            attrs
+        };
+        let band = |freqs: Vec<Vec<Nlattr<Nl80211FrequencyAttr, Vec<u8>>>>| {
+            let freqs = freqs
+                .into_iter()
+                .enumerate()
+                .map(|(i, f)| Nlattr::new_nested(i as u16, &f).unwrap())
+                .collect::<Vec<_>>();
+            let band = vec![Nlattr::new_nested(Nl80211BandAttr::Freqs, &freqs).unwrap()];
+            Nlattr::new_nested(
+                Nl80211Attr::WiphyBands,
+                &[Nlattr::new_nested(u16::from(Nl80211Band::Band2ghz), &band).unwrap()],
+            )
+            .unwrap()
+        };
+        let first = round_trip(
+            Nl80211Cmd::NewWiphy,
+            vec![
+                Nlattr::new(None, Nl80211Attr::Wiphy, 0u32).unwrap(),
+                Nlattr::new(None, Nl80211Attr::WiphyName, "phy0").unwrap(),
+                Nlattr::new(None, Nl80211Attr::MaxNumScanSsids, 4u8).unwrap(),
+                Nlattr::new_nested(
+                    Nl80211Attr::SupportedIftypes,
+                    &[
+                        Nlattr::new(None, 2u16, Vec::<u8>::new()).unwrap(),
+                        Nlattr::new(None, 3u16, Vec::<u8>::new()).unwrap(),
+                    ],
+                )
+                .unwrap(),
+                band(vec![freq(2412, false)]),
+            ],
+        );
+        let second = round_trip(
+            Nl80211Cmd::NewWiphy,
+            vec![
+                Nlattr::new(None, Nl80211Attr::Wiphy, 0u32).unwrap(),
+                band(vec![freq(2417, false), freq(2484, true)]),
+            ],
+        );
+        let mut wiphy = Nl80211Wiphy::from_attrs(&first.get_attr_handle()).unwrap();
+        wiphy.merge_attrs(&second.get_attr_handle()).unwrap();
+        assert_eq!(wiphy.name, "phy0");
+        assert_eq!(wiphy.max_scan_ssids, Some(4));
+        assert_eq!(
+            wiphy.supported_iftypes,
+            vec![Nl80211Iftype::Station, Nl80211Iftype::Ap]
+        );
+        assert_eq!(wiphy.bands.len(), 1);
+        let freqs = &wiphy.bands[0].frequencies;
+        assert_eq!(
+            freqs.iter().map(|f| f.freq).collect::<Vec<_>>(),
+            vec![2412, 2417, 2484]
+        );
+        assert!(freqs[2].disabled);
+    }
+
+    #[test]
+    fn test_bss() {
+        let ies = vec![0, 4, b't', b'e', b's', b't', 1, 2, 0x82, 0x84, 221];
+        let bss_attrs = vec![
+            Nlattr::new(None, Nl80211BssAttr::Bssid, vec![0, 1, 2, 3, 4, 5]).unwrap(),
+            Nlattr::new(None, Nl80211BssAttr::Frequency, 5180u32).unwrap(),
+            Nlattr::new(None, Nl80211BssAttr::InformationElements, ies).unwrap(),
+            Nlattr::new(None, Nl80211BssAttr::SignalMbm, -4500i32).unwrap(),
+            Nlattr::new(None, Nl80211BssAttr::Status, 1u32).unwrap(),
+        ];
+        let msg = round_trip(
+            Nl80211Cmd::NewScanResults,
+            vec![
+                Nlattr::new(None, Nl80211Attr::Ifindex, 3u32).unwrap(),
+                Nlattr::new_nested(Nl80211Attr::Bss, &bss_attrs).unwrap(),
+            ],
+        );
+        let bss = Nl80211Bss::from_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(bss.bssid, [0, 1, 2, 3, 4, 5]);
+        assert_eq!(bss.frequency, 5180);
+        assert_eq!(bss.ssid(), Some(&b"test"[..]));
+        // The truncated element at the end is dropped
+        assert_eq!(bss.ies.len(), 2);
+        assert_eq!(bss.signal_mbm, Some(-4500));
+        assert_eq!(bss.status, Some(Nl80211BssStatus::Associated));
+    }
+
+    #[test]
+    fn test_station() {
+        let rate = vec![
+            Nlattr::new(None, Nl80211RateInfo::Bitrate, 65u16).unwrap(),
+            Nlattr::new(None, Nl80211RateInfo::Bitrate32, 866u32).unwrap(),
+            Nlattr::new(None, Nl80211RateInfo::VhtMcs, 9u8).unwrap(),
+            Nlattr::new(None, Nl80211RateInfo::Width80Mhz, Vec::<u8>::new()).unwrap(),
+        ];
+        let sta_info = vec![
+            Nlattr::new(None, Nl80211StaInfo::RxBytes, 10u32).unwrap(),
+            Nlattr::new(None, Nl80211StaInfo::RxBytes64, 1u64 << 33).unwrap(),
+            Nlattr::new(None, Nl80211StaInfo::Signal, -50i8 as u8).unwrap(),
+            Nlattr::new_nested(Nl80211StaInfo::TxBitrate, &rate).unwrap(),
+        ];
+        let msg = round_trip(
+            Nl80211Cmd::NewStation,
+            vec![
+                Nlattr::new(None, Nl80211Attr::Mac, vec![0, 1, 2, 3, 4, 5]).unwrap(),
+                Nlattr::new_nested(Nl80211Attr::StaInfo, &sta_info).unwrap(),
+            ],
+        );
+        let station = Nl80211Station::from_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(station.rx_bytes, Some(1 << 33));
+        assert_eq!(station.signal_dbm, Some(-50));
+        let tx = station.tx_bitrate.unwrap();
+        assert_eq!(tx.bitrate, Some(866));
+        assert_eq!(tx.vht_mcs, Some(9));
+        assert_eq!(tx.width_mhz, 80);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_get_interfaces() {
+        let mut socket = Nl80211Socket::connect().unwrap();
+        let wiphys = socket.get_wiphys().unwrap();
+        for interface in socket.get_interfaces().unwrap() {
+            assert!(wiphys.iter().any(|w| Some(w.index) == interface.wiphy));
+        }
+    }
+}
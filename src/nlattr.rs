@@ -52,6 +52,10 @@
 //!     attribute payloads contained in the `Genlmsghdr` payload
 //!     * Only `Nlattr` knows what is padding and what is not in its own payload - to every other
 //!     serialization and deserialization method, it may or may not be padding
+//!
+//! The `NLA_F_NESTED` and `NLA_F_NET_BYTEORDER` flags that some netlink families set in the
+//! attribute type are stripped during deserialization so that the type can be matched against
+//! the constant enums.
 
 use std::slice;
 
@@ -183,6 +187,19 @@ where
         Ok(attr)
     }
 
+    /// Create an attribute of type `nla_type` with `attrs` nested in its payload
+    pub fn new_nested<TT, P>(nla_type: T, attrs: &[Nlattr<TT, P>]) -> Result<Self, SerError>
+    where
+        TT: NlAttrType,
+        P: Nl,
+    {
+        let mut attr = Nlattr::new(None, nla_type, Vec::<u8>::new())?;
+        for nested in attrs.iter() {
+            attr.add_nested_attribute(nested)?;
+        }
+        Ok(attr)
+    }
+
     /// Set the payload to a data type that implements `Nl` -
     /// this function will overwrite the current payload
     pub fn set_payload<P>(&mut self, payload: P) -> Result<(), SerError>
@@ -213,6 +230,26 @@ where
         Ok(())
     }
 
+    /// Set the `NLA_F_NESTED` flag in the attribute type - some families require it on all
+    /// attributes containing nested attributes
+    pub fn with_nested_flag(self) -> Self {
+        self.with_type_flag(libc::NLA_F_NESTED as u16)
+    }
+
+    /// Set the `NLA_F_NET_BYTEORDER` flag in the attribute type - some families require it on
+    /// attributes with payloads in network byte order
+    pub fn with_net_byteorder_flag(self) -> Self {
+        self.with_type_flag(libc::NLA_F_NET_BYTEORDER as u16)
+    }
+
+    fn with_type_flag(self, flag: u16) -> Self {
+        let nla_type: u16 = self.nla_type.into();
+        Nlattr {
+            nla_type: T::from(nla_type | flag),
+            ..self
+        }
+    }
+
     /// Get an `Nlattr` payload as a provided type
     pub fn get_payload_as<R>(&self) -> Result<R, DeError>
     where
@@ -252,7 +289,8 @@ where
         B: AsRef<[u8]>,
     {
         let nla_len = u16::deserialize(mem)?;
-        let nla_type = T::deserialize(mem)?;
+        // Strip NLA_F_NESTED and NLA_F_NET_BYTEORDER flags set by some families
+        let nla_type = T::from(u16::deserialize(mem)? & libc::NLA_TYPE_MASK as u16);
         mem.set_size_hint(nla_len as usize - (nla_len.size() + nla_type.size()));
         let payload = P::deserialize(mem)?;
         let nla = Nlattr {
@@ -386,6 +424,45 @@ mod test {
         assert_eq!(nlattr.asize(), 8);
     }
 
+    #[test]
+    fn test_type_flags() {
+        let nlattr = Nlattr::new(None, CtrlAttr::FamilyId, 4u16)
+            .unwrap()
+            .with_nested_flag()
+            .with_net_byteorder_flag();
+        let mut mem = StreamWriteBuffer::new_growable(Some(nlattr.asize()));
+        nlattr.serialize(&mut mem).unwrap();
+        let ty =
+            u16::from(CtrlAttr::FamilyId) | (libc::NLA_F_NESTED | libc::NLA_F_NET_BYTEORDER) as u16;
+        assert_eq!(&mem.as_ref()[2..4], &ty.to_ne_bytes());
+
+        let mut buf = StreamReadBuffer::new(mem.as_ref());
+        let nlattr = Nlattr::<CtrlAttr, Vec<u8>>::deserialize(&mut buf).unwrap();
+        assert_eq!(nlattr.nla_type, CtrlAttr::FamilyId);
+    }
+
+    #[test]
+    fn test_new_nested() {
+        let attrs = vec![
+            Nlattr::new(None, CtrlAttr::FamilyId, 4u16).unwrap(),
+            Nlattr::new(None, CtrlAttr::FamilyName, "nlctrl").unwrap(),
+        ];
+        let nested = Nlattr::new_nested(CtrlAttr::McastGroups, &attrs).unwrap();
+        let mut expected = Nlattr::new(None, CtrlAttr::McastGroups, Vec::<u8>::new()).unwrap();
+        for attr in attrs.iter() {
+            expected.add_nested_attribute(attr).unwrap();
+        }
+        assert_eq!(nested, expected);
+        assert_eq!(nested.nla_len as usize, 4 + 8 + 12);
+        let handle = nested.get_nested_attributes::<CtrlAttr>().unwrap();
+        assert_eq!(
+            handle
+                .get_attr_payload_as::<u16>(CtrlAttr::FamilyId)
+                .unwrap(),
+            4
+        );
+    }
+
     #[test]
     fn test_nl_nlattr() {
         let nlattr = Nlattr::new(None, CtrlAttr::Unspec, 4u16).unwrap();
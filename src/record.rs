@@ -0,0 +1,440 @@
+//! Recording of the datagrams exchanged by an `NlSocket` and their replay, eg. to turn a bug
+//! report into a test fixture.
+//!
+//! `NlSocket::record` attaches a `Recorder` that logs every datagram sent or received on the
+//! socket with its timestamp, direction and netlink family. `ReplayTransport` feeds the received
+//! datagrams of a recording back into a socket created with `NlSocket::with_transport`, so that
+//! `recv_nl`, `recv_all` and friends parse exactly what the kernel sent:
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufWriter;
+//!
+//! use neli::consts::{NlFamily, Nlmsg};
+//! use neli::record::{Recorder, ReplayTransport};
+//! use neli::socket::NlSocket;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut socket = NlSocket::connect(NlFamily::Route, None, None, true)?;
+//! socket.record(Recorder::new(BufWriter::new(File::create("route.pcapng")?))?)?;
+//! // Talk to the kernel...
+//! socket.stop_recording()?;
+//!
+//! let replay = ReplayTransport::from_reader(File::open("route.pcapng")?)?;
+//! let mut socket = NlSocket::with_transport(replay, false);
+//! let msg = socket.recv_nl::<Nlmsg, Vec<u8>>(None)?;
+//! # Ok(()) }
+//! ```
+//!
+//! # Design decisions
+//!
+//! Recordings are pcapng files of the `LINKTYPE_NETLINK` link type, the format of captures on
+//! an `nlmon` interface, so they can also be inspected with Wireshark. Each datagram is preceded
+//! by a Linux cooked capture header holding its direction and the netlink family of the socket.
+//! Only files in host byte order, as written by `Recorder`, can be read back.
+//!
+//! Replay ignores the datagrams the socket sends: sequence numbers and port IDs differ between
+//! runs, so the requests are not compared with the recording.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use libc;
+
+use crate::consts::NlFamily;
+use crate::netfilter::pcapng::{
+    pad, push_option, BYTE_ORDER_MAGIC, EPB_FLAGS, EPB_FLAG_INBOUND, EPB_FLAG_OUTBOUND, EPB_TYPE,
+    IDB_TYPE, OPT_ENDOFOPT, SHB_TYPE,
+};
+use crate::socket::NlTransport;
+
+// Link type from https://www.tcpdump.org/linktypes.html
+const LINKTYPE_NETLINK: u16 = 253;
+
+// Linux cooked capture header
+const SLL_HLEN: usize = 16;
+const PACKET_HOST: u16 = 0;
+const PACKET_OUTGOING: u16 = 4;
+const ARPHRD_NETLINK: u16 = 824;
+
+/// Direction of a recorded datagram
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// Sent by the socket
+    Sent,
+    /// Received by the socket
+    Received,
+}
+
+/// Datagram sent or received by a socket
+#[derive(Clone, Debug, PartialEq)]
+pub struct Datagram {
+    /// Time at which the datagram was sent or received
+    pub timestamp: SystemTime,
+    /// Direction of the datagram
+    pub direction: Direction,
+    /// Netlink family of the socket
+    pub family: NlFamily,
+    /// Contents of the datagram
+    pub data: Vec<u8>,
+}
+
+impl Datagram {
+    /// Create a datagram with the current time as timestamp
+    pub fn new(direction: Direction, family: NlFamily, data: Vec<u8>) -> Self {
+        Datagram {
+            timestamp: SystemTime::now(),
+            direction,
+            family,
+            data,
+        }
+    }
+}
+
+/// Writer of recordings of netlink traffic
+pub struct Recorder {
+    writer: Box<dyn Write + Send>,
+}
+
+impl Recorder {
+    /// Create a recorder and write the section header and the interface description to
+    /// `writer`
+    pub fn new<W>(writer: W) -> Result<Self, io::Error>
+    where
+        W: Write + Send + 'static,
+    {
+        let mut recorder = Recorder {
+            writer: Box::new(writer),
+        };
+        let mut body = Vec::with_capacity(16);
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_ne_bytes());
+        // Version 1.0
+        body.extend_from_slice(&1u16.to_ne_bytes());
+        body.extend_from_slice(&0u16.to_ne_bytes());
+        // Unknown section length
+        body.extend_from_slice(&(-1i64).to_ne_bytes());
+        recorder.write_block(SHB_TYPE, &body)?;
+
+        let mut body = Vec::with_capacity(12);
+        body.extend_from_slice(&LINKTYPE_NETLINK.to_ne_bytes());
+        // Reserved
+        body.extend_from_slice(&0u16.to_ne_bytes());
+        // No snap length limit
+        body.extend_from_slice(&0u32.to_ne_bytes());
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        recorder.write_block(IDB_TYPE, &body)?;
+        Ok(recorder)
+    }
+
+    /// Write a datagram to the recording
+    pub fn write_datagram(&mut self, datagram: &Datagram) -> Result<(), io::Error> {
+        let (pkttype, flags) = match datagram.direction {
+            Direction::Sent => (PACKET_OUTGOING, EPB_FLAG_OUTBOUND),
+            Direction::Received => (PACKET_HOST, EPB_FLAG_INBOUND),
+        };
+        let mut data = Vec::with_capacity(SLL_HLEN + datagram.data.len());
+        data.extend_from_slice(&pkttype.to_be_bytes());
+        data.extend_from_slice(&ARPHRD_NETLINK.to_be_bytes());
+        // No link layer address
+        data.extend_from_slice(&[0; 10]);
+        data.extend_from_slice(&(libc::c_int::from(&datagram.family) as u16).to_be_bytes());
+        data.extend_from_slice(&datagram.data);
+
+        let usecs = datagram
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() * 1_000_000 + u64::from(d.subsec_micros()))
+            .unwrap_or(0);
+
+        let mut body = Vec::with_capacity(32 + data.len());
+        // Only interface
+        body.extend_from_slice(&0u32.to_ne_bytes());
+        body.extend_from_slice(&((usecs >> 32) as u32).to_ne_bytes());
+        body.extend_from_slice(&(usecs as u32).to_ne_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_ne_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_ne_bytes());
+        body.extend_from_slice(&data);
+        pad(&mut body);
+        push_option(&mut body, EPB_FLAGS, &flags.to_ne_bytes());
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        self.write_block(EPB_TYPE, &body)
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        self.writer.flush()
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> Result<(), io::Error> {
+        let total_len = (body.len() + 12) as u32;
+        self.writer.write_all(&block_type.to_ne_bytes())?;
+        self.writer.write_all(&total_len.to_ne_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&total_len.to_ne_bytes())?;
+        Ok(())
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    let mut bytes = [0; 2];
+    bytes.copy_from_slice(&buf[offset..offset + 2]);
+    u16::from_ne_bytes(bytes)
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+/// Read all datagrams of a recording written by `Recorder`
+pub fn read_datagrams<R>(mut reader: R) -> Result<Vec<Datagram>, io::Error>
+where
+    R: Read,
+{
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+
+    let mut datagrams = Vec::new();
+    let mut link_types = Vec::new();
+    let mut offset = 0;
+    while offset < buf.len() {
+        if buf.len() - offset < 12 {
+            return Err(invalid("Truncated pcapng block"));
+        }
+        let block_type = u32_at(&buf, offset);
+        let len = u32_at(&buf, offset + 4) as usize;
+        if len < 12 || len & 3 != 0 || len > buf.len() - offset {
+            return Err(invalid("Invalid pcapng block length"));
+        }
+        let body = &buf[offset + 8..offset + len - 4];
+        offset += len;
+
+        match block_type {
+            SHB_TYPE => {
+                if body.len() < 4 || u32_at(body, 0) != BYTE_ORDER_MAGIC {
+                    return Err(invalid(
+                        "Only pcapng files in host byte order are supported",
+                    ));
+                }
+                link_types.clear();
+            }
+            IDB_TYPE if body.len() >= 2 => link_types.push(u16_at(body, 0)),
+            EPB_TYPE if body.len() >= 20 => {
+                let interface_id = u32_at(body, 0) as usize;
+                if link_types.get(interface_id) != Some(&LINKTYPE_NETLINK) {
+                    continue;
+                }
+                let usecs = u64::from(u32_at(body, 4)) << 32 | u64::from(u32_at(body, 8));
+                let caplen = u32_at(body, 12) as usize;
+                let data = body
+                    .get(20..20 + caplen)
+                    .ok_or_else(|| invalid("Truncated packet block"))?;
+                if data.len() < SLL_HLEN {
+                    return Err(invalid("Packet is shorter than the cooked capture header"));
+                }
+                let direction = match u16::from_be_bytes([data[0], data[1]]) {
+                    PACKET_OUTGOING => Direction::Sent,
+                    _ => Direction::Received,
+                };
+                let family = u16::from_be_bytes([data[14], data[15]]);
+                datagrams.push(Datagram {
+                    timestamp: UNIX_EPOCH + Duration::from_micros(usecs),
+                    direction,
+                    family: NlFamily::from(libc::c_int::from(family)),
+                    data: data[SLL_HLEN..].to_vec(),
+                });
+            }
+            // Other blocks such as statistics are of no interest
+            _ => (),
+        }
+    }
+    Ok(datagrams)
+}
+
+/// Transport replaying the received datagrams of a recording to an `NlSocket`
+pub struct ReplayTransport {
+    family: NlFamily,
+    datagrams: Mutex<VecDeque<Vec<u8>>>,
+}
+
+impl ReplayTransport {
+    /// Replay the received datagrams out of `datagrams`. The family of the first datagram, if
+    /// any, is reported as the family of the transport.
+    pub fn new(datagrams: Vec<Datagram>) -> Self {
+        ReplayTransport {
+            family: datagrams
+                .first()
+                .map(|datagram| datagram.family.clone())
+                .unwrap_or(NlFamily::Usersock),
+            datagrams: Mutex::new(
+                datagrams
+                    .into_iter()
+                    .filter(|datagram| datagram.direction == Direction::Received)
+                    .map(|datagram| datagram.data)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Replay the received datagrams of the recording read from `reader`
+    pub fn from_reader<R>(reader: R) -> Result<Self, io::Error>
+    where
+        R: Read,
+    {
+        Ok(ReplayTransport::new(read_datagrams(reader)?))
+    }
+}
+
+impl NlTransport for ReplayTransport {
+    fn send(&self, buf: &[u8], _flags: i32) -> Result<usize, io::Error> {
+        Ok(buf.len())
+    }
+
+    fn recv(&self, buf: &mut [u8], flags: i32) -> Result<usize, io::Error> {
+        let mut datagrams = self.datagrams.lock().unwrap_or_else(|e| e.into_inner());
+        let datagram = if flags & libc::MSG_PEEK != 0 {
+            datagrams.front().cloned()
+        } else {
+            datagrams.pop_front()
+        }
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "End of the recording reached")
+        })?;
+        let len = datagram.len().min(buf.len());
+        buf[..len].copy_from_slice(&datagram[..len]);
+        Ok(len)
+    }
+
+    fn port_id(&self) -> Result<u32, io::Error> {
+        Ok(0)
+    }
+
+    fn family(&self) -> NlFamily {
+        self.family.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::os::unix::net::UnixDatagram;
+    use std::sync::Arc;
+
+    use crate::socket::NlSocket;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_read_datagrams() {
+        let datagrams = vec![
+            Datagram {
+                timestamp: UNIX_EPOCH + Duration::new(1_600_000_000, 123_000),
+                direction: Direction::Sent,
+                family: NlFamily::Route,
+                data: vec![1, 2, 3],
+            },
+            Datagram {
+                timestamp: UNIX_EPOCH + Duration::new(1_600_000_001, 0),
+                direction: Direction::Received,
+                family: NlFamily::Route,
+                data: vec![4; 20],
+            },
+        ];
+        let buf = SharedBuf::default();
+        let mut recorder = Recorder::new(buf.clone()).unwrap();
+        for datagram in datagrams.iter() {
+            recorder.write_datagram(datagram).unwrap();
+        }
+
+        let bytes = buf.0.lock().unwrap().clone();
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(read_datagrams(&bytes[..]).unwrap(), datagrams);
+        assert!(read_datagrams(&bytes[..bytes.len() - 4]).is_err());
+
+        let replay = ReplayTransport::new(datagrams);
+        let mut mem = [0; 64];
+        assert_eq!(replay.recv(&mut mem, libc::MSG_PEEK).unwrap(), 20);
+        assert_eq!(replay.recv(&mut mem, 0).unwrap(), 20);
+        assert!(replay.recv(&mut mem, 0).is_err());
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let buf = SharedBuf::default();
+        let mut socket = NlSocket::connect(NlFamily::Generic, None, None, true).unwrap();
+        socket.record(Recorder::new(buf.clone()).unwrap()).unwrap();
+        let id = socket.resolve_genl_family("nlctrl").unwrap();
+        assert!(socket.stop_recording().unwrap().is_some());
+
+        let bytes = buf.0.lock().unwrap().clone();
+        let datagrams = read_datagrams(&bytes[..]).unwrap();
+        assert_eq!(datagrams[0].direction, Direction::Sent);
+        assert!(datagrams
+            .iter()
+            .all(|datagram| datagram.family == NlFamily::Generic));
+        assert!(datagrams[1..]
+            .iter()
+            .all(|datagram| datagram.direction == Direction::Received));
+
+        // Recording a replay yields the same received datagrams
+        let rerecorded = SharedBuf::default();
+        let mut replay = NlSocket::with_transport(ReplayTransport::new(datagrams.clone()), true);
+        replay
+            .record(Recorder::new(rerecorded.clone()).unwrap())
+            .unwrap();
+        assert_eq!(replay.resolve_genl_family("nlctrl").unwrap(), id);
+        replay.stop_recording().unwrap();
+
+        let bytes = rerecorded.0.lock().unwrap().clone();
+        let replayed = read_datagrams(&bytes[..]).unwrap();
+        assert!(replayed
+            .iter()
+            .all(|datagram| datagram.family == NlFamily::Generic));
+        let received = |datagrams: &[Datagram]| {
+            datagrams
+                .iter()
+                .filter(|datagram| datagram.direction == Direction::Received)
+                .map(|datagram| datagram.data.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(received(&replayed), received(&datagrams));
+    }
+
+    #[test]
+    fn test_record_truncated() {
+        let (transport, kernel) = UnixDatagram::pair().unwrap();
+        let buf = SharedBuf::default();
+        let mut socket = NlSocket::with_transport(transport, false);
+        socket.record(Recorder::new(buf.clone()).unwrap()).unwrap();
+        kernel.send(&[1; 8]).unwrap();
+        let mut mem = [0; 4];
+        assert_eq!(socket.recv(&mut mem[..], libc::MSG_TRUNC).unwrap(), 8);
+        socket.stop_recording().unwrap();
+
+        let bytes = buf.0.lock().unwrap().clone();
+        let datagrams = read_datagrams(&bytes[..]).unwrap();
+        assert_eq!(datagrams.len(), 1);
+        assert_eq!(datagrams[0].family, NlFamily::Usersock);
+        assert_eq!(datagrams[0].data, vec![1; 4]);
+    }
+}
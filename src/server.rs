@@ -0,0 +1,508 @@
+//! Server side of netlink protocols spoken between userspace processes, most notably
+//! `NETLINK_USERSOCK` (`NlFamily::Usersock`).
+//!
+//! An `NlServer` binds a port ID, receives requests and dispatches them to handlers registered
+//! either by `nl_type` or, for generic netlink style families, by `nl_type` and `Genlmsghdr`
+//! command. Replies follow the conventions of the kernel so that `NlSocket::recv_all` and
+//! `NlSocket::recv_ack` work unchanged on the client side:
+//! * Replies carry the sequence number and port ID of the request.
+//! * Dumps are sent as `NLM_F_MULTI` messages terminated by an `NLMSG_DONE` message.
+//! * Errors are reported as an `Nlmsgerr` with a negative errno.
+//! * Successful requests with `NLM_F_ACK` set are acknowledged with an error code of `0`.
+//!
+//! Clients reach the server by connecting their socket to its port ID with
+//! `NlSocket::connect_peer`.
+//!
+//! ```no_run
+//! use neli::consts::{NlFamily, NlmF};
+//! use neli::err::NlError;
+//! use neli::nl::Nlmsghdr;
+//! use neli::server::{NlReply, NlServer};
+//! use neli::socket::NlSocket;
+//!
+//! const ECHO: u16 = 0x10;
+//!
+//! # fn main() -> Result<(), NlError> {
+//! let mut server = NlServer::bind(NlFamily::Usersock, 0)?;
+//! server.handle(ECHO, |req| NlReply::msg(ECHO, req.msg.nl_payload.clone()));
+//!
+//! let mut client = NlSocket::connect(NlFamily::Usersock, Some(0), None, true)?;
+//! client.connect_peer(server.port_id())?;
+//! client.send_nl(Nlmsghdr::new(None, ECHO, vec![NlmF::Request], None, None, 42u32))?;
+//!
+//! server.serve_one()?;
+//! let reply = client.recv_all::<u16, u32>()?;
+//! assert_eq!(reply[0].nl_payload, 42);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Design decisions
+//!
+//! Handlers receive the request with its payload still encoded so that a single server can speak
+//! several message formats. Errors returned by a handler are answered with `EINVAL`; use
+//! `NlReply::Error` to choose the error code.
+
+use std::io;
+
+use buffering::{StreamReadBuffer, StreamWriteBuffer};
+use libc;
+
+use consts::{alignto, Cmd, NlAttrType, NlFamily, NlType, NlmF, Nlmsg};
+use err::{DeError, NlError, Nlmsgerr, SerError};
+use genl::Genlmsghdr;
+use nl::{NlEmpty, Nlmsghdr};
+use socket::NlSocket;
+use {Nl, MAX_NL_LENGTH};
+
+/// Smallest message type that is not a netlink control message (`NLMSG_MIN_TYPE`)
+const NLMSG_MIN_TYPE: u16 = 0x10;
+
+/// Length of a netlink message header (`NLMSG_HDRLEN`)
+const NLMSG_HDRLEN: usize = 16;
+
+type Handler = Box<dyn FnMut(&NlRequest) -> Result<NlReply, NlError>>;
+
+struct Route {
+    nl_type: u16,
+    cmd: Option<u8>,
+    handler: Handler,
+}
+
+pub(crate) fn payload_bytes<P>(payload: &P) -> Result<Vec<u8>, SerError>
+where
+    P: Nl,
+{
+    let mut mem = StreamWriteBuffer::new_growable(Some(payload.asize()));
+    payload.serialize(&mut mem)?;
+    Ok(mem.as_ref().to_vec())
+}
+
+/// Request received by an `NlServer`
+#[derive(Debug, PartialEq)]
+pub struct NlRequest {
+    /// Port ID of the process that sent the request
+    pub port_id: u32,
+    /// Request message with its payload still encoded
+    pub msg: Nlmsghdr<u16, Vec<u8>>,
+}
+
+impl NlRequest {
+    /// Whether the request asks for a multi-part dump
+    pub fn is_dump(&self) -> bool {
+        self.msg.nl_flags.contains(&NlmF::Dump)
+    }
+
+    /// Whether the request asks for an ACK
+    pub fn wants_ack(&self) -> bool {
+        self.msg.nl_flags.contains(&NlmF::Ack)
+    }
+
+    /// Decode the payload of the request
+    pub fn payload<P>(&self) -> Result<P, DeError>
+    where
+        P: Nl,
+    {
+        let mut buf = StreamReadBuffer::new(&self.msg.nl_payload);
+        buf.set_size_hint(self.msg.nl_payload.len());
+        P::deserialize(&mut buf)
+    }
+
+    /// Decode the payload of the request as a generic netlink message
+    pub fn genl<C, T>(&self) -> Result<Genlmsghdr<C, T>, DeError>
+    where
+        C: Cmd,
+        T: NlAttrType,
+    {
+        self.payload()
+    }
+}
+
+/// Reply of a handler to a request
+#[derive(Clone, Debug, PartialEq)]
+pub enum NlReply {
+    /// Nothing besides an ACK if the request asked for one
+    Ack,
+    /// An error code such as `libc::EINVAL`
+    Error(libc::c_int),
+    /// A single message of the given type and encoded payload
+    Msg(u16, Vec<u8>),
+    /// A multi-part dump of messages of the given type and encoded payloads
+    Dump(u16, Vec<Vec<u8>>),
+}
+
+impl NlReply {
+    /// Reply with a single message
+    pub fn msg<T, P>(nl_type: T, payload: P) -> Result<Self, NlError>
+    where
+        T: NlType,
+        P: Nl,
+    {
+        Ok(NlReply::Msg(nl_type.into(), payload_bytes(&payload)?))
+    }
+
+    /// Reply with a multi-part dump, which may be empty
+    pub fn dump<T, P>(nl_type: T, payloads: Vec<P>) -> Result<Self, NlError>
+    where
+        T: NlType,
+        P: Nl,
+    {
+        let payloads = payloads
+            .iter()
+            .map(payload_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(NlReply::Dump(nl_type.into(), payloads))
+    }
+
+    /// Encode the messages answering `req`, one per datagram
+    pub(crate) fn encode(self, req: &NlRequest) -> Result<Vec<Vec<u8>>, NlError> {
+        let msg = |nl_type: u16, flags: Vec<NlmF>, payload: Vec<u8>| {
+            payload_bytes(&Nlmsghdr::new(
+                None,
+                nl_type,
+                flags,
+                Some(req.msg.nl_seq),
+                Some(req.port_id),
+                payload,
+            ))
+        };
+        let ack = |error: libc::c_int| {
+            let err = Nlmsgerr {
+                error: -error,
+                nlmsg: Nlmsghdr::new(
+                    Some(req.msg.nl_len),
+                    req.msg.nl_type,
+                    req.msg.nl_flags.clone(),
+                    Some(req.msg.nl_seq),
+                    Some(req.msg.nl_pid),
+                    NlEmpty,
+                ),
+            };
+            msg(Nlmsg::Error.into(), vec![], payload_bytes(&err)?)
+        };
+        let mut msgs = Vec::new();
+        match self {
+            NlReply::Ack => {
+                if req.wants_ack() {
+                    msgs.push(ack(0)?);
+                }
+            }
+            NlReply::Error(error) => msgs.push(ack(error)?),
+            NlReply::Msg(nl_type, payload) => {
+                msgs.push(msg(nl_type, vec![], payload)?);
+                if req.wants_ack() {
+                    msgs.push(ack(0)?);
+                }
+            }
+            NlReply::Dump(nl_type, payloads) => {
+                for payload in payloads {
+                    msgs.push(msg(nl_type, vec![NlmF::Multi], payload)?);
+                }
+                msgs.push(msg(
+                    Nlmsg::Done.into(),
+                    vec![NlmF::Multi],
+                    payload_bytes(&0i32)?,
+                )?);
+            }
+        }
+        Ok(msgs)
+    }
+}
+
+/// Netlink server dispatching requests from userspace clients to handlers
+pub struct NlServer {
+    socket: NlSocket,
+    port_id: u32,
+    routes: Vec<Route>,
+}
+
+impl NlServer {
+    /// Bind a server to `port_id` with the given protocol - `0` lets the kernel assign the port
+    /// ID, which is then available from `port_id()`
+    pub fn bind(proto: NlFamily, port_id: u32) -> Result<Self, io::Error> {
+        let socket = NlSocket::connect(proto, Some(port_id), None, false)?;
+        let port_id = socket.port_id()?;
+        Ok(NlServer {
+            socket,
+            port_id,
+            routes: Vec::new(),
+        })
+    }
+
+    /// Port ID clients send their requests to
+    pub fn port_id(&self) -> u32 {
+        self.port_id
+    }
+
+    /// Access the underlying socket, eg. to make it non-blocking
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+
+    /// Register the handler for requests of type `nl_type`. Handlers registered earlier take
+    /// precedence.
+    pub fn handle<T, F>(&mut self, nl_type: T, handler: F) -> &mut Self
+    where
+        T: NlType,
+        F: FnMut(&NlRequest) -> Result<NlReply, NlError> + 'static,
+    {
+        self.routes.push(Route {
+            nl_type: nl_type.into(),
+            cmd: None,
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Register the handler for generic netlink requests of type `nl_type` with the command `cmd`
+    pub fn handle_genl<T, C, F>(&mut self, nl_type: T, cmd: C, handler: F) -> &mut Self
+    where
+        T: NlType,
+        C: Cmd,
+        F: FnMut(&NlRequest) -> Result<NlReply, NlError> + 'static,
+    {
+        self.routes.push(Route {
+            nl_type: nl_type.into(),
+            cmd: Some(cmd.into()),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Receive a single datagram and answer every request in it
+    ///
+    /// Like the kernel, the server ignores the rest of a datagram from the first message whose
+    /// length does not fit a netlink header or the datagram, and drops replies that cannot be
+    /// delivered, eg. because the client closed its socket. Only errors receiving the datagram
+    /// are returned.
+    pub fn serve_one(&mut self) -> Result<(), NlError> {
+        let mut mem = vec![0; MAX_NL_LENGTH];
+        let (mem_read, port_id) = self.socket.recv_from(&mut mem, 0)?;
+        mem.truncate(mem_read);
+        let mut pos = 0;
+        while let Some(msg) = next_msg(&mem[pos..]) {
+            pos = (pos + alignto(msg.nl_len as usize)).min(mem.len());
+            let req = NlRequest { port_id, msg };
+            let reply = self.dispatch(&req);
+            if self.reply(&req, reply).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer requests until receiving a datagram fails
+    pub fn serve(&mut self) -> Result<(), NlError> {
+        loop {
+            self.serve_one()?;
+        }
+    }
+
+    fn dispatch(&mut self, req: &NlRequest) -> NlReply {
+        // Like the kernel, only requests are passed on and control messages are merely
+        // acknowledged
+        if !req.msg.nl_flags.contains(&NlmF::Request) || req.msg.nl_type < NLMSG_MIN_TYPE {
+            return NlReply::Ack;
+        }
+        let cmd = req.msg.nl_payload.first().cloned();
+        let route = self.routes.iter_mut().find(|route| {
+            route.nl_type == req.msg.nl_type && (route.cmd.is_none() || route.cmd == cmd)
+        });
+        match route {
+            Some(route) => (route.handler)(req).unwrap_or(NlReply::Error(libc::EINVAL)),
+            None => NlReply::Error(libc::EOPNOTSUPP),
+        }
+    }
+
+    fn reply(&self, req: &NlRequest, reply: NlReply) -> Result<(), NlError> {
+        for msg in reply.encode(req)? {
+            self.socket.send_to(msg, req.port_id, 0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the message at the start of `mem` if its length is consistent with `mem`
+fn next_msg(mem: &[u8]) -> Option<Nlmsghdr<u16, Vec<u8>>> {
+    let mut buf = StreamReadBuffer::new(mem);
+    let nl_len = u32::deserialize(&mut buf).ok()? as usize;
+    if nl_len < NLMSG_HDRLEN || nl_len > mem.len() {
+        return None;
+    }
+    let mut buf = StreamReadBuffer::new(&mem[..alignto(nl_len).min(mem.len())]);
+    Nlmsghdr::deserialize(&mut buf).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::thread;
+
+    use consts::{CtrlAttr, CtrlCmd};
+    use nlattr::Nlattr;
+
+    const ECHO: u16 = 0x10;
+    const LIST: u16 = 0x11;
+    const FAMILY: u16 = 0x12;
+
+    fn server() -> NlServer {
+        let mut server = NlServer::bind(NlFamily::Usersock, 0).unwrap();
+        server
+            .handle(ECHO, |req| {
+                let value = req.payload::<u32>()?;
+                if value == 0 {
+                    return Ok(NlReply::Error(libc::ERANGE));
+                }
+                NlReply::msg(ECHO, value + 1)
+            })
+            .handle(LIST, |req| {
+                let count = req.payload::<u32>()?;
+                NlReply::dump(LIST, (0..count).collect())
+            })
+            .handle_genl(FAMILY, CtrlCmd::Getfamily, |req| {
+                let genl = req.genl::<CtrlCmd, CtrlAttr>()?;
+                let name = genl
+                    .get_attr_handle()
+                    .get_attr_payload_as::<String>(CtrlAttr::FamilyName)?;
+                let attrs = vec![
+                    Nlattr::new(None, CtrlAttr::FamilyName, name)?,
+                    Nlattr::new(None, CtrlAttr::FamilyId, 0x20u16)?,
+                ];
+                NlReply::msg(FAMILY, Genlmsghdr::new(CtrlCmd::Newfamily, 2, attrs)?)
+            })
+            .handle_genl(FAMILY, CtrlCmd::Delfamily, |_| Ok(NlReply::Ack));
+        server
+    }
+
+    fn request<P>(client: &mut NlSocket, nl_type: u16, flags: Vec<NlmF>, payload: P)
+    where
+        P: Nl,
+    {
+        client
+            .send_nl(Nlmsghdr::new(None, nl_type, flags, None, None, payload))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_server_dispatch() {
+        let (port_tx, port_rx) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut server = server();
+            port_tx.send(server.port_id()).unwrap();
+            for _ in 0..7 {
+                server.serve_one().unwrap();
+            }
+        });
+        let port_id = port_rx.recv().unwrap();
+
+        let mut client = NlSocket::connect(NlFamily::Usersock, Some(0), None, true).unwrap();
+        client.connect_peer(port_id).unwrap();
+
+        // Single reply followed by an ACK
+        request(&mut client, ECHO, vec![NlmF::Request, NlmF::Ack], 41u32);
+        let reply = client.recv_all::<u16, u32>().unwrap();
+        assert_eq!(reply.len(), 1);
+        assert_eq!(reply[0].nl_payload, 42);
+        assert_eq!(reply[0].nl_pid, client.port_id().unwrap());
+        client.recv_ack().unwrap();
+
+        // Error chosen by the handler
+        request(&mut client, ECHO, vec![NlmF::Request, NlmF::Ack], 0u32);
+        match client.recv_all::<u16, u32>() {
+            Err(NlError::Msg(msg)) => {
+                assert_eq!(msg, io::Error::from_raw_os_error(libc::ERANGE).to_string())
+            }
+            _ => panic!("Expected error"),
+        }
+
+        // Multi-part dump terminated by NLMSG_DONE
+        request(&mut client, LIST, vec![NlmF::Request, NlmF::Dump], 3u32);
+        let dump = client.recv_all::<u16, u32>().unwrap();
+        let values = dump.iter().map(|msg| msg.nl_payload).collect::<Vec<_>>();
+        assert_eq!(values, vec![0, 1, 2]);
+
+        // Generic netlink dispatch by command
+        let attrs = vec![Nlattr::new(None, CtrlAttr::FamilyName, "test").unwrap()];
+        let genl = Genlmsghdr::new(CtrlCmd::Getfamily, 2, attrs).unwrap();
+        request(&mut client, FAMILY, vec![NlmF::Request], genl);
+        let reply = client
+            .recv_all::<u16, Genlmsghdr<CtrlCmd, CtrlAttr>>()
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(reply.nl_payload.cmd, CtrlCmd::Newfamily);
+        let handle_attrs = reply.nl_payload.get_attr_handle();
+        assert_eq!(
+            handle_attrs
+                .get_attr_payload_as::<u16>(CtrlAttr::FamilyId)
+                .unwrap(),
+            0x20
+        );
+
+        let genl = Genlmsghdr::<CtrlCmd, CtrlAttr>::new(CtrlCmd::Delfamily, 2, vec![]).unwrap();
+        request(&mut client, FAMILY, vec![NlmF::Request, NlmF::Ack], genl);
+        client.recv_ack().unwrap();
+
+        // Unknown command and unknown type
+        let genl = Genlmsghdr::<CtrlCmd, CtrlAttr>::new(CtrlCmd::Newops, 2, vec![]).unwrap();
+        request(&mut client, FAMILY, vec![NlmF::Request], genl);
+        assert!(client.recv_all::<u16, Vec<u8>>().is_err());
+        request(&mut client, 0x30, vec![NlmF::Request, NlmF::Ack], 1u32);
+        match client.recv_all::<u16, Vec<u8>>() {
+            Err(NlError::Msg(msg)) => assert_eq!(
+                msg,
+                io::Error::from_raw_os_error(libc::EOPNOTSUPP).to_string()
+            ),
+            _ => panic!("Expected error"),
+        }
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_survives_garbage() {
+        let mut server = server();
+
+        // Replies to a client that closed its socket are dropped
+        let mut gone = NlSocket::connect(NlFamily::Usersock, Some(0), None, true).unwrap();
+        gone.connect_peer(server.port_id()).unwrap();
+        request(&mut gone, ECHO, vec![NlmF::Request, NlmF::Ack], 1u32);
+        drop(gone);
+        server.serve_one().unwrap();
+
+        let mut client = NlSocket::connect(NlFamily::Usersock, Some(0), None, true).unwrap();
+        client.connect_peer(server.port_id()).unwrap();
+        let mut short_len = 4u32.to_ne_bytes().to_vec();
+        short_len.extend_from_slice(&[0; 16]);
+        let datagrams = vec![
+            vec![0xff; 3],
+            vec![0xff; 40],
+            short_len,
+            vec![0x14, 0, 0, 0, 0x10, 0, 1, 0],
+        ];
+        for datagram in datagrams {
+            client.send(&datagram, 0).unwrap();
+            server.serve_one().unwrap();
+        }
+
+        // Requests before a malformed message are still answered
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        Nlmsghdr::new(None, ECHO, vec![NlmF::Request], Some(7), None, 41u32)
+            .serialize(&mut mem)
+            .unwrap();
+        let mut datagram = mem.as_ref().to_vec();
+        datagram.extend_from_slice(&[0xff; 8]);
+        client.send(&datagram, 0).unwrap();
+        server.serve_one().unwrap();
+        let reply = client.recv_nl::<u16, u32>(None).unwrap();
+        assert_eq!(reply.nl_seq, 7);
+        assert_eq!(reply.nl_payload, 42);
+
+        request(&mut client, ECHO, vec![NlmF::Request], 9u32);
+        server.serve_one().unwrap();
+        let reply = client.recv_all::<u16, u32>().unwrap();
+        assert_eq!(reply[0].nl_payload, 10);
+    }
+}
@@ -10,7 +10,13 @@
 //! the `Nl` trait.
 //! * `iter` provides a loop based iteration through messages that are received in a stream over
 //! the socket.
+//! * `event_iter` is like `iter` but is meant for multicast listeners. When the kernel reports that
+//! messages were dropped (`ENOBUFS`), it reports the loss and calls a resynchronization callback
+//! so that state mirrored from the kernel can be rebuilt.
 //! * `recv_ack` receives an ACK message and verifies it matches the request.
+//! * `send_to`, `recv_from` and `connect_peer` address userspace peers by port ID.
+//! * `with_transport` runs the socket over an `NlTransport` such as a socketpair or a mock.
+//! * `record` logs the datagrams exchanged by the socket so they can be replayed later.
 //!
 //! ## Features
 //! The `stream` feature exposed by `cargo` allows the socket to use Rust's tokio for async IO.
@@ -25,6 +31,8 @@ use std::io;
 use std::marker::PhantomData;
 use std::mem::{size_of, zeroed};
 use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use std::sync::Mutex;
 
 use buffering::{StreamReadBuffer, StreamWriteBuffer};
 use libc::{self, c_int, c_void};
@@ -34,9 +42,11 @@ use consts::{
     NlmF,
 };
 use err::{NlError, Nlmsgerr};
+use filter::{self, SockFilter};
 use genl::Genlmsghdr;
 use nl::Nlmsghdr;
 use nlattr::Nlattr;
+use record::{Datagram, Direction, Recorder};
 use {Nl, MAX_NL_LENGTH};
 
 /// Iterator over messages returned from a `recv_nl` call
@@ -76,31 +86,199 @@ where
     }
 }
 
+/// Event yielded by `NlEventIter`
+#[derive(Debug, PartialEq)]
+pub enum NlEvent<T, P> {
+    /// A message received from the socket
+    Msg(Nlmsghdr<T, P>),
+    /// The socket receive buffer overflowed and messages were lost - the resynchronization
+    /// callback has already been run when this is yielded
+    Overrun,
+}
+
+/// Iterator over multicast events that recovers from receive buffer overruns
+pub struct NlEventIter<'a, T, P, F> {
+    socket_ref: &'a mut NlSocket,
+    resync: F,
+    data_type: PhantomData<T>,
+    data_payload: PhantomData<P>,
+}
+
+impl<'a, T, P, F> NlEventIter<'a, T, P, F>
+where
+    T: Nl + NlType,
+    P: Nl,
+    F: FnMut(&mut NlSocket) -> Result<(), NlError>,
+{
+    /// Construct a new iterator that yields `NlEvent`s and calls `resync` with the socket after
+    /// an overrun is detected
+    pub fn new(socket_ref: &'a mut NlSocket, resync: F) -> Self {
+        NlEventIter {
+            socket_ref,
+            resync,
+            data_type: PhantomData,
+            data_payload: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, P, F> Iterator for NlEventIter<'a, T, P, F>
+where
+    T: Nl + NlType,
+    P: Nl,
+    F: FnMut(&mut NlSocket) -> Result<(), NlError>,
+{
+    type Item = Result<NlEvent<T, P>, NlError>;
+
+    fn next(&mut self) -> Option<Result<NlEvent<T, P>, NlError>> {
+        match self.socket_ref.recv_nl(None) {
+            Ok(rn) => Some(Ok(NlEvent::Msg(rn))),
+            Err(NlError::Overrun) => {
+                // Anything left over from before the overrun is stale at this point
+                self.socket_ref.buffer = None;
+                Some((self.resync)(self.socket_ref).map(|_| NlEvent::Overrun))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Transport carrying the datagrams of an `NlSocket` in place of a netlink socket, eg. for
+/// tests without a kernel - see the `mock` module
+pub trait NlTransport: Send {
+    /// Send a datagram
+    fn send(&self, buf: &[u8], flags: i32) -> Result<usize, io::Error>;
+
+    /// Receive a datagram into `buf`, truncating it if it does not fit
+    fn recv(&self, buf: &mut [u8], flags: i32) -> Result<usize, io::Error>;
+
+    /// Port ID the transport is addressed by
+    fn port_id(&self) -> Result<u32, io::Error>;
+
+    /// Netlink family the datagrams belong to, as recorded by `NlSocket::record`
+    fn family(&self) -> NlFamily {
+        NlFamily::Usersock
+    }
+
+    /// Send a datagram to `port_id`. The peer of a transport plays the kernel, so by default only
+    /// port ID `0` can be reached.
+    fn send_to(&self, buf: &[u8], port_id: u32, flags: i32) -> Result<usize, io::Error> {
+        match port_id {
+            0 => self.send(buf, flags),
+            _ => Err(io::Error::from_raw_os_error(libc::ECONNREFUSED)),
+        }
+    }
+
+    /// Receive a datagram into `buf` along with the port ID of its sender, `0` by default
+    fn recv_from(&self, buf: &mut [u8], flags: i32) -> Result<(usize, u32), io::Error> {
+        Ok((self.recv(buf, flags)?, 0))
+    }
+}
+
+/// One end of a `UnixDatagram::pair()` with the peer playing the kernel
+impl NlTransport for UnixDatagram {
+    fn send(&self, buf: &[u8], flags: i32) -> Result<usize, io::Error> {
+        match unsafe {
+            libc::send(
+                self.as_raw_fd(),
+                buf as *const _ as *const c_void,
+                buf.len(),
+                flags,
+            )
+        } {
+            i if i >= 0 => Ok(i as usize),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    fn recv(&self, buf: &mut [u8], flags: i32) -> Result<usize, io::Error> {
+        match unsafe {
+            libc::recv(
+                self.as_raw_fd(),
+                buf as *mut _ as *mut c_void,
+                buf.len(),
+                flags,
+            )
+        } {
+            i if i >= 0 => Ok(i as usize),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    fn port_id(&self) -> Result<u32, io::Error> {
+        Ok(0)
+    }
+}
+
 /// Handle for the socket file descriptor
 pub struct NlSocket {
     fd: c_int,
+    family: NlFamily,
+    transport: Option<Box<dyn NlTransport>>,
+    recorder: Option<Mutex<Recorder>>,
     buffer: Option<StreamReadBuffer<Vec<u8>>>,
     pid: Option<u32>,
+    port_id: Option<u32>,
     seq: Option<u32>,
 }
 
 impl NlSocket {
     /// Wrapper around `socket()` syscall filling in the netlink-specific information
     pub fn new(proto: NlFamily, track_seq: bool) -> Result<Self, io::Error> {
-        let fd =
-            match unsafe { libc::socket(AddrFamily::Netlink.into(), libc::SOCK_RAW, proto.into()) }
-            {
-                i if i >= 0 => Ok(i),
-                _ => Err(io::Error::last_os_error()),
-            }?;
+        let fd = match unsafe {
+            libc::socket(
+                AddrFamily::Netlink.into(),
+                libc::SOCK_RAW,
+                proto.clone().into(),
+            )
+        } {
+            i if i >= 0 => Ok(i),
+            _ => Err(io::Error::last_os_error()),
+        }?;
         Ok(NlSocket {
             fd,
+            family: proto,
+            transport: None,
+            recorder: None,
             buffer: None,
             pid: None,
+            port_id: None,
             seq: if track_seq { Some(0) } else { None },
         })
     }
 
+    /// Create a socket sending and receiving over `transport` instead of a netlink socket.
+    ///
+    /// `send_to`, `recv_from` and `port_id` are passed on to the transport and `connect_peer`
+    /// only accepts the port ID of the transport's peer, `0`. Operations that need a file
+    /// descriptor, such as `bind`, `nonblock`, filters and the tokio `NlSocket`, return an error.
+    pub fn with_transport<T>(transport: T, track_seq: bool) -> Self
+    where
+        T: NlTransport + 'static,
+    {
+        NlSocket {
+            fd: -1,
+            family: transport.family(),
+            transport: Some(Box::new(transport)),
+            recorder: None,
+            buffer: None,
+            pid: None,
+            port_id: None,
+            seq: if track_seq { Some(0) } else { None },
+        }
+    }
+
+    /// File descriptor of the netlink socket, which sockets running over a transport do not have
+    fn fd(&self) -> Result<c_int, io::Error> {
+        match self.transport {
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Operation requires a netlink socket and is not supported over a transport",
+            )),
+            None => Ok(self.fd),
+        }
+    }
+
     /// Manually increment sequence number
     pub fn increment_seq(&mut self) {
         self.seq.map(|seq| seq + 1);
@@ -108,11 +286,12 @@ impl NlSocket {
 
     /// Set underlying socket file descriptor to be blocking
     pub fn block(&mut self) -> Result<&mut Self, io::Error> {
+        let fd = self.fd()?;
         match unsafe {
             libc::fcntl(
-                self.fd,
+                fd,
                 libc::F_SETFL,
-                libc::fcntl(self.fd, libc::F_GETFL, 0) & !libc::O_NONBLOCK,
+                libc::fcntl(fd, libc::F_GETFL, 0) & !libc::O_NONBLOCK,
             )
         } {
             i if i < 0 => Err(io::Error::last_os_error()),
@@ -122,11 +301,12 @@ impl NlSocket {
 
     /// Set underlying socket file descriptor to be non blocking
     pub fn nonblock(&mut self) -> Result<&mut Self, io::Error> {
+        let fd = self.fd()?;
         match unsafe {
             libc::fcntl(
-                self.fd,
+                fd,
                 libc::F_SETFL,
-                libc::fcntl(self.fd, libc::F_GETFL, 0) | libc::O_NONBLOCK,
+                libc::fcntl(fd, libc::F_GETFL, 0) | libc::O_NONBLOCK,
             )
         } {
             i if i < 0 => Err(io::Error::last_os_error()),
@@ -137,7 +317,7 @@ impl NlSocket {
     /// Determines if underlying file descriptor is blocking - `Stream` feature will throw an
     /// error if this function returns false
     pub fn is_blocking(&self) -> Result<bool, io::Error> {
-        let is_blocking = match unsafe { libc::fcntl(self.fd, libc::F_GETFL, 0) } {
+        let is_blocking = match unsafe { libc::fcntl(self.fd()?, libc::F_GETFL, 0) } {
             i if i >= 0 => i & libc::O_NONBLOCK == 0,
             _ => return Err(io::Error::last_os_error()),
         };
@@ -149,17 +329,17 @@ impl NlSocket {
     ///
     /// The pid parameter sets PID checking.
     /// * `None` means checking is off.
-    /// * `Some(0)` turns checking on, but takes the PID from the first received message.
+    /// * `Some(0)` turns checking on and lets the kernel assign the port ID, which is then
+    ///   retrieved with `port_id()` and used for checking.
     /// * `Some(pid)` uses the given PID.
     pub fn bind(&mut self, pid: Option<u32>, groups: Option<Vec<u32>>) -> Result<(), io::Error> {
         let mut nladdr = unsafe { zeroed::<libc::sockaddr_nl>() };
         nladdr.nl_family = libc::c_int::from(AddrFamily::Netlink) as u16;
         nladdr.nl_pid = pid.unwrap_or(0);
-        self.pid = pid;
         nladdr.nl_groups = 0;
         match unsafe {
             libc::bind(
-                self.fd,
+                self.fd()?,
                 &nladdr as *const _ as *const libc::sockaddr,
                 size_of::<libc::sockaddr_nl>() as u32,
             )
@@ -167,30 +347,123 @@ impl NlSocket {
             i if i >= 0 => (),
             _ => return Err(io::Error::last_os_error()),
         };
+        self.pid = match pid {
+            Some(0) => Some(self.port_id()?),
+            p => p,
+        };
+        self.port_id = self.pid;
         if let Some(grps) = groups {
             self.set_mcast_groups(grps)?;
         }
         Ok(())
     }
 
+    /// Get the port ID the socket is bound to using `getsockname()`. This will be the port ID
+    /// assigned by the kernel if the socket was bound with a PID of 0 and `0` if the socket has not
+    /// been bound yet.
+    pub fn port_id(&self) -> Result<u32, io::Error> {
+        if let Some(ref transport) = self.transport {
+            return transport.port_id();
+        }
+        let mut nladdr = unsafe { zeroed::<libc::sockaddr_nl>() };
+        let mut len = size_of::<libc::sockaddr_nl>() as libc::socklen_t;
+        match unsafe {
+            libc::getsockname(
+                self.fd,
+                &mut nladdr as *mut _ as *mut libc::sockaddr,
+                &mut len as *mut libc::socklen_t,
+            )
+        } {
+            i if i >= 0 => Ok(nladdr.nl_pid),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Port ID to fill in on sent messages. It is cached once it is known, which saves a
+    /// `getsockname()` call per message; an unbound socket only gets one when the kernel binds it
+    /// on the first send.
+    fn cached_port_id(&mut self) -> Result<u32, io::Error> {
+        if let Some(port_id) = self.port_id {
+            return Ok(port_id);
+        }
+        let port_id = self.port_id()?;
+        if port_id != 0 {
+            self.port_id = Some(port_id);
+        }
+        Ok(port_id)
+    }
+
     /// Set multicast groups for socket
     pub fn set_mcast_groups(&mut self, groups: Vec<u32>) -> Result<(), io::Error> {
-        let grps = groups
-            .into_iter()
-            .fold(0, |acc, next| acc | (1 << (next - 1)));
+        // NETLINK_ADD_MEMBERSHIP takes a single group number, not a bitmask
+        let fd = self.fd()?;
+        for grp in groups {
+            match unsafe {
+                libc::setsockopt(
+                    fd,
+                    libc::SOL_NETLINK,
+                    libc::NETLINK_ADD_MEMBERSHIP,
+                    &grp as *const _ as *const libc::c_void,
+                    size_of::<u32>() as libc::socklen_t,
+                )
+            } {
+                0 => (),
+                _ => return Err(io::Error::last_os_error()),
+            }
+        }
+        self.pid = None;
+        Ok(())
+    }
+
+    /// Attach a classic BPF filter to the socket so that the kernel drops messages the filter
+    /// rejects - see the `filter` module for building common filters
+    pub fn attach_filter(&mut self, filter: &[SockFilter]) -> Result<(), io::Error> {
+        let prog = filter::sock_fprog(filter);
         match unsafe {
             libc::setsockopt(
-                self.fd,
-                libc::SOL_NETLINK,
-                libc::NETLINK_ADD_MEMBERSHIP,
-                &grps as *const _ as *const libc::c_void,
-                size_of::<u32>() as libc::socklen_t,
+                self.fd()?,
+                libc::SOL_SOCKET,
+                filter::SO_ATTACH_FILTER,
+                &prog as *const _ as *const libc::c_void,
+                filter::sock_fprog_size() as libc::socklen_t,
             )
         } {
-            i if i == 0 => {
-                self.pid = None;
-                Ok(())
-            }
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Remove the BPF filter attached to the socket
+    pub fn detach_filter(&mut self) -> Result<(), io::Error> {
+        let unused: c_int = 0;
+        match unsafe {
+            libc::setsockopt(
+                self.fd()?,
+                libc::SOL_SOCKET,
+                filter::SO_DETACH_FILTER,
+                &unused as *const _ as *const libc::c_void,
+                size_of::<c_int>() as libc::socklen_t,
+            )
+        } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Prevent the attached BPF filter from being detached or replaced for the lifetime of the
+    /// socket
+    pub fn lock_filter(&mut self) -> Result<(), io::Error> {
+        let lock: c_int = 1;
+        match unsafe {
+            libc::setsockopt(
+                self.fd()?,
+                libc::SOL_SOCKET,
+                filter::SO_LOCK_FILTER,
+                &lock as *const _ as *const libc::c_void,
+                size_of::<c_int>() as libc::socklen_t,
+            )
+        } {
+            0 => Ok(()),
             _ => Err(io::Error::last_os_error()),
         }
     }
@@ -201,17 +474,22 @@ impl NlSocket {
     where
         B: AsRef<[u8]>,
     {
-        match unsafe {
-            libc::send(
-                self.fd,
-                buf.as_ref() as *const _ as *const c_void,
-                buf.as_ref().len(),
-                flags,
-            )
-        } {
-            i if i >= 0 => Ok(i as libc::size_t),
-            _ => Err(io::Error::last_os_error()),
-        }
+        let len = match self.transport {
+            Some(ref transport) => transport.send(buf.as_ref(), flags)?,
+            None => match unsafe {
+                libc::send(
+                    self.fd,
+                    buf.as_ref() as *const _ as *const c_void,
+                    buf.as_ref().len(),
+                    flags,
+                )
+            } {
+                i if i >= 0 => i as libc::size_t,
+                _ => return Err(io::Error::last_os_error()),
+            },
+        };
+        self.record_datagram(Direction::Sent, buf.as_ref(), len, flags)?;
+        Ok(len)
     }
 
     /// Receive message encoded as byte slice from the netlink socket
@@ -219,19 +497,154 @@ impl NlSocket {
     where
         B: AsMut<[u8]>,
     {
+        let len = match self.transport {
+            Some(ref transport) => transport.recv(buf.as_mut(), flags)?,
+            None => match unsafe {
+                libc::recv(
+                    self.fd,
+                    buf.as_mut() as *mut _ as *mut c_void,
+                    buf.as_mut().len(),
+                    flags,
+                )
+            } {
+                i if i >= 0 => i as libc::size_t,
+                _ => return Err(io::Error::last_os_error()),
+            },
+        };
+        self.record_datagram(Direction::Received, buf.as_mut(), len, flags)?;
+        Ok(len)
+    }
+
+    /// Send message encoded as byte slice to the given netlink port ID rather than the one the
+    /// socket is connected to - used to reply to userspace peers, for example over
+    /// `NETLINK_USERSOCK`
+    pub fn send_to<B>(&self, buf: B, port_id: u32, flags: i32) -> Result<libc::size_t, io::Error>
+    where
+        B: AsRef<[u8]>,
+    {
+        let len = match self.transport {
+            Some(ref transport) => transport.send_to(buf.as_ref(), port_id, flags)?,
+            None => {
+                let mut nladdr = unsafe { zeroed::<libc::sockaddr_nl>() };
+                nladdr.nl_family = libc::c_int::from(AddrFamily::Netlink) as u16;
+                nladdr.nl_pid = port_id;
+                match unsafe {
+                    libc::sendto(
+                        self.fd,
+                        buf.as_ref() as *const _ as *const c_void,
+                        buf.as_ref().len(),
+                        flags,
+                        &nladdr as *const _ as *const libc::sockaddr,
+                        size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+                    )
+                } {
+                    i if i >= 0 => i as libc::size_t,
+                    _ => return Err(io::Error::last_os_error()),
+                }
+            }
+        };
+        self.record_datagram(Direction::Sent, buf.as_ref(), len, flags)?;
+        Ok(len)
+    }
+
+    /// Receive message encoded as byte slice from the netlink socket along with the port ID of
+    /// the sender
+    pub fn recv_from<B>(&self, mut buf: B, flags: i32) -> Result<(libc::size_t, u32), io::Error>
+    where
+        B: AsMut<[u8]>,
+    {
+        let (len, port_id) = match self.transport {
+            Some(ref transport) => transport.recv_from(buf.as_mut(), flags)?,
+            None => {
+                let mut nladdr = unsafe { zeroed::<libc::sockaddr_nl>() };
+                let mut len = size_of::<libc::sockaddr_nl>() as libc::socklen_t;
+                match unsafe {
+                    libc::recvfrom(
+                        self.fd,
+                        buf.as_mut() as *mut _ as *mut c_void,
+                        buf.as_mut().len(),
+                        flags,
+                        &mut nladdr as *mut _ as *mut libc::sockaddr,
+                        &mut len as *mut libc::socklen_t,
+                    )
+                } {
+                    i if i >= 0 => (i as libc::size_t, nladdr.nl_pid),
+                    _ => return Err(io::Error::last_os_error()),
+                }
+            }
+        };
+        self.record_datagram(Direction::Received, buf.as_mut(), len, flags)?;
+        Ok((len, port_id))
+    }
+
+    /// Wrapper around `connect()` making the given port ID the destination of `send` and
+    /// `send_nl` instead of the kernel. This is how a client talks to a userspace server such as
+    /// `server::NlServer`.
+    pub fn connect_peer(&mut self, port_id: u32) -> Result<(), io::Error> {
+        // A transport is already connected to its peer
+        if self.transport.is_some() {
+            return match port_id {
+                0 => Ok(()),
+                _ => Err(io::Error::from_raw_os_error(libc::EISCONN)),
+            };
+        }
+        let mut nladdr = unsafe { zeroed::<libc::sockaddr_nl>() };
+        nladdr.nl_family = libc::c_int::from(AddrFamily::Netlink) as u16;
+        nladdr.nl_pid = port_id;
         match unsafe {
-            libc::recv(
+            libc::connect(
                 self.fd,
-                buf.as_mut() as *mut _ as *mut c_void,
-                buf.as_mut().len(),
-                flags,
+                &nladdr as *const _ as *const libc::sockaddr,
+                size_of::<libc::sockaddr_nl>() as libc::socklen_t,
             )
         } {
-            i if i >= 0 => Ok(i as libc::size_t),
+            0 => Ok(()),
             _ => Err(io::Error::last_os_error()),
         }
     }
 
+    /// Record every datagram sent or received on the socket from now on with `recorder` - see
+    /// the `record` module. Sending and receiving fail if the datagram cannot be recorded. Sockets
+    /// running over a transport are recorded with the family reported by `NlTransport::family`.
+    pub fn record(&mut self, recorder: Recorder) -> Result<(), io::Error> {
+        self.recorder = Some(Mutex::new(recorder));
+        Ok(())
+    }
+
+    /// Stop recording and return the recorder after flushing it
+    pub fn stop_recording(&mut self) -> Result<Option<Recorder>, io::Error> {
+        match self.recorder.take() {
+            Some(recorder) => {
+                let mut recorder = recorder.into_inner().unwrap_or_else(|e| e.into_inner());
+                recorder.flush()?;
+                Ok(Some(recorder))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn record_datagram(
+        &self,
+        direction: Direction,
+        buf: &[u8],
+        len: usize,
+        flags: i32,
+    ) -> Result<(), io::Error> {
+        // Peeked datagrams are recorded once they are actually received
+        if flags & libc::MSG_PEEK != 0 {
+            return Ok(());
+        }
+        // With MSG_TRUNC, the length of the datagram is returned even if it did not fit in `buf`
+        let buf = &buf[..len.min(buf.len())];
+        match self.recorder {
+            Some(ref recorder) => recorder
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .write_datagram(&Datagram::new(direction, self.family.clone(), buf.to_vec())),
+            None => Ok(()),
+        }
+    }
+
     /// Equivalent of `socket` and `bind` calls.
     pub fn connect(
         proto: NlFamily,
@@ -263,7 +676,12 @@ impl NlSocket {
         );
         self.send_nl(nlhdr)?;
 
-        let msg = self.recv_nl(None)?;
+        // recv_all() turns an error reply, eg. for an unknown family, into an error instead of
+        // parsing it as the family
+        let msg = self
+            .recv_all()?
+            .pop()
+            .ok_or_else(|| NlError::new("No family returned"))?;
         self.recv_ack()?;
         Ok(msg)
     }
@@ -306,7 +724,8 @@ impl NlSocket {
             .ok_or_else(|| NlError::new("Failed to resolve multicast group ID"))
     }
 
-    /// Convenience function to send an `Nlmsghdr` struct
+    /// Convenience function to send an `Nlmsghdr` struct - if `nl_pid` is `0`, it will be filled
+    /// in with the port ID of the socket
     pub fn send_nl<T, P>(&mut self, mut msg: Nlmsghdr<T, P>) -> Result<(), NlError>
     where
         T: Nl + NlType,
@@ -317,11 +736,39 @@ impl NlSocket {
             *seq += 1;
             msg.nl_seq = *seq;
         }
+        if msg.nl_pid == 0 {
+            msg.nl_pid = self.cached_port_id()?;
+        }
         msg.serialize(&mut mem)?;
         self.send(mem, 0)?;
         Ok(())
     }
 
+    /// Send several `Nlmsghdr` structs in a single datagram, as required for batched
+    /// transactions - sequence numbers and port IDs are filled in on `msgs` as in `send_nl()` so
+    /// that the responses can be matched to the messages
+    pub fn send_batch<T, P>(&mut self, msgs: &mut [Nlmsghdr<T, P>]) -> Result<(), NlError>
+    where
+        T: Nl + NlType,
+        P: Nl,
+    {
+        let size = msgs.iter().fold(0, |acc, msg| acc + msg.asize());
+        let mut mem = StreamWriteBuffer::new_growable(Some(size));
+        let port_id = self.cached_port_id()?;
+        for msg in msgs.iter_mut() {
+            if let Some(ref mut seq) = self.seq {
+                *seq += 1;
+                msg.nl_seq = *seq;
+            }
+            if msg.nl_pid == 0 {
+                msg.nl_pid = port_id;
+            }
+            msg.serialize(&mut mem)?;
+        }
+        self.send(mem, 0)?;
+        Ok(())
+    }
+
     /// Convenience function to begin receiving a stream of `Nlmsghdr` structs
     pub fn recv_nl<T, P>(&mut self, buf_sz: Option<usize>) -> Result<Nlmsghdr<T, P>, NlError>
     where
@@ -344,22 +791,71 @@ impl NlSocket {
         match self.pid {
             // PID checking turned off.
             None => (),
-            // No PID set yet, store the current one.
-            Some(0) => self.pid = Some(msg.nl_pid),
             // PID check OK
             Some(pid) if pid == msg.nl_pid => (),
             // PID doesn't match
             Some(_) => return Err(NlError::BadPid),
         }
-        if let Some(seq) = self.seq.as_mut() {
-            *seq += 1;
-        }
         if self.buffer.as_ref().map(|b| b.at_end()).unwrap_or(false) {
             self.buffer = None;
         }
         Ok(msg)
     }
 
+    /// Receive all messages of a response until the end of the response is reached. This handles
+    /// both single message responses and multi-part responses (dumps) terminated by
+    /// `NLMSG_DONE`. An error message from the kernel is returned as an `Err` and an ACK ends the
+    /// response.
+    pub fn recv_all<T, P>(&mut self) -> Result<Vec<Nlmsghdr<T, P>>, NlError>
+    where
+        T: Nl + NlType,
+        P: Nl,
+    {
+        let mut msgs = Vec::new();
+        loop {
+            let msg = self.recv_nl::<u16, Vec<u8>>(None)?;
+            if let Some(seq) = self.seq {
+                if seq != msg.nl_seq {
+                    return Err(NlError::BadSeq);
+                }
+            }
+            match consts::Nlmsg::from(msg.nl_type) {
+                consts::Nlmsg::Done => break,
+                consts::Nlmsg::Noop => continue,
+                consts::Nlmsg::Error => {
+                    let mut buf = StreamReadBuffer::new(&msg.nl_payload);
+                    buf.set_size_hint(msg.nl_payload.len());
+                    let err = Nlmsgerr::<u16>::deserialize(&mut buf)?;
+                    if err.error == 0 {
+                        break;
+                    }
+                    let err = std::io::Error::from_raw_os_error(-err.error as _);
+                    return Err(NlError::Msg(err.to_string()));
+                }
+                _ => {
+                    let multi = msg.nl_flags.contains(&NlmF::Multi);
+                    let nl_payload = {
+                        let mut buf = StreamReadBuffer::new(&msg.nl_payload);
+                        buf.set_size_hint(msg.nl_payload.len());
+                        P::deserialize(&mut buf)?
+                    };
+                    msgs.push(Nlmsghdr {
+                        nl_len: msg.nl_len,
+                        nl_type: T::from(msg.nl_type),
+                        nl_flags: msg.nl_flags,
+                        nl_seq: msg.nl_seq,
+                        nl_pid: msg.nl_pid,
+                        nl_payload,
+                    });
+                    if !multi {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(msgs)
+    }
+
     /// Consume an ACK and return an error if an ACK is not found
     pub fn recv_ack(&mut self) -> Result<(), NlError> {
         if let Ok(ack) = self.recv_nl::<consts::Nlmsg, Nlmsgerr<consts::Nlmsg>>(None) {
@@ -395,8 +891,22 @@ impl NlSocket {
     {
         NlMessageIter::new(self)
     }
+
+    /// Return an iterator over multicast events that calls `resync` whenever the kernel reports
+    /// that messages were lost due to a receive buffer overrun. The callback is passed the socket
+    /// so that it can, for example, request a fresh dump whose responses will be yielded by the
+    /// iterator.
+    pub fn event_iter<T, P, F>(&mut self, resync: F) -> NlEventIter<'_, T, P, F>
+    where
+        T: Nl + NlType,
+        P: Nl,
+        F: FnMut(&mut NlSocket) -> Result<(), NlError>,
+    {
+        NlEventIter::new(self, resync)
+    }
 }
 
+/// Sockets running over a transport have no file descriptor and return `-1`
 impl AsRawFd for NlSocket {
     fn as_raw_fd(&self) -> RawFd {
         self.fd
@@ -438,7 +948,8 @@ pub mod tokio {
     where
         T: NlType,
     {
-        /// Setup NlSocket for use with tokio - set to nonblocking state and wrap in polling mechanism
+        /// Setup NlSocket for use with tokio - set to nonblocking state and wrap in polling mechanism.
+        /// Sockets running over a transport cannot be polled and return an error.
         pub fn new(mut sock: super::NlSocket) -> io::Result<Self> {
             if sock.is_blocking()? {
                 sock.nonblock()?;
@@ -531,8 +1042,10 @@ pub mod tokio {
 impl Drop for NlSocket {
     /// Closes underlying file descriptor to avoid file descriptor leaks.
     fn drop(&mut self) {
-        unsafe {
-            libc::close(self.fd);
+        if self.fd >= 0 {
+            unsafe {
+                libc::close(self.fd);
+            }
         }
     }
 }
@@ -544,6 +1057,7 @@ mod test {
     use std::io::Read;
 
     use consts::Nlmsg;
+    use nl::NlEmpty;
 
     #[test]
     fn test_socket_nonblock() {
@@ -563,6 +1077,101 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_socket_port_id() {
+        let s = NlSocket::connect(NlFamily::Generic, Some(0), None, true).unwrap();
+        let port_id = s.port_id().unwrap();
+        assert_ne!(port_id, 0);
+        assert_eq!(s.pid, Some(port_id));
+        assert_eq!(s.port_id, Some(port_id));
+    }
+
+    #[test]
+    fn test_port_id_cached_on_send() {
+        let mut s = NlSocket::connect(NlFamily::Generic, None, None, true).unwrap();
+        s.resolve_genl_family("nlctrl").unwrap();
+        assert_eq!(s.port_id, Some(s.port_id().unwrap()));
+    }
+
+    #[test]
+    fn test_resolve_unknown_family() {
+        let mut s = NlSocket::connect(NlFamily::Generic, None, None, true).unwrap();
+        assert!(s.resolve_genl_family("neli-no-such-family").is_err());
+        // The socket is still usable after the error
+        assert!(s.resolve_genl_family("nlctrl").is_ok());
+    }
+
+    #[test]
+    fn test_set_mcast_groups() {
+        let mut s = NlSocket::connect(NlFamily::Generic, None, None, true).unwrap();
+        let id = s.resolve_nl_mcast_group("nlctrl", "notify").unwrap();
+        s.set_mcast_groups(vec![id]).unwrap();
+    }
+
+    #[test]
+    fn test_socket_transport() {
+        let (transport, kernel) = UnixDatagram::pair().unwrap();
+        let mut s = NlSocket::with_transport(transport, true);
+        assert!(s.set_mcast_groups(vec![1]).is_err());
+
+        let attrs = vec![Nlattr::new(None, CtrlAttr::FamilyName, "nlctrl").unwrap()];
+        let genlhdr = Genlmsghdr::new(CtrlCmd::Getfamily, 2, attrs).unwrap();
+        s.send_nl(Nlmsghdr::new(
+            None,
+            GenlId::Ctrl,
+            vec![NlmF::Request],
+            None,
+            None,
+            genlhdr,
+        ))
+        .unwrap();
+
+        let mut buf = vec![0; MAX_NL_LENGTH];
+        let len = kernel.recv(&mut buf).unwrap();
+        let mut mem = StreamReadBuffer::new(&buf[..len]);
+        let mut msg =
+            Nlmsghdr::<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>>::deserialize(&mut mem).unwrap();
+        assert_eq!(msg.nl_seq, 1);
+
+        msg.nl_payload.cmd = CtrlCmd::Newfamily;
+        let mut reply = StreamWriteBuffer::new_growable(Some(msg.asize()));
+        msg.serialize(&mut reply).unwrap();
+        kernel.send(reply.as_ref()).unwrap();
+        let reply = s
+            .recv_all::<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>>()
+            .unwrap();
+        assert_eq!(reply[0].nl_payload.cmd, CtrlCmd::Newfamily);
+    }
+
+    #[test]
+    fn test_socket_transport_addressing() {
+        let (transport, kernel) = UnixDatagram::pair().unwrap();
+        let mut s = NlSocket::with_transport(transport, false);
+        assert!(s.bind(Some(0), None).is_err());
+        assert!(s.nonblock().is_err());
+        assert!(s.attach_filter(&[]).is_err());
+
+        s.connect_peer(0).unwrap();
+        assert_eq!(
+            s.connect_peer(1234).unwrap_err().raw_os_error(),
+            Some(libc::EISCONN)
+        );
+
+        assert_eq!(s.send_to(&[1, 2, 3, 4], 0, 0).unwrap(), 4);
+        let mut buf = [0; 8];
+        assert_eq!(kernel.recv(&mut buf).unwrap(), 4);
+        assert_eq!(
+            s.send_to(&[1, 2, 3, 4], 1234, 0)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::ECONNREFUSED)
+        );
+
+        kernel.send(&[5, 6]).unwrap();
+        assert_eq!(s.recv_from(&mut buf, 0).unwrap(), (2, 0));
+        assert_eq!(&buf[..2], &[5, 6]);
+    }
+
     #[test]
     fn multi_msg_iter() {
         let mut vec = vec![];
@@ -606,9 +1215,13 @@ mod test {
 
         let mut s = NlSocket {
             fd: -1,
+            family: NlFamily::Generic,
+            transport: None,
+            recorder: None,
             buffer: Some(StreamReadBuffer::new(vec)),
             seq: None,
             pid: None,
+            port_id: None,
         };
         let mut iter = s.iter();
         if let Some(Ok(nl_next)) = iter.next() {
@@ -622,4 +1235,211 @@ mod test {
             panic!("Expected message not found");
         }
     }
+
+    #[test]
+    fn event_iter_overrun() {
+        let mut s = NlSocket::connect(NlFamily::Generic, None, None, true).unwrap();
+        // Shrink the receive buffer to its minimum so that a single family dump datagram
+        // carrying several messages already fills it
+        let size: libc::c_int = 0;
+        assert_eq!(
+            unsafe {
+                libc::setsockopt(
+                    s.fd,
+                    libc::SOL_SOCKET,
+                    libc::SO_RCVBUF,
+                    &size as *const _ as *const c_void,
+                    size_of::<libc::c_int>() as libc::socklen_t,
+                )
+            },
+            0
+        );
+
+        let getfamily = |flags| {
+            Nlmsghdr::new(
+                None,
+                GenlId::Ctrl,
+                flags,
+                None,
+                None,
+                Genlmsghdr::new(
+                    CtrlCmd::Getfamily,
+                    2,
+                    vec![Nlattr::new(None, CtrlAttr::FamilyName, "nlctrl").unwrap()],
+                )
+                .unwrap(),
+            )
+        };
+        s.send_nl(getfamily(vec![NlmF::Request, NlmF::Dump]))
+            .unwrap();
+        // The kernel drops these replies as the buffer is full and flags the overrun
+        for _ in 0..4 {
+            s.send_nl(getfamily(vec![NlmF::Request, NlmF::Ack]))
+                .unwrap();
+        }
+
+        let mut resyncs = 0;
+        {
+            let mut iter = s.event_iter::<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>, _>(|_| {
+                resyncs += 1;
+                Ok(())
+            });
+            match iter.next() {
+                Some(Ok(NlEvent::Overrun)) => (),
+                _ => panic!("Expected overrun"),
+            }
+            // The dump datagram that was queued before the overrun is still delivered
+            match iter.next() {
+                Some(Ok(NlEvent::Msg(msg))) => {
+                    assert!(msg.nl_flags.contains(&NlmF::Multi));
+                    assert_eq!(msg.nl_payload.cmd, CtrlCmd::Newfamily);
+                }
+                _ => panic!("Expected message not found"),
+            }
+        }
+        assert_eq!(resyncs, 1);
+    }
+
+    #[test]
+    fn event_iter_yields_messages() {
+        let mut vec = vec![];
+        let nl = Nlmsghdr::new(
+            None,
+            Nlmsg::Noop,
+            vec![NlmF::Multi],
+            None,
+            None,
+            Genlmsghdr::new(
+                CtrlCmd::Unspec,
+                2,
+                vec![Nlattr::new(None, CtrlAttr::FamilyId, 5u32).unwrap()],
+            )
+            .unwrap(),
+        );
+        nl.serialize(&mut StreamWriteBuffer::new_growable_ref(&mut vec))
+            .unwrap();
+
+        let mut s = NlSocket {
+            fd: -1,
+            family: NlFamily::Generic,
+            transport: None,
+            recorder: None,
+            buffer: Some(StreamReadBuffer::new(vec)),
+            seq: None,
+            pid: None,
+            port_id: None,
+        };
+        let mut resyncs = 0;
+        {
+            let mut iter = s.event_iter(|_| {
+                resyncs += 1;
+                Ok(())
+            });
+            match iter.next() {
+                Some(Ok(NlEvent::Msg(msg))) => assert_eq!(msg, nl),
+                _ => panic!("Expected message not found"),
+            }
+        }
+        assert_eq!(resyncs, 0);
+    }
+
+    fn buffered_socket(vec: Vec<u8>, seq: Option<u32>) -> NlSocket {
+        NlSocket {
+            fd: -1,
+            family: NlFamily::Generic,
+            buffer: Some(StreamReadBuffer::new(vec)),
+            seq,
+            pid: None,
+            port_id: None,
+            transport: None,
+            recorder: None,
+        }
+    }
+
+    fn family_msg(seq: u32, id: u32) -> Nlmsghdr<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>> {
+        Nlmsghdr::new(
+            None,
+            GenlId::Ctrl,
+            vec![NlmF::Multi],
+            Some(seq),
+            None,
+            Genlmsghdr::new(
+                CtrlCmd::Newfamily,
+                2,
+                vec![Nlattr::new(None, CtrlAttr::FamilyId, id).unwrap()],
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn recv_all_collects_dump() {
+        let mut vec = vec![];
+        {
+            let mut stream = StreamWriteBuffer::new_growable_ref(&mut vec);
+            family_msg(5, 1).serialize(&mut stream).unwrap();
+            family_msg(5, 2).serialize(&mut stream).unwrap();
+            Nlmsghdr::new(None, Nlmsg::Done, vec![NlmF::Multi], Some(5), None, 0u32)
+                .serialize(&mut stream)
+                .unwrap();
+        }
+
+        let mut s = buffered_socket(vec, Some(5));
+        let msgs = s
+            .recv_all::<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>>()
+            .unwrap();
+        assert_eq!(msgs, vec![family_msg(5, 1), family_msg(5, 2)]);
+        // Receiving does not advance the sequence number, only sending does
+        assert_eq!(s.seq, Some(5));
+        assert!(s.buffer.is_none());
+    }
+
+    #[test]
+    fn recv_all_single_message() {
+        let mut vec = vec![];
+        let mut msg = family_msg(1, 1);
+        msg.nl_flags = vec![];
+        msg.serialize(&mut StreamWriteBuffer::new_growable_ref(&mut vec))
+            .unwrap();
+
+        let mut s = buffered_socket(vec, Some(1));
+        let msgs = s
+            .recv_all::<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>>()
+            .unwrap();
+        assert_eq!(msgs, vec![msg]);
+    }
+
+    #[test]
+    fn recv_all_error() {
+        let mut vec = vec![];
+        let err = Nlmsgerr {
+            error: -libc::ENOENT,
+            nlmsg: Nlmsghdr::new(None, GenlId::Ctrl, vec![], Some(3), None, NlEmpty),
+        };
+        Nlmsghdr::new(None, Nlmsg::Error, vec![], Some(3), None, err)
+            .serialize(&mut StreamWriteBuffer::new_growable_ref(&mut vec))
+            .unwrap();
+
+        let mut s = buffered_socket(vec, Some(3));
+        match s.recv_all::<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>>() {
+            Err(NlError::Msg(msg)) => {
+                assert_eq!(msg, io::Error::from_raw_os_error(libc::ENOENT).to_string())
+            }
+            _ => panic!("Expected error message"),
+        }
+    }
+
+    #[test]
+    fn recv_all_bad_seq() {
+        let mut vec = vec![];
+        family_msg(7, 1)
+            .serialize(&mut StreamWriteBuffer::new_growable_ref(&mut vec))
+            .unwrap();
+
+        let mut s = buffered_socket(vec, Some(6));
+        match s.recv_all::<GenlId, Genlmsghdr<CtrlCmd, CtrlAttr>>() {
+            Err(NlError::BadSeq) => (),
+            _ => panic!("Expected sequence number mismatch"),
+        }
+    }
 }
@@ -0,0 +1,615 @@
+//! This module provides access to the taskstats generic netlink family, which reports delay
+//! accounting, CPU, memory and IO statistics of tasks.
+//!
+//! ```no_run
+//! use neli::taskstats::TaskstatsSocket;
+//!
+//! let mut socket = TaskstatsSocket::connect().unwrap();
+//! let stats = socket.get_pid(std::process::id()).unwrap();
+//! println!("{} waited {}ns for a CPU", stats.ac_comm, stats.cpu_delay_total);
+//!
+//! // Statistics of every task exiting on CPUs 0 to 3
+//! let mut listener = TaskstatsSocket::connect().unwrap();
+//! listener.register_cpumask("0-3").unwrap();
+//! loop {
+//!     for record in listener.recv_exit().unwrap() {
+//!         println!("{:?} read {} bytes", record.id, record.stats.read_bytes);
+//!     }
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! `struct taskstats` only ever grows at the end, and the kernel fills in `version` with the
+//! version of the layout it sends. `Taskstats` has the fields of version 13; fields a kernel
+//! older than that does not send are zero, and the bytes of fields a newer kernel sends are
+//! kept in `extra`.
+//!
+//! Exit notifications are sent to every socket that registered a cpumask containing the CPU
+//! the task exited on. They may arrive at any time, so replies to `get_pid()` and `get_tgid()`
+//! should be requested on a separate socket.
+
+use buffering::{StreamReadBuffer, StreamWriteBuffer};
+
+use consts::taskstats::{TaskstatsCmd, TaskstatsCmdAttr, TaskstatsType};
+use consts::{NlFamily, NlmF};
+use err::{DeError, NlError, SerError};
+use genl::Genlmsghdr;
+use nl::Nlmsghdr;
+use nlattr::{AttrHandle, Nlattr};
+use socket::NlSocket;
+use Nl;
+
+/// Name of the taskstats generic netlink family
+pub const TASKSTATS_GENL_NAME: &str = "TASKSTATS";
+/// Version of the taskstats generic netlink family
+const TASKSTATS_GENL_VERSION: u8 = 1;
+/// Version of `struct taskstats` whose fields `Taskstats` knows
+pub const TASKSTATS_VERSION: u16 = 13;
+/// Length of the command name in `struct taskstats`
+pub const TS_COMM_LEN: usize = 32;
+
+/// Size of version 1 of `struct taskstats`, the smallest the kernel sends
+const TASKSTATS_V1_LEN: usize = 80;
+/// Size of version 13 of `struct taskstats`
+const TASKSTATS_LEN: usize = 416;
+
+/// Read fields of a `struct taskstats` by offset. Fields past the end of a struct sent by an
+/// older kernel read as zero.
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn bytes(&self, offset: usize, buf: &mut [u8]) {
+        if let Some(field) = self.0.get(offset..offset + buf.len()) {
+            buf.copy_from_slice(field);
+        }
+    }
+
+    fn u8(&self, offset: usize) -> u8 {
+        self.0.get(offset).cloned().unwrap_or(0)
+    }
+
+    fn u16(&self, offset: usize) -> u16 {
+        let mut buf = [0; 2];
+        self.bytes(offset, &mut buf);
+        u16::from_ne_bytes(buf)
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        let mut buf = [0; 4];
+        self.bytes(offset, &mut buf);
+        u32::from_ne_bytes(buf)
+    }
+
+    fn u64(&self, offset: usize) -> u64 {
+        let mut buf = [0; 8];
+        self.bytes(offset, &mut buf);
+        u64::from_ne_bytes(buf)
+    }
+}
+
+/// Per-task or per-thread group statistics (`struct taskstats`). Delays are in nanoseconds and
+/// times in microseconds unless noted otherwise.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Taskstats {
+    /// Version of the struct sent by the kernel
+    pub version: u16,
+    /// Exit status
+    pub ac_exitcode: u32,
+    /// Accounting flags (`AFORK`, `ASU`, `ACORE`, `AXSIG`)
+    pub ac_flag: u8,
+    /// Nice value
+    pub ac_nice: u8,
+    /// Number of times the task waited for a CPU
+    pub cpu_count: u64,
+    /// Total time waited for a CPU
+    pub cpu_delay_total: u64,
+    /// Number of synchronous block IO waits
+    pub blkio_count: u64,
+    /// Total time waited for synchronous block IO
+    pub blkio_delay_total: u64,
+    /// Number of swap-in waits
+    pub swapin_count: u64,
+    /// Total time waited for pages to be swapped in
+    pub swapin_delay_total: u64,
+    /// Wall clock CPU time in nanoseconds
+    pub cpu_run_real_total: u64,
+    /// Virtual CPU time in nanoseconds
+    pub cpu_run_virtual_total: u64,
+    /// Command name
+    pub ac_comm: String,
+    /// Scheduling discipline
+    pub ac_sched: u8,
+    /// User ID
+    pub ac_uid: u32,
+    /// Group ID
+    pub ac_gid: u32,
+    /// Process ID
+    pub ac_pid: u32,
+    /// Parent process ID
+    pub ac_ppid: u32,
+    /// Begin time in seconds since the epoch, truncated to 32 bits
+    pub ac_btime: u32,
+    /// Elapsed time
+    pub ac_etime: u64,
+    /// User CPU time
+    pub ac_utime: u64,
+    /// System CPU time
+    pub ac_stime: u64,
+    /// Minor page faults
+    pub ac_minflt: u64,
+    /// Major page faults
+    pub ac_majflt: u64,
+    /// Accumulated RSS usage in MB-usec
+    pub coremem: u64,
+    /// Accumulated virtual memory usage in MB-usec
+    pub virtmem: u64,
+    /// High-watermark of RSS usage in KB
+    pub hiwater_rss: u64,
+    /// High-watermark of virtual memory usage in KB
+    pub hiwater_vm: u64,
+    /// Bytes read
+    pub read_char: u64,
+    /// Bytes written
+    pub write_char: u64,
+    /// Read system calls
+    pub read_syscalls: u64,
+    /// Write system calls
+    pub write_syscalls: u64,
+    /// Bytes of read IO
+    pub read_bytes: u64,
+    /// Bytes of write IO
+    pub write_bytes: u64,
+    /// Bytes of cancelled write IO
+    pub cancelled_write_bytes: u64,
+    /// Voluntary context switches
+    pub nvcsw: u64,
+    /// Involuntary context switches
+    pub nivcsw: u64,
+    /// User CPU time scaled by CPU frequency
+    pub ac_utimescaled: u64,
+    /// System CPU time scaled by CPU frequency
+    pub ac_stimescaled: u64,
+    /// `cpu_run_real_total` scaled by CPU frequency
+    pub cpu_scaled_run_real_total: u64,
+    /// Number of memory reclaim waits
+    pub freepages_count: u64,
+    /// Total time waited for memory reclaim
+    pub freepages_delay_total: u64,
+    /// Number of thrashing waits
+    pub thrashing_count: u64,
+    /// Total time waited because of thrashing
+    pub thrashing_delay_total: u64,
+    /// Begin time in seconds since the epoch
+    pub ac_btime64: u64,
+    /// Number of memory compaction waits
+    pub compact_count: u64,
+    /// Total time waited for memory compaction
+    pub compact_delay_total: u64,
+    /// Thread group ID
+    pub ac_tgid: u32,
+    /// Elapsed time of the thread group
+    pub ac_tgetime: u64,
+    /// Device ID of the program binary
+    pub ac_exe_dev: u64,
+    /// Inode number of the program binary
+    pub ac_exe_inode: u64,
+    /// Number of write-protect copy waits
+    pub wpcopy_count: u64,
+    /// Total time waited for write-protect copies
+    pub wpcopy_delay_total: u64,
+    /// Fields added after version 13
+    pub extra: Vec<u8>,
+}
+
+impl Taskstats {
+    fn parse(bytes: &[u8]) -> Self {
+        let f = Fields(bytes);
+        let mut comm = [0; TS_COMM_LEN];
+        f.bytes(80, &mut comm);
+        let comm_len = comm.iter().position(|b| *b == 0).unwrap_or(TS_COMM_LEN);
+        Taskstats {
+            version: f.u16(0),
+            ac_exitcode: f.u32(4),
+            ac_flag: f.u8(8),
+            ac_nice: f.u8(9),
+            cpu_count: f.u64(16),
+            cpu_delay_total: f.u64(24),
+            blkio_count: f.u64(32),
+            blkio_delay_total: f.u64(40),
+            swapin_count: f.u64(48),
+            swapin_delay_total: f.u64(56),
+            cpu_run_real_total: f.u64(64),
+            cpu_run_virtual_total: f.u64(72),
+            ac_comm: String::from_utf8_lossy(&comm[..comm_len]).into_owned(),
+            ac_sched: f.u8(112),
+            ac_uid: f.u32(120),
+            ac_gid: f.u32(124),
+            ac_pid: f.u32(128),
+            ac_ppid: f.u32(132),
+            ac_btime: f.u32(136),
+            ac_etime: f.u64(144),
+            ac_utime: f.u64(152),
+            ac_stime: f.u64(160),
+            ac_minflt: f.u64(168),
+            ac_majflt: f.u64(176),
+            coremem: f.u64(184),
+            virtmem: f.u64(192),
+            hiwater_rss: f.u64(200),
+            hiwater_vm: f.u64(208),
+            read_char: f.u64(216),
+            write_char: f.u64(224),
+            read_syscalls: f.u64(232),
+            write_syscalls: f.u64(240),
+            read_bytes: f.u64(248),
+            write_bytes: f.u64(256),
+            cancelled_write_bytes: f.u64(264),
+            nvcsw: f.u64(272),
+            nivcsw: f.u64(280),
+            ac_utimescaled: f.u64(288),
+            ac_stimescaled: f.u64(296),
+            cpu_scaled_run_real_total: f.u64(304),
+            freepages_count: f.u64(312),
+            freepages_delay_total: f.u64(320),
+            thrashing_count: f.u64(328),
+            thrashing_delay_total: f.u64(336),
+            ac_btime64: f.u64(344),
+            compact_count: f.u64(352),
+            compact_delay_total: f.u64(360),
+            ac_tgid: f.u32(368),
+            ac_tgetime: f.u64(376),
+            ac_exe_dev: f.u64(384),
+            ac_exe_inode: f.u64(392),
+            wpcopy_count: f.u64(400),
+            wpcopy_delay_total: f.u64(408),
+            extra: bytes.get(TASKSTATS_LEN..).unwrap_or(&[]).to_vec(),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; TASKSTATS_LEN];
+        {
+            let mut put = |offset: usize, field: &[u8]| {
+                bytes[offset..offset + field.len()].copy_from_slice(field)
+            };
+            put(0, &self.version.to_ne_bytes());
+            put(4, &self.ac_exitcode.to_ne_bytes());
+            put(8, &[self.ac_flag, self.ac_nice]);
+            put(16, &self.cpu_count.to_ne_bytes());
+            put(24, &self.cpu_delay_total.to_ne_bytes());
+            put(32, &self.blkio_count.to_ne_bytes());
+            put(40, &self.blkio_delay_total.to_ne_bytes());
+            put(48, &self.swapin_count.to_ne_bytes());
+            put(56, &self.swapin_delay_total.to_ne_bytes());
+            put(64, &self.cpu_run_real_total.to_ne_bytes());
+            put(72, &self.cpu_run_virtual_total.to_ne_bytes());
+            let comm = self.ac_comm.as_bytes();
+            put(80, &comm[..comm.len().min(TS_COMM_LEN - 1)]);
+            put(112, &[self.ac_sched]);
+            put(120, &self.ac_uid.to_ne_bytes());
+            put(124, &self.ac_gid.to_ne_bytes());
+            put(128, &self.ac_pid.to_ne_bytes());
+            put(132, &self.ac_ppid.to_ne_bytes());
+            put(136, &self.ac_btime.to_ne_bytes());
+            let u64_fields = [
+                self.ac_etime,
+                self.ac_utime,
+                self.ac_stime,
+                self.ac_minflt,
+                self.ac_majflt,
+                self.coremem,
+                self.virtmem,
+                self.hiwater_rss,
+                self.hiwater_vm,
+                self.read_char,
+                self.write_char,
+                self.read_syscalls,
+                self.write_syscalls,
+                self.read_bytes,
+                self.write_bytes,
+                self.cancelled_write_bytes,
+                self.nvcsw,
+                self.nivcsw,
+                self.ac_utimescaled,
+                self.ac_stimescaled,
+                self.cpu_scaled_run_real_total,
+                self.freepages_count,
+                self.freepages_delay_total,
+                self.thrashing_count,
+                self.thrashing_delay_total,
+                self.ac_btime64,
+                self.compact_count,
+                self.compact_delay_total,
+            ];
+            for (i, field) in u64_fields.iter().enumerate() {
+                put(144 + 8 * i, &field.to_ne_bytes());
+            }
+            put(368, &self.ac_tgid.to_ne_bytes());
+            put(376, &self.ac_tgetime.to_ne_bytes());
+            put(384, &self.ac_exe_dev.to_ne_bytes());
+            put(392, &self.ac_exe_inode.to_ne_bytes());
+            put(400, &self.wpcopy_count.to_ne_bytes());
+            put(408, &self.wpcopy_delay_total.to_ne_bytes());
+        }
+        bytes.extend_from_slice(&self.extra);
+        bytes
+    }
+}
+
+impl Nl for Taskstats {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.to_bytes().serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let hint = buf.take_size_hint().unwrap_or(TASKSTATS_LEN);
+        if hint < TASKSTATS_V1_LEN {
+            return Err(DeError::new("Taskstats struct is truncated"));
+        }
+        buf.set_size_hint(hint);
+        Ok(Taskstats::parse(&Vec::<u8>::deserialize(buf)?))
+    }
+
+    fn size(&self) -> usize {
+        TASKSTATS_LEN + self.extra.len()
+    }
+}
+
+/// Task the statistics of a `TaskstatsRecord` belong to
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskstatsId {
+    /// Single thread
+    Pid(u32),
+    /// Thread group, with the statistics of all of its threads
+    Tgid(u32),
+}
+
+/// Statistics of a thread or thread group as sent in replies and exit notifications
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskstatsRecord {
+    /// Thread or thread group
+    pub id: TaskstatsId,
+    /// Statistics
+    pub stats: Taskstats,
+}
+
+impl TaskstatsRecord {
+    /// Parse the `TaskstatsType::AggrPid` and `TaskstatsType::AggrTgid` attributes of a
+    /// message. An exit notification for the last thread of a thread group has both.
+    pub fn parse_all(handle: &AttrHandle<TaskstatsType>) -> Result<Vec<Self>, DeError> {
+        let mut records = Vec::new();
+        for attr in handle.iter() {
+            if attr.nla_type != TaskstatsType::AggrPid && attr.nla_type != TaskstatsType::AggrTgid {
+                continue;
+            }
+            let aggr = attr.get_nested_attributes::<TaskstatsType>()?;
+            let id = match (
+                aggr.get_attribute(TaskstatsType::Pid),
+                aggr.get_attribute(TaskstatsType::Tgid),
+            ) {
+                (Some(pid), _) => TaskstatsId::Pid(pid.get_payload_as()?),
+                (None, Some(tgid)) => TaskstatsId::Tgid(tgid.get_payload_as()?),
+                (None, None) => return Err(DeError::new("Missing task ID")),
+            };
+            let stats = aggr.get_attr_payload_as(TaskstatsType::Stats)?;
+            records.push(TaskstatsRecord { id, stats });
+        }
+        Ok(records)
+    }
+}
+
+/// Socket for the taskstats family with its resolved family ID
+pub struct TaskstatsSocket {
+    socket: NlSocket,
+    family_id: u16,
+}
+
+impl TaskstatsSocket {
+    /// Connect to the taskstats family
+    pub fn connect() -> Result<Self, NlError> {
+        let mut socket = NlSocket::connect(NlFamily::Generic, None, None, true)?;
+        let family_id = socket.resolve_genl_family(TASKSTATS_GENL_NAME)?;
+        Ok(TaskstatsSocket { socket, family_id })
+    }
+
+    /// Generic netlink family ID of taskstats
+    pub fn family_id(&self) -> u16 {
+        self.family_id
+    }
+
+    /// Underlying socket, to send requests not covered by this type
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+
+    /// Get the statistics of a thread
+    pub fn get_pid(&mut self, pid: u32) -> Result<Taskstats, NlError> {
+        self.get(TaskstatsCmdAttr::Pid, pid, TaskstatsId::Pid(pid))
+    }
+
+    /// Get the statistics of a thread group
+    pub fn get_tgid(&mut self, tgid: u32) -> Result<Taskstats, NlError> {
+        self.get(TaskstatsCmdAttr::Tgid, tgid, TaskstatsId::Tgid(tgid))
+    }
+
+    /// Receive the statistics of tasks exiting on the CPUs in `cpumask`, a list of CPUs and CPU
+    /// ranges like `"0-3,8"`
+    pub fn register_cpumask(&mut self, cpumask: &str) -> Result<(), NlError> {
+        self.set_cpumask(TaskstatsCmdAttr::RegisterCpumask, cpumask)
+    }
+
+    /// Stop receiving the statistics of tasks exiting on the CPUs in `cpumask`
+    pub fn deregister_cpumask(&mut self, cpumask: &str) -> Result<(), NlError> {
+        self.set_cpumask(TaskstatsCmdAttr::DeregisterCpumask, cpumask)
+    }
+
+    /// Receive the next exit notification
+    pub fn recv_exit(&mut self) -> Result<Vec<TaskstatsRecord>, NlError> {
+        let msg = self
+            .socket
+            .recv_nl::<u16, Genlmsghdr<TaskstatsCmd, TaskstatsType>>(None)?;
+        Ok(TaskstatsRecord::parse_all(
+            &msg.nl_payload.get_attr_handle(),
+        )?)
+    }
+
+    fn send(
+        &mut self,
+        flags: Vec<NlmF>,
+        attr: Nlattr<TaskstatsCmdAttr, Vec<u8>>,
+    ) -> Result<(), NlError> {
+        let genlhdr = Genlmsghdr::new(TaskstatsCmd::Get, TASKSTATS_GENL_VERSION, vec![attr])?;
+        let nlhdr = Nlmsghdr::new(None, self.family_id, flags, None, None, genlhdr);
+        self.socket.send_nl(nlhdr)
+    }
+
+    fn get(
+        &mut self,
+        attr_type: TaskstatsCmdAttr,
+        task: u32,
+        id: TaskstatsId,
+    ) -> Result<Taskstats, NlError> {
+        self.send(vec![NlmF::Request], Nlattr::new(None, attr_type, task)?)?;
+        let msg = self
+            .socket
+            .recv_all::<u16, Genlmsghdr<TaskstatsCmd, TaskstatsType>>()?
+            .pop()
+            .ok_or_else(|| NlError::new("No reply returned"))?;
+        TaskstatsRecord::parse_all(&msg.nl_payload.get_attr_handle())?
+            .into_iter()
+            .find(|record| record.id == id)
+            .map(|record| record.stats)
+            .ok_or_else(|| NlError::new("Reply is missing the requested task"))
+    }
+
+    fn set_cpumask(&mut self, attr_type: TaskstatsCmdAttr, cpumask: &str) -> Result<(), NlError> {
+        self.send(
+            vec![NlmF::Request, NlmF::Ack],
+            Nlattr::new(None, attr_type, cpumask)?,
+        )?;
+        self.socket.recv_ack()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::process::Command;
+
+    #[test]
+    fn test_taskstats_serialize() {
+        let stats = Taskstats {
+            version: TASKSTATS_VERSION,
+            ac_exitcode: 256,
+            ac_comm: "neli-test".to_string(),
+            ac_pid: 42,
+            cpu_delay_total: 1_000,
+            read_bytes: 4096,
+            ac_btime64: 1_600_000_000,
+            ac_tgid: 42,
+            wpcopy_delay_total: 7,
+            ..Taskstats::default()
+        };
+        let bytes = stats.to_bytes();
+        assert_eq!(bytes.len(), TASKSTATS_LEN);
+        assert_eq!(&bytes[80..89], b"neli-test");
+        assert_eq!(&bytes[128..132], &42u32.to_ne_bytes());
+        assert_eq!(&bytes[248..256], &4096u64.to_ne_bytes());
+        assert_eq!(&bytes[408..416], &7u64.to_ne_bytes());
+
+        let mut buf = StreamReadBuffer::new(&bytes);
+        buf.set_size_hint(bytes.len());
+        assert_eq!(Taskstats::deserialize(&mut buf).unwrap(), stats);
+    }
+
+    #[test]
+    fn test_taskstats_versions() {
+        // A version 1 struct from an old kernel
+        let mut bytes = vec![0; TASKSTATS_V1_LEN];
+        bytes[..2].copy_from_slice(&1u16.to_ne_bytes());
+        bytes[24..32].copy_from_slice(&5u64.to_ne_bytes());
+        let mut buf = StreamReadBuffer::new(&bytes);
+        buf.set_size_hint(bytes.len());
+        let stats = Taskstats::deserialize(&mut buf).unwrap();
+        assert_eq!(stats.version, 1);
+        assert_eq!(stats.cpu_delay_total, 5);
+        assert_eq!(stats.ac_pid, 0);
+
+        // A newer struct keeps the unknown fields
+        let mut bytes = vec![0; TASKSTATS_LEN + 16];
+        bytes[TASKSTATS_LEN] = 9;
+        let mut buf = StreamReadBuffer::new(&bytes);
+        buf.set_size_hint(bytes.len());
+        let stats = Taskstats::deserialize(&mut buf).unwrap();
+        assert_eq!(stats.extra.len(), 16);
+        assert_eq!(stats.size(), bytes.len());
+
+        let mut buf = StreamReadBuffer::new(&bytes[..40]);
+        buf.set_size_hint(40);
+        assert!(Taskstats::deserialize(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_parse_records() {
+        let stats = Taskstats {
+            version: TASKSTATS_VERSION,
+            ac_pid: 10,
+            ac_tgid: 9,
+            ..Taskstats::default()
+        };
+        let aggr_pid = Nlattr::new_nested(
+            TaskstatsType::AggrPid,
+            &[
+                Nlattr::new(None, TaskstatsType::Pid, 10u32).unwrap(),
+                Nlattr::new(None, TaskstatsType::Stats, stats.to_bytes()).unwrap(),
+            ],
+        )
+        .unwrap();
+        let aggr_tgid = Nlattr::new_nested(
+            TaskstatsType::AggrTgid,
+            &[
+                Nlattr::new(None, TaskstatsType::Tgid, 9u32).unwrap(),
+                Nlattr::new(None, TaskstatsType::Stats, stats.to_bytes()).unwrap(),
+            ],
+        )
+        .unwrap();
+        let attrs = vec![
+            aggr_pid,
+            Nlattr::new(None, TaskstatsType::Null, Vec::<u8>::new()).unwrap(),
+            aggr_tgid,
+        ];
+        let records = TaskstatsRecord::parse_all(&AttrHandle::new(attrs)).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, TaskstatsId::Pid(10));
+        assert_eq!(records[1].id, TaskstatsId::Tgid(9));
+        assert_eq!(records[1].stats, stats);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_exit_notification() {
+        let mut socket = TaskstatsSocket::connect().unwrap();
+        let stats = socket.get_pid(std::process::id()).unwrap();
+        assert_eq!(stats.ac_pid, std::process::id());
+
+        let mut listener = TaskstatsSocket::connect().unwrap();
+        let cpus = format!("0-{}", num_cpus() - 1);
+        listener.register_cpumask(&cpus).unwrap();
+        let child = Command::new("true").spawn().unwrap().id();
+        loop {
+            let records = listener.recv_exit().unwrap();
+            if records.iter().any(|r| r.id == TaskstatsId::Pid(child)) {
+                break;
+            }
+        }
+        listener.deregister_cpumask(&cpus).unwrap();
+    }
+
+    fn num_cpus() -> usize {
+        unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) as usize }
+    }
+}
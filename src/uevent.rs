@@ -0,0 +1,366 @@
+//! This module provides a listener for device events (uevents) on the
+//! `NlFamily::KobjectUevent` protocol. Uevents are not framed with an `Nlmsghdr` so they cannot
+//! be received with `NlSocket.recv_nl()`.
+//!
+//! Two formats are sent on the protocol:
+//! * Kernel uevents, sent to `UeventGrp::Kernel`, are an `ACTION@DEVPATH` header followed by
+//! `KEY=VALUE` properties, all separated by null bytes.
+//! * udev events, sent to `UeventGrp::Udev` once udev has processed a kernel uevent, start with
+//! the libudev monitor header followed by the properties.
+//!
+//! Both are parsed into a `Uevent`.
+//!
+//! ```no_run
+//! use neli::consts::UeventGrp;
+//! use neli::uevent::UeventSocket;
+//!
+//! let mut socket = UeventSocket::connect(&[UeventGrp::Kernel]).unwrap();
+//! socket.add_subsystem_filter("block");
+//! loop {
+//!     let event = socket.recv().unwrap();
+//!     println!("{} {}", event.action, event.devpath);
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! Subsystem filters are applied when receiving rather than with a BPF filter as the kernel
+//! uevent format has no fixed offsets to match against.
+
+use std::collections::HashMap;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use consts::{NlFamily, UeventGrp};
+use err::{DeError, NlError};
+use socket::NlSocket;
+
+/// Prefix identifying messages sent by libudev
+const LIBUDEV_PREFIX: &[u8] = b"libudev\0";
+/// Magic number of the libudev monitor header
+const LIBUDEV_MAGIC: u32 = 0xfeed_cafe;
+/// Size of the libudev monitor header
+const LIBUDEV_HEADER_SIZE: usize = 40;
+/// Size of the receive buffer, matching libudev
+const UEVENT_BUFFER_SIZE: usize = 8192;
+
+fn u32_at(buf: &[u8], offset: usize) -> Result<[u8; 4], DeError> {
+    buf.get(offset..offset + 4)
+        .map(|b| [b[0], b[1], b[2], b[3]])
+        .ok_or_else(|| DeError::new("Truncated libudev header"))
+}
+
+fn be32_at(buf: &[u8], offset: usize) -> Result<u32, DeError> {
+    u32_at(buf, offset).map(u32::from_be_bytes)
+}
+
+fn ne32_at(buf: &[u8], offset: usize) -> Result<u32, DeError> {
+    u32_at(buf, offset).map(u32::from_ne_bytes)
+}
+
+/// MurmurHash2 as used by libudev for the subsystem and device type hashes in the monitor header
+fn murmur_hash2(key: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let mut h = seed ^ key.len() as u32;
+    let mut chunks = key.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M) ^ k;
+    }
+    let rest = chunks.remainder();
+    if rest.len() >= 3 {
+        h ^= u32::from(rest[2]) << 16;
+    }
+    if rest.len() >= 2 {
+        h ^= u32::from(rest[1]) << 8;
+    }
+    if !rest.is_empty() {
+        h ^= u32::from(rest[0]);
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^ h >> 15
+}
+
+/// Device event
+#[derive(Clone, Debug, PartialEq)]
+pub struct Uevent {
+    /// Action such as `add`, `remove`, `change`, `bind` or `unbind`
+    pub action: String,
+    /// Path of the device in sysfs without the `/sys` prefix
+    pub devpath: String,
+    /// Subsystem of the device
+    pub subsystem: String,
+    /// All properties of the event, including `ACTION`, `DEVPATH` and `SUBSYSTEM`
+    pub env: HashMap<String, String>,
+    /// Whether the event was sent by udev rather than by the kernel
+    pub from_udev: bool,
+}
+
+impl Uevent {
+    /// Create an event with the properties describing `action`, `devpath` and `subsystem`
+    pub fn new(action: &str, devpath: &str, subsystem: &str) -> Self {
+        let mut env = HashMap::new();
+        env.insert("ACTION".to_string(), action.to_string());
+        env.insert("DEVPATH".to_string(), devpath.to_string());
+        env.insert("SUBSYSTEM".to_string(), subsystem.to_string());
+        Uevent {
+            action: action.to_string(),
+            devpath: devpath.to_string(),
+            subsystem: subsystem.to_string(),
+            env,
+            from_udev: false,
+        }
+    }
+
+    /// Get the value of property `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.env.get(key).map(|v| v.as_str())
+    }
+
+    /// Sequence number assigned by the kernel
+    pub fn seqnum(&self) -> Option<u64> {
+        self.get("SEQNUM").and_then(|v| v.parse().ok())
+    }
+
+    /// Parse a kernel uevent or a libudev monitor message
+    pub fn parse(buf: &[u8]) -> Result<Self, DeError> {
+        if buf.starts_with(LIBUDEV_PREFIX) {
+            Self::parse_udev(buf)
+        } else {
+            Self::parse_kernel(buf)
+        }
+    }
+
+    fn parse_kernel(buf: &[u8]) -> Result<Self, DeError> {
+        let mut fields = buf.split(|b| *b == 0);
+        let header = fields
+            .next()
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .ok_or_else(|| DeError::new("Invalid uevent header"))?;
+        let at = header
+            .find('@')
+            .ok_or_else(|| DeError::new("Invalid uevent header"))?;
+        let mut event = Self::from_properties(fields)?;
+        if event.action.is_empty() {
+            event.action = header[..at].to_string();
+        }
+        if event.devpath.is_empty() {
+            event.devpath = header[at + 1..].to_string();
+        }
+        Ok(event)
+    }
+
+    fn parse_udev(buf: &[u8]) -> Result<Self, DeError> {
+        if be32_at(buf, 8)? != LIBUDEV_MAGIC {
+            return Err(DeError::new("Invalid libudev magic number"));
+        }
+        let properties_off = ne32_at(buf, 16)? as usize;
+        let properties_len = ne32_at(buf, 20)? as usize;
+        let properties = properties_off
+            .checked_add(properties_len)
+            .and_then(|end| buf.get(properties_off..end))
+            .ok_or_else(|| DeError::new("Truncated libudev message"))?;
+        let mut event = Self::from_properties(properties.split(|b| *b == 0))?;
+        event.from_udev = true;
+        Ok(event)
+    }
+
+    fn from_properties<'a, I>(properties: I) -> Result<Self, DeError>
+    where
+        I: Iterator<Item = &'a [u8]>,
+    {
+        let mut env = HashMap::new();
+        for property in properties.filter(|p| !p.is_empty()) {
+            let property = std::str::from_utf8(property)
+                .map_err(|_| DeError::new("Uevent property is not valid UTF-8"))?;
+            let mut split = property.splitn(2, '=');
+            if let (Some(key), Some(value)) = (split.next(), split.next()) {
+                env.insert(key.to_string(), value.to_string());
+            }
+        }
+        let field = |key| env.get(key).cloned().unwrap_or_default();
+        let (action, devpath, subsystem) = (field("ACTION"), field("DEVPATH"), field("SUBSYSTEM"));
+        Ok(Uevent {
+            action,
+            devpath,
+            subsystem,
+            env,
+            from_udev: false,
+        })
+    }
+
+    fn properties(&self) -> Vec<u8> {
+        let mut keys = self
+            .env
+            .keys()
+            .filter(|k| !["ACTION", "DEVPATH", "SUBSYSTEM"].contains(&k.as_str()))
+            .collect::<Vec<_>>();
+        keys.sort();
+        let mut buf = Vec::new();
+        let mut push = |key: &str, value: &str| {
+            buf.extend_from_slice(key.as_bytes());
+            buf.push(b'=');
+            buf.extend_from_slice(value.as_bytes());
+            buf.push(0);
+        };
+        push("ACTION", &self.action);
+        push("DEVPATH", &self.devpath);
+        push("SUBSYSTEM", &self.subsystem);
+        for key in keys {
+            push(key, &self.env[key]);
+        }
+        buf
+    }
+
+    /// Encode the event in the kernel uevent format
+    pub fn to_kernel_bytes(&self) -> Vec<u8> {
+        let mut buf = format!("{}@{}\0", self.action, self.devpath).into_bytes();
+        buf.extend(self.properties());
+        buf
+    }
+
+    /// Encode the event in the libudev monitor format
+    pub fn to_udev_bytes(&self) -> Vec<u8> {
+        let properties = self.properties();
+        let devtype_hash = self
+            .get("DEVTYPE")
+            .map(|devtype| murmur_hash2(devtype.as_bytes(), 0))
+            .unwrap_or(0);
+        let mut buf = LIBUDEV_PREFIX.to_vec();
+        buf.extend_from_slice(&LIBUDEV_MAGIC.to_be_bytes());
+        for value in [
+            LIBUDEV_HEADER_SIZE as u32,
+            LIBUDEV_HEADER_SIZE as u32,
+            properties.len() as u32,
+        ]
+        .iter()
+        {
+            buf.extend_from_slice(&value.to_ne_bytes());
+        }
+        buf.extend_from_slice(&murmur_hash2(self.subsystem.as_bytes(), 0).to_be_bytes());
+        buf.extend_from_slice(&devtype_hash.to_be_bytes());
+        // No tag bloom filter
+        buf.extend_from_slice(&[0; 8]);
+        buf.extend(properties);
+        buf
+    }
+}
+
+/// Socket receiving uevents
+pub struct UeventSocket {
+    socket: NlSocket,
+    subsystems: Vec<String>,
+    buffer: Vec<u8>,
+}
+
+impl UeventSocket {
+    /// Create a socket subscribed to `groups`
+    pub fn connect(groups: &[UeventGrp]) -> Result<Self, io::Error> {
+        let mut socket = NlSocket::new(NlFamily::KobjectUevent, false)?;
+        socket.bind(None, None)?;
+        for group in groups {
+            socket.set_mcast_groups(vec![u32::from(group)])?;
+        }
+        Ok(UeventSocket {
+            socket,
+            subsystems: Vec::new(),
+            buffer: vec![0; UEVENT_BUFFER_SIZE],
+        })
+    }
+
+    /// Only return events of `subsystem` from `recv()`. Events of all subsystems are returned
+    /// if no filter was added.
+    pub fn add_subsystem_filter(&mut self, subsystem: &str) {
+        self.subsystems.push(subsystem.to_string());
+    }
+
+    /// Remove all subsystem filters
+    pub fn clear_subsystem_filters(&mut self) {
+        self.subsystems.clear();
+    }
+
+    /// Receive the next event matching the subsystem filters
+    pub fn recv(&mut self) -> Result<Uevent, NlError> {
+        loop {
+            let len = self.socket.recv(&mut self.buffer[..], 0)?;
+            let event = Uevent::parse(&self.buffer[..len])?;
+            if self.subsystems.is_empty() || self.subsystems.contains(&event.subsystem) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Access the underlying socket, eg. to make it non blocking
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+}
+
+impl AsRawFd for UeventSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn test_parse_kernel_uevent() {
+        let buf = b"add@/devices/virtual/net/lo\0ACTION=add\0DEVPATH=/devices/virtual/net/lo\0\
+                    SUBSYSTEM=net\0INTERFACE=lo\0IFINDEX=1\0SEQNUM=1234\0";
+        let event = Uevent::parse(&buf[..]).unwrap();
+        assert_eq!(event.action, "add");
+        assert_eq!(event.devpath, "/devices/virtual/net/lo");
+        assert_eq!(event.subsystem, "net");
+        assert_eq!(event.get("INTERFACE"), Some("lo"));
+        assert_eq!(event.seqnum(), Some(1234));
+        assert!(!event.from_udev);
+        assert_eq!(Uevent::parse(&event.to_kernel_bytes()).unwrap(), event);
+    }
+
+    #[test]
+    fn test_parse_udev_event() {
+        let mut event = Uevent::new("change", "/devices/virtual/block/loop0", "block");
+        event.env.insert("DEVTYPE".to_string(), "disk".to_string());
+        event.from_udev = true;
+        let buf = event.to_udev_bytes();
+        assert_eq!(&buf[..8], LIBUDEV_PREFIX);
+        // Subsystem hash as computed by libudev
+        assert_eq!(be32_at(&buf, 24).unwrap(), murmur_hash2(b"block", 0));
+        assert_eq!(Uevent::parse(&buf).unwrap(), event);
+    }
+
+    #[test]
+    fn test_parse_truncated_udev_event() {
+        let mut buf = Uevent::new("add", "/devices/virtual/net/lo", "net").to_udev_bytes();
+        buf[16..20].copy_from_slice(&u32::max_value().to_ne_bytes());
+        buf[20..24].copy_from_slice(&u32::max_value().to_ne_bytes());
+        assert!(Uevent::parse(&buf).is_err());
+    }
+
+    #[test]
+    fn test_murmur_hash2() {
+        assert_eq!(murmur_hash2(b"", 0), 0);
+        assert_eq!(murmur_hash2(b"net", 0), 0xa74d_3cc8);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_uevent_socket() {
+        let mut s = UeventSocket::connect(&[UeventGrp::Kernel]).unwrap();
+        s.add_subsystem_filter("net");
+        fs::write("/sys/class/net/lo/uevent", "change").unwrap();
+        let event = s.recv().unwrap();
+        assert_eq!(event.action, "change");
+        assert_eq!(event.get("INTERFACE"), Some("lo"));
+    }
+}
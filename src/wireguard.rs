@@ -0,0 +1,713 @@
+//! This module provides typed access to the WireGuard generic netlink family, which is the only
+//! way to configure WireGuard interfaces.
+//!
+//! ```no_run
+//! use neli::wireguard::{WgDeviceId, WgSocket};
+//!
+//! let mut socket = WgSocket::connect().unwrap();
+//! let device = socket.get_device(&WgDeviceId::Ifname("wg0".to_string())).unwrap();
+//! for peer in device.peers.iter() {
+//!     println!("{:?} {:?} {:?}", peer.endpoint, peer.last_handshake_time, peer.allowed_ips);
+//! }
+//! ```
+//!
+//! # Design decisions
+//!
+//! The kernel answers `WG_CMD_GET_DEVICE` with a dump that spreads the peers, and even the
+//! allowed IPs of a single peer, over several messages. `WgSocket.get_device()` merges them
+//! into one `WgDevice`.
+//!
+//! In the other direction, a `WgDeviceConfig` with many peers may not fit into one message.
+//! `WgSocket.set_device()` splits it into several `WG_CMD_SET_DEVICE` requests the same way:
+//! only the first carries the device settings, and a peer split over two requests repeats its
+//! public key with the remaining allowed IPs.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use consts::wireguard::{
+    WgAllowedIpAttr, WgCmd, WgDeviceAttr, WgDeviceFlag, WgPeerAttr, WgPeerFlag,
+};
+use consts::{NlFamily, NlmF};
+use err::{DeError, NlError, SerError};
+use genl::Genlmsghdr;
+use nl::Nlmsghdr;
+use nlattr::{AttrHandle, Nlattr};
+use socket::NlSocket;
+use Nl;
+
+/// Name of the WireGuard generic netlink family
+pub const WG_GENL_NAME: &str = "wireguard";
+/// Version of the WireGuard generic netlink family
+const WG_GENL_VERSION: u8 = 1;
+/// Length of WireGuard keys
+pub const WG_KEY_LEN: usize = 32;
+/// Maximum size of a `WG_CMD_SET_DEVICE` request before it is split, the message size used by
+/// the WireGuard tools
+pub const WG_SET_MSG_LIMIT: usize = 4096;
+
+/// Size of the netlink and generic netlink headers
+const HDR_LEN: usize = 16 + 4;
+/// Size of an attribute header
+const NLA_HDR_LEN: usize = 4;
+
+/// Attributes of one `WG_CMD_SET_DEVICE` request
+pub type WgDeviceAttrs = Vec<Nlattr<WgDeviceAttr, Vec<u8>>>;
+
+/// WireGuard public, private or preshared key
+pub type WgKey = [u8; WG_KEY_LEN];
+
+fn key_from_payload(payload: &[u8]) -> Result<WgKey, DeError> {
+    if payload.len() != WG_KEY_LEN {
+        return Err(DeError::new("WireGuard key has the wrong length"));
+    }
+    let mut key = [0; WG_KEY_LEN];
+    key.copy_from_slice(payload);
+    Ok(key)
+}
+
+/// Parse a `sockaddr_in` or `sockaddr_in6`
+fn parse_endpoint(payload: &[u8]) -> Result<SocketAddr, DeError> {
+    if payload.len() < 2 {
+        return Err(DeError::new("Endpoint is too short"));
+    }
+    let family = i32::from(u16::from_ne_bytes([payload[0], payload[1]]));
+    match family {
+        libc::AF_INET if payload.len() >= 8 => {
+            let port = u16::from_be_bytes([payload[2], payload[3]]);
+            let ip = Ipv4Addr::new(payload[4], payload[5], payload[6], payload[7]);
+            Ok(SocketAddr::V4(SocketAddrV4::new(ip, port)))
+        }
+        libc::AF_INET6 if payload.len() >= 28 => {
+            let port = u16::from_be_bytes([payload[2], payload[3]]);
+            let flowinfo = u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]);
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&payload[8..24]);
+            let scope_id = u32::from_ne_bytes([payload[24], payload[25], payload[26], payload[27]]);
+            Ok(SocketAddr::V6(SocketAddrV6::new(
+                Ipv6Addr::from(octets),
+                port,
+                flowinfo,
+                scope_id,
+            )))
+        }
+        _ => Err(DeError::new("Unsupported endpoint address family")),
+    }
+}
+
+/// Serialize a `SocketAddr` as a `sockaddr_in` or `sockaddr_in6`
+fn endpoint_bytes(endpoint: &SocketAddr) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    match *endpoint {
+        SocketAddr::V4(ref addr) => {
+            bytes.extend_from_slice(&(libc::AF_INET as u16).to_ne_bytes());
+            bytes.extend_from_slice(&addr.port().to_be_bytes());
+            bytes.extend_from_slice(&addr.ip().octets());
+            bytes.extend_from_slice(&[0; 8]);
+        }
+        SocketAddr::V6(ref addr) => {
+            bytes.extend_from_slice(&(libc::AF_INET6 as u16).to_ne_bytes());
+            bytes.extend_from_slice(&addr.port().to_be_bytes());
+            bytes.extend_from_slice(&addr.flowinfo().to_be_bytes());
+            bytes.extend_from_slice(&addr.ip().octets());
+            bytes.extend_from_slice(&addr.scope_id().to_ne_bytes());
+        }
+    }
+    bytes
+}
+
+/// Parse a `struct __kernel_timespec`, `None` for the zero time of peers without a handshake
+fn parse_timespec(payload: &[u8]) -> Result<Option<SystemTime>, DeError> {
+    if payload.len() < 16 {
+        return Err(DeError::new("Handshake time is too short"));
+    }
+    let mut sec = [0; 8];
+    sec.copy_from_slice(&payload[..8]);
+    let mut nsec = [0; 8];
+    nsec.copy_from_slice(&payload[8..16]);
+    let (sec, nsec) = (i64::from_ne_bytes(sec), i64::from_ne_bytes(nsec));
+    if sec == 0 && nsec == 0 {
+        return Ok(None);
+    }
+    Ok(Some(UNIX_EPOCH + Duration::new(sec as u64, nsec as u32)))
+}
+
+/// Device a request applies to
+#[derive(Clone, Debug, PartialEq)]
+pub enum WgDeviceId {
+    /// Interface index
+    Ifindex(u32),
+    /// Interface name
+    Ifname(String),
+}
+
+impl WgDeviceId {
+    fn to_attr(&self) -> Result<Nlattr<WgDeviceAttr, Vec<u8>>, SerError> {
+        match *self {
+            WgDeviceId::Ifindex(index) => Nlattr::new(None, WgDeviceAttr::Ifindex, index),
+            WgDeviceId::Ifname(ref name) => Nlattr::new(None, WgDeviceAttr::Ifname, name.as_str()),
+        }
+    }
+}
+
+/// IP range a peer is allowed to use as source address and that is routed to the peer
+#[derive(Clone, Debug, PartialEq)]
+pub struct WgAllowedIp {
+    /// Network address
+    pub addr: IpAddr,
+    /// Prefix length
+    pub cidr: u8,
+}
+
+impl WgAllowedIp {
+    /// Create an allowed IP range
+    pub fn new(addr: IpAddr, cidr: u8) -> Self {
+        WgAllowedIp { addr, cidr }
+    }
+
+    /// Parse an allowed IP range from its nested attributes
+    pub fn from_attrs(handle: &AttrHandle<WgAllowedIpAttr>) -> Result<Self, DeError> {
+        let family = handle.get_attr_payload_as::<u16>(WgAllowedIpAttr::Family)?;
+        let addr = match handle.get_attribute(WgAllowedIpAttr::Ipaddr) {
+            Some(attr) => attr.payload.as_slice(),
+            None => return Err(DeError::new("Missing allowed IP address")),
+        };
+        let addr = match i32::from(family) {
+            libc::AF_INET if addr.len() == 4 => {
+                IpAddr::from(Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+            }
+            libc::AF_INET6 if addr.len() == 16 => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(addr);
+                IpAddr::from(Ipv6Addr::from(octets))
+            }
+            _ => return Err(DeError::new("Invalid allowed IP address")),
+        };
+        Ok(WgAllowedIp {
+            addr,
+            cidr: handle.get_attr_payload_as(WgAllowedIpAttr::CidrMask)?,
+        })
+    }
+
+    /// Serialize the allowed IP range as entry `index` of the list of allowed IPs
+    pub fn to_attr(&self, index: u16) -> Result<Nlattr<u16, Vec<u8>>, SerError> {
+        let (family, addr) = match self.addr {
+            IpAddr::V4(v4) => (libc::AF_INET as u16, v4.octets().to_vec()),
+            IpAddr::V6(v6) => (libc::AF_INET6 as u16, v6.octets().to_vec()),
+        };
+        let attrs = [
+            Nlattr::new(None, WgAllowedIpAttr::Family, family)?,
+            Nlattr::new(None, WgAllowedIpAttr::Ipaddr, addr)?,
+            Nlattr::new(None, WgAllowedIpAttr::CidrMask, self.cidr)?,
+        ];
+        Ok(Nlattr::new_nested(index, &attrs)?.with_nested_flag())
+    }
+}
+
+/// Peer of a WireGuard device as reported by the kernel
+#[derive(Clone, Debug, PartialEq)]
+pub struct WgPeer {
+    /// Public key of the peer
+    pub public_key: WgKey,
+    /// Preshared key, `None` if no preshared key is set
+    pub preshared_key: Option<WgKey>,
+    /// Current endpoint of the peer
+    pub endpoint: Option<SocketAddr>,
+    /// Persistent keepalive interval in seconds, `0` if disabled
+    pub persistent_keepalive_interval: u16,
+    /// Time of the last handshake, `None` if there was none
+    pub last_handshake_time: Option<SystemTime>,
+    /// Bytes received from the peer
+    pub rx_bytes: u64,
+    /// Bytes sent to the peer
+    pub tx_bytes: u64,
+    /// Allowed IP ranges
+    pub allowed_ips: Vec<WgAllowedIp>,
+    /// Protocol version
+    pub protocol_version: u32,
+}
+
+impl WgPeer {
+    /// Parse a peer from its nested attributes
+    pub fn from_attrs(handle: &AttrHandle<WgPeerAttr>) -> Result<Self, DeError> {
+        let public_key = match handle.get_attribute(WgPeerAttr::PublicKey) {
+            Some(attr) => key_from_payload(&attr.payload)?,
+            None => return Err(DeError::new("Missing peer public key")),
+        };
+        let mut peer = WgPeer {
+            public_key,
+            preshared_key: None,
+            endpoint: None,
+            persistent_keepalive_interval: 0,
+            last_handshake_time: None,
+            rx_bytes: 0,
+            tx_bytes: 0,
+            allowed_ips: Vec::new(),
+            protocol_version: 0,
+        };
+        for attr in handle.iter() {
+            match attr.nla_type {
+                WgPeerAttr::PresharedKey => {
+                    let key = key_from_payload(&attr.payload)?;
+                    if key != [0; WG_KEY_LEN] {
+                        peer.preshared_key = Some(key);
+                    }
+                }
+                WgPeerAttr::Endpoint => peer.endpoint = Some(parse_endpoint(&attr.payload)?),
+                WgPeerAttr::PersistentKeepaliveInterval => {
+                    peer.persistent_keepalive_interval = attr.get_payload_as()?
+                }
+                WgPeerAttr::LastHandshakeTime => {
+                    peer.last_handshake_time = parse_timespec(&attr.payload)?
+                }
+                WgPeerAttr::RxBytes => peer.rx_bytes = attr.get_payload_as()?,
+                WgPeerAttr::TxBytes => peer.tx_bytes = attr.get_payload_as()?,
+                WgPeerAttr::Allowedips => {
+                    for ip in attr.get_nested_attributes::<u16>()?.iter() {
+                        peer.allowed_ips
+                            .push(WgAllowedIp::from_attrs(&ip.get_nested_attributes()?)?);
+                    }
+                }
+                WgPeerAttr::ProtocolVersion => peer.protocol_version = attr.get_payload_as()?,
+                _ => (),
+            }
+        }
+        Ok(peer)
+    }
+}
+
+/// WireGuard device as reported by the kernel
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WgDevice {
+    /// Interface index
+    pub ifindex: u32,
+    /// Interface name
+    pub ifname: String,
+    /// Private key, `None` if not set or if the caller may not read it
+    pub private_key: Option<WgKey>,
+    /// Public key derived from the private key
+    pub public_key: Option<WgKey>,
+    /// UDP port the device listens on
+    pub listen_port: u16,
+    /// Firewall mark of outgoing packets, `0` if not set
+    pub fwmark: u32,
+    /// Peers of the device
+    pub peers: Vec<WgPeer>,
+}
+
+impl WgDevice {
+    /// Add the attributes of one message of a `WG_CMD_GET_DEVICE` dump to the device. A peer
+    /// continuing the last peer of the previous message has its allowed IPs appended.
+    pub fn merge_attrs(&mut self, handle: &AttrHandle<WgDeviceAttr>) -> Result<(), DeError> {
+        for attr in handle.iter() {
+            match attr.nla_type {
+                WgDeviceAttr::Ifindex => self.ifindex = attr.get_payload_as()?,
+                WgDeviceAttr::Ifname => self.ifname = attr.get_payload_as()?,
+                WgDeviceAttr::PrivateKey => {
+                    self.private_key = Some(key_from_payload(&attr.payload)?)
+                }
+                WgDeviceAttr::PublicKey => self.public_key = Some(key_from_payload(&attr.payload)?),
+                WgDeviceAttr::ListenPort => self.listen_port = attr.get_payload_as()?,
+                WgDeviceAttr::Fwmark => self.fwmark = attr.get_payload_as()?,
+                WgDeviceAttr::Peers => {
+                    for peer in attr.get_nested_attributes::<u16>()?.iter() {
+                        let peer = WgPeer::from_attrs(&peer.get_nested_attributes()?)?;
+                        match self.peers.last_mut() {
+                            Some(last) if last.public_key == peer.public_key => {
+                                last.allowed_ips.extend(peer.allowed_ips)
+                            }
+                            _ => self.peers.push(peer),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Configuration of a peer for `WgSocket.set_device()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct WgPeerConfig {
+    /// Public key identifying the peer
+    pub public_key: WgKey,
+    /// Remove the peer instead of adding or updating it
+    pub remove: bool,
+    /// Only update the peer if it exists instead of adding it
+    pub update_only: bool,
+    /// Preshared key; all zeros removes the preshared key
+    pub preshared_key: Option<WgKey>,
+    /// Endpoint of the peer
+    pub endpoint: Option<SocketAddr>,
+    /// Persistent keepalive interval in seconds, `0` disables it
+    pub persistent_keepalive_interval: Option<u16>,
+    /// Replace the allowed IPs of the peer instead of adding to them
+    pub replace_allowed_ips: bool,
+    /// Allowed IP ranges to add
+    pub allowed_ips: Vec<WgAllowedIp>,
+}
+
+impl WgPeerConfig {
+    /// Configuration that adds or updates the peer with public key `public_key`
+    pub fn new(public_key: WgKey) -> Self {
+        WgPeerConfig {
+            public_key,
+            remove: false,
+            update_only: false,
+            preshared_key: None,
+            endpoint: None,
+            persistent_keepalive_interval: None,
+            replace_allowed_ips: false,
+            allowed_ips: Vec::new(),
+        }
+    }
+
+    /// Peer attributes other than the allowed IPs
+    fn to_attrs(&self) -> Result<Vec<Nlattr<WgPeerAttr, Vec<u8>>>, SerError> {
+        let mut attrs = vec![Nlattr::new(
+            None,
+            WgPeerAttr::PublicKey,
+            &self.public_key[..],
+        )?];
+        let mut flags = 0;
+        if self.remove {
+            flags |= u32::from(WgPeerFlag::RemoveMe);
+        }
+        if self.replace_allowed_ips {
+            flags |= u32::from(WgPeerFlag::ReplaceAllowedips);
+        }
+        if self.update_only {
+            flags |= u32::from(WgPeerFlag::UpdateOnly);
+        }
+        if flags != 0 {
+            attrs.push(Nlattr::new(None, WgPeerAttr::Flags, flags)?);
+        }
+        if let Some(ref key) = self.preshared_key {
+            attrs.push(Nlattr::new(None, WgPeerAttr::PresharedKey, &key[..])?);
+        }
+        if let Some(ref endpoint) = self.endpoint {
+            attrs.push(Nlattr::new(
+                None,
+                WgPeerAttr::Endpoint,
+                endpoint_bytes(endpoint),
+            )?);
+        }
+        if let Some(interval) = self.persistent_keepalive_interval {
+            attrs.push(Nlattr::new(
+                None,
+                WgPeerAttr::PersistentKeepaliveInterval,
+                interval,
+            )?);
+        }
+        Ok(attrs)
+    }
+}
+
+/// Configuration of a device for `WgSocket.set_device()`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WgDeviceConfig {
+    /// Private key; all zeros removes the private key
+    pub private_key: Option<WgKey>,
+    /// UDP port to listen on, `0` picks a random port
+    pub listen_port: Option<u16>,
+    /// Firewall mark of outgoing packets, `0` removes it
+    pub fwmark: Option<u32>,
+    /// Remove all peers that are not in `peers`
+    pub replace_peers: bool,
+    /// Peers to add, update or remove
+    pub peers: Vec<WgPeerConfig>,
+}
+
+impl WgDeviceConfig {
+    fn to_attrs(&self) -> Result<WgDeviceAttrs, SerError> {
+        let mut attrs = Vec::new();
+        if let Some(ref key) = self.private_key {
+            attrs.push(Nlattr::new(None, WgDeviceAttr::PrivateKey, &key[..])?);
+        }
+        if let Some(port) = self.listen_port {
+            attrs.push(Nlattr::new(None, WgDeviceAttr::ListenPort, port)?);
+        }
+        if let Some(fwmark) = self.fwmark {
+            attrs.push(Nlattr::new(None, WgDeviceAttr::Fwmark, fwmark)?);
+        }
+        if self.replace_peers {
+            attrs.push(Nlattr::new(
+                None,
+                WgDeviceAttr::Flags,
+                u32::from(WgDeviceFlag::ReplacePeers),
+            )?);
+        }
+        Ok(attrs)
+    }
+
+    /// Split the configuration into the attributes of `WG_CMD_SET_DEVICE` requests of at most
+    /// `limit` bytes each
+    pub fn to_messages(
+        &self,
+        dev: &WgDeviceId,
+        limit: usize,
+    ) -> Result<Vec<WgDeviceAttrs>, SerError> {
+        let mut msgs = Vec::new();
+        let mut attrs = vec![dev.to_attr()?];
+        attrs.extend(self.to_attrs()?);
+        let mut peers = Vec::new();
+        let mut size = HDR_LEN + attrs.iter().map(Nl::asize).sum::<usize>() + NLA_HDR_LEN;
+
+        for peer in self.peers.iter() {
+            let mut remaining = peer.allowed_ips.as_slice();
+            let mut first = true;
+            loop {
+                let peer_attrs = if first {
+                    peer.to_attrs()?
+                } else {
+                    // Continuations only add allowed IPs to the peer
+                    vec![Nlattr::new(
+                        None,
+                        WgPeerAttr::PublicKey,
+                        &peer.public_key[..],
+                    )?]
+                };
+                let mut ips = Vec::new();
+                let mut peer_size =
+                    NLA_HDR_LEN + peer_attrs.iter().map(Nl::asize).sum::<usize>() + NLA_HDR_LEN;
+                while let Some(ip) = remaining.first() {
+                    let ip = ip.to_attr(ips.len() as u16)?;
+                    if size + peer_size + ip.asize() > limit {
+                        break;
+                    }
+                    peer_size += ip.asize();
+                    ips.push(ip);
+                    remaining = &remaining[1..];
+                }
+
+                if size + peer_size > limit || (ips.is_empty() && !remaining.is_empty()) {
+                    if peers.is_empty() {
+                        return Err(SerError::new("WireGuard peer does not fit into a message"));
+                    }
+                    // Start a new message and retry this part of the peer
+                    msgs.push(finish_message(attrs, &peers)?);
+                    attrs = vec![dev.to_attr()?];
+                    peers = Vec::new();
+                    size = HDR_LEN + attrs.iter().map(Nl::asize).sum::<usize>() + NLA_HDR_LEN;
+                    continue;
+                }
+
+                let mut peer_attr =
+                    Nlattr::new_nested(peers.len() as u16, &peer_attrs)?.with_nested_flag();
+                if !ips.is_empty() {
+                    peer_attr.add_nested_attribute(
+                        &Nlattr::new_nested(WgPeerAttr::Allowedips, &ips)?.with_nested_flag(),
+                    )?;
+                }
+                peers.push(peer_attr);
+                size += peer_size;
+                first = false;
+
+                if remaining.is_empty() {
+                    break;
+                }
+                msgs.push(finish_message(attrs, &peers)?);
+                attrs = vec![dev.to_attr()?];
+                peers = Vec::new();
+                size = HDR_LEN + attrs.iter().map(Nl::asize).sum::<usize>() + NLA_HDR_LEN;
+            }
+        }
+        msgs.push(finish_message(attrs, &peers)?);
+        Ok(msgs)
+    }
+}
+
+fn finish_message(
+    mut attrs: WgDeviceAttrs,
+    peers: &[Nlattr<u16, Vec<u8>>],
+) -> Result<WgDeviceAttrs, SerError> {
+    if !peers.is_empty() {
+        attrs.push(Nlattr::new_nested(WgDeviceAttr::Peers, peers)?.with_nested_flag());
+    }
+    Ok(attrs)
+}
+
+/// Socket for the WireGuard family with its resolved family ID
+pub struct WgSocket {
+    socket: NlSocket,
+    family_id: u16,
+}
+
+impl WgSocket {
+    /// Connect to the WireGuard family
+    pub fn connect() -> Result<Self, NlError> {
+        let mut socket = NlSocket::connect(NlFamily::Generic, None, None, true)?;
+        let family_id = socket.resolve_genl_family(WG_GENL_NAME)?;
+        Ok(WgSocket { socket, family_id })
+    }
+
+    /// Generic netlink family ID of WireGuard
+    pub fn family_id(&self) -> u16 {
+        self.family_id
+    }
+
+    /// Underlying socket, to send requests not covered by this type
+    pub fn socket_mut(&mut self) -> &mut NlSocket {
+        &mut self.socket
+    }
+
+    /// Get the configuration and peers of a device
+    pub fn get_device(&mut self, dev: &WgDeviceId) -> Result<WgDevice, NlError> {
+        // The kernel only implements WG_CMD_GET_DEVICE as a dump
+        let genlhdr = Genlmsghdr::new(WgCmd::GetDevice, WG_GENL_VERSION, vec![dev.to_attr()?])?;
+        let nlhdr = Nlmsghdr::new(
+            None,
+            self.family_id,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            genlhdr,
+        );
+        self.socket.send_nl(nlhdr)?;
+        let mut device = WgDevice::default();
+        for msg in self
+            .socket
+            .recv_all::<u16, Genlmsghdr<WgCmd, WgDeviceAttr>>()?
+            .iter()
+        {
+            device.merge_attrs(&msg.nl_payload.get_attr_handle())?;
+        }
+        Ok(device)
+    }
+
+    /// Change the configuration of a device, splitting the request into several messages if
+    /// the peers do not fit into one
+    pub fn set_device(&mut self, dev: &WgDeviceId, config: &WgDeviceConfig) -> Result<(), NlError> {
+        for attrs in config.to_messages(dev, WG_SET_MSG_LIMIT)? {
+            let genlhdr = Genlmsghdr::new(WgCmd::SetDevice, WG_GENL_VERSION, attrs)?;
+            let nlhdr = Nlmsghdr::new(
+                None,
+                self.family_id,
+                vec![NlmF::Request, NlmF::Ack],
+                None,
+                None,
+                genlhdr,
+            );
+            self.socket.send_nl(nlhdr)?;
+            self.socket.recv_ack()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(cmd: WgCmd, attrs: WgDeviceAttrs) -> Genlmsghdr<WgCmd, WgDeviceAttr> {
+        ::round_trip(&Genlmsghdr::new(cmd, WG_GENL_VERSION, attrs).unwrap())
+    }
+
+    fn peer_config(key: u8, ips: usize) -> WgPeerConfig {
+        WgPeerConfig {
+            endpoint: Some("[2001:db8::1]:51820".parse().unwrap()),
+            persistent_keepalive_interval: Some(25),
+            allowed_ips: (0..ips)
+                .map(|i| WgAllowedIp::new(IpAddr::V4(Ipv4Addr::new(10, key, i as u8, 0)), 24))
+                .collect(),
+            ..WgPeerConfig::new([key; WG_KEY_LEN])
+        }
+    }
+
+    #[test]
+    fn test_endpoint() {
+        for endpoint in ["192.0.2.1:51820", "[2001:db8::1]:443"].iter() {
+            let endpoint = endpoint.parse().unwrap();
+            assert_eq!(
+                parse_endpoint(&endpoint_bytes(&endpoint)).unwrap(),
+                endpoint
+            );
+        }
+        let mut timespec = 1_600_000_000i64.to_ne_bytes().to_vec();
+        timespec.extend_from_slice(&5i64.to_ne_bytes());
+        assert_eq!(
+            parse_timespec(&timespec).unwrap(),
+            Some(UNIX_EPOCH + Duration::new(1_600_000_000, 5))
+        );
+        assert_eq!(parse_timespec(&[0; 16]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_single_message() {
+        let config = WgDeviceConfig {
+            private_key: Some([7; WG_KEY_LEN]),
+            listen_port: Some(51820),
+            replace_peers: true,
+            peers: vec![peer_config(1, 2), peer_config(2, 1)],
+            ..WgDeviceConfig::default()
+        };
+        let dev = WgDeviceId::Ifname("wg0".to_string());
+        let msgs = config.to_messages(&dev, WG_SET_MSG_LIMIT).unwrap();
+        assert_eq!(msgs.len(), 1);
+
+        // Decode the request as if it were a reply
+        let msg = round_trip(WgCmd::SetDevice, msgs.into_iter().next().unwrap());
+        let mut device = WgDevice::default();
+        device.merge_attrs(&msg.get_attr_handle()).unwrap();
+        assert_eq!(device.ifname, "wg0");
+        assert_eq!(device.private_key, Some([7; WG_KEY_LEN]));
+        assert_eq!(device.listen_port, 51820);
+        assert_eq!(device.peers.len(), 2);
+        assert_eq!(device.peers[0].endpoint, config.peers[0].endpoint);
+        assert_eq!(device.peers[0].persistent_keepalive_interval, 25);
+        assert_eq!(device.peers[0].allowed_ips, config.peers[0].allowed_ips);
+        assert_eq!(device.peers[1].public_key, [2; WG_KEY_LEN]);
+    }
+
+    #[test]
+    fn test_fragmented_messages() {
+        let config = WgDeviceConfig {
+            replace_peers: true,
+            peers: vec![peer_config(1, 3), peer_config(2, 40), peer_config(3, 0)],
+            ..WgDeviceConfig::default()
+        };
+        let dev = WgDeviceId::Ifindex(4);
+        let limit = 512;
+        let msgs = config.to_messages(&dev, limit).unwrap();
+        assert!(msgs.len() > 2);
+
+        let mut device = WgDevice::default();
+        for (i, attrs) in msgs.into_iter().enumerate() {
+            let size = HDR_LEN + attrs.iter().map(Nl::asize).sum::<usize>();
+            assert!(size <= limit);
+            let msg = round_trip(WgCmd::SetDevice, attrs);
+            let handle = msg.get_attr_handle();
+            // Only the first message replaces the peers
+            assert_eq!(handle.get_attribute(WgDeviceAttr::Flags).is_some(), i == 0);
+            device.merge_attrs(&handle).unwrap();
+        }
+        assert_eq!(device.ifindex, 4);
+        assert_eq!(device.peers.len(), 3);
+        for (peer, config) in device.peers.iter().zip(config.peers.iter()) {
+            assert_eq!(peer.public_key, config.public_key);
+            assert_eq!(peer.allowed_ips, config.allowed_ips);
+        }
+
+        let too_small = config.to_messages(&dev, 64);
+        assert!(too_small.is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_get_device() {
+        let mut socket = WgSocket::connect().unwrap();
+        let dev = WgDeviceId::Ifname("wg0".to_string());
+        let config = WgDeviceConfig {
+            listen_port: Some(51820),
+            replace_peers: true,
+            peers: (1..=100).map(|i| peer_config(i, 20)).collect(),
+            ..WgDeviceConfig::default()
+        };
+        socket.set_device(&dev, &config).unwrap();
+        let device = socket.get_device(&dev).unwrap();
+        assert_eq!(device.listen_port, 51820);
+        assert_eq!(device.peers.len(), 100);
+        assert!(device.peers.iter().all(|p| p.allowed_ips.len() == 20));
+    }
+}
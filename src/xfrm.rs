@@ -0,0 +1,1823 @@
+//! This module provides IPsec state (SA) and policy (SP) management on the `NlFamily::Xfrm`
+//! protocol.
+//!
+//! Each XFRM message starts with a fixed structure that depends on the message type, followed
+//! by `XfrmAttr` attributes. `XfrmMessage` pairs such a structure with its attributes.
+//!
+//! ```no_run
+//! use std::net::IpAddr;
+//!
+//! use neli::consts::rtnl::RtAddrFamily;
+//! use neli::consts::xfrm::{XfrmAttr, XfrmMode, XfrmPolicyAction, XfrmPolicyDir};
+//! use neli::consts::NlFamily;
+//! use neli::socket::NlSocket;
+//! use neli::xfrm::{
+//!     XfrmAlgo, XfrmAlgoAuth, XfrmId, XfrmMessage, XfrmSelector, XfrmUserTmpl,
+//!     XfrmUserpolicyInfo, XfrmUsersaInfo,
+//! };
+//!
+//! let local: IpAddr = "192.0.2.1".parse().unwrap();
+//! let remote: IpAddr = "192.0.2.2".parse().unwrap();
+//! let esp = libc::IPPROTO_ESP as u8;
+//! let mut socket = NlSocket::connect(NlFamily::Xfrm, None, None, true).unwrap();
+//!
+//! // Outbound SA
+//! let id = XfrmId::new(remote, 0x1000, esp);
+//! let mut sa = XfrmMessage::new(XfrmUsersaInfo::new(id.clone(), local, XfrmMode::Tunnel, 1));
+//! sa.add_attr(XfrmAttr::AlgCrypt, XfrmAlgo::new("cbc(aes)", vec![0; 16])).unwrap();
+//! sa.add_attr(XfrmAttr::AlgAuthTrunc, XfrmAlgoAuth::new("hmac(sha256)", vec![0; 32], 128))
+//!     .unwrap();
+//! socket.xfrm_add_sa(sa).unwrap();
+//!
+//! // Outbound policy using the SA
+//! let sel = XfrmSelector::new(local, 32, remote, 32);
+//! let mut policy = XfrmMessage::new(XfrmUserpolicyInfo::new(
+//!     sel,
+//!     XfrmPolicyDir::Out,
+//!     XfrmPolicyAction::Allow,
+//! ));
+//! policy
+//!     .set_templates(&[XfrmUserTmpl::new(id, local, XfrmMode::Tunnel, 1)])
+//!     .unwrap();
+//! socket.xfrm_add_policy(policy).unwrap();
+//! ```
+//!
+//! # Design decisions
+//!
+//! Addresses are stored as `IpAddr`. On the wire they are always 16 bytes and the address
+//! family is given by a separate field, often after the address itself, so addresses are
+//! converted once the whole structure has been read.
+//!
+//! Acquire and expire notifications and SA and policy changes are sent to the `XfrmNlGrp`
+//! multicast groups and can be received with `NlSocket.xfrm_recv_event()`.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use buffering::{StreamReadBuffer, StreamWriteBuffer};
+
+use consts::rtnl::RtAddrFamily;
+use consts::xfrm::{
+    XfrmAttr, XfrmMode, XfrmMsg, XfrmPolicyAction, XfrmPolicyDir, XfrmPolicyFlag, XfrmSadAttr,
+    XfrmSpdAttr, XfrmStateFlag,
+};
+use consts::{NlAttrType, NlmF};
+use err::{DeError, NlError, SerError};
+use nl::Nlmsghdr;
+use nlattr::{AttrHandle, Nlattr};
+use socket::NlSocket;
+use Nl;
+
+/// Value of byte and packet limits without limit
+pub const XFRM_INF: u64 = !0;
+
+/// Size of the name of an algorithm
+const XFRM_ALGO_NAME_LEN: usize = 64;
+
+fn serialize_addr(addr: &IpAddr, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+    let mut octets = [0u8; 16];
+    match *addr {
+        IpAddr::V4(v4) => octets[..4].copy_from_slice(&v4.octets()),
+        IpAddr::V6(v6) => octets.copy_from_slice(&v6.octets()),
+    }
+    (&octets[..]).serialize(buf)
+}
+
+/// Read an address as IPv6, to be converted by `addr_for_family()` once the family is known
+fn deserialize_addr<B>(buf: &mut StreamReadBuffer<B>) -> Result<IpAddr, DeError>
+where
+    B: AsRef<[u8]>,
+{
+    buf.set_size_hint(16);
+    let bytes = Vec::<u8>::deserialize(buf)?;
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(&bytes);
+    Ok(IpAddr::from(Ipv6Addr::from(octets)))
+}
+
+fn addr_for_family(addr: IpAddr, family: &RtAddrFamily) -> IpAddr {
+    match (addr, family) {
+        (IpAddr::V6(v6), &RtAddrFamily::Inet) => {
+            let o = v6.octets();
+            IpAddr::from(Ipv4Addr::new(o[0], o[1], o[2], o[3]))
+        }
+        (addr, _) => addr,
+    }
+}
+
+fn family_of(addr: &IpAddr) -> RtAddrFamily {
+    match *addr {
+        IpAddr::V4(_) => RtAddrFamily::Inet,
+        IpAddr::V6(_) => RtAddrFamily::Inet6,
+    }
+}
+
+fn serialize_family(family: &RtAddrFamily, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+    u16::from(u8::from(family)).serialize(buf)
+}
+
+fn deserialize_family<B>(buf: &mut StreamReadBuffer<B>) -> Result<RtAddrFamily, DeError>
+where
+    B: AsRef<[u8]>,
+{
+    Ok(RtAddrFamily::from(u16::deserialize(buf)? as u8))
+}
+
+fn serialize_pad(len: usize, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+    vec![0u8; len].serialize(buf)
+}
+
+fn deserialize_pad<B>(len: usize, buf: &mut StreamReadBuffer<B>) -> Result<(), DeError>
+where
+    B: AsRef<[u8]>,
+{
+    buf.set_size_hint(len);
+    Vec::<u8>::deserialize(buf)?;
+    Ok(())
+}
+
+fn flags_to_u8<F>(flags: &[F]) -> u8
+where
+    for<'a> &'a F: Into<u8>,
+{
+    flags.iter().fold(0, |acc, flag| acc | flag.into())
+}
+
+fn flags_from_u8<F>(flags: u8) -> Vec<F>
+where
+    F: From<u8>,
+{
+    (0..8)
+        .map(|bit| 1 << bit)
+        .filter(|flag| flags & flag != 0)
+        .map(F::from)
+        .collect()
+}
+
+/// Traffic selector (`struct xfrm_selector`). Ports are in host byte order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmSelector {
+    /// Destination address
+    pub daddr: IpAddr,
+    /// Source address
+    pub saddr: IpAddr,
+    /// Destination port
+    pub dport: u16,
+    /// Destination port mask
+    pub dport_mask: u16,
+    /// Source port
+    pub sport: u16,
+    /// Source port mask
+    pub sport_mask: u16,
+    /// Address family
+    pub family: RtAddrFamily,
+    /// Destination prefix length
+    pub prefixlen_d: u8,
+    /// Source prefix length
+    pub prefixlen_s: u8,
+    /// IP protocol, `0` for any
+    pub proto: u8,
+    /// Interface index, `0` for any
+    pub ifindex: i32,
+    /// Owner of the selector
+    pub user: u32,
+}
+
+impl XfrmSelector {
+    /// Create a selector for all traffic from `saddr/prefixlen_s` to `daddr/prefixlen_d`
+    pub fn new(saddr: IpAddr, prefixlen_s: u8, daddr: IpAddr, prefixlen_d: u8) -> Self {
+        XfrmSelector {
+            family: family_of(&daddr),
+            daddr,
+            saddr,
+            dport: 0,
+            dport_mask: 0,
+            sport: 0,
+            sport_mask: 0,
+            prefixlen_d,
+            prefixlen_s,
+            proto: 0,
+            ifindex: 0,
+            user: 0,
+        }
+    }
+}
+
+impl Nl for XfrmSelector {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        serialize_addr(&self.daddr, buf)?;
+        serialize_addr(&self.saddr, buf)?;
+        self.dport.to_be().serialize(buf)?;
+        self.dport_mask.to_be().serialize(buf)?;
+        self.sport.to_be().serialize(buf)?;
+        self.sport_mask.to_be().serialize(buf)?;
+        serialize_family(&self.family, buf)?;
+        self.prefixlen_d.serialize(buf)?;
+        self.prefixlen_s.serialize(buf)?;
+        self.proto.serialize(buf)?;
+        serialize_pad(3, buf)?;
+        self.ifindex.serialize(buf)?;
+        self.user.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let daddr = deserialize_addr(buf)?;
+        let saddr = deserialize_addr(buf)?;
+        let dport = u16::from_be(u16::deserialize(buf)?);
+        let dport_mask = u16::from_be(u16::deserialize(buf)?);
+        let sport = u16::from_be(u16::deserialize(buf)?);
+        let sport_mask = u16::from_be(u16::deserialize(buf)?);
+        let family = deserialize_family(buf)?;
+        let prefixlen_d = u8::deserialize(buf)?;
+        let prefixlen_s = u8::deserialize(buf)?;
+        let proto = u8::deserialize(buf)?;
+        deserialize_pad(3, buf)?;
+        Ok(XfrmSelector {
+            daddr: addr_for_family(daddr, &family),
+            saddr: addr_for_family(saddr, &family),
+            dport,
+            dport_mask,
+            sport,
+            sport_mask,
+            family,
+            prefixlen_d,
+            prefixlen_s,
+            proto,
+            ifindex: i32::deserialize(buf)?,
+            user: u32::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        56
+    }
+}
+
+/// Lifetime limits (`struct xfrm_lifetime_cfg`). The default has no limits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmLifetimeCfg {
+    /// Bytes after which a soft expire notification is sent
+    pub soft_byte_limit: u64,
+    /// Bytes after which the SA expires
+    pub hard_byte_limit: u64,
+    /// Packets after which a soft expire notification is sent
+    pub soft_packet_limit: u64,
+    /// Packets after which the SA expires
+    pub hard_packet_limit: u64,
+    /// Seconds after creation after which a soft expire notification is sent
+    pub soft_add_expires_seconds: u64,
+    /// Seconds after creation after which the SA expires
+    pub hard_add_expires_seconds: u64,
+    /// Seconds after first use after which a soft expire notification is sent
+    pub soft_use_expires_seconds: u64,
+    /// Seconds after first use after which the SA expires
+    pub hard_use_expires_seconds: u64,
+}
+
+impl Default for XfrmLifetimeCfg {
+    fn default() -> Self {
+        XfrmLifetimeCfg {
+            soft_byte_limit: XFRM_INF,
+            hard_byte_limit: XFRM_INF,
+            soft_packet_limit: XFRM_INF,
+            hard_packet_limit: XFRM_INF,
+            soft_add_expires_seconds: 0,
+            hard_add_expires_seconds: 0,
+            soft_use_expires_seconds: 0,
+            hard_use_expires_seconds: 0,
+        }
+    }
+}
+
+impl Nl for XfrmLifetimeCfg {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.soft_byte_limit.serialize(buf)?;
+        self.hard_byte_limit.serialize(buf)?;
+        self.soft_packet_limit.serialize(buf)?;
+        self.hard_packet_limit.serialize(buf)?;
+        self.soft_add_expires_seconds.serialize(buf)?;
+        self.hard_add_expires_seconds.serialize(buf)?;
+        self.soft_use_expires_seconds.serialize(buf)?;
+        self.hard_use_expires_seconds.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        Ok(XfrmLifetimeCfg {
+            soft_byte_limit: u64::deserialize(buf)?,
+            hard_byte_limit: u64::deserialize(buf)?,
+            soft_packet_limit: u64::deserialize(buf)?,
+            hard_packet_limit: u64::deserialize(buf)?,
+            soft_add_expires_seconds: u64::deserialize(buf)?,
+            hard_add_expires_seconds: u64::deserialize(buf)?,
+            soft_use_expires_seconds: u64::deserialize(buf)?,
+            hard_use_expires_seconds: u64::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        64
+    }
+}
+
+/// Current lifetime (`struct xfrm_lifetime_cur`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XfrmLifetimeCur {
+    /// Processed bytes
+    pub bytes: u64,
+    /// Processed packets
+    pub packets: u64,
+    /// Creation time in seconds since the epoch
+    pub add_time: u64,
+    /// First use time in seconds since the epoch
+    pub use_time: u64,
+}
+
+impl Nl for XfrmLifetimeCur {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.bytes.serialize(buf)?;
+        self.packets.serialize(buf)?;
+        self.add_time.serialize(buf)?;
+        self.use_time.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        Ok(XfrmLifetimeCur {
+            bytes: u64::deserialize(buf)?,
+            packets: u64::deserialize(buf)?,
+            add_time: u64::deserialize(buf)?,
+            use_time: u64::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        32
+    }
+}
+
+/// SA statistics (`struct xfrm_stats`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XfrmStats {
+    /// Packets rejected by the replay window
+    pub replay_window: u32,
+    /// Replayed packets
+    pub replay: u32,
+    /// Packets failing the integrity check
+    pub integrity_failed: u32,
+}
+
+impl Nl for XfrmStats {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.replay_window.serialize(buf)?;
+        self.replay.serialize(buf)?;
+        self.integrity_failed.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        Ok(XfrmStats {
+            replay_window: u32::deserialize(buf)?,
+            replay: u32::deserialize(buf)?,
+            integrity_failed: u32::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        12
+    }
+}
+
+/// SA identifier (`struct xfrm_id`). The SPI is in host byte order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmId {
+    /// Destination address
+    pub daddr: IpAddr,
+    /// Security parameter index
+    pub spi: u32,
+    /// IPsec protocol (`IPPROTO_ESP`, `IPPROTO_AH` or `IPPROTO_COMP`)
+    pub proto: u8,
+}
+
+impl XfrmId {
+    /// Create an SA identifier
+    pub fn new(daddr: IpAddr, spi: u32, proto: u8) -> Self {
+        XfrmId { daddr, spi, proto }
+    }
+
+    fn set_family(&mut self, family: &RtAddrFamily) {
+        self.daddr = addr_for_family(self.daddr, family);
+    }
+}
+
+impl Nl for XfrmId {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        serialize_addr(&self.daddr, buf)?;
+        self.spi.to_be().serialize(buf)?;
+        self.proto.serialize(buf)?;
+        serialize_pad(3, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let daddr = deserialize_addr(buf)?;
+        let spi = u32::from_be(u32::deserialize(buf)?);
+        let proto = u8::deserialize(buf)?;
+        deserialize_pad(3, buf)?;
+        Ok(XfrmId { daddr, spi, proto })
+    }
+
+    fn size(&self) -> usize {
+        24
+    }
+}
+
+/// Security association (`struct xfrm_usersa_info`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUsersaInfo {
+    /// Traffic the SA applies to
+    pub sel: XfrmSelector,
+    /// Identifier of the SA
+    pub id: XfrmId,
+    /// Source address
+    pub saddr: IpAddr,
+    /// Lifetime limits
+    pub lft: XfrmLifetimeCfg,
+    /// Current lifetime, only set by the kernel
+    pub curlft: XfrmLifetimeCur,
+    /// Statistics, only set by the kernel
+    pub stats: XfrmStats,
+    /// Sequence number of the acquire the SA answers, `0` otherwise
+    pub seq: u32,
+    /// Request ID linking the SA to policy templates
+    pub reqid: u32,
+    /// Address family of the SA addresses
+    pub family: RtAddrFamily,
+    /// Encapsulation mode
+    pub mode: XfrmMode,
+    /// Size of the replay window
+    pub replay_window: u8,
+    /// Flags
+    pub flags: Vec<XfrmStateFlag>,
+}
+
+impl XfrmUsersaInfo {
+    /// Create an SA from `saddr` identified by `id`, applying to all traffic of its address
+    /// family
+    pub fn new(id: XfrmId, saddr: IpAddr, mode: XfrmMode, reqid: u32) -> Self {
+        let family = family_of(&id.daddr);
+        let any = match family {
+            RtAddrFamily::Inet6 => IpAddr::from(Ipv6Addr::UNSPECIFIED),
+            _ => IpAddr::from(Ipv4Addr::UNSPECIFIED),
+        };
+        XfrmUsersaInfo {
+            sel: XfrmSelector::new(any, 0, any, 0),
+            id,
+            saddr,
+            lft: XfrmLifetimeCfg::default(),
+            curlft: XfrmLifetimeCur::default(),
+            stats: XfrmStats::default(),
+            seq: 0,
+            reqid,
+            family,
+            mode,
+            replay_window: 32,
+            flags: Vec::new(),
+        }
+    }
+}
+
+impl Nl for XfrmUsersaInfo {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.sel.serialize(buf)?;
+        self.id.serialize(buf)?;
+        serialize_addr(&self.saddr, buf)?;
+        self.lft.serialize(buf)?;
+        self.curlft.serialize(buf)?;
+        self.stats.serialize(buf)?;
+        self.seq.serialize(buf)?;
+        self.reqid.serialize(buf)?;
+        serialize_family(&self.family, buf)?;
+        self.mode.serialize(buf)?;
+        self.replay_window.serialize(buf)?;
+        flags_to_u8(&self.flags).serialize(buf)?;
+        serialize_pad(7, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let sel = XfrmSelector::deserialize(buf)?;
+        let mut id = XfrmId::deserialize(buf)?;
+        let saddr = deserialize_addr(buf)?;
+        let lft = XfrmLifetimeCfg::deserialize(buf)?;
+        let curlft = XfrmLifetimeCur::deserialize(buf)?;
+        let stats = XfrmStats::deserialize(buf)?;
+        let seq = u32::deserialize(buf)?;
+        let reqid = u32::deserialize(buf)?;
+        let family = deserialize_family(buf)?;
+        let mode = XfrmMode::deserialize(buf)?;
+        let replay_window = u8::deserialize(buf)?;
+        let flags = flags_from_u8(u8::deserialize(buf)?);
+        deserialize_pad(7, buf)?;
+        id.set_family(&family);
+        Ok(XfrmUsersaInfo {
+            sel,
+            id,
+            saddr: addr_for_family(saddr, &family),
+            lft,
+            curlft,
+            stats,
+            seq,
+            reqid,
+            family,
+            mode,
+            replay_window,
+            flags,
+        })
+    }
+
+    fn size(&self) -> usize {
+        224
+    }
+}
+
+/// SA lookup key (`struct xfrm_usersa_id`). The SPI is in host byte order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUsersaId {
+    /// Destination address
+    pub daddr: IpAddr,
+    /// Security parameter index
+    pub spi: u32,
+    /// Address family
+    pub family: RtAddrFamily,
+    /// IPsec protocol
+    pub proto: u8,
+}
+
+impl XfrmUsersaId {
+    /// Create the lookup key of the SA identified by `id`
+    pub fn new(id: &XfrmId) -> Self {
+        XfrmUsersaId {
+            daddr: id.daddr,
+            spi: id.spi,
+            family: family_of(&id.daddr),
+            proto: id.proto,
+        }
+    }
+}
+
+impl Nl for XfrmUsersaId {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        serialize_addr(&self.daddr, buf)?;
+        self.spi.to_be().serialize(buf)?;
+        serialize_family(&self.family, buf)?;
+        self.proto.serialize(buf)?;
+        serialize_pad(1, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let daddr = deserialize_addr(buf)?;
+        let spi = u32::from_be(u32::deserialize(buf)?);
+        let family = deserialize_family(buf)?;
+        let proto = u8::deserialize(buf)?;
+        deserialize_pad(1, buf)?;
+        Ok(XfrmUsersaId {
+            daddr: addr_for_family(daddr, &family),
+            spi,
+            family,
+            proto,
+        })
+    }
+
+    fn size(&self) -> usize {
+        24
+    }
+}
+
+/// SA flush request (`struct xfrm_usersa_flush`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUsersaFlush {
+    /// IPsec protocol of the SAs to flush, `0` for all
+    pub proto: u8,
+}
+
+impl Nl for XfrmUsersaFlush {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.proto.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        Ok(XfrmUsersaFlush {
+            proto: u8::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        1
+    }
+}
+
+/// Security policy (`struct xfrm_userpolicy_info`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUserpolicyInfo {
+    /// Traffic the policy applies to
+    pub sel: XfrmSelector,
+    /// Lifetime limits
+    pub lft: XfrmLifetimeCfg,
+    /// Current lifetime, only set by the kernel
+    pub curlft: XfrmLifetimeCur,
+    /// Priority, lower values take precedence
+    pub priority: u32,
+    /// Index of the policy, assigned by the kernel if `0`
+    pub index: u32,
+    /// Direction
+    pub dir: XfrmPolicyDir,
+    /// Action
+    pub action: XfrmPolicyAction,
+    /// Flags
+    pub flags: Vec<XfrmPolicyFlag>,
+    /// Sharing mode (`XFRM_SHARE_*`)
+    pub share: u8,
+}
+
+impl XfrmUserpolicyInfo {
+    /// Create a policy for the traffic matching `sel`
+    pub fn new(sel: XfrmSelector, dir: XfrmPolicyDir, action: XfrmPolicyAction) -> Self {
+        XfrmUserpolicyInfo {
+            sel,
+            lft: XfrmLifetimeCfg::default(),
+            curlft: XfrmLifetimeCur::default(),
+            priority: 0,
+            index: 0,
+            dir,
+            action,
+            flags: Vec::new(),
+            share: 0,
+        }
+    }
+}
+
+impl Nl for XfrmUserpolicyInfo {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.sel.serialize(buf)?;
+        self.lft.serialize(buf)?;
+        self.curlft.serialize(buf)?;
+        self.priority.serialize(buf)?;
+        self.index.serialize(buf)?;
+        self.dir.serialize(buf)?;
+        self.action.serialize(buf)?;
+        flags_to_u8(&self.flags).serialize(buf)?;
+        self.share.serialize(buf)?;
+        serialize_pad(4, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let policy = XfrmUserpolicyInfo {
+            sel: XfrmSelector::deserialize(buf)?,
+            lft: XfrmLifetimeCfg::deserialize(buf)?,
+            curlft: XfrmLifetimeCur::deserialize(buf)?,
+            priority: u32::deserialize(buf)?,
+            index: u32::deserialize(buf)?,
+            dir: XfrmPolicyDir::deserialize(buf)?,
+            action: XfrmPolicyAction::deserialize(buf)?,
+            flags: flags_from_u8(u8::deserialize(buf)?),
+            share: u8::deserialize(buf)?,
+        };
+        deserialize_pad(4, buf)?;
+        Ok(policy)
+    }
+
+    fn size(&self) -> usize {
+        168
+    }
+}
+
+/// Policy lookup key (`struct xfrm_userpolicy_id`). The policy is looked up by `index` if it is
+/// not `0`, by `sel` otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUserpolicyId {
+    /// Traffic selector of the policy
+    pub sel: XfrmSelector,
+    /// Index of the policy
+    pub index: u32,
+    /// Direction of the policy
+    pub dir: XfrmPolicyDir,
+}
+
+impl XfrmUserpolicyId {
+    /// Create the lookup key of `policy`
+    pub fn new(policy: &XfrmUserpolicyInfo) -> Self {
+        XfrmUserpolicyId {
+            sel: policy.sel.clone(),
+            index: policy.index,
+            dir: policy.dir.clone(),
+        }
+    }
+}
+
+impl Nl for XfrmUserpolicyId {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.sel.serialize(buf)?;
+        self.index.serialize(buf)?;
+        self.dir.serialize(buf)?;
+        serialize_pad(3, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let id = XfrmUserpolicyId {
+            sel: XfrmSelector::deserialize(buf)?,
+            index: u32::deserialize(buf)?,
+            dir: XfrmPolicyDir::deserialize(buf)?,
+        };
+        deserialize_pad(3, buf)?;
+        Ok(id)
+    }
+
+    fn size(&self) -> usize {
+        64
+    }
+}
+
+/// Policy template (`struct xfrm_user_tmpl`) describing an SA that traffic matching the policy
+/// has to be processed with
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUserTmpl {
+    /// Identifier of the SA, an SPI of `0` matches any SA
+    pub id: XfrmId,
+    /// Address family
+    pub family: RtAddrFamily,
+    /// Source address
+    pub saddr: IpAddr,
+    /// Request ID of the SA, `0` for any
+    pub reqid: u32,
+    /// Encapsulation mode
+    pub mode: XfrmMode,
+    /// Sharing mode (`XFRM_SHARE_*`)
+    pub share: u8,
+    /// Whether the transformation is optional on inbound traffic
+    pub optional: bool,
+    /// Allowed authentication algorithms, as a bitmask of SADB identifiers
+    pub aalgos: u32,
+    /// Allowed encryption algorithms
+    pub ealgos: u32,
+    /// Allowed compression algorithms
+    pub calgos: u32,
+}
+
+impl XfrmUserTmpl {
+    /// Create a template for the SA identified by `id`, allowing all algorithms
+    pub fn new(id: XfrmId, saddr: IpAddr, mode: XfrmMode, reqid: u32) -> Self {
+        XfrmUserTmpl {
+            family: family_of(&id.daddr),
+            id,
+            saddr,
+            reqid,
+            mode,
+            share: 0,
+            optional: false,
+            aalgos: !0,
+            ealgos: !0,
+            calgos: !0,
+        }
+    }
+}
+
+impl Nl for XfrmUserTmpl {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.id.serialize(buf)?;
+        serialize_family(&self.family, buf)?;
+        serialize_pad(2, buf)?;
+        serialize_addr(&self.saddr, buf)?;
+        self.reqid.serialize(buf)?;
+        self.mode.serialize(buf)?;
+        self.share.serialize(buf)?;
+        (self.optional as u8).serialize(buf)?;
+        serialize_pad(1, buf)?;
+        self.aalgos.serialize(buf)?;
+        self.ealgos.serialize(buf)?;
+        self.calgos.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let mut id = XfrmId::deserialize(buf)?;
+        let family = deserialize_family(buf)?;
+        deserialize_pad(2, buf)?;
+        let saddr = deserialize_addr(buf)?;
+        let reqid = u32::deserialize(buf)?;
+        let mode = XfrmMode::deserialize(buf)?;
+        let share = u8::deserialize(buf)?;
+        let optional = u8::deserialize(buf)? != 0;
+        deserialize_pad(1, buf)?;
+        id.set_family(&family);
+        Ok(XfrmUserTmpl {
+            id,
+            saddr: addr_for_family(saddr, &family),
+            family,
+            reqid,
+            mode,
+            share,
+            optional,
+            aalgos: u32::deserialize(buf)?,
+            ealgos: u32::deserialize(buf)?,
+            calgos: u32::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        64
+    }
+}
+
+/// SPI allocation request (`struct xfrm_userspi_info`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUserSpiInfo {
+    /// SA to allocate the SPI for, usually built from an acquire notification
+    pub info: XfrmUsersaInfo,
+    /// Lowest SPI to allocate
+    pub min: u32,
+    /// Highest SPI to allocate
+    pub max: u32,
+}
+
+impl Nl for XfrmUserSpiInfo {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.info.serialize(buf)?;
+        self.min.serialize(buf)?;
+        self.max.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        Ok(XfrmUserSpiInfo {
+            info: XfrmUsersaInfo::deserialize(buf)?,
+            min: u32::deserialize(buf)?,
+            max: u32::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        232
+    }
+}
+
+/// Acquire notification (`struct xfrm_user_acquire`), sent when traffic matches a policy
+/// without a matching SA
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUserAcquire {
+    /// Identifier of the missing SA
+    pub id: XfrmId,
+    /// Source address of the missing SA
+    pub saddr: IpAddr,
+    /// Selector of the triggering traffic
+    pub sel: XfrmSelector,
+    /// Matching policy
+    pub policy: XfrmUserpolicyInfo,
+    /// Allowed authentication algorithms
+    pub aalgos: u32,
+    /// Allowed encryption algorithms
+    pub ealgos: u32,
+    /// Allowed compression algorithms
+    pub calgos: u32,
+    /// Sequence number to set in the SA answering the acquire
+    pub seq: u32,
+}
+
+impl Nl for XfrmUserAcquire {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.id.serialize(buf)?;
+        serialize_addr(&self.saddr, buf)?;
+        self.sel.serialize(buf)?;
+        self.policy.serialize(buf)?;
+        self.aalgos.serialize(buf)?;
+        self.ealgos.serialize(buf)?;
+        self.calgos.serialize(buf)?;
+        self.seq.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let mut id = XfrmId::deserialize(buf)?;
+        let saddr = deserialize_addr(buf)?;
+        let sel = XfrmSelector::deserialize(buf)?;
+        let policy = XfrmUserpolicyInfo::deserialize(buf)?;
+        // The SA addresses have the family of the policy
+        let family = policy.sel.family.clone();
+        id.set_family(&family);
+        Ok(XfrmUserAcquire {
+            id,
+            saddr: addr_for_family(saddr, &family),
+            sel,
+            policy,
+            aalgos: u32::deserialize(buf)?,
+            ealgos: u32::deserialize(buf)?,
+            calgos: u32::deserialize(buf)?,
+            seq: u32::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        280
+    }
+}
+
+/// SA expire notification (`struct xfrm_user_expire`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUserExpire {
+    /// Expiring SA
+    pub state: XfrmUsersaInfo,
+    /// Whether a hard limit was reached and the SA was deleted
+    pub hard: bool,
+}
+
+impl Nl for XfrmUserExpire {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.state.serialize(buf)?;
+        (self.hard as u8).serialize(buf)?;
+        serialize_pad(7, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let expire = XfrmUserExpire {
+            state: XfrmUsersaInfo::deserialize(buf)?,
+            hard: u8::deserialize(buf)? != 0,
+        };
+        deserialize_pad(7, buf)?;
+        Ok(expire)
+    }
+
+    fn size(&self) -> usize {
+        232
+    }
+}
+
+/// Policy expire notification (`struct xfrm_user_polexpire`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmUserPolexpire {
+    /// Expiring policy
+    pub pol: XfrmUserpolicyInfo,
+    /// Whether a hard limit was reached and the policy was deleted
+    pub hard: bool,
+}
+
+impl Nl for XfrmUserPolexpire {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.pol.serialize(buf)?;
+        (self.hard as u8).serialize(buf)?;
+        serialize_pad(7, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let expire = XfrmUserPolexpire {
+            pol: XfrmUserpolicyInfo::deserialize(buf)?,
+            hard: u8::deserialize(buf)? != 0,
+        };
+        deserialize_pad(7, buf)?;
+        Ok(expire)
+    }
+
+    fn size(&self) -> usize {
+        176
+    }
+}
+
+fn serialize_algo_name(name: &str, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+    if name.len() >= XFRM_ALGO_NAME_LEN {
+        return Err(SerError::new("Algorithm names are at most 63 bytes long"));
+    }
+    name.as_bytes().serialize(buf)?;
+    serialize_pad(XFRM_ALGO_NAME_LEN - name.len(), buf)
+}
+
+fn deserialize_algo_name<B>(buf: &mut StreamReadBuffer<B>) -> Result<String, DeError>
+where
+    B: AsRef<[u8]>,
+{
+    buf.set_size_hint(XFRM_ALGO_NAME_LEN);
+    let name = Vec::<u8>::deserialize(buf)?;
+    let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+    String::from_utf8(name[..end].to_vec())
+        .map_err(|_| DeError::new("Algorithm name is not valid UTF-8"))
+}
+
+fn deserialize_key<B>(
+    buf: &mut StreamReadBuffer<B>,
+    key_bits: u32,
+    available: usize,
+) -> Result<Vec<u8>, DeError>
+where
+    B: AsRef<[u8]>,
+{
+    let key_len = (key_bits as usize).div_ceil(8);
+    if key_len > available {
+        return Err(DeError::new("Algorithm key is truncated"));
+    }
+    buf.set_size_hint(key_len);
+    Vec::<u8>::deserialize(buf)
+}
+
+/// Encryption or compression algorithm (`struct xfrm_algo`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmAlgo {
+    /// Crypto API name of the algorithm, eg. `cbc(aes)`
+    pub name: String,
+    /// Key
+    pub key: Vec<u8>,
+}
+
+impl XfrmAlgo {
+    /// Create an algorithm
+    pub fn new(name: &str, key: Vec<u8>) -> Self {
+        XfrmAlgo {
+            name: name.to_string(),
+            key,
+        }
+    }
+}
+
+impl Nl for XfrmAlgo {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        serialize_algo_name(&self.name, buf)?;
+        (self.key.len() as u32 * 8).serialize(buf)?;
+        self.key.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let hint = buf.take_size_hint().unwrap_or(0);
+        let name = deserialize_algo_name(buf)?;
+        let key_bits = u32::deserialize(buf)?;
+        let available = hint.saturating_sub(XFRM_ALGO_NAME_LEN + 4);
+        Ok(XfrmAlgo {
+            name,
+            key: deserialize_key(buf, key_bits, available)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        XFRM_ALGO_NAME_LEN + 4 + self.key.len()
+    }
+}
+
+/// Authentication algorithm with truncation (`struct xfrm_algo_auth`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmAlgoAuth {
+    /// Crypto API name of the algorithm, eg. `hmac(sha256)`
+    pub name: String,
+    /// Key
+    pub key: Vec<u8>,
+    /// Length of the truncated ICV in bits
+    pub trunc_len: u32,
+}
+
+impl XfrmAlgoAuth {
+    /// Create an authentication algorithm
+    pub fn new(name: &str, key: Vec<u8>, trunc_len: u32) -> Self {
+        XfrmAlgoAuth {
+            name: name.to_string(),
+            key,
+            trunc_len,
+        }
+    }
+}
+
+impl Nl for XfrmAlgoAuth {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        serialize_algo_name(&self.name, buf)?;
+        (self.key.len() as u32 * 8).serialize(buf)?;
+        self.trunc_len.serialize(buf)?;
+        self.key.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let hint = buf.take_size_hint().unwrap_or(0);
+        let name = deserialize_algo_name(buf)?;
+        let key_bits = u32::deserialize(buf)?;
+        let trunc_len = u32::deserialize(buf)?;
+        let available = hint.saturating_sub(XFRM_ALGO_NAME_LEN + 8);
+        Ok(XfrmAlgoAuth {
+            name,
+            key: deserialize_key(buf, key_bits, available)?,
+            trunc_len,
+        })
+    }
+
+    fn size(&self) -> usize {
+        XFRM_ALGO_NAME_LEN + 8 + self.key.len()
+    }
+}
+
+/// AEAD algorithm (`struct xfrm_algo_aead`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmAlgoAead {
+    /// Crypto API name of the algorithm, eg. `rfc4106(gcm(aes))`
+    pub name: String,
+    /// Key, including the salt
+    pub key: Vec<u8>,
+    /// Length of the ICV in bits
+    pub icv_len: u32,
+}
+
+impl XfrmAlgoAead {
+    /// Create an AEAD algorithm
+    pub fn new(name: &str, key: Vec<u8>, icv_len: u32) -> Self {
+        XfrmAlgoAead {
+            name: name.to_string(),
+            key,
+            icv_len,
+        }
+    }
+}
+
+impl Nl for XfrmAlgoAead {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        serialize_algo_name(&self.name, buf)?;
+        (self.key.len() as u32 * 8).serialize(buf)?;
+        self.icv_len.serialize(buf)?;
+        self.key.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let hint = buf.take_size_hint().unwrap_or(0);
+        let name = deserialize_algo_name(buf)?;
+        let key_bits = u32::deserialize(buf)?;
+        let icv_len = u32::deserialize(buf)?;
+        let available = hint.saturating_sub(XFRM_ALGO_NAME_LEN + 8);
+        Ok(XfrmAlgoAead {
+            name,
+            key: deserialize_key(buf, key_bits, available)?,
+            icv_len,
+        })
+    }
+
+    fn size(&self) -> usize {
+        XFRM_ALGO_NAME_LEN + 8 + self.key.len()
+    }
+}
+
+/// UDP encapsulation for NAT traversal (`struct xfrm_encap_tmpl`). Ports are in host byte
+/// order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmEncapTmpl {
+    /// Encapsulation type (`UDP_ENCAP_ESPINUDP`)
+    pub encap_type: u16,
+    /// Source port
+    pub sport: u16,
+    /// Destination port
+    pub dport: u16,
+    /// Original address before NAT, read as an IPv6 address from the kernel
+    pub oa: IpAddr,
+}
+
+impl XfrmEncapTmpl {
+    /// Create an ESP in UDP encapsulation between ports `sport` and `dport`
+    pub fn espinudp(sport: u16, dport: u16) -> Self {
+        XfrmEncapTmpl {
+            encap_type: 2,
+            sport,
+            dport,
+            oa: IpAddr::from(Ipv4Addr::UNSPECIFIED),
+        }
+    }
+}
+
+impl Nl for XfrmEncapTmpl {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.encap_type.serialize(buf)?;
+        self.sport.to_be().serialize(buf)?;
+        self.dport.to_be().serialize(buf)?;
+        serialize_pad(2, buf)?;
+        serialize_addr(&self.oa, buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let encap_type = u16::deserialize(buf)?;
+        let sport = u16::from_be(u16::deserialize(buf)?);
+        let dport = u16::from_be(u16::deserialize(buf)?);
+        deserialize_pad(2, buf)?;
+        Ok(XfrmEncapTmpl {
+            encap_type,
+            sport,
+            dport,
+            oa: deserialize_addr(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        24
+    }
+}
+
+/// Mark and mask (`struct xfrm_mark`) matched against the packet mark
+#[derive(Clone, Debug, PartialEq)]
+pub struct XfrmMark {
+    /// Mark value
+    pub v: u32,
+    /// Mask applied to the packet mark before the comparison
+    pub m: u32,
+}
+
+impl Nl for XfrmMark {
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.v.serialize(buf)?;
+        self.m.serialize(buf)
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        Ok(XfrmMark {
+            v: u32::deserialize(buf)?,
+            m: u32::deserialize(buf)?,
+        })
+    }
+
+    fn size(&self) -> usize {
+        8
+    }
+}
+
+/// XFRM message: a fixed structure such as `XfrmUsersaInfo` followed by attributes
+#[derive(Debug, PartialEq)]
+pub struct XfrmMessage<H> {
+    /// Fixed structure of the message
+    pub header: H,
+    attrs: Vec<Nlattr<XfrmAttr, Vec<u8>>>,
+}
+
+impl<H> XfrmMessage<H>
+where
+    H: Nl,
+{
+    /// Create a message without attributes
+    pub fn new(header: H) -> Self {
+        XfrmMessage {
+            header,
+            attrs: Vec::new(),
+        }
+    }
+
+    /// Add attribute `attr_type`, eg. `XfrmAttr::AlgCrypt` with an `XfrmAlgo` payload
+    pub fn add_attr<P>(&mut self, attr_type: XfrmAttr, payload: P) -> Result<&mut Self, SerError>
+    where
+        P: Nl,
+    {
+        self.attrs.push(Nlattr::new(None, attr_type, payload)?);
+        Ok(self)
+    }
+
+    /// Get handle for attribute parsing and traversal
+    pub fn get_attr_handle(&self) -> AttrHandle<'_, XfrmAttr> {
+        AttrHandle::new_borrowed(&self.attrs)
+    }
+
+    /// Set the `XfrmAttr::Tmpl` attribute of a policy or acquire message to `templates`
+    pub fn set_templates(&mut self, templates: &[XfrmUserTmpl]) -> Result<&mut Self, SerError> {
+        let mut buf = StreamWriteBuffer::new_growable(Some(templates.len() * 64));
+        for template in templates {
+            template.serialize(&mut buf)?;
+        }
+        self.attrs.retain(|attr| attr.nla_type != XfrmAttr::Tmpl);
+        self.add_attr(XfrmAttr::Tmpl, buf.as_ref().to_vec())
+    }
+
+    /// Parse the templates of the `XfrmAttr::Tmpl` attribute
+    pub fn templates(&self) -> Result<Vec<XfrmUserTmpl>, DeError> {
+        let attr = match self.get_attr_handle().get_attribute(XfrmAttr::Tmpl) {
+            Some(attr) => attr.payload.clone(),
+            None => return Ok(Vec::new()),
+        };
+        let mut buf = StreamReadBuffer::new(&attr);
+        let mut templates = Vec::with_capacity(attr.len() / 64);
+        for _ in 0..attr.len() / 64 {
+            templates.push(XfrmUserTmpl::deserialize(&mut buf)?);
+        }
+        Ok(templates)
+    }
+}
+
+impl<H> Nl for XfrmMessage<H>
+where
+    H: Nl,
+{
+    fn serialize(&self, buf: &mut StreamWriteBuffer) -> Result<(), SerError> {
+        self.header.serialize(buf)?;
+        self.attrs.serialize(buf)?;
+        Ok(())
+    }
+
+    fn deserialize<B>(buf: &mut StreamReadBuffer<B>) -> Result<Self, DeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let hint = buf
+            .take_size_hint()
+            .ok_or_else(|| DeError::new("XfrmMessage requires a size hint to deserialize"))?;
+        let header = H::deserialize(buf)?;
+        let attrs_len = hint
+            .checked_sub(header.size())
+            .ok_or_else(|| DeError::new("XFRM message is shorter than its header"))?;
+        buf.set_size_hint(attrs_len);
+        let attrs = Vec::<Nlattr<XfrmAttr, Vec<u8>>>::deserialize(buf)?;
+        Ok(XfrmMessage { header, attrs })
+    }
+
+    fn size(&self) -> usize {
+        self.header.size() + self.attrs.asize()
+    }
+}
+
+/// Security policy database information
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XfrmSpdInfo {
+    /// Number of inbound policies
+    pub incnt: u32,
+    /// Number of outbound policies
+    pub outcnt: u32,
+    /// Number of forward policies
+    pub fwdcnt: u32,
+    /// Number of inbound socket policies
+    pub inscnt: u32,
+    /// Number of outbound socket policies
+    pub outscnt: u32,
+    /// Number of forward socket policies
+    pub fwdscnt: u32,
+    /// Number of policy hash buckets
+    pub spdhcnt: u32,
+    /// Maximal number of policy hash buckets
+    pub spdhmcnt: u32,
+}
+
+/// Security association database information
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XfrmSadInfo {
+    /// Number of SAs
+    pub sadcnt: u32,
+    /// Number of SA hash buckets
+    pub sadhcnt: u32,
+    /// Maximal number of SA hash buckets
+    pub sadhmcnt: u32,
+}
+
+fn u32_words(payload: &[u8]) -> Vec<u32> {
+    payload
+        .chunks_exact(4)
+        .map(|w| u32::from_ne_bytes([w[0], w[1], w[2], w[3]]))
+        .collect()
+}
+
+/// Parse the attributes following the flags of a SAD or SPD information message
+fn info_attrs<T>(payload: &[u8]) -> Result<Vec<Nlattr<T, Vec<u8>>>, DeError>
+where
+    T: NlAttrType,
+{
+    let attrs = payload
+        .get(4..)
+        .ok_or_else(|| DeError::new("XFRM information message is truncated"))?;
+    let mut buf = StreamReadBuffer::new(attrs);
+    buf.set_size_hint(attrs.len());
+    Vec::<Nlattr<T, Vec<u8>>>::deserialize(&mut buf)
+}
+
+impl XfrmSpdInfo {
+    fn parse(payload: &[u8]) -> Result<Self, DeError> {
+        let mut info = XfrmSpdInfo::default();
+        for attr in info_attrs::<XfrmSpdAttr>(payload)? {
+            let words = u32_words(&attr.payload);
+            match (attr.nla_type, words.as_slice()) {
+                (XfrmSpdAttr::Info, &[incnt, outcnt, fwdcnt, inscnt, outscnt, fwdscnt, ..]) => {
+                    info.incnt = incnt;
+                    info.outcnt = outcnt;
+                    info.fwdcnt = fwdcnt;
+                    info.inscnt = inscnt;
+                    info.outscnt = outscnt;
+                    info.fwdscnt = fwdscnt;
+                }
+                (XfrmSpdAttr::Hinfo, &[spdhcnt, spdhmcnt, ..]) => {
+                    info.spdhcnt = spdhcnt;
+                    info.spdhmcnt = spdhmcnt;
+                }
+                _ => (),
+            }
+        }
+        Ok(info)
+    }
+}
+
+impl XfrmSadInfo {
+    fn parse(payload: &[u8]) -> Result<Self, DeError> {
+        let mut info = XfrmSadInfo::default();
+        for attr in info_attrs::<XfrmSadAttr>(payload)? {
+            let words = u32_words(&attr.payload);
+            match (attr.nla_type, words.as_slice()) {
+                (XfrmSadAttr::Cnt, &[sadcnt, ..]) => info.sadcnt = sadcnt,
+                (XfrmSadAttr::Hinfo, &[sadhcnt, sadhmcnt, ..]) => {
+                    info.sadhcnt = sadhcnt;
+                    info.sadhmcnt = sadhmcnt;
+                }
+                _ => (),
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// Notification received from the `XfrmNlGrp` multicast groups
+#[derive(Debug, PartialEq)]
+pub enum XfrmEvent {
+    /// An SA is needed for traffic matching a policy
+    Acquire(XfrmMessage<XfrmUserAcquire>),
+    /// An SA reached a soft or hard lifetime limit
+    Expire(XfrmMessage<XfrmUserExpire>),
+    /// A policy reached a soft or hard lifetime limit
+    PolExpire(XfrmMessage<XfrmUserPolexpire>),
+    /// An SA was added or updated
+    NewSa(XfrmMessage<XfrmUsersaInfo>),
+    /// An SA was deleted, the `XfrmAttr::Sa` attribute holds the deleted SA
+    DelSa(XfrmMessage<XfrmUsersaId>),
+    /// A policy was added or updated
+    NewPolicy(XfrmMessage<XfrmUserpolicyInfo>),
+    /// A policy was deleted, the `XfrmAttr::Policy` attribute holds the deleted policy
+    DelPolicy(XfrmMessage<XfrmUserpolicyId>),
+    /// SAs were flushed
+    FlushSa(XfrmUsersaFlush),
+    /// Policies were flushed
+    FlushPolicy,
+    /// Other notification with its raw payload
+    Other(XfrmMsg, Vec<u8>),
+}
+
+impl XfrmEvent {
+    /// Parse the payload of a notification of type `msg_type`
+    pub fn parse(msg_type: XfrmMsg, payload: &[u8]) -> Result<Self, DeError> {
+        let mut buf = StreamReadBuffer::new(payload);
+        buf.set_size_hint(payload.len());
+        Ok(match msg_type {
+            XfrmMsg::Acquire => XfrmEvent::Acquire(XfrmMessage::deserialize(&mut buf)?),
+            XfrmMsg::Expire => XfrmEvent::Expire(XfrmMessage::deserialize(&mut buf)?),
+            XfrmMsg::PolExpire => XfrmEvent::PolExpire(XfrmMessage::deserialize(&mut buf)?),
+            XfrmMsg::NewSa | XfrmMsg::UpdSa => {
+                XfrmEvent::NewSa(XfrmMessage::deserialize(&mut buf)?)
+            }
+            XfrmMsg::DelSa => XfrmEvent::DelSa(XfrmMessage::deserialize(&mut buf)?),
+            XfrmMsg::NewPolicy | XfrmMsg::UpdPolicy => {
+                XfrmEvent::NewPolicy(XfrmMessage::deserialize(&mut buf)?)
+            }
+            XfrmMsg::DelPolicy => XfrmEvent::DelPolicy(XfrmMessage::deserialize(&mut buf)?),
+            XfrmMsg::FlushSa => XfrmEvent::FlushSa(XfrmUsersaFlush::deserialize(&mut buf)?),
+            XfrmMsg::FlushPolicy => XfrmEvent::FlushPolicy,
+            msg_type => XfrmEvent::Other(msg_type, payload.to_vec()),
+        })
+    }
+}
+
+impl NlSocket {
+    /// Add SA `sa`. Fails with `EEXIST` if the SA already exists.
+    pub fn xfrm_add_sa(&mut self, sa: XfrmMessage<XfrmUsersaInfo>) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::NewSa, sa)
+    }
+
+    /// Add or replace SA `sa`
+    pub fn xfrm_update_sa(&mut self, sa: XfrmMessage<XfrmUsersaInfo>) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::UpdSa, sa)
+    }
+
+    /// Delete the SA identified by `id`. The `XfrmAttr::Srcaddr` and `XfrmAttr::Mark`
+    /// attributes narrow the lookup.
+    pub fn xfrm_del_sa(&mut self, id: XfrmMessage<XfrmUsersaId>) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::DelSa, id)
+    }
+
+    /// Get the SA identified by `id`
+    pub fn xfrm_get_sa(
+        &mut self,
+        id: XfrmMessage<XfrmUsersaId>,
+    ) -> Result<XfrmMessage<XfrmUsersaInfo>, NlError> {
+        self.xfrm_get(XfrmMsg::GetSa, id)
+    }
+
+    /// List all SAs
+    pub fn xfrm_list_sa(&mut self) -> Result<Vec<XfrmMessage<XfrmUsersaInfo>>, NlError> {
+        let id = XfrmUsersaId::new(&XfrmId::new(IpAddr::from(Ipv4Addr::UNSPECIFIED), 0, 0));
+        self.xfrm_dump(XfrmMsg::GetSa, XfrmMessage::new(id))
+    }
+
+    /// Delete all SAs of IPsec protocol `proto`, or all SAs if `proto` is `0`
+    pub fn xfrm_flush_sa(&mut self, proto: u8) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::FlushSa, XfrmUsersaFlush { proto })
+    }
+
+    /// Allocate an SPI in the range given by `spi` and create a larval SA with it. The SA is
+    /// returned with the allocated SPI and has to be completed with `xfrm_update_sa()`.
+    pub fn xfrm_alloc_spi(
+        &mut self,
+        spi: XfrmMessage<XfrmUserSpiInfo>,
+    ) -> Result<XfrmMessage<XfrmUsersaInfo>, NlError> {
+        self.xfrm_get(XfrmMsg::AllocSpi, spi)
+    }
+
+    /// Add policy `policy`. Fails with `EEXIST` if the policy already exists.
+    pub fn xfrm_add_policy(
+        &mut self,
+        policy: XfrmMessage<XfrmUserpolicyInfo>,
+    ) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::NewPolicy, policy)
+    }
+
+    /// Add or replace policy `policy`
+    pub fn xfrm_update_policy(
+        &mut self,
+        policy: XfrmMessage<XfrmUserpolicyInfo>,
+    ) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::UpdPolicy, policy)
+    }
+
+    /// Delete the policy identified by `id`
+    pub fn xfrm_del_policy(&mut self, id: XfrmMessage<XfrmUserpolicyId>) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::DelPolicy, id)
+    }
+
+    /// Get the policy identified by `id`
+    pub fn xfrm_get_policy(
+        &mut self,
+        id: XfrmMessage<XfrmUserpolicyId>,
+    ) -> Result<XfrmMessage<XfrmUserpolicyInfo>, NlError> {
+        self.xfrm_get(XfrmMsg::GetPolicy, id)
+    }
+
+    /// List all policies
+    pub fn xfrm_list_policy(&mut self) -> Result<Vec<XfrmMessage<XfrmUserpolicyInfo>>, NlError> {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            XfrmMsg::GetPolicy,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            Vec::<u8>::new(),
+        );
+        self.send_nl(nlhdr)?;
+        Ok(self
+            .recv_all::<XfrmMsg, XfrmMessage<XfrmUserpolicyInfo>>()?
+            .into_iter()
+            .map(|msg| msg.nl_payload)
+            .collect())
+    }
+
+    /// Delete all policies of the main policy type
+    pub fn xfrm_flush_policy(&mut self) -> Result<(), NlError> {
+        self.xfrm_request(XfrmMsg::FlushPolicy, Vec::<u8>::new())
+    }
+
+    /// Get security policy database information
+    pub fn xfrm_get_spd_info(&mut self) -> Result<XfrmSpdInfo, NlError> {
+        let payload = self.xfrm_get::<_, Vec<u8>>(XfrmMsg::GetSpdInfo, !0u32)?;
+        Ok(XfrmSpdInfo::parse(&payload)?)
+    }
+
+    /// Get security association database information
+    pub fn xfrm_get_sad_info(&mut self) -> Result<XfrmSadInfo, NlError> {
+        let payload = self.xfrm_get::<_, Vec<u8>>(XfrmMsg::GetSadInfo, !0u32)?;
+        Ok(XfrmSadInfo::parse(&payload)?)
+    }
+
+    /// Receive the next notification. The socket must be subscribed to `XfrmNlGrp` groups.
+    pub fn xfrm_recv_event(&mut self) -> Result<XfrmEvent, NlError> {
+        let msg = self.recv_nl::<XfrmMsg, Vec<u8>>(None)?;
+        Ok(XfrmEvent::parse(msg.nl_type, &msg.nl_payload)?)
+    }
+
+    fn xfrm_request<P>(&mut self, msg_type: XfrmMsg, payload: P) -> Result<(), NlError>
+    where
+        P: Nl,
+    {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            msg_type,
+            vec![NlmF::Request, NlmF::Ack],
+            None,
+            None,
+            payload,
+        );
+        self.send_nl(nlhdr)?;
+        self.recv_ack()
+    }
+
+    fn xfrm_get<P, R>(&mut self, msg_type: XfrmMsg, payload: P) -> Result<R, NlError>
+    where
+        P: Nl,
+        R: Nl,
+    {
+        let nlhdr = Nlmsghdr::new(None, msg_type, vec![NlmF::Request], None, None, payload);
+        self.send_nl(nlhdr)?;
+        self.recv_all::<XfrmMsg, R>()?
+            .pop()
+            .map(|msg| msg.nl_payload)
+            .ok_or_else(|| NlError::new("No XFRM reply received"))
+    }
+
+    fn xfrm_dump<P, R>(&mut self, msg_type: XfrmMsg, payload: P) -> Result<Vec<R>, NlError>
+    where
+        P: Nl,
+        R: Nl,
+    {
+        let nlhdr = Nlmsghdr::new(
+            None,
+            msg_type,
+            vec![NlmF::Request, NlmF::Dump],
+            None,
+            None,
+            payload,
+        );
+        self.send_nl(nlhdr)?;
+        Ok(self
+            .recv_all::<XfrmMsg, R>()?
+            .into_iter()
+            .map(|msg| msg.nl_payload)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use consts::NlFamily;
+    use round_trip;
+
+    #[test]
+    fn test_sa_round_trip() {
+        let local: IpAddr = "192.0.2.1".parse().unwrap();
+        let remote: IpAddr = "192.0.2.2".parse().unwrap();
+        let id = XfrmId::new(remote, 0x1234, libc::IPPROTO_ESP as u8);
+        let mut sa = XfrmMessage::new(XfrmUsersaInfo::new(id, local, XfrmMode::Tunnel, 7));
+        sa.header.flags = vec![XfrmStateFlag::Noecn, XfrmStateFlag::Esn];
+        sa.add_attr(XfrmAttr::AlgCrypt, XfrmAlgo::new("cbc(aes)", vec![1; 16]))
+            .unwrap()
+            .add_attr(
+                XfrmAttr::AlgAuthTrunc,
+                XfrmAlgoAuth::new("hmac(sha256)", vec![2; 32], 128),
+            )
+            .unwrap()
+            .add_attr(XfrmAttr::Encap, XfrmEncapTmpl::espinudp(4500, 4500))
+            .unwrap()
+            .add_attr(XfrmAttr::Mark, XfrmMark { v: 1, m: !0 })
+            .unwrap()
+            .add_attr(XfrmAttr::IfId, 42u32)
+            .unwrap();
+        assert_eq!(sa.header.size(), 224);
+
+        let parsed = round_trip(&sa);
+        assert_eq!(parsed, sa);
+        let handle = parsed.get_attr_handle();
+        let auth = handle
+            .get_attr_payload_as::<XfrmAlgoAuth>(XfrmAttr::AlgAuthTrunc)
+            .unwrap();
+        assert_eq!(auth.key, vec![2; 32]);
+        assert_eq!(auth.trunc_len, 128);
+        let encap = handle
+            .get_attr_payload_as::<XfrmEncapTmpl>(XfrmAttr::Encap)
+            .unwrap();
+        assert_eq!(encap.dport, 4500);
+    }
+
+    #[test]
+    fn test_policy_round_trip() {
+        let local: IpAddr = "2001:db8::1".parse().unwrap();
+        let remote: IpAddr = "2001:db8::2".parse().unwrap();
+        let sel = XfrmSelector::new(local, 128, remote, 128);
+        let mut policy = XfrmMessage::new(XfrmUserpolicyInfo::new(
+            sel,
+            XfrmPolicyDir::Out,
+            XfrmPolicyAction::Allow,
+        ));
+        let tmpl = XfrmUserTmpl::new(
+            XfrmId::new(remote, 0, libc::IPPROTO_ESP as u8),
+            local,
+            XfrmMode::Transport,
+            3,
+        );
+        policy.set_templates(&[tmpl.clone(), tmpl]).unwrap();
+        let parsed = round_trip(&policy);
+        assert_eq!(parsed, policy);
+        assert_eq!(parsed.templates().unwrap().len(), 2);
+        assert_eq!(parsed.templates().unwrap()[0].id.daddr, remote);
+    }
+
+    #[test]
+    fn test_acquire_event() {
+        let local: IpAddr = "192.0.2.1".parse().unwrap();
+        let remote: IpAddr = "192.0.2.2".parse().unwrap();
+        let sel = XfrmSelector::new(local, 32, remote, 32);
+        let acquire = XfrmMessage::new(XfrmUserAcquire {
+            id: XfrmId::new(remote, 0, libc::IPPROTO_ESP as u8),
+            saddr: local,
+            sel: sel.clone(),
+            policy: XfrmUserpolicyInfo::new(sel, XfrmPolicyDir::Out, XfrmPolicyAction::Allow),
+            aalgos: !0,
+            ealgos: !0,
+            calgos: !0,
+            seq: 9,
+        });
+        let mut mem = StreamWriteBuffer::new_growable(None);
+        acquire.serialize(&mut mem).unwrap();
+        assert_eq!(
+            XfrmEvent::parse(XfrmMsg::Acquire, mem.as_ref()).unwrap(),
+            XfrmEvent::Acquire(acquire)
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_xfrm_policy() {
+        let mut s = NlSocket::connect(NlFamily::Xfrm, None, None, true).unwrap();
+        let local: IpAddr = "198.51.100.1".parse().unwrap();
+        let remote: IpAddr = "198.51.100.2".parse().unwrap();
+        let mut policy = XfrmMessage::new(XfrmUserpolicyInfo::new(
+            XfrmSelector::new(local, 32, remote, 32),
+            XfrmPolicyDir::Out,
+            XfrmPolicyAction::Allow,
+        ));
+        policy
+            .set_templates(&[XfrmUserTmpl::new(
+                XfrmId::new(remote, 0, libc::IPPROTO_ESP as u8),
+                local,
+                XfrmMode::Tunnel,
+                1,
+            )])
+            .unwrap();
+        let id = XfrmUserpolicyId::new(&policy.header);
+        let sel = policy.header.sel.clone();
+        // Left over by an earlier failed run, deleted with a separate socket because a failed
+        // request leaves its error in the receive buffer
+        let _ = NlSocket::connect(NlFamily::Xfrm, None, None, true)
+            .unwrap()
+            .xfrm_del_policy(XfrmMessage::new(id.clone()));
+
+        s.xfrm_add_policy(policy).unwrap();
+        let parsed = s.xfrm_get_policy(XfrmMessage::new(id.clone())).unwrap();
+        assert_eq!(parsed.templates().unwrap()[0].id.daddr, remote);
+        assert!(s
+            .xfrm_list_policy()
+            .unwrap()
+            .iter()
+            .any(|p| p.header.sel == sel));
+        assert!(s.xfrm_get_spd_info().unwrap().outcnt >= 1);
+        s.xfrm_get_sad_info().unwrap();
+        s.xfrm_del_policy(XfrmMessage::new(id)).unwrap();
+    }
+}